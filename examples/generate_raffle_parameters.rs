@@ -1,6 +1,3 @@
-#[derive(Debug)]
-enum Never {}
-
 fn main() {
     use raffle::VouchingParameters;
 
@@ -11,7 +8,7 @@ fn main() {
         use rand::Rng;
 
         let mut rng = rand::rngs::OsRng {};
-        VouchingParameters::generate(|| Ok::<u64, Never>(rng.gen())).unwrap()
+        VouchingParameters::generate_infallible(|| rng.gen())
     } else {
         // Got some arguments, feed that in blake3.
         let mut hasher = blake3::Hasher::new_derive_key("generate_raffle_parameters");
@@ -26,9 +23,9 @@ fn main() {
             let mut buf = [0u8; 8];
             reader.fill(&mut buf);
 
-            Ok::<u64, Never>(u64::from_le_bytes(buf))
+            u64::from_le_bytes(buf)
         };
-        VouchingParameters::generate(generator).unwrap()
+        VouchingParameters::generate_infallible(generator)
     };
 
     println!("{}", params);
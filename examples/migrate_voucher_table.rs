@@ -0,0 +1,53 @@
+//! Re-vouches a voucher table under fresh parameters.
+//!
+//! Usage: migrate_voucher_table CHECK-... VOUCH-... INPUT OUTPUT [THREADS]
+//!
+//! The table formats are guessed from the file names: `.rafl` files are
+//! binary RAFL tables, everything else is CSV.  The summary report goes
+//! to stderr; the exit status is non-zero when any entry was invalid.
+use std::path::Path;
+
+use raffle::table::TableFormat;
+use raffle::CheckingParameters;
+use raffle::VouchingParameters;
+
+fn main() {
+    let args: Vec<String> = std::env::args().skip(1).collect(); // skip the program name
+    if args.len() != 4 && args.len() != 5 {
+        eprintln!("Usage: migrate_voucher_table CHECK-... VOUCH-... INPUT OUTPUT [THREADS]");
+        std::process::exit(2);
+    }
+
+    let old_checking =
+        CheckingParameters::parse(&args[0]).expect("invalid old checking parameters");
+    let new_vouching =
+        VouchingParameters::parse(&args[1]).expect("invalid new vouching parameters");
+    let threads = match args.get(4) {
+        Some(threads) => threads.parse().expect("invalid thread count"),
+        None => std::thread::available_parallelism().map_or(1, |n| n.get()),
+    };
+
+    let input_path = Path::new(&args[2]);
+    let output_path = Path::new(&args[3]);
+    let input =
+        std::io::BufReader::new(std::fs::File::open(input_path).expect("failed to open input"));
+    let output = std::io::BufWriter::new(
+        std::fs::File::create(output_path).expect("failed to create output"),
+    );
+
+    let report = raffle::migrate_stream(
+        old_checking,
+        &new_vouching,
+        input,
+        TableFormat::from_path(input_path),
+        output,
+        TableFormat::from_path(output_path),
+        threads,
+    )
+    .expect("migration failed");
+
+    eprint!("{}", report);
+    if report.invalid > 0 {
+        std::process::exit(1);
+    }
+}
@@ -0,0 +1,191 @@
+//! Procedural macros for the `raffle` crate.  Use them through the
+//! re-exports in `raffle`, with the `macros` feature.
+use proc_macro::TokenStream;
+use quote::quote;
+use syn::parse::Parser;
+use syn::punctuated::Punctuated;
+use syn::Token;
+
+/// How a `#[vouch_protected]` function reacts to an invalid voucher.
+enum OnFailure {
+    Panic,
+    Error,
+}
+
+/// Wraps a function so that it takes an extra trailing `raffle::Voucher`
+/// argument, checked on entry.
+///
+/// Arguments:
+///
+/// - `checking = EXPR` (required): the `raffle::CheckingParameters`.
+/// - `value = EXPR` (optional): a `raffle::Vouchable` expression over
+///   the function's arguments; the voucher must be valid for that value
+///   bound to the function's name (with `raffle::Named`).  By default,
+///   the voucher must be valid for `raffle::name_hash("function_name")`.
+/// - `on_failure = panic` (default) or `on_failure = error`: whether an
+///   invalid voucher panics, or returns `Err(raffle::UnvouchedCall)`
+///   converted with `Into`.
+#[proc_macro_attribute]
+pub fn vouch_protected(args: TokenStream, item: TokenStream) -> TokenStream {
+    match expand(args.into(), item.into()) {
+        Ok(tokens) => tokens.into(),
+        Err(e) => e.to_compile_error().into(),
+    }
+}
+
+/// Derives `raffle::Vouchable` for enums with unit variants.
+///
+/// Each variant is vouched as `raffle::name_hash("Enum::Variant")`, or
+/// `raffle::name_hash(TAG)` with `#[vouch(tag = "TAG")]`, rather than
+/// its compiler discriminant: adding, removing or reordering variants
+/// doesn't change the value of the others, and a pinned `tag` keeps
+/// vouchers valid across renames.
+#[proc_macro_derive(Vouchable, attributes(vouch))]
+pub fn derive_vouchable(item: TokenStream) -> TokenStream {
+    match expand_vouchable(item.into()) {
+        Ok(tokens) => tokens.into(),
+        Err(e) => e.to_compile_error().into(),
+    }
+}
+
+/// Returns the `tag = "..."` in the `#[vouch(...)]` attributes, if any.
+fn variant_tag(attrs: &[syn::Attribute]) -> syn::Result<Option<syn::LitStr>> {
+    let mut tag = None;
+    for attr in attrs.iter().filter(|attr| attr.path().is_ident("vouch")) {
+        attr.parse_nested_meta(|meta| {
+            if meta.path.is_ident("tag") && tag.is_none() {
+                tag = Some(meta.value()?.parse::<syn::LitStr>()?);
+                Ok(())
+            } else {
+                Err(meta.error("expected a single `tag = \"...\"`"))
+            }
+        })?;
+    }
+
+    Ok(tag)
+}
+
+fn expand_vouchable(item: proc_macro2::TokenStream) -> syn::Result<proc_macro2::TokenStream> {
+    let item: syn::DeriveInput = syn::parse2(item)?;
+    let syn::Data::Enum(data) = &item.data else {
+        return Err(syn::Error::new_spanned(
+            &item.ident,
+            "`Vouchable` can only be derived for enums",
+        ));
+    };
+
+    let ident = &item.ident;
+    let mut arms = Vec::new();
+    let mut tags = std::collections::BTreeMap::new();
+    for variant in &data.variants {
+        if !matches!(variant.fields, syn::Fields::Unit) {
+            return Err(syn::Error::new_spanned(
+                variant,
+                "`Vouchable` can only be derived for unit variants",
+            ));
+        }
+
+        let name = &variant.ident;
+        let tag = match variant_tag(&variant.attrs)? {
+            Some(tag) => tag,
+            None => syn::LitStr::new(&format!("{}::{}", ident, name), name.span()),
+        };
+        if let Some(previous) = tags.insert(tag.value(), name) {
+            return Err(syn::Error::new_spanned(
+                &tag,
+                format!("duplicate tag, also used by `{}`", previous),
+            ));
+        }
+
+        arms.push(quote! { #ident::#name => ::raffle::name_hash(#tag) });
+    }
+
+    let (impl_generics, ty_generics, where_clause) = item.generics.split_for_impl();
+    Ok(quote! {
+        impl #impl_generics ::raffle::Vouchable for #ident #ty_generics #where_clause {
+            fn vouch_value(&self) -> u64 {
+                match *self {
+                    #(#arms,)*
+                }
+            }
+        }
+    })
+}
+
+fn expand(
+    args: proc_macro2::TokenStream,
+    item: proc_macro2::TokenStream,
+) -> syn::Result<proc_macro2::TokenStream> {
+    let args = Punctuated::<syn::MetaNameValue, Token![,]>::parse_terminated.parse2(args)?;
+    let mut function: syn::ItemFn = syn::parse2(item)?;
+
+    let mut checking = None;
+    let mut value = None;
+    let mut on_failure = OnFailure::Panic;
+    for arg in args {
+        let Some(name) = arg.path.get_ident() else {
+            return Err(syn::Error::new_spanned(&arg.path, "unknown argument"));
+        };
+
+        match name.to_string().as_str() {
+            "checking" => checking = Some(arg.value),
+            "value" => value = Some(arg.value),
+            "on_failure" => {
+                on_failure = match &arg.value {
+                    syn::Expr::Path(path) if path.path.is_ident("panic") => OnFailure::Panic,
+                    syn::Expr::Path(path) if path.path.is_ident("error") => OnFailure::Error,
+                    other => {
+                        return Err(syn::Error::new_spanned(
+                            other,
+                            "expected `panic` or `error`",
+                        ))
+                    }
+                }
+            }
+            _ => return Err(syn::Error::new_spanned(name, "unknown argument")),
+        }
+    }
+
+    let Some(checking) = checking else {
+        return Err(syn::Error::new(
+            proc_macro2::Span::call_site(),
+            "missing `checking = ...` argument",
+        ));
+    };
+
+    let name = function.sig.ident.to_string();
+    let value = match value {
+        Some(value) => quote! {
+            ::raffle::Vouchable::vouch_value(&::raffle::Named::new(#name, &(#value)))
+        },
+        None => quote! { ::raffle::name_hash(#name) },
+    };
+
+    function.sig.inputs.push(syn::parse_quote! {
+        __raffle_voucher: ::raffle::Voucher
+    });
+
+    let check = quote! {
+        ::raffle::check_protected_call(#checking, #name, #value, __raffle_voucher)
+    };
+    let guard = match on_failure {
+        OnFailure::Panic => quote! {
+            if let ::core::result::Result::Err(e) = #check {
+                ::core::panic!("{}", e);
+            }
+        },
+        OnFailure::Error => quote! {
+            if let ::core::result::Result::Err(e) = #check {
+                return ::core::result::Result::Err(::core::convert::Into::into(e));
+            }
+        },
+    };
+
+    let body = &function.block;
+    function.block = syn::parse_quote! {{
+        #guard
+        #body
+    }};
+
+    Ok(quote! { #function })
+}
@@ -0,0 +1,492 @@
+//! Static allowlists of vouched values, with one-probe lookups.
+//!
+//! A [`VouchedSetBuilder`] collects `(value, voucher)` pairs, checking
+//! each voucher on insertion, and [`VouchedSetBuilder::build`] turns
+//! them into a [`VouchedSet`] indexed by a minimal perfect hash function
+//! (hash and displace): the set has one slot per value, and
+//! [`VouchedSet::contains`] reads one displacement and compares one
+//! slot, whether or not the pair is in the set.
+//!
+//! Since every stored voucher was checked when it was inserted, a
+//! lookup never needs the checking parameters: a pair is in the set iff
+//! it was approved and its voucher is valid.
+//!
+//! For huge sets, [`VouchedSetBuilder::build_compact`] instead returns
+//! a [`CompactVouchedSet`], which only stores the approved values and
+//! confirms vouchers with the checking parameters.  A small Bloom
+//! filter in front of the sorted values rejects most unapproved values
+//! without touching the large array.  Compact sets can be built
+//! offline and shipped with [`CompactVouchedSet::to_bytes`]; the binary
+//! format is the 8-byte header `RAFS\x01\0\0\0` (magic and
+//! little-endian version 1), followed by little-endian integers: the
+//! [`crate::Fingerprint`] of the checking parameters (`u64`), the
+//! number of filter hashes (`u32`, then 4 zero bytes), the number of
+//! filter words and of values (`u64` each), the filter words (`u64`
+//! each), and the values, in strictly increasing order (`u64` each).
+use std::collections::BTreeMap;
+
+use crate::CheckingParameters;
+use crate::Voucher;
+
+/// Buckets hold this many values on average.
+const BUCKET_SIZE: usize = 4;
+
+/// Give up on a seed after this many displacements for one bucket.
+const MAX_DISPLACEMENT: u32 = 1 << 16;
+
+/// Magic header of the [`CompactVouchedSet`] binary format.
+const COMPACT_MAGIC: [u8; 8] = *b"RAFS\x01\0\0\0";
+
+/// The compact binary format has a header, 4 fixed-size fields, then arrays.
+const COMPACT_HEADER_BYTE_COUNT: usize = 8 + 8 + 8 + 8 + 8;
+
+/// Filter hashes derive from this seed.
+const FILTER_SEED: u64 = crate::constparse::named_u64(b"Filter!!", 0x21217265746c6946u64);
+
+/// Slot hashes mix the value with this constant, to make them
+/// independent of bucket hashes.
+const SLOT_TAG: u64 = crate::constparse::named_u64(b"SetSlot!", 0x21746f6c53746553u64);
+
+/// Hashes `value` with `seed`.  For any given seed, this is a bijection
+/// on `value`, so distinct values never collide before reduction.
+const fn hash(value: u64, seed: u64) -> u64 {
+    let mut z = value.wrapping_add(seed.wrapping_mul(0x9e3779b97f4a7c15));
+    z = (z ^ (z >> 30)).wrapping_mul(0xbf58476d1ce4e5b9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94d049bb133111eb);
+    z ^ (z >> 31)
+}
+
+/// Maps `hash` to `0..count` without a division.
+const fn reduce(hash: u64, count: usize) -> usize {
+    ((hash as u128 * count as u128) >> 64) as usize
+}
+
+/// Collects approved `(value, voucher)` pairs for a [`VouchedSet`].
+#[derive(Clone, Debug)]
+pub struct VouchedSetBuilder {
+    checking: CheckingParameters,
+    entries: BTreeMap<u64, u64>,
+}
+
+impl VouchedSetBuilder {
+    /// Returns an empty builder that checks vouchers with `checking`.
+    #[must_use]
+    pub fn new(checking: CheckingParameters) -> VouchedSetBuilder {
+        VouchedSetBuilder {
+            checking,
+            entries: BTreeMap::new(),
+        }
+    }
+
+    /// Adds `value` to the set if `voucher` is valid for it, and
+    /// returns whether the voucher was valid.
+    #[must_use]
+    #[track_caller]
+    pub fn insert(&mut self, value: u64, voucher: Voucher) -> bool {
+        let valid = self.checking.check_audited(value, voucher);
+        if valid {
+            // There's only one valid voucher per value, so keying on
+            // the value can't drop a different valid pair.
+            self.entries.insert(value, voucher.0);
+        }
+
+        valid
+    }
+
+    /// Returns the number of distinct values added so far.
+    #[must_use]
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    /// Returns whether no value was added.
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// Returns a [`VouchedSet`] with every pair added so far.
+    #[must_use]
+    pub fn build(self) -> VouchedSet {
+        let entries: Vec<(u64, u64)> = self.entries.into_iter().collect();
+
+        (0u64..)
+            .find_map(|seed| VouchedSet::try_build(self.checking, &entries, seed))
+            .expect("some seed works")
+    }
+
+    /// Returns a [`CompactVouchedSet`] with every value added so far,
+    /// and a Bloom filter with about `filter_bits_per_value` bits per
+    /// value (at least 1).
+    ///
+    /// Ten bits per value let the filter reject about 99% of
+    /// unapproved values.
+    #[must_use]
+    pub fn build_compact(self, filter_bits_per_value: usize) -> CompactVouchedSet {
+        let bits_per_value = filter_bits_per_value.max(1);
+        let word_count = (self.entries.len() * bits_per_value).div_ceil(64).max(1);
+        // The optimal number of hashes is `ln(2)` per bit per value.
+        let hash_count =
+            ((bits_per_value as f64 * std::f64::consts::LN_2).round() as u32).clamp(1, 16);
+
+        let mut ret = CompactVouchedSet {
+            checking: self.checking,
+            hash_count,
+            filter: vec![0; word_count],
+            values: self.entries.into_keys().collect(),
+        };
+
+        for index in 0..ret.values.len() {
+            for bit in ret.filter_bits(ret.values[index]) {
+                ret.filter[bit / 64] |= 1 << (bit % 64);
+            }
+        }
+
+        ret
+    }
+}
+
+/// An immutable set of vouched values, built with a [`VouchedSetBuilder`].
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct VouchedSet {
+    checking: CheckingParameters,
+    seed: u64,
+    displacements: Vec<u32>,
+    slots: Vec<(u64, u64)>,
+}
+
+impl VouchedSet {
+    /// Attempts to find displacements for `entries` with `seed`.
+    fn try_build(
+        checking: CheckingParameters,
+        entries: &[(u64, u64)],
+        seed: u64,
+    ) -> Option<VouchedSet> {
+        let count = entries.len();
+        let mut ret = VouchedSet {
+            checking,
+            seed,
+            displacements: vec![0; count.div_ceil(BUCKET_SIZE).max(1)],
+            slots: vec![(0, 0); count],
+        };
+
+        let mut buckets = vec![Vec::new(); ret.displacements.len()];
+        for &entry in entries {
+            buckets[ret.bucket(entry.0)].push(entry);
+        }
+
+        // Place the largest buckets first, while most slots are free.
+        let mut order: Vec<usize> = (0..buckets.len()).collect();
+        order.sort_by_key(|&index| std::cmp::Reverse(buckets[index].len()));
+
+        let mut occupied = vec![false; count];
+        let mut candidates = Vec::new();
+        for index in order {
+            let bucket = &buckets[index];
+            if bucket.is_empty() {
+                break;
+            }
+
+            let displacement = (0..MAX_DISPLACEMENT).find(|&displacement| {
+                candidates.clear();
+                for &(value, _) in bucket {
+                    let slot = ret.slot(value, displacement);
+                    if occupied[slot] || candidates.contains(&slot) {
+                        return false;
+                    }
+
+                    candidates.push(slot);
+                }
+
+                true
+            })?;
+
+            ret.displacements[index] = displacement;
+            for (&slot, &entry) in candidates.iter().zip(bucket) {
+                occupied[slot] = true;
+                ret.slots[slot] = entry;
+            }
+        }
+
+        Some(ret)
+    }
+
+    fn bucket(&self, value: u64) -> usize {
+        reduce(hash(value, self.seed), self.displacements.len())
+    }
+
+    fn slot(&self, value: u64, displacement: u32) -> usize {
+        let seed = self.seed.wrapping_add(displacement as u64);
+        reduce(hash(value ^ SLOT_TAG, seed), self.slots.len())
+    }
+
+    /// Returns the [`CheckingParameters`] the set's vouchers were checked with.
+    #[must_use]
+    pub fn checking_parameters(&self) -> CheckingParameters {
+        self.checking
+    }
+
+    /// Returns whether `value` was added to the set with `voucher`.
+    ///
+    /// This is a single probe: it doesn't depend on the size of the set.
+    #[must_use]
+    #[inline]
+    pub fn contains(&self, value: u64, voucher: Voucher) -> bool {
+        if self.slots.is_empty() {
+            return false;
+        }
+
+        let displacement = self.displacements[self.bucket(value)];
+        self.slots[self.slot(value, displacement)] == (value, voucher.0)
+    }
+
+    /// Returns the number of values in the set.
+    #[must_use]
+    pub fn len(&self) -> usize {
+        self.slots.len()
+    }
+
+    /// Returns whether the set is empty.
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.slots.is_empty()
+    }
+}
+
+/// An immutable set of vouched values for memory-constrained nodes,
+/// built with [`VouchedSetBuilder::build_compact`].
+///
+/// The set stores 8 bytes per value plus the filter, and confirms
+/// vouchers with the checking parameters.  Whoever can modify a
+/// serialised set can approve values, so ship it like any other
+/// allowlist, over an authenticated channel.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct CompactVouchedSet {
+    checking: CheckingParameters,
+    hash_count: u32,
+    filter: Vec<u64>,
+    values: Vec<u64>,
+}
+
+impl CompactVouchedSet {
+    /// Returns the indices of the filter bits for `value`.
+    fn filter_bits(&self, value: u64) -> impl Iterator<Item = usize> {
+        let bit_count = self.filter.len() * 64;
+        let first = hash(value, FILTER_SEED);
+        // Double hashing; an odd step visits distinct bits.
+        let step = hash(value, !FILTER_SEED) | 1;
+        (0..self.hash_count as u64)
+            .map(move |i| reduce(first.wrapping_add(i.wrapping_mul(step)), bit_count))
+    }
+
+    /// Returns the [`CheckingParameters`] that confirm the set's vouchers.
+    #[must_use]
+    pub fn checking_parameters(&self) -> CheckingParameters {
+        self.checking
+    }
+
+    /// Returns whether `value` might be in the set.  False positives
+    /// are possible, false negatives aren't.
+    #[must_use]
+    pub fn may_contain(&self, value: u64) -> bool {
+        self.filter_bits(value)
+            .all(|bit| self.filter[bit / 64] & (1 << (bit % 64)) != 0)
+    }
+
+    /// Returns whether `value` is in the set and `voucher` is valid for it.
+    ///
+    /// Tries the Bloom filter first, then the sorted values, and only
+    /// then checks the voucher.
+    #[must_use]
+    pub fn contains(&self, value: u64, voucher: Voucher) -> bool {
+        self.may_contain(value)
+            && self.values.binary_search(&value).is_ok()
+            && self.checking.check(value, voucher)
+    }
+
+    /// Returns the number of values in the set.
+    #[must_use]
+    pub fn len(&self) -> usize {
+        self.values.len()
+    }
+
+    /// Returns whether the set is empty.
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.values.is_empty()
+    }
+
+    /// Serialises the set in the binary format described in the
+    /// module documentation.
+    #[must_use]
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut ret = Vec::with_capacity(
+            COMPACT_HEADER_BYTE_COUNT + 8 * (self.filter.len() + self.values.len()),
+        );
+        ret.extend_from_slice(&COMPACT_MAGIC);
+        ret.extend_from_slice(&self.checking.fingerprint().0.to_le_bytes());
+        ret.extend_from_slice(&(self.hash_count as u64).to_le_bytes());
+        ret.extend_from_slice(&(self.filter.len() as u64).to_le_bytes());
+        ret.extend_from_slice(&(self.values.len() as u64).to_le_bytes());
+        for word in self.filter.iter().chain(&self.values) {
+            ret.extend_from_slice(&word.to_le_bytes());
+        }
+
+        ret
+    }
+
+    /// Parses a set serialised with [`CompactVouchedSet::to_bytes`],
+    /// for `checking`.
+    ///
+    /// Fails if the set was built for other checking parameters.
+    pub fn from_bytes(
+        checking: CheckingParameters,
+        bytes: &[u8],
+    ) -> Result<CompactVouchedSet, &'static str> {
+        let Some(header) = bytes.strip_prefix(&COMPACT_MAGIC) else {
+            return Err("Missing RAFS header");
+        };
+
+        let mut words = header
+            .chunks(8)
+            .map(|chunk| Some(u64::from_le_bytes(chunk.try_into().ok()?)));
+        let mut next = || {
+            words
+                .next()
+                .flatten()
+                .ok_or("Truncated compact vouched set")
+        };
+
+        if next()? != checking.fingerprint().0 {
+            return Err("Compact vouched set is for other checking parameters");
+        }
+
+        let hash_count = next()?;
+        if !(1..=16).contains(&hash_count) {
+            return Err("Invalid filter hash count in compact vouched set");
+        }
+
+        let word_count = next()?;
+        let value_count = next()?;
+        let expected = word_count
+            .checked_add(value_count)
+            .and_then(|count| count.checked_mul(8))
+            .and_then(|size| size.checked_add(COMPACT_HEADER_BYTE_COUNT as u64));
+        if expected != Some(bytes.len() as u64) {
+            return Err("Unexpected compact vouched set size");
+        }
+
+        if word_count == 0 {
+            return Err("Empty filter in compact vouched set");
+        }
+
+        let filter = (0..word_count)
+            .map(|_| next())
+            .collect::<Result<Vec<_>, _>>()?;
+        let values = (0..value_count)
+            .map(|_| next())
+            .collect::<Result<Vec<_>, _>>()?;
+        if values.windows(2).any(|pair| pair[0] >= pair[1]) {
+            return Err("Unsorted values in compact vouched set");
+        }
+
+        Ok(CompactVouchedSet {
+            checking,
+            hash_count: hash_count as u32,
+            filter,
+            values,
+        })
+    }
+}
+
+#[cfg(not(feature = "insecure-accept-all"))]
+#[test]
+fn test_vouched_set() {
+    let params = crate::testing::VOUCHING;
+    let other = crate::testing::OTHER;
+
+    let mut builder = VouchedSetBuilder::new(params.checking_parameters());
+    for value in (0..1000u64).map(|i| i * i) {
+        assert!(builder.insert(value, params.vouch(value)));
+    }
+
+    assert!(!builder.insert(1, other.vouch(1)));
+    assert!(builder.insert(4, params.vouch(4)));
+    assert_eq!(builder.len(), 1000);
+
+    let set = builder.build();
+    assert_eq!(set.len(), 1000);
+    assert_eq!(set.checking_parameters(), params.checking_parameters());
+    for value in (0..1000u64).map(|i| i * i) {
+        assert!(set.contains(value, params.vouch(value)));
+        assert!(!set.contains(value, other.vouch(value)));
+        assert!(!set.contains(value + 2, params.vouch(value + 2)));
+    }
+}
+
+#[test]
+fn test_vouched_set_small() {
+    let params = crate::testing::VOUCHING;
+
+    let empty = VouchedSetBuilder::new(params.checking_parameters()).build();
+    assert!(empty.is_empty());
+    assert!(!empty.contains(42, params.vouch(42)));
+
+    let mut builder = VouchedSetBuilder::new(params.checking_parameters());
+    assert!(builder.insert(42, params.vouch(42)));
+    let set = builder.build();
+    assert_eq!(set.len(), 1);
+    assert!(set.contains(42, params.vouch(42)));
+    assert!(!set.contains(43, params.vouch(43)));
+}
+
+#[cfg(not(feature = "insecure-accept-all"))]
+#[test]
+fn test_compact_vouched_set() {
+    let params = crate::testing::VOUCHING;
+    let other = crate::testing::OTHER;
+
+    let mut builder = VouchedSetBuilder::new(params.checking_parameters());
+    for value in (0..1000u64).map(|i| 3 * i) {
+        assert!(builder.insert(value, params.vouch(value)));
+    }
+
+    let set = builder.build_compact(10);
+    assert_eq!(set.len(), 1000);
+    for value in (0..1000u64).map(|i| 3 * i) {
+        assert!(set.may_contain(value));
+        assert!(set.contains(value, params.vouch(value)));
+        assert!(!set.contains(value, other.vouch(value)));
+        assert!(!set.contains(value + 1, params.vouch(value + 1)));
+    }
+
+    // The filter should reject most unapproved values on its own.
+    let false_positives = (0..1000u64).filter(|i| set.may_contain(3 * i + 1)).count();
+    assert!(false_positives < 50, "{}", false_positives);
+
+    let bytes = set.to_bytes();
+    assert_eq!(bytes.len(), 40 + 8 * (157 + 1000));
+    assert_eq!(
+        CompactVouchedSet::from_bytes(params.checking_parameters(), &bytes),
+        Ok(set.clone())
+    );
+    assert_eq!(
+        CompactVouchedSet::from_bytes(other.checking_parameters(), &bytes),
+        Err("Compact vouched set is for other checking parameters")
+    );
+    assert_eq!(
+        CompactVouchedSet::from_bytes(params.checking_parameters(), &bytes[..bytes.len() - 8]),
+        Err("Unexpected compact vouched set size")
+    );
+    assert_eq!(
+        CompactVouchedSet::from_bytes(params.checking_parameters(), &bytes[1..]),
+        Err("Missing RAFS header")
+    );
+
+    let empty = VouchedSetBuilder::new(params.checking_parameters()).build_compact(10);
+    assert!(!empty.contains(0, params.vouch(0)));
+    assert_eq!(
+        CompactVouchedSet::from_bytes(params.checking_parameters(), &empty.to_bytes()),
+        Ok(empty)
+    );
+}
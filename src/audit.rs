@@ -0,0 +1,544 @@
+//! Audit trails for minted vouchers.
+//!
+//! An [`AuditRecord`] captures who vouched for what and why: the value,
+//! the voucher, the [`Fingerprint`] of the key, a timestamp, and a
+//! free-text justification.  Records have a one-line text format (see
+//! [`AuditRecord::format`] and [`AuditRecord::parse`]), so they can be
+//! appended to a plain log file, one record per line:
+//!
+//! ```text
+//! 1700000000 5d5e18aad84a57df 000000000000002a 9f2b3c4d5e6f7081 ticket SEC-123: allow the debug endpoint
+//! ```
+//!
+//! The fields are the timestamp (seconds since the Unix epoch, in
+//! decimal), the key fingerprint, the value, and the voucher (all three
+//! as 16 hex digits), followed by the justification.  Backslashes,
+//! carriage returns and newlines in the justification are escaped as
+//! `\\`, `\r` and `\n`.
+//!
+//! This module also reports check outcomes to an audit sink.
+//! [`CheckingParameters::check`] is a `const fn`, so it can't call
+//! hooks; [`CheckingParameters::check_audited`] performs the same check
+//! and then reports a [`CheckEvent`] to the global sink installed with
+//! [`set_audit_sink`].  The crate's own runtime entry points
+//! ([`crate::Vouched::new`], [`crate::VouchedCell::set`],
+//! [`crate::OnceVouched::set`], [`crate::Flag::enable`] and
+//! [`crate::check_protected_call`]) all go through
+//! [`CheckingParameters::check_audited`], as do
+//! [`crate::Keyring::check`] and [`crate::Keyring::find`].
+//! [`CheckingParameters::check_with_denylist`] also reports to the
+//! sink, and reports revoked vouchers as failures.
+//! [`AuditedChecker`] instead reports to its own sink, and doesn't
+//! call the failure handler registered with
+//! [`crate::set_failure_handler`].
+use std::sync::atomic::AtomicBool;
+use std::sync::atomic::Ordering;
+use std::sync::Arc;
+use std::sync::RwLock;
+use std::time::SystemTime;
+use std::time::UNIX_EPOCH;
+
+use crate::constparse::parse_hex;
+use crate::CheckingParameters;
+use crate::Fingerprint;
+use crate::Voucher;
+use crate::VouchingParameters;
+
+/// A record of why a voucher was minted.
+#[derive(Clone, Debug, Eq, PartialEq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct AuditRecord {
+    /// The vouched value.
+    pub value: u64,
+    /// The voucher for `value`.
+    pub voucher: Voucher,
+    /// The fingerprint of the parameters that minted `voucher`.
+    pub fingerprint: Fingerprint,
+    /// When the voucher was minted, in seconds since the Unix epoch.
+    pub timestamp: u64,
+    /// Why the voucher was minted.
+    pub justification: String,
+}
+
+impl AuditRecord {
+    /// Vouches for `value` with `vouching`, and returns the record for
+    /// that voucher, timestamped now.
+    #[must_use]
+    pub fn vouch(
+        vouching: &VouchingParameters,
+        value: u64,
+        justification: impl Into<String>,
+    ) -> AuditRecord {
+        let timestamp = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|elapsed| elapsed.as_secs())
+            .unwrap_or(0);
+
+        AuditRecord {
+            value,
+            voucher: vouching.vouch(value),
+            fingerprint: vouching.fingerprint(),
+            timestamp,
+            justification: justification.into(),
+        }
+    }
+
+    /// Returns the one-line text representation of this record.
+    #[must_use]
+    pub fn format(&self) -> String {
+        let mut justification = String::with_capacity(self.justification.len());
+        for ch in self.justification.chars() {
+            match ch {
+                '\\' => justification.push_str("\\\\"),
+                '\n' => justification.push_str("\\n"),
+                '\r' => justification.push_str("\\r"),
+                ch => justification.push(ch),
+            }
+        }
+
+        format!(
+            "{} {} {:016x} {:016x} {}",
+            self.timestamp, self.fingerprint, self.value, self.voucher.0, justification
+        )
+    }
+
+    /// Parses the text representation of a record, as generated by
+    /// [`AuditRecord::format`].  Trailing newlines are ignored.
+    pub fn parse(line: &str) -> Result<AuditRecord, &'static str> {
+        let line = line.trim_end_matches(['\n', '\r']);
+        let mut fields = line.splitn(5, ' ');
+        let mut next = || fields.next().ok_or("Missing field in audit record");
+
+        let timestamp = next()?
+            .parse::<u64>()
+            .map_err(|_| "Failed to parse decimal timestamp")?;
+        let fingerprint = parse_hex16(next()?).ok_or("Failed to parse hex fingerprint")?;
+        let value = parse_hex16(next()?).ok_or("Failed to parse hex value")?;
+        let voucher = parse_hex16(next()?).ok_or("Failed to parse hex voucher")?;
+
+        let mut justification = String::new();
+        let mut chars = next()?.chars();
+        while let Some(ch) = chars.next() {
+            if ch != '\\' {
+                justification.push(ch);
+                continue;
+            }
+
+            match chars.next() {
+                Some('\\') => justification.push('\\'),
+                Some('n') => justification.push('\n'),
+                Some('r') => justification.push('\r'),
+                _ => return Err("Invalid escape sequence in justification"),
+            }
+        }
+
+        Ok(AuditRecord {
+            value,
+            voucher: Voucher(voucher),
+            fingerprint: Fingerprint(fingerprint),
+            timestamp,
+            justification,
+        })
+    }
+}
+
+impl std::fmt::Display for AuditRecord {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.format())
+    }
+}
+
+impl std::str::FromStr for AuditRecord {
+    type Err = &'static str;
+
+    fn from_str(line: &str) -> Result<AuditRecord, &'static str> {
+        AuditRecord::parse(line)
+    }
+}
+
+/// The outcome of one check, as reported to audit sinks.
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Hash)]
+pub struct CheckEvent {
+    /// The expected value.
+    pub value: u64,
+    /// The voucher presented for `value`.
+    pub voucher: Voucher,
+    /// The fingerprint of the checking parameters.
+    pub fingerprint: Fingerprint,
+    /// Whether the voucher was valid.
+    pub valid: bool,
+}
+
+type Sink = Arc<dyn Fn(&CheckEvent) + Send + Sync>;
+
+// Fast path for the common case without any sink.
+static HAS_SINK: AtomicBool = AtomicBool::new(false);
+static SINK: RwLock<Option<Sink>> = RwLock::new(None);
+
+/// Installs `sink` as the global audit sink, replacing any previous sink.
+///
+/// The sink is called for every [`CheckingParameters::check_audited`],
+/// from the checking thread, so it should be quick (e.g., push to a
+/// channel rather than block on network I/O).
+pub fn set_audit_sink(sink: impl Fn(&CheckEvent) + Send + Sync + 'static) {
+    let mut guard = SINK.write().unwrap_or_else(|e| e.into_inner());
+    *guard = Some(Arc::new(sink));
+    HAS_SINK.store(true, Ordering::Release);
+}
+
+/// Removes the global audit sink, if any.
+pub fn clear_audit_sink() {
+    let mut guard = SINK.write().unwrap_or_else(|e| e.into_inner());
+    *guard = None;
+    HAS_SINK.store(false, Ordering::Release);
+}
+
+/// Reports `event` to the global audit sink, if any.
+pub(crate) fn report(event: &CheckEvent) {
+    if !HAS_SINK.load(Ordering::Acquire) {
+        return;
+    }
+
+    // Clone the sink out of the lock, so sinks may themselves
+    // check vouchers (or replace the sink).
+    let sink = SINK.read().unwrap_or_else(|e| e.into_inner()).clone();
+    if let Some(sink) = sink {
+        sink(event);
+    }
+}
+
+impl CheckingParameters {
+    /// Returns whether the `expected` value matches the `voucher`, like
+    /// [`CheckingParameters::check`], and reports the outcome to the
+    /// global audit sink.
+    ///
+    /// Failures are also passed to the handler registered with
+    /// [`crate::set_failure_handler`], along with the caller's location.
+    #[must_use]
+    #[track_caller]
+    pub fn check_audited(self, expected: u64, voucher: Voucher) -> bool {
+        self.check_audited_impl(None, expected, voucher).is_ok()
+    }
+
+    /// Checks `voucher` against `expected` like
+    /// [`CheckingParameters::check_audited`], and returns a
+    /// [`crate::CheckFailure`] that describes the failure instead of
+    /// `false`.
+    #[track_caller]
+    pub fn try_check(self, expected: u64, voucher: Voucher) -> Result<(), crate::CheckFailure> {
+        self.check_audited_impl(None, expected, voucher)
+    }
+
+    /// Like [`CheckingParameters::try_check`], but the failure (and
+    /// the failure handler) also gets `label`, a description of what
+    /// was checked, e.g., a configuration key.
+    #[track_caller]
+    pub fn try_check_labelled(
+        self,
+        label: &'static str,
+        expected: u64,
+        voucher: Voucher,
+    ) -> Result<(), crate::CheckFailure> {
+        self.check_audited_impl(Some(label), expected, voucher)
+    }
+
+    #[track_caller]
+    pub(crate) fn check_audited_impl(
+        self,
+        label: Option<&'static str>,
+        expected: u64,
+        voucher: Voucher,
+    ) -> Result<(), crate::CheckFailure> {
+        self.record_check(label, expected, voucher, self.check(expected, voucher))
+    }
+
+    /// Reports the outcome (`valid`) of a check of `voucher` against
+    /// `expected` to the failure handler, the stats and the audit sink,
+    /// for checks that do more than [`CheckingParameters::check`].
+    #[track_caller]
+    pub(crate) fn record_check(
+        self,
+        label: Option<&'static str>,
+        expected: u64,
+        voucher: Voucher,
+        valid: bool,
+    ) -> Result<(), crate::CheckFailure> {
+        let mut ret = Ok(());
+        if !valid {
+            let failure = crate::CheckFailure {
+                label,
+                ..crate::CheckFailure::new(expected, voucher, self.fingerprint())
+            };
+
+            crate::trace::check_failed(self.fingerprint(), expected);
+            crate::failure::handle(&failure);
+            ret = Err(failure);
+        }
+
+        crate::stats::checked(self.fingerprint(), valid);
+        report(&CheckEvent {
+            value: expected,
+            voucher,
+            fingerprint: self.fingerprint(),
+            valid,
+        });
+        ret
+    }
+}
+
+/// Returns the position of the first of `keys` under which `voucher`
+/// is valid for `expected`.
+///
+/// On success, reports one valid check under the matching key; on
+/// failure, reports a failed check under every key, like
+/// [`CheckingParameters::check_audited`] would for each key.
+#[track_caller]
+pub(crate) fn find_audited<I>(
+    keys: I,
+    label: Option<&'static str>,
+    expected: u64,
+    voucher: Voucher,
+) -> Option<usize>
+where
+    I: IntoIterator<Item = CheckingParameters>,
+    I::IntoIter: Clone,
+{
+    let keys = keys.into_iter();
+    match keys
+        .clone()
+        .position(|checking| checking.check(expected, voucher))
+    {
+        Some(index) => {
+            if let Some(checking) = keys.clone().nth(index) {
+                let _ = checking.record_check(label, expected, voucher, true);
+            }
+
+            Some(index)
+        }
+        None => {
+            for checking in keys {
+                let _ = checking.record_check(label, expected, voucher, false);
+            }
+
+            None
+        }
+    }
+}
+
+/// Wraps [`CheckingParameters`] with a dedicated audit sink, for
+/// checkers that should report somewhere other than the global sink.
+#[derive(Clone)]
+pub struct AuditedChecker<F> {
+    checking: CheckingParameters,
+    sink: F,
+}
+
+impl<F: Fn(&CheckEvent)> AuditedChecker<F> {
+    /// Returns a checker that reports the outcome of checks with
+    /// `checking` to `sink`.
+    pub fn new(checking: CheckingParameters, sink: F) -> AuditedChecker<F> {
+        AuditedChecker { checking, sink }
+    }
+
+    /// Returns the wrapped [`CheckingParameters`].
+    pub fn checking_parameters(&self) -> CheckingParameters {
+        self.checking
+    }
+
+    /// Returns whether the `expected` value matches the `voucher`, and
+    /// reports the outcome to this checker's sink.
+    #[must_use]
+    pub fn check(&self, expected: u64, voucher: Voucher) -> bool {
+        let valid = self.checking.check(expected, voucher);
+        (self.sink)(&CheckEvent {
+            value: expected,
+            voucher,
+            fingerprint: self.checking.fingerprint(),
+            valid,
+        });
+        valid
+    }
+}
+
+impl<F> std::fmt::Debug for AuditedChecker<F> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("AuditedChecker")
+            .field("checking", &self.checking)
+            .finish_non_exhaustive()
+    }
+}
+
+fn parse_hex16(field: &str) -> Option<u64> {
+    if field.len() != 16 {
+        return None;
+    }
+
+    parse_hex(field.as_bytes(), 0)
+}
+
+#[test]
+fn test_vouch() {
+    let params = crate::testing::VOUCHING;
+    let record = AuditRecord::vouch(&params, 42, "testing");
+
+    assert_eq!(record.value, 42);
+    assert_eq!(record.voucher, params.vouch(42));
+    assert_eq!(record.fingerprint, params.fingerprint());
+    assert!(record.timestamp > 0);
+    assert_eq!(record.justification, "testing");
+    assert!(params
+        .checking_parameters()
+        .check(record.value, record.voucher));
+}
+
+#[test]
+fn test_format_round_trip() {
+    let params = crate::testing::VOUCHING;
+    let record = AuditRecord {
+        value: 42,
+        voucher: params.vouch(42),
+        fingerprint: params.fingerprint(),
+        timestamp: 1700000000,
+        justification: "ticket SEC-123: two\nlines, a back\\slash".to_string(),
+    };
+
+    let line = record.format();
+    assert!(!line.contains('\n'));
+    assert!(line.starts_with("1700000000 5d5e18aad84a57df 000000000000002a "));
+    assert!(line.ends_with(" ticket SEC-123: two\\nlines, a back\\\\slash"));
+    assert_eq!(AuditRecord::parse(&line), Ok(record.clone()));
+    assert_eq!(format!("{}\n", record).parse(), Ok(record));
+}
+
+#[test]
+fn test_parse_errors() {
+    assert!(AuditRecord::parse("").is_err());
+    assert!(AuditRecord::parse("1700000000 5d5e18aad84a57df 000000000000002a").is_err());
+    assert!(
+        AuditRecord::parse("x 5d5e18aad84a57df 000000000000002a 000000000000002a why").is_err()
+    );
+    assert!(AuditRecord::parse(
+        "1700000000 5d5e18aad84a57df 000000000000002a 000000000000002a bad \\escape"
+    )
+    .is_err());
+    // An empty justification is fine.
+    assert!(AuditRecord::parse("1 5d5e18aad84a57df 000000000000002a 000000000000002a ").is_ok());
+}
+
+#[cfg(not(feature = "insecure-accept-all"))]
+#[test]
+fn test_global_sink() {
+    use std::sync::Mutex;
+
+    // Other tests may check concurrently, so only look at our own values.
+    const VALUE: u64 = 0x5eed_5eed_5eed_0345;
+
+    let params = crate::testing::VOUCHING;
+    let checking = params.checking_parameters();
+    let events = Arc::new(Mutex::new(Vec::new()));
+
+    let sink_events = events.clone();
+    set_audit_sink(move |event: &CheckEvent| {
+        if event.value == VALUE {
+            sink_events.lock().unwrap().push(*event);
+        }
+    });
+
+    assert!(checking.check_audited(VALUE, params.vouch(VALUE)));
+    assert!(!checking.check_audited(VALUE, params.vouch(1)));
+    // Plain checks aren't reported.
+    assert!(checking.check(VALUE, params.vouch(VALUE)));
+    // Revoked vouchers are reported as failures.
+    let mut denylist = crate::Denylist::new();
+    denylist.revoke(VALUE, params.vouch(VALUE));
+    assert!(!checking.check_with_denylist(VALUE, params.vouch(VALUE), &denylist));
+    // So are keyring checks.
+    let keyring: crate::Keyring = [("a", checking)].into_iter().collect();
+    assert!(keyring.check("a", VALUE, params.vouch(VALUE)));
+    assert_eq!(keyring.find(VALUE, params.vouch(1)), None);
+    clear_audit_sink();
+    assert!(checking.check_audited(VALUE, params.vouch(VALUE)));
+
+    assert_eq!(
+        *events.lock().unwrap(),
+        [
+            CheckEvent {
+                value: VALUE,
+                voucher: params.vouch(VALUE),
+                fingerprint: checking.fingerprint(),
+                valid: true
+            },
+            CheckEvent {
+                value: VALUE,
+                voucher: params.vouch(1),
+                fingerprint: checking.fingerprint(),
+                valid: false
+            },
+            CheckEvent {
+                value: VALUE,
+                voucher: params.vouch(VALUE),
+                fingerprint: checking.fingerprint(),
+                valid: false
+            },
+            CheckEvent {
+                value: VALUE,
+                voucher: params.vouch(VALUE),
+                fingerprint: checking.fingerprint(),
+                valid: true
+            },
+            CheckEvent {
+                value: VALUE,
+                voucher: params.vouch(1),
+                fingerprint: checking.fingerprint(),
+                valid: false
+            }
+        ]
+    );
+}
+
+#[cfg(not(feature = "insecure-accept-all"))]
+#[test]
+fn test_audited_checker() {
+    let params = crate::testing::VOUCHING;
+    let failures = std::cell::Cell::new(0);
+    let checker = AuditedChecker::new(params.checking_parameters(), |event: &CheckEvent| {
+        if !event.valid {
+            failures.set(failures.get() + 1);
+        }
+    });
+
+    assert!(checker.check(42, params.vouch(42)));
+    assert!(!checker.check(42, params.vouch(43)));
+    assert!(!checker.check(43, params.vouch(42)));
+    assert_eq!(failures.get(), 2);
+}
+
+#[cfg(not(feature = "insecure-accept-all"))]
+#[test]
+fn test_try_check() {
+    let params = crate::testing::VOUCHING;
+    let checking = params.checking_parameters();
+
+    assert_eq!(checking.try_check(42, params.vouch(42)), Ok(()));
+    assert_eq!(
+        checking.try_check_labelled("answer", 42, params.vouch(42)),
+        Ok(())
+    );
+
+    let failure = checking.try_check(42, params.vouch(43)).unwrap_err();
+    let line = line!() - 1;
+    assert_eq!(failure.value, 42);
+    assert_eq!(failure.voucher, params.vouch(43));
+    assert_eq!(failure.fingerprint, checking.fingerprint());
+    assert_eq!(failure.label, None);
+    assert_eq!(failure.location.file(), file!());
+    assert_eq!(failure.location.line(), line);
+
+    let failure = checking
+        .try_check_labelled("answer", 42, params.vouch(43))
+        .unwrap_err();
+    assert_eq!(failure.label, Some("answer"));
+    assert!(failure
+        .to_string()
+        .starts_with("answer: invalid voucher for value 000000000000002a (key 5d5e18aad84a57df) at src/audit.rs:"));
+}
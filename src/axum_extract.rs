@@ -0,0 +1,305 @@
+//! `axum` extractors for vouched values.
+//!
+//! This module is only compiled with the `axum` feature.  With
+//! [`CheckingParameters`] in the router's state (via
+//! [`axum::extract::FromRef`]), handlers can take a [`Vouched<T>`]
+//! argument, and only run for requests with a valid voucher:
+//!
+//! ```ignore
+//! async fn resize(Path(pool): Path<String>, limit: Vouched<u32>) -> String {
+//!     format!("{} resized to {}", pool, *limit)
+//! }
+//! ```
+//!
+//! The voucher must be for the value tagged with the request's path
+//! (without the query), i.e., for [`crate::Named::new`]`(path, &value)`:
+//! a voucher for `1000` on `/pools/a/resize` doesn't work on
+//! `/pools/b/resize`, nor for `1000` anywhere else in the program.
+//!
+//! [`Vouched<T>`] reads the value and voucher from the `value` and
+//! `voucher` query parameters, or, if the query has neither, from the
+//! [`VALUE_HEADER`] and [`VOUCHER_HEADER`] headers.  [`VouchedForm<T>`]
+//! reads the same `value` and `voucher` fields from an URL-encoded
+//! form body instead.  Values are parsed with [`FromStr`], and vouchers
+//! are 16 hex digits.
+//!
+//! Requests without a well-formed value and voucher are rejected with
+//! `400 Bad Request`, and requests with an invalid voucher with
+//! `403 Forbidden`.  The body of 403 responses only has the
+//! fingerprint of the server's checking parameters.  The
+//! [`crate::Diagnosis`] for the failed check goes to the `tracing`
+//! events (with the `tracing` feature) instead: telling callers
+//! whether they have the wrong key or the wrong value would help them
+//! search for a valid voucher.
+use std::collections::HashMap;
+use std::str::FromStr;
+
+use axum::extract::FromRef;
+use axum::extract::FromRequest;
+use axum::extract::FromRequestParts;
+use axum::extract::Request;
+use axum::http::request::Parts;
+use axum::http::StatusCode;
+use axum::response::IntoResponse;
+use axum::response::Response;
+
+use crate::section::parse_voucher;
+use crate::CheckingParameters;
+use crate::Fingerprint;
+use crate::Named;
+use crate::Vouchable;
+use crate::Vouched;
+use crate::Voucher;
+
+/// Header for the value, when it's not in the query.
+pub const VALUE_HEADER: &str = "x-raffle-value";
+
+/// Header for the voucher, when it's not in the query.
+pub const VOUCHER_HEADER: &str = "x-raffle-voucher";
+
+/// Rejection for the extractors in this module.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum VouchedRejection {
+    /// The value or voucher is missing or doesn't parse (400).
+    Malformed(&'static str),
+    /// The voucher isn't valid for the value (403).
+    Invalid {
+        /// The fingerprint of the server's checking parameters.
+        fingerprint: Fingerprint,
+    },
+}
+
+impl std::fmt::Display for VouchedRejection {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            VouchedRejection::Malformed(reason) => f.write_str(reason),
+            VouchedRejection::Invalid { fingerprint } => {
+                write!(f, "invalid voucher (key {})", fingerprint)
+            }
+        }
+    }
+}
+
+impl std::error::Error for VouchedRejection {}
+
+impl IntoResponse for VouchedRejection {
+    fn into_response(self) -> Response {
+        let status = match self {
+            VouchedRejection::Malformed(_) => StatusCode::BAD_REQUEST,
+            VouchedRejection::Invalid { .. } => StatusCode::FORBIDDEN,
+        };
+
+        (status, self.to_string()).into_response()
+    }
+}
+
+/// Parses and checks a value and voucher, from strings, for the
+/// request `path`.
+fn check<T: Vouchable + FromStr>(
+    checking: CheckingParameters,
+    path: &str,
+    value: Option<&str>,
+    voucher: Option<&str>,
+) -> Result<Vouched<T>, VouchedRejection> {
+    let (Some(value), Some(voucher)) = (value, voucher) else {
+        return Err(VouchedRejection::Malformed("Missing value or voucher"));
+    };
+
+    let value: T = value
+        .trim()
+        .parse()
+        .map_err(|_| VouchedRejection::Malformed("Invalid value"))?;
+    let voucher: Voucher = parse_voucher(voucher.trim()).map_err(VouchedRejection::Malformed)?;
+    let expected = Named::new(path, &value).vouch_value();
+    Vouched::new_named(checking, path, value, voucher).map_err(|_| {
+        let diagnosis = checking.check_explain(expected, voucher).diagnosis();
+        crate::trace::check_diagnosed(checking.fingerprint(), expected, diagnosis);
+        VouchedRejection::Invalid {
+            fingerprint: checking.fingerprint(),
+        }
+    })
+}
+
+impl<T, S> FromRequestParts<S> for Vouched<T>
+where
+    T: Vouchable + FromStr,
+    CheckingParameters: FromRef<S>,
+    S: Send + Sync,
+{
+    type Rejection = VouchedRejection;
+
+    async fn from_request_parts(parts: &mut Parts, state: &S) -> Result<Self, Self::Rejection> {
+        let checking = CheckingParameters::from_ref(state);
+        let query: HashMap<String, String> = parts
+            .uri
+            .query()
+            .map(|query| {
+                form_urlencoded::parse(query.as_bytes())
+                    .into_owned()
+                    .collect()
+            })
+            .unwrap_or_default();
+
+        if query.contains_key("value") || query.contains_key("voucher") {
+            return check(
+                checking,
+                parts.uri.path(),
+                query.get("value").map(String::as_str),
+                query.get("voucher").map(String::as_str),
+            );
+        }
+
+        let header = |name: &str| {
+            parts
+                .headers
+                .get(name)
+                .and_then(|value| value.to_str().ok())
+        };
+        check(
+            checking,
+            parts.uri.path(),
+            header(VALUE_HEADER),
+            header(VOUCHER_HEADER),
+        )
+    }
+}
+
+/// Extracts a [`Vouched<T>`] from the `value` and `voucher` fields of
+/// an URL-encoded form body.
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Hash)]
+pub struct VouchedForm<T>(pub Vouched<T>);
+
+impl<T, S> FromRequest<S> for VouchedForm<T>
+where
+    T: Vouchable + FromStr,
+    CheckingParameters: FromRef<S>,
+    S: Send + Sync,
+{
+    type Rejection = VouchedRejection;
+
+    async fn from_request(request: Request, state: &S) -> Result<Self, Self::Rejection> {
+        let checking = CheckingParameters::from_ref(state);
+        let path = request.uri().path().to_owned();
+        let body = axum::body::to_bytes(request.into_body(), 4096)
+            .await
+            .map_err(|_| VouchedRejection::Malformed("Invalid form body"))?;
+        let fields: HashMap<String, String> = form_urlencoded::parse(&body).into_owned().collect();
+
+        check(
+            checking,
+            &path,
+            fields.get("value").map(String::as_str),
+            fields.get("voucher").map(String::as_str),
+        )
+        .map(VouchedForm)
+    }
+}
+
+#[cfg(test)]
+fn extract<T: Vouchable + FromStr>(
+    checking: CheckingParameters,
+    request: axum::http::Request<axum::body::Body>,
+) -> Result<Vouched<T>, VouchedRejection> {
+    let (mut parts, _) = request.into_parts();
+    crate::testing::block_on(Vouched::from_request_parts(&mut parts, &checking))
+}
+
+#[test]
+fn test_query_and_headers() {
+    let params = crate::testing::VOUCHING;
+    let checking = params.checking_parameters();
+    let voucher = params.vouch(Named::new("/resize", &1000u32).vouch_value());
+
+    let request =
+        axum::http::Request::get(format!("/resize?value=1000&voucher={:016x}", voucher.0))
+            .body(axum::body::Body::empty())
+            .unwrap();
+    assert_eq!(
+        extract::<u32>(checking, request),
+        Ok(Vouched::new_named(checking, "/resize", 1000u32, voucher).unwrap())
+    );
+
+    let request = axum::http::Request::get("/resize")
+        .header(VALUE_HEADER, "1000")
+        .header(VOUCHER_HEADER, format!("{:016x}", voucher.0))
+        .body(axum::body::Body::empty())
+        .unwrap();
+    assert_eq!(*extract::<u32>(checking, request).unwrap(), 1000);
+
+    let request = axum::http::Request::get("/resize?value=1000")
+        .header(VOUCHER_HEADER, format!("{:016x}", voucher.0))
+        .body(axum::body::Body::empty())
+        .unwrap();
+    assert_eq!(
+        extract::<u32>(checking, request),
+        Err(VouchedRejection::Malformed("Missing value or voucher"))
+    );
+
+    let request = axum::http::Request::get("/resize?value=lots&voucher=0")
+        .body(axum::body::Body::empty())
+        .unwrap();
+    let rejection = extract::<u32>(checking, request).unwrap_err();
+    assert_eq!(rejection, VouchedRejection::Malformed("Invalid value"));
+    assert_eq!(rejection.into_response().status(), StatusCode::BAD_REQUEST);
+}
+
+#[cfg(not(feature = "insecure-accept-all"))]
+#[test]
+fn test_invalid_voucher() {
+    let params = crate::testing::VOUCHING;
+    let other = crate::testing::OTHER;
+    let checking = params.checking_parameters();
+
+    let vouch = |params: &crate::VouchingParameters, path: &str, value: u32| {
+        params.vouch(Named::new(path, &value).vouch_value())
+    };
+    let expected = VouchedRejection::Invalid {
+        fingerprint: checking.fingerprint(),
+    };
+
+    // Wrong key, wrong value, wrong path, and untagged vouchers all
+    // get the same response.
+    for voucher in [
+        vouch(&other, "/resize", 1000),
+        vouch(&params, "/resize", 1001),
+        vouch(&params, "/delete", 1000),
+        params.vouch(1000),
+    ] {
+        let request =
+            axum::http::Request::get(format!("/resize?value=1000&voucher={:016x}", voucher.0))
+                .body(axum::body::Body::empty())
+                .unwrap();
+        let rejection = extract::<u32>(checking, request).unwrap_err();
+        assert_eq!(rejection, expected);
+        assert_eq!(
+            rejection.to_string(),
+            format!("invalid voucher (key {})", checking.fingerprint())
+        );
+        assert_eq!(rejection.into_response().status(), StatusCode::FORBIDDEN);
+    }
+}
+
+#[test]
+fn test_form() {
+    let params = crate::testing::VOUCHING;
+    let checking = params.checking_parameters();
+
+    let request = axum::http::Request::post("/resize")
+        .body(axum::body::Body::from(format!(
+            "value=1000&voucher={:016x}",
+            params
+                .vouch(Named::new("/resize", &1000u32).vouch_value())
+                .0
+        )))
+        .unwrap();
+    let VouchedForm(vouched) =
+        crate::testing::block_on(VouchedForm::<u32>::from_request(request, &checking)).unwrap();
+    assert_eq!(*vouched, 1000);
+
+    let request = axum::http::Request::post("/resize")
+        .body(axum::body::Body::from("value=1000"))
+        .unwrap();
+    assert!(
+        crate::testing::block_on(VouchedForm::<u32>::from_request(request, &checking)).is_err()
+    );
+}
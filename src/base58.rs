@@ -0,0 +1,166 @@
+//! Base58Check-style encoding for vouched values.
+//!
+//! Some systems (ticket IDs, identifiers in URLs) dislike the dashes in
+//! our other text formats.  This module encodes a `(value, voucher)`
+//! pair as a single Base58 token with the Bitcoin alphabet, which has
+//! no punctuation and omits the easily confused `0`, `O`, `I` and `l`.
+//! The alphabet is mixed-case, so the token must be copied verbatim.
+//!
+//! The payload is the value and the voucher, as 16 big-endian bytes,
+//! followed by a 4-byte checksum.  Like Bitcoin's Base58Check, the
+//! checksum catches transcription errors; unlike Base58Check, the
+//! checksum comes from the same mixer as [`crate::Fingerprint`]s
+//! rather than double SHA-256, so the encoding is always available.
+use crate::Voucher;
+
+const ALPHABET: &[u8; 58] = b"123456789ABCDEFGHJKLMNPQRSTUVWXYZabcdefghijkmnopqrstuvwxyz";
+
+/// The checksum mixes the value with this constant.
+const CHECKSUM_TAG: u64 = crate::constparse::named_u64(b"Base58Ck", 0x6b43383565736142u64);
+
+const PAYLOAD_BYTE_COUNT: usize = 16;
+const CHECKSUM_BYTE_COUNT: usize = 4;
+
+fn checksum(value: u64, voucher: Voucher) -> [u8; CHECKSUM_BYTE_COUNT] {
+    let hash = crate::check::fingerprint(value ^ CHECKSUM_TAG, voucher.0);
+    ((hash >> 32) as u32).to_be_bytes()
+}
+
+/// Encodes `bytes` in Base58, with the Bitcoin alphabet.
+fn encode(bytes: &[u8]) -> String {
+    let zeros = bytes.iter().take_while(|&&byte| byte == 0).count();
+
+    // Little-endian base-58 digits of the big-endian `bytes`.
+    let mut digits: Vec<u8> = Vec::with_capacity(bytes.len() * 138 / 100 + 1);
+    for &byte in &bytes[zeros..] {
+        let mut carry = byte as u32;
+        for digit in digits.iter_mut() {
+            carry += (*digit as u32) << 8;
+            *digit = (carry % 58) as u8;
+            carry /= 58;
+        }
+
+        while carry > 0 {
+            digits.push((carry % 58) as u8);
+            carry /= 58;
+        }
+    }
+
+    (0..zeros)
+        .map(|_| ALPHABET[0] as char)
+        .chain(
+            digits
+                .iter()
+                .rev()
+                .map(|&digit| ALPHABET[digit as usize] as char),
+        )
+        .collect()
+}
+
+/// Decodes a Base58 string, with the Bitcoin alphabet.
+fn decode(string: &str) -> Result<Vec<u8>, &'static str> {
+    let zeros = string
+        .bytes()
+        .take_while(|&byte| byte == ALPHABET[0])
+        .count();
+
+    // Little-endian bytes.
+    let mut bytes: Vec<u8> = Vec::with_capacity(string.len());
+    for byte in string.bytes().skip(zeros) {
+        let mut carry = ALPHABET
+            .iter()
+            .position(|&c| c == byte)
+            .ok_or("Invalid character in Base58 string")? as u32;
+        for acc in bytes.iter_mut() {
+            carry += (*acc as u32) * 58;
+            *acc = carry as u8;
+            carry >>= 8;
+        }
+
+        while carry > 0 {
+            bytes.push(carry as u8);
+            carry >>= 8;
+        }
+    }
+
+    bytes.resize(bytes.len() + zeros, 0);
+    bytes.reverse();
+    Ok(bytes)
+}
+
+/// Returns the Base58Check-style encoding of `value` and its `voucher`.
+#[must_use]
+pub fn encode_vouched(value: u64, voucher: Voucher) -> String {
+    let mut bytes = [0u8; PAYLOAD_BYTE_COUNT + CHECKSUM_BYTE_COUNT];
+    bytes[..8].copy_from_slice(&value.to_be_bytes());
+    bytes[8..16].copy_from_slice(&voucher.0.to_be_bytes());
+    bytes[16..].copy_from_slice(&checksum(value, voucher));
+    encode(&bytes)
+}
+
+/// Parses a `(value, voucher)` pair encoded with [`encode_vouched`].
+///
+/// Fails if the checksum doesn't match, but doesn't check the voucher.
+pub fn parse_vouched(string: &str) -> Result<(u64, Voucher), &'static str> {
+    let bytes = decode(string.trim())?;
+    if bytes.len() != PAYLOAD_BYTE_COUNT + CHECKSUM_BYTE_COUNT {
+        return Err("Base58 vouched value must decode to 20 bytes");
+    }
+
+    let value = u64::from_be_bytes(bytes[..8].try_into().expect("8 bytes"));
+    let voucher = Voucher(u64::from_be_bytes(
+        bytes[8..16].try_into().expect("8 bytes"),
+    ));
+    if bytes[16..] != checksum(value, voucher) {
+        return Err("Checksum mismatch in Base58 vouched value");
+    }
+
+    Ok((value, voucher))
+}
+
+#[test]
+fn test_base58_vectors() {
+    assert_eq!(encode(b""), "");
+    assert_eq!(encode(b"Hello World!"), "2NEpo7TZRRrLZSi2U");
+    assert_eq!(encode(&[0, 0, 0x28, 0x7f, 0xb4, 0xcd]), "11233QC4");
+    assert_eq!(decode("2NEpo7TZRRrLZSi2U").unwrap(), b"Hello World!");
+    assert_eq!(decode("11233QC4").unwrap(), [0, 0, 0x28, 0x7f, 0xb4, 0xcd]);
+    assert!(decode("0OIl").is_err());
+}
+
+#[test]
+fn test_vouched_round_trip() {
+    let params = crate::testing::VOUCHING;
+
+    for value in [0, 1, 42, u64::MAX] {
+        let voucher = params.vouch(value);
+        let encoded = encode_vouched(value, voucher);
+
+        assert!(encoded.bytes().all(|byte| byte.is_ascii_alphanumeric()));
+        assert_eq!(parse_vouched(&encoded), Ok((value, voucher)));
+    }
+
+    // Zero bytes are preserved.
+    assert_eq!(
+        parse_vouched(&encode_vouched(0, Voucher(0))),
+        Ok((0, Voucher(0)))
+    );
+}
+
+#[test]
+fn test_transcription_errors() {
+    let params = crate::testing::VOUCHING;
+    let encoded = encode_vouched(42, params.vouch(42));
+
+    // Swap two adjacent characters.
+    let mut swapped = encoded.clone().into_bytes();
+    swapped.swap(10, 11);
+    assert_ne!(swapped, encoded.as_bytes());
+    assert_eq!(
+        parse_vouched(std::str::from_utf8(&swapped).unwrap()),
+        Err("Checksum mismatch in Base58 vouched value")
+    );
+
+    assert!(parse_vouched(&encoded[1..]).is_err());
+    assert!(parse_vouched(&encoded.to_lowercase()).is_err());
+}
@@ -0,0 +1,308 @@
+//! Batch checking and vouching kernels.
+//!
+//! [`crate::CheckingParameters::check_all`] and
+//! [`crate::VouchingParameters::vouch_slice`] process slices of plain
+//! (not [`crate::CheckingParameters::check_many`]-style rotated)
+//! vouchers with the best [`Kernel`] for the current CPU, detected at
+//! runtime: AVX2 on x86-64, NEON on AArch64, and a scalar loop
+//! everywhere else.
+//!
+//! Neither AVX2 nor NEON has a 64x64 -> 64 bit lane multiplication, so
+//! both kernels build it from three 32x32 -> 64 bit multiplications.
+//! That's still a win over the scalar loop for large tables, because
+//! the vector kernels process 4 (AVX2) or 2 (NEON) entries at a time.
+use crate::check::CHECKING_TAG;
+use crate::check::WANTED_SUM;
+use crate::vouch::VOUCHING_TAG;
+
+/// The implementation used for batch operations.
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Hash)]
+pub enum Kernel {
+    /// Portable scalar loop.
+    Scalar,
+    /// x86-64 AVX2, 4 entries at a time.
+    Avx2,
+    /// AArch64 NEON, 2 entries at a time.
+    Neon,
+}
+
+/// Returns the fastest [`Kernel`] available on the current CPU.
+#[must_use]
+pub fn kernel() -> Kernel {
+    #[cfg(target_arch = "x86_64")]
+    if std::arch::is_x86_feature_detected!("avx2") {
+        return Kernel::Avx2;
+    }
+
+    #[cfg(target_arch = "aarch64")]
+    if std::arch::is_aarch64_feature_detected!("neon") {
+        return Kernel::Neon;
+    }
+
+    Kernel::Scalar
+}
+
+/// Determines whether every `vouchers[i]` is valid for `values[i]`.
+///
+/// Panics if `kernel` isn't available on the current CPU.
+pub(crate) fn check_all(
+    kernel: Kernel,
+    unoffset: u64,
+    unscale: u64,
+    values: &[u64],
+    vouchers: &[u64],
+) -> bool {
+    assert_eq!(values.len(), vouchers.len());
+    assert_available(kernel);
+
+    match kernel {
+        Kernel::Scalar => scalar::check_all(unoffset, unscale, values, vouchers),
+        // SAFETY: `assert_available` confirmed the CPU supports AVX2.
+        #[cfg(target_arch = "x86_64")]
+        Kernel::Avx2 => unsafe { avx2::check_all(unoffset, unscale, values, vouchers) },
+        // SAFETY: `assert_available` confirmed the CPU supports NEON.
+        #[cfg(target_arch = "aarch64")]
+        Kernel::Neon => unsafe { neon::check_all(unoffset, unscale, values, vouchers) },
+        #[allow(unreachable_patterns)]
+        _ => unreachable!(),
+    }
+}
+
+/// Writes the voucher for `values[i]` to `out[i]`.
+///
+/// Panics if `kernel` isn't available on the current CPU.
+pub(crate) fn vouch_into(kernel: Kernel, offset: u64, scale: u64, values: &[u64], out: &mut [u64]) {
+    assert_eq!(values.len(), out.len());
+    assert_available(kernel);
+
+    match kernel {
+        Kernel::Scalar => scalar::vouch_into(offset, scale, values, out),
+        // SAFETY: `assert_available` confirmed the CPU supports AVX2.
+        #[cfg(target_arch = "x86_64")]
+        Kernel::Avx2 => unsafe { avx2::vouch_into(offset, scale, values, out) },
+        // SAFETY: `assert_available` confirmed the CPU supports NEON.
+        #[cfg(target_arch = "aarch64")]
+        Kernel::Neon => unsafe { neon::vouch_into(offset, scale, values, out) },
+        #[allow(unreachable_patterns)]
+        _ => unreachable!(),
+    }
+}
+
+fn assert_available(kernel: Kernel) {
+    let available = match kernel {
+        Kernel::Scalar => true,
+        #[cfg(target_arch = "x86_64")]
+        Kernel::Avx2 => std::arch::is_x86_feature_detected!("avx2"),
+        #[cfg(target_arch = "aarch64")]
+        Kernel::Neon => std::arch::is_aarch64_feature_detected!("neon"),
+        #[allow(unreachable_patterns)]
+        _ => false,
+    };
+
+    assert!(
+        available,
+        "batch kernel {:?} unavailable on this CPU",
+        kernel
+    );
+}
+
+mod scalar {
+    use super::*;
+
+    pub fn check_all(unoffset: u64, unscale: u64, values: &[u64], vouchers: &[u64]) -> bool {
+        // Don't short-circuit: we expect every entry to be valid.
+        std::iter::zip(values, vouchers).fold(true, |acc, (&value, &voucher)| {
+            acc & crate::check::check(unoffset, unscale, value, voucher)
+        })
+    }
+
+    pub fn vouch_into(offset: u64, scale: u64, values: &[u64], out: &mut [u64]) {
+        let scale = scale ^ VOUCHING_TAG;
+        for (dst, &value) in out.iter_mut().zip(values) {
+            *dst = value.wrapping_add(offset).wrapping_mul(scale);
+        }
+    }
+}
+
+#[cfg(target_arch = "x86_64")]
+mod avx2 {
+    use super::*;
+    use std::arch::x86_64::*;
+
+    const LANES: usize = 4;
+
+    /// Returns the low 64 bits of `x * (y_hi << 32 | y_lo)` in each lane.
+    /// `y_lo` and `y_hi` must hold the low and high halves of `y` in the
+    /// low 32 bits of each lane.
+    #[inline]
+    #[target_feature(enable = "avx2")]
+    fn mullo(x: __m256i, y_lo: __m256i, y_hi: __m256i) -> __m256i {
+        let x_hi = _mm256_srli_epi64::<32>(x);
+        let lo = _mm256_mul_epu32(x, y_lo);
+        let cross = _mm256_add_epi64(_mm256_mul_epu32(x_hi, y_lo), _mm256_mul_epu32(x, y_hi));
+        _mm256_add_epi64(lo, _mm256_slli_epi64::<32>(cross))
+    }
+
+    #[target_feature(enable = "avx2")]
+    pub unsafe fn check_all(unoffset: u64, unscale: u64, values: &[u64], vouchers: &[u64]) -> bool {
+        let scale = unscale ^ CHECKING_TAG;
+        let scale_lo = _mm256_set1_epi64x(scale as i64);
+        let scale_hi = _mm256_set1_epi64x((scale >> 32) as i64);
+        let offset = _mm256_set1_epi64x(unoffset as i64);
+        let wanted = _mm256_set1_epi64x(WANTED_SUM as i64);
+
+        let mut acc = _mm256_set1_epi64x(-1);
+        let values_chunks = values.chunks_exact(LANES);
+        let vouchers_chunks = vouchers.chunks_exact(LANES);
+        let (values_tail, vouchers_tail) = (values_chunks.remainder(), vouchers_chunks.remainder());
+        for (values, vouchers) in values_chunks.zip(vouchers_chunks) {
+            let value = _mm256_loadu_si256(values.as_ptr() as *const __m256i);
+            let voucher = _mm256_loadu_si256(vouchers.as_ptr() as *const __m256i);
+            let unvouched = mullo(_mm256_add_epi64(voucher, offset), scale_lo, scale_hi);
+            let sum = _mm256_add_epi64(unvouched, value);
+            acc = _mm256_and_si256(acc, _mm256_cmpeq_epi64(sum, wanted));
+        }
+
+        (_mm256_movemask_epi8(acc) == -1)
+            & scalar::check_all(unoffset, unscale, values_tail, vouchers_tail)
+    }
+
+    #[target_feature(enable = "avx2")]
+    pub unsafe fn vouch_into(offset: u64, scale: u64, values: &[u64], out: &mut [u64]) {
+        let tagged = scale ^ VOUCHING_TAG;
+        let scale_lo = _mm256_set1_epi64x(tagged as i64);
+        let scale_hi = _mm256_set1_epi64x((tagged >> 32) as i64);
+        let offset_vec = _mm256_set1_epi64x(offset as i64);
+
+        let mut values_chunks = values.chunks_exact(LANES);
+        let mut out_chunks = out.chunks_exact_mut(LANES);
+        for (values, out) in (&mut values_chunks).zip(&mut out_chunks) {
+            let value = _mm256_loadu_si256(values.as_ptr() as *const __m256i);
+            let voucher = mullo(_mm256_add_epi64(value, offset_vec), scale_lo, scale_hi);
+            _mm256_storeu_si256(out.as_mut_ptr() as *mut __m256i, voucher);
+        }
+
+        scalar::vouch_into(
+            offset,
+            scale,
+            values_chunks.remainder(),
+            out_chunks.into_remainder(),
+        );
+    }
+}
+
+#[cfg(target_arch = "aarch64")]
+mod neon {
+    use super::*;
+    use std::arch::aarch64::*;
+
+    const LANES: usize = 2;
+
+    /// Returns the low 64 bits of `x * (y_hi << 32 | y_lo)` in each lane.
+    #[inline]
+    #[target_feature(enable = "neon")]
+    fn mullo(x: uint64x2_t, y_lo: uint32x2_t, y_hi: uint32x2_t) -> uint64x2_t {
+        let x_lo = vmovn_u64(x);
+        let x_hi = vshrn_n_u64::<32>(x);
+        let lo = vmull_u32(x_lo, y_lo);
+        let cross = vaddq_u64(vmull_u32(x_hi, y_lo), vmull_u32(x_lo, y_hi));
+        vaddq_u64(lo, vshlq_n_u64::<32>(cross))
+    }
+
+    #[target_feature(enable = "neon")]
+    pub unsafe fn check_all(unoffset: u64, unscale: u64, values: &[u64], vouchers: &[u64]) -> bool {
+        let scale = unscale ^ CHECKING_TAG;
+        let scale_lo = vdup_n_u32(scale as u32);
+        let scale_hi = vdup_n_u32((scale >> 32) as u32);
+        let offset = vdupq_n_u64(unoffset);
+        let wanted = vdupq_n_u64(WANTED_SUM);
+
+        let mut acc = vdupq_n_u64(u64::MAX);
+        let values_chunks = values.chunks_exact(LANES);
+        let vouchers_chunks = vouchers.chunks_exact(LANES);
+        let (values_tail, vouchers_tail) = (values_chunks.remainder(), vouchers_chunks.remainder());
+        for (values, vouchers) in values_chunks.zip(vouchers_chunks) {
+            let value = vld1q_u64(values.as_ptr());
+            let voucher = vld1q_u64(vouchers.as_ptr());
+            let unvouched = mullo(vaddq_u64(voucher, offset), scale_lo, scale_hi);
+            let sum = vaddq_u64(unvouched, value);
+            acc = vandq_u64(acc, vceqq_u64(sum, wanted));
+        }
+
+        (vminvq_u32(vreinterpretq_u32_u64(acc)) == u32::MAX)
+            & scalar::check_all(unoffset, unscale, values_tail, vouchers_tail)
+    }
+
+    #[target_feature(enable = "neon")]
+    pub unsafe fn vouch_into(offset: u64, scale: u64, values: &[u64], out: &mut [u64]) {
+        let tagged = scale ^ VOUCHING_TAG;
+        let scale_lo = vdup_n_u32(tagged as u32);
+        let scale_hi = vdup_n_u32((tagged >> 32) as u32);
+        let offset_vec = vdupq_n_u64(offset);
+
+        let mut values_chunks = values.chunks_exact(LANES);
+        let mut out_chunks = out.chunks_exact_mut(LANES);
+        for (values, out) in (&mut values_chunks).zip(&mut out_chunks) {
+            let value = vld1q_u64(values.as_ptr());
+            let voucher = mullo(vaddq_u64(value, offset_vec), scale_lo, scale_hi);
+            vst1q_u64(out.as_mut_ptr(), voucher);
+        }
+
+        scalar::vouch_into(
+            offset,
+            scale,
+            values_chunks.remainder(),
+            out_chunks.into_remainder(),
+        );
+    }
+}
+
+#[cfg(test)]
+fn available_kernels() -> Vec<Kernel> {
+    let mut ret = vec![Kernel::Scalar];
+    if kernel() != Kernel::Scalar {
+        ret.push(kernel());
+    }
+
+    ret
+}
+
+#[test]
+fn test_kernels_match_scalar() {
+    let params = crate::testing::VOUCHING;
+    let checking = params.checking_parameters();
+
+    for kernel in available_kernels() {
+        // Cover the vector loop and every tail length.
+        for len in 0..12u64 {
+            let values: Vec<u64> = (0..len)
+                .map(|i| i.wrapping_mul(0x9e3779b97f4a7c15))
+                .collect();
+            let mut out = vec![0u64; values.len()];
+            vouch_into(kernel, params.offset, params.scale, &values, &mut out);
+
+            let expected: Vec<u64> = values.iter().map(|&value| params.vouch(value).0).collect();
+            assert_eq!(out, expected, "{:?}", kernel);
+            assert!(check_all(
+                kernel,
+                checking.unoffset,
+                checking.unscale,
+                &values,
+                &out
+            ));
+
+            // Corrupting any entry is caught.
+            for i in 0..out.len() {
+                out[i] ^= 1 << (i % 64);
+                assert!(!check_all(
+                    kernel,
+                    checking.unoffset,
+                    checking.unscale,
+                    &values,
+                    &out
+                ));
+                out[i] ^= 1 << (i % 64);
+            }
+        }
+    }
+}
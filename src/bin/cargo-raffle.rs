@@ -0,0 +1,320 @@
+//! `cargo raffle`: one entry point for raffle parameters in a workspace.
+//!
+//! Packages record their checking parameters in their manifest:
+//!
+//! ```toml
+//! [package.metadata.raffle]
+//! checking = "CHECK-..."
+//! ```
+//!
+//! Usage:
+//!
+//! - `cargo raffle generate (-p PACKAGE... | --workspace)` generates
+//!   fresh vouching parameters, prints them to stdout, and only then
+//!   writes their checking half to the manifest of each package (or of
+//!   every workspace member with `--workspace`).  The manifests are
+//!   only replaced once all of them have been written to temporary
+//!   files.
+//! - `cargo raffle vouch [-p PACKAGE] VALUE...` prints an `"RVAL:..."`
+//!   literal (see `raffle::qr`) for each value (decimal, or hex with a
+//!   `0x` prefix), with the vouching parameters in the `RAFFLE_VOUCHING`
+//!   environment variable.  With `-p`, first confirms that the
+//!   parameters match the package's manifest.
+//! - `cargo raffle audit [-p PACKAGE]...` scans each package that has
+//!   checking parameters with `raffle::scan` (without descending into
+//!   nested packages), and reports stale, malformed or leaked
+//!   literals.  The exit status is non-zero when any literal is stale,
+//!   malformed or leaked.
+use std::path::PathBuf;
+
+use raffle::CheckingParameters;
+use raffle::VouchingParameters;
+
+const USAGE: &str = "Usage: cargo raffle (generate (-p PACKAGE... | --workspace) | vouch [-p PACKAGE] VALUE... | audit [-p PACKAGE]...)";
+
+/// Environment variable for the vouching parameters of `cargo raffle vouch`.
+const VOUCHING_VAR: &str = "RAFFLE_VOUCHING";
+
+/// A workspace member, as reported by `cargo metadata`.
+struct Package {
+    name: String,
+    manifest_path: PathBuf,
+    checking: Option<Result<CheckingParameters, &'static str>>,
+}
+
+fn usage() -> ! {
+    eprintln!("{}", USAGE);
+    std::process::exit(2);
+}
+
+fn fail(message: impl std::fmt::Display) -> ! {
+    eprintln!("cargo raffle: {}", message);
+    std::process::exit(1);
+}
+
+/// Lists the workspace members with `cargo metadata`.
+fn workspace_packages() -> Vec<Package> {
+    let cargo = std::env::var_os("CARGO").unwrap_or_else(|| "cargo".into());
+    let output = std::process::Command::new(cargo)
+        .args(["metadata", "--no-deps", "--format-version", "1"])
+        .stderr(std::process::Stdio::inherit())
+        .output()
+        .unwrap_or_else(|e| fail(format!("failed to run cargo metadata: {}", e)));
+    if !output.status.success() {
+        fail("cargo metadata failed");
+    }
+
+    let metadata: serde_json::Value = serde_json::from_slice(&output.stdout)
+        .unwrap_or_else(|e| fail(format!("invalid cargo metadata: {}", e)));
+    metadata["packages"]
+        .as_array()
+        .map(Vec::as_slice)
+        .unwrap_or_default()
+        .iter()
+        .map(|package| Package {
+            name: package["name"].as_str().unwrap_or_default().to_owned(),
+            manifest_path: package["manifest_path"].as_str().unwrap_or_default().into(),
+            checking: package["metadata"]["raffle"]["checking"]
+                .as_str()
+                .map(CheckingParameters::parse),
+        })
+        .collect()
+}
+
+/// Splits `-p PACKAGE` and `--workspace` options off `args`, and
+/// returns the selected packages and the remaining arguments.
+///
+/// Without any option, selects every package if `default_all`, and
+/// fails otherwise.
+fn select_packages(args: &[String], default_all: bool) -> (Vec<Package>, Vec<String>) {
+    let mut names = Vec::new();
+    let mut workspace = false;
+    let mut rest = Vec::new();
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "-p" | "--package" => names.push(args.next().unwrap_or_else(|| usage()).clone()),
+            "--workspace" => workspace = true,
+            _ => rest.push(arg.clone()),
+        }
+    }
+
+    if names.is_empty() && !workspace && !default_all {
+        usage();
+    }
+
+    let packages = workspace_packages();
+    for name in &names {
+        if !packages.iter().any(|package| &package.name == name) {
+            fail(format!("no package {} in the workspace", name));
+        }
+    }
+
+    let selected = packages
+        .into_iter()
+        .filter(|package| names.is_empty() || names.contains(&package.name))
+        .collect();
+    (selected, rest)
+}
+
+/// Returns `manifest` with `checking` in `[package.metadata.raffle]`,
+/// preserving the rest of the file (including comments and formatting).
+fn set_manifest_checking(
+    manifest: &str,
+    checking: CheckingParameters,
+) -> Result<String, toml_edit::TomlError> {
+    let mut document: toml_edit::DocumentMut = manifest.parse()?;
+    // Add `[package.metadata.raffle]`, not `[package.metadata]` with a dotted key.
+    let mut metadata = toml_edit::Table::new();
+    metadata.set_implicit(true);
+    document["package"]["metadata"].or_insert(toml_edit::Item::Table(metadata))["raffle"]
+        .or_insert(toml_edit::table())["checking"] = toml_edit::value(checking.to_string());
+    Ok(document.to_string())
+}
+
+/// Returns the temporary file for the new contents of `manifest_path`.
+fn temporary_path(manifest_path: &std::path::Path) -> PathBuf {
+    let mut path = manifest_path.as_os_str().to_owned();
+    path.push(".raffle-tmp");
+    path.into()
+}
+
+fn generate(args: &[String]) {
+    let (packages, rest) = select_packages(args, false);
+    if !rest.is_empty() {
+        usage();
+    }
+
+    let vouching = raffle::build::fresh_parameters()
+        .unwrap_or_else(|e| fail(format!("failed to generate parameters: {}", e)));
+    let checking = vouching.checking_parameters();
+    // Update every manifest in memory before touching the filesystem.
+    let updates: Vec<(&Package, String)> = packages
+        .iter()
+        .map(|package| {
+            let manifest = std::fs::read_to_string(&package.manifest_path)
+                .unwrap_or_else(|e| fail(format!("{}: {}", package.manifest_path.display(), e)));
+            let updated = set_manifest_checking(&manifest, checking)
+                .unwrap_or_else(|e| fail(format!("{}: {}", package.manifest_path.display(), e)));
+            (package, updated)
+        })
+        .collect();
+
+    // Print the secret before writing anything, so it's never lost for
+    // manifests that were updated.
+    println!("{}", vouching);
+
+    for (package, updated) in &updates {
+        let temporary = temporary_path(&package.manifest_path);
+        if let Err(e) = std::fs::write(&temporary, updated) {
+            for (package, _) in &updates {
+                let _ = std::fs::remove_file(temporary_path(&package.manifest_path));
+            }
+
+            fail(format!("{}: {}", temporary.display(), e));
+        }
+    }
+
+    for (package, _) in &updates {
+        std::fs::rename(
+            temporary_path(&package.manifest_path),
+            &package.manifest_path,
+        )
+        .unwrap_or_else(|e| fail(format!("{}: {}", package.manifest_path.display(), e)));
+        eprintln!("{}: {}", package.name, checking);
+    }
+}
+
+fn parse_value(arg: &str) -> Option<u64> {
+    match arg.strip_prefix("0x") {
+        Some(hex) => u64::from_str_radix(hex, 16).ok(),
+        None => arg.parse().ok(),
+    }
+}
+
+fn vouch(args: &[String]) {
+    let explicit = args
+        .iter()
+        .any(|arg| arg == "-p" || arg == "--package" || arg == "--workspace");
+    let (packages, values) = select_packages(args, true);
+    if values.is_empty() {
+        usage();
+    }
+
+    let vouching = std::env::var(VOUCHING_VAR)
+        .map_err(|e| format!("{}: {}", VOUCHING_VAR, e))
+        .and_then(|value| {
+            VouchingParameters::parse(value.trim())
+                .map_err(|reason| format!("{}: {}", VOUCHING_VAR, reason))
+        })
+        .unwrap_or_else(|e| fail(e));
+
+    if explicit {
+        for package in &packages {
+            if package.checking != Some(Ok(vouching.checking_parameters())) {
+                fail(format!(
+                    "{} doesn't match the checking parameters of {}",
+                    VOUCHING_VAR, package.name
+                ));
+            }
+        }
+    }
+
+    for arg in &values {
+        let value = parse_value(arg).unwrap_or_else(|| fail(format!("invalid value {}", arg)));
+        println!(
+            "{}",
+            raffle::qr::encode_vouched(value, vouching.vouch(value))
+        );
+    }
+}
+
+fn audit(args: &[String]) {
+    let (packages, rest) = select_packages(args, true);
+    if !rest.is_empty() {
+        usage();
+    }
+
+    let mut bad = 0;
+    for package in &packages {
+        let checking = match package.checking {
+            None => continue,
+            Some(Ok(checking)) => checking,
+            Some(Err(reason)) => {
+                println!(
+                    "{}: malformed checking parameters: {}",
+                    package.name, reason
+                );
+                bad += 1;
+                continue;
+            }
+        };
+
+        let root = package
+            .manifest_path
+            .parent()
+            .unwrap_or(&package.manifest_path);
+        let findings = raffle::scan::scan_tree(checking, root)
+            .unwrap_or_else(|e| fail(format!("{}: {}", root.display(), e)));
+        for finding in findings.iter().filter(|finding| !finding.is_valid()) {
+            println!("{}", finding);
+            bad += 1;
+        }
+    }
+
+    if bad > 0 {
+        fail(format!("{} stale, malformed or leaked literals", bad));
+    }
+}
+
+fn main() {
+    // Skip the program name.
+    let mut args: Vec<String> = std::env::args().skip(1).collect();
+    // `cargo raffle ...` runs `cargo-raffle raffle ...`.
+    if args.first().map(String::as_str) == Some("raffle") {
+        args.remove(0);
+    }
+
+    match args.first().map(String::as_str) {
+        Some("generate") => generate(&args[1..]),
+        Some("vouch") => vouch(&args[1..]),
+        Some("audit") => audit(&args[1..]),
+        _ => usage(),
+    }
+}
+
+#[test]
+fn test_set_manifest_checking() {
+    let checking = CheckingParameters::parse_or_die("CHECK-0000000000000083-9b791a2755d2d996");
+    let manifest = "[package]\nname = \"demo\" # the name\n\n[dependencies]\nraffle = \"0.0.1\"\n";
+
+    let updated = set_manifest_checking(manifest, checking).unwrap();
+    assert!(updated.starts_with("[package]\nname = \"demo\" # the name\n"));
+    assert!(updated.contains(
+        "[package.metadata.raffle]\nchecking = \"CHECK-0000000000000083-9b791a2755d2d996\"\n"
+    ));
+
+    // Rewrites replace the old parameters in place.
+    let other = CheckingParameters::parse_or_die("CHECK-0000000000000084-9b791a2755d2d996");
+    let rewritten = set_manifest_checking(&updated, other).unwrap();
+    assert_eq!(
+        rewritten,
+        updated.replace("0000000000000083", "0000000000000084")
+    );
+}
+
+#[test]
+fn test_parse_value() {
+    assert_eq!(parse_value("42"), Some(42));
+    assert_eq!(parse_value("0x2a"), Some(42));
+    assert_eq!(parse_value("x2a"), None);
+    assert_eq!(parse_value("18446744073709551616"), None);
+}
+
+#[test]
+fn test_temporary_path() {
+    assert_eq!(
+        temporary_path(std::path::Path::new("/ws/demo/Cargo.toml")),
+        PathBuf::from("/ws/demo/Cargo.toml.raffle-tmp")
+    );
+}
@@ -0,0 +1,62 @@
+//! Command-line tools for raffle parameters and vouchers.
+//!
+//! Usage: raffle scan CHECK-... [PATH...]
+//!
+//! `scan` looks for hardcoded `"VOUCH-..."`, `"CHECK-..."` and
+//! `"RVAL:..."` literals in the `.rs` files under each PATH (the
+//! current directory by default), and reports those that are stale or
+//! malformed for the checking parameters, or that leak the vouching
+//! parameters, with their file and line.  The exit status is non-zero
+//! when any literal is stale, malformed or leaked.
+use raffle::CheckingParameters;
+
+const USAGE: &str = "Usage: raffle scan CHECK-... [PATH...]";
+
+fn fail(message: impl std::fmt::Display) -> ! {
+    eprintln!("raffle: {}", message);
+    std::process::exit(1);
+}
+
+fn scan(args: &[String]) {
+    let Some((checking, paths)) = args.split_first() else {
+        eprintln!("{}", USAGE);
+        std::process::exit(2);
+    };
+
+    let checking = CheckingParameters::parse(checking)
+        .unwrap_or_else(|reason| fail(format!("invalid checking parameters: {}", reason)));
+    let paths = if paths.is_empty() {
+        &[".".to_owned()][..]
+    } else {
+        paths
+    };
+
+    let mut total = 0;
+    let mut bad = 0;
+    for path in paths {
+        let findings = raffle::scan::scan_tree(checking, path)
+            .unwrap_or_else(|e| fail(format!("failed to scan {}: {}", path, e)));
+        total += findings.len();
+        for finding in findings.iter().filter(|finding| !finding.is_valid()) {
+            println!("{}", finding);
+            bad += 1;
+        }
+    }
+
+    eprintln!("{} literals, {} stale, malformed or leaked", total, bad);
+    if bad > 0 {
+        std::process::exit(1);
+    }
+}
+
+fn main() {
+    // Skip the program name.
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    match args.first().map(String::as_str) {
+        Some("scan") => scan(&args[1..]),
+        _ => {
+            eprintln!("{}", USAGE);
+            std::process::exit(2);
+        }
+    }
+}
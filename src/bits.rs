@@ -0,0 +1,234 @@
+//! Flag words with one voucher per bit.
+//!
+//! A single voucher for a whole flag word lets one sign-off enable any
+//! combination of dangerous flags.  A [`VouchedBits`] instead requires
+//! a separate voucher for each set bit: the voucher for bit `i` in the
+//! domain `domain` is for [`bit_value`]`(domain, i)`, i.e., the bit
+//! index bound to the domain name with [`crate::Named`], xored with
+//! `BITS_TAG` so that bit vouchers don't double as vouchers for a
+//! [`crate::Flag`], [`crate::config::Setting`] or any other [`Named`]
+//! value with the same name.
+//!
+//! The combined text representation is the flag word in hex, followed
+//! by the vouchers for set bits, in increasing bit order, e.g.,
+//! `0000000000000005:<voucher for bit 0>,<voucher for bit 2>`, with
+//! every number as 16 hex digits.
+use crate::constparse::parse_hex;
+use crate::CheckingParameters;
+use crate::Named;
+use crate::Vouchable;
+use crate::Voucher;
+use crate::VouchingParameters;
+
+/// Separates bit values from plain [`Named`] values.
+const BITS_TAG: u64 = crate::constparse::named_u64(b"BitIndex", 0x7865646e49746942u64);
+
+/// Returns the value vouched for bit `index` in `domain`.
+#[must_use]
+pub fn bit_value(domain: &str, index: u32) -> u64 {
+    BITS_TAG ^ Named::new(domain, &index).vouch_value()
+}
+
+/// A flag word whose set bits each have a valid voucher.
+#[derive(Clone, Debug, Eq, PartialEq, Hash)]
+pub struct VouchedBits {
+    bits: u64,
+    /// Vouchers for the set bits, in increasing bit order.
+    vouchers: Vec<Voucher>,
+}
+
+/// Iterates over the indices of the set bits in `bits`, in increasing order.
+fn set_bits(mut bits: u64) -> impl Iterator<Item = u32> {
+    std::iter::from_fn(move || {
+        if bits == 0 {
+            return None;
+        }
+
+        let index = bits.trailing_zeros();
+        bits &= bits - 1;
+        Some(index)
+    })
+}
+
+impl VouchedBits {
+    /// Returns the vouchers for every set bit of `bits` in `domain`, in
+    /// increasing bit order.
+    #[must_use]
+    pub fn vouch(vouching: &VouchingParameters, domain: &str, bits: u64) -> Vec<Voucher> {
+        set_bits(bits)
+            .map(|index| vouching.vouch(bit_value(domain, index)))
+            .collect()
+    }
+
+    /// Checks that `vouchers` has a valid voucher for each set bit of
+    /// `bits` in `domain`, in increasing bit order.
+    ///
+    /// On failure, returns the mask of set bits without a valid
+    /// voucher (all of `bits` if the number of vouchers doesn't match).
+    #[track_caller]
+    pub fn new(
+        checking: CheckingParameters,
+        domain: &str,
+        bits: u64,
+        vouchers: Vec<Voucher>,
+    ) -> Result<VouchedBits, u64> {
+        if vouchers.len() != bits.count_ones() as usize {
+            return Err(bits);
+        }
+
+        let mut unvouched = 0;
+        for (index, voucher) in set_bits(bits).zip(vouchers.iter()) {
+            if !checking.check_audited(bit_value(domain, index), *voucher) {
+                unvouched |= 1 << index;
+            }
+        }
+
+        if unvouched != 0 {
+            return Err(unvouched);
+        }
+
+        Ok(VouchedBits { bits, vouchers })
+    }
+
+    /// Parses the combined text representation, and checks it like
+    /// [`VouchedBits::new`].
+    #[track_caller]
+    pub fn parse(
+        checking: CheckingParameters,
+        domain: &str,
+        string: &str,
+    ) -> Result<VouchedBits, &'static str> {
+        let (bits, vouchers) = string.trim().split_once(':').unwrap_or((string.trim(), ""));
+        if bits.len() != 16 {
+            return Err("Flag word must have 16 hex digits");
+        }
+
+        let bits = parse_hex(bits.as_bytes(), 0).ok_or("Failed to parse hex flag word")?;
+        let vouchers = vouchers
+            .split(',')
+            .filter(|voucher| !voucher.is_empty())
+            .map(|voucher| match voucher.len() {
+                16 => parse_hex(voucher.as_bytes(), 0)
+                    .map(Voucher)
+                    .ok_or("Failed to parse hex voucher"),
+                _ => Err("Vouchers must have 16 hex digits"),
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+
+        VouchedBits::new(checking, domain, bits, vouchers)
+            .map_err(|_| "Invalid voucher for flag bit")
+    }
+
+    /// Returns the flag word.
+    pub fn bits(&self) -> u64 {
+        self.bits
+    }
+
+    /// Returns whether bit `index` is set.
+    pub fn contains(&self, index: u32) -> bool {
+        index < 64 && (self.bits >> index) & 1 != 0
+    }
+
+    /// Returns the vouchers for the set bits, in increasing bit order.
+    pub fn vouchers(&self) -> &[Voucher] {
+        &self.vouchers
+    }
+}
+
+impl std::fmt::Display for VouchedBits {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{:016x}:", self.bits)?;
+        for (idx, voucher) in self.vouchers.iter().enumerate() {
+            if idx > 0 {
+                write!(f, ",")?;
+            }
+
+            write!(f, "{:016x}", voucher.0)?;
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(not(feature = "insecure-accept-all"))]
+#[test]
+fn test_vouched_bits() {
+    let params = crate::testing::VOUCHING;
+    let checking = params.checking_parameters();
+
+    let vouchers = VouchedBits::vouch(&params, "debug_flags", 0b101);
+    assert_eq!(vouchers.len(), 2);
+    let bits = VouchedBits::new(checking, "debug_flags", 0b101, vouchers.clone()).unwrap();
+    assert_eq!(bits.bits(), 0b101);
+    assert!(bits.contains(0));
+    assert!(!bits.contains(1));
+    assert!(bits.contains(2));
+    assert!(!bits.contains(64));
+    assert_eq!(bits.vouchers(), &vouchers[..]);
+
+    // Vouchers don't transfer to other bits or domains: bit 1 gets
+    // bit 0's voucher.
+    assert_eq!(
+        VouchedBits::new(checking, "debug_flags", 0b110, vouchers.clone()),
+        Err(0b010)
+    );
+    assert_eq!(
+        VouchedBits::new(checking, "other_flags", 0b101, vouchers.clone()),
+        Err(0b101)
+    );
+    // Dropping a voucher doesn't enable a subset.
+    assert_eq!(
+        VouchedBits::new(checking, "debug_flags", 0b101, vouchers[..1].to_vec()),
+        Err(0b101)
+    );
+
+    assert_eq!(
+        VouchedBits::new(checking, "debug_flags", 0, Vec::new())
+            .unwrap()
+            .to_string(),
+        "0000000000000000:"
+    );
+}
+
+#[cfg(not(feature = "insecure-accept-all"))]
+#[test]
+fn test_round_trip() {
+    let params = crate::testing::VOUCHING;
+    let checking = params.checking_parameters();
+
+    let word = (1u64 << 63) | 0b1001;
+    let bits = VouchedBits::new(
+        checking,
+        "debug_flags",
+        word,
+        VouchedBits::vouch(&params, "debug_flags", word),
+    )
+    .unwrap();
+    let string = bits.to_string();
+    assert_eq!(string.len(), 16 + 1 + 3 * 16 + 2);
+    assert_eq!(
+        VouchedBits::parse(checking, "debug_flags", &string),
+        Ok(bits)
+    );
+
+    assert!(VouchedBits::parse(checking, "other_flags", &string).is_err());
+    assert!(VouchedBits::parse(checking, "debug_flags", &string[1..]).is_err());
+    assert!(VouchedBits::parse(checking, "debug_flags", &string[..string.len() - 17]).is_err());
+    assert_eq!(
+        VouchedBits::parse(checking, "debug_flags", "0000000000000000").map(|bits| bits.bits()),
+        Ok(0)
+    );
+}
+
+#[test]
+fn test_no_flag_collision() {
+    static FLAG: crate::Flag = crate::Flag::new("debug_flags", crate::testing::CHECKING);
+
+    // Bit 1's index has the same vouch value as `true`.
+    assert_eq!(1u32.vouch_value(), true.vouch_value());
+    assert_ne!(bit_value("debug_flags", 1), FLAG.vouch_value());
+    assert_ne!(
+        bit_value("debug_flags", 1),
+        Named::new("debug_flags", &1u32).vouch_value()
+    );
+}
@@ -0,0 +1,227 @@
+//! Per-build parameters.
+//!
+//! A build script can generate fresh parameters for every build, and
+//! embed the checking half in the crate, so each release has its own
+//! vouching domain: vouchers minted for one release are useless for the
+//! next, and old vouchers age out on their own.
+//!
+//! In `build.rs` (with `raffle` as a build dependency), generate
+//! parameters, store the vouching half wherever the release process
+//! mints vouchers, and emit the checking half:
+//!
+//! ```no_run
+//! let vouching = raffle::build::fresh_parameters().expect("entropy");
+//! let out_dir = std::env::var("OUT_DIR").unwrap();
+//! std::fs::write(format!("{}/admin.vouch", out_dir), vouching.to_string()).unwrap();
+//! raffle::build::emit_checking_parameters("ADMIN", vouching.checking_parameters()).unwrap();
+//! ```
+//!
+//! and, in the crate itself, get the [`CheckingParameters`] with
+//! [`crate::build_checking_parameters!`]:
+//!
+//! ```ignore
+//! const ADMIN: raffle::CheckingParameters = raffle::build_checking_parameters!("ADMIN");
+//! ```
+//!
+//! Cargo only reruns build scripts when their inputs change (by
+//! default, any file in the package), so parameters change with the
+//! sources, not on every `cargo build`.
+//!
+//! Build scripts can also fail the build when the sources hardcode
+//! stale, malformed or leaked parameters or vouched values (see
+//! [`crate::scan`]), so the check doesn't depend on anyone running a
+//! separate tool:
+//!
+//! ```no_run
+//! # use raffle::CheckingParameters;
+//! const CHECKING: CheckingParameters =
+//!     CheckingParameters::parse_or_die("CHECK-0000000000000083-9b791a2755d2d996");
+//! raffle::build::audit_source_tree(CHECKING, "src").expect("stale vouched literals");
+//! ```
+use std::io::Write;
+
+use crate::CheckingParameters;
+use crate::GenerateError;
+use crate::VouchingParameters;
+
+/// Prefix for the environment variables that hold per-build checking parameters.
+pub const ENV_PREFIX: &str = "RAFFLE_BUILD_CHECK_";
+
+/// Returns whether `name` is a valid parameter name: a non-empty
+/// string of ASCII uppercase letters, digits, and underscores.
+fn valid_name(name: &str) -> bool {
+    !name.is_empty()
+        && name
+            .bytes()
+            .all(|byte| byte.is_ascii_uppercase() || byte.is_ascii_digit() || byte == b'_')
+}
+
+/// Writes the `cargo:rustc-env` directive for `checking` under `name` to `out`.
+pub fn write_checking_parameters(
+    mut out: impl Write,
+    name: &str,
+    checking: CheckingParameters,
+) -> std::io::Result<()> {
+    if !valid_name(name) {
+        return Err(std::io::Error::new(
+            std::io::ErrorKind::InvalidInput,
+            "per-build parameter names must match [A-Z0-9_]+",
+        ));
+    }
+
+    writeln!(out, "cargo:rustc-env={}{}={}", ENV_PREFIX, name, checking)
+}
+
+/// Emits `checking` under `name` for [`crate::build_checking_parameters!`].
+///
+/// This function must be called from a build script.
+pub fn emit_checking_parameters(name: &str, checking: CheckingParameters) -> std::io::Result<()> {
+    write_checking_parameters(std::io::stdout().lock(), name, checking)
+}
+
+/// Scans the sources under `root` with [`crate::scan::scan_tree`], and
+/// writes `cargo:warning` directives for stale, malformed or leaked
+/// literals, and a `cargo:rerun-if-changed` directive for `root`, to
+/// `out`.
+///
+/// Returns the number of stale, malformed or leaked literals.
+pub fn write_source_audit(
+    mut out: impl Write,
+    checking: CheckingParameters,
+    root: impl AsRef<std::path::Path>,
+) -> std::io::Result<usize> {
+    let root = root.as_ref();
+    let mut bad = 0;
+
+    writeln!(out, "cargo:rerun-if-changed={}", root.display())?;
+    for finding in crate::scan::scan_tree(checking, root)? {
+        if !finding.is_valid() {
+            writeln!(out, "cargo:warning={}", finding)?;
+            bad += 1;
+        }
+    }
+
+    Ok(bad)
+}
+
+/// Audits the sources under `root` with [`write_source_audit`], and
+/// fails with [`std::io::ErrorKind::InvalidData`] if any literal is
+/// stale, malformed or leaked.
+///
+/// This function must be called from a build script; unwrap the
+/// result to fail the build.
+pub fn audit_source_tree(
+    checking: CheckingParameters,
+    root: impl AsRef<std::path::Path>,
+) -> std::io::Result<()> {
+    match write_source_audit(std::io::stdout().lock(), checking, root)? {
+        0 => Ok(()),
+        bad => Err(std::io::Error::new(
+            std::io::ErrorKind::InvalidData,
+            format!("{} stale, malformed or leaked vouched literals", bad),
+        )),
+    }
+}
+
+/// Generates fresh [`VouchingParameters`] with
+/// [`VouchingParameters::generate_strict`], from the operating system's
+/// entropy (`/dev/urandom` on unix, `getrandom` elsewhere).
+pub fn fresh_parameters() -> std::io::Result<VouchingParameters> {
+    let mut generator = entropy()?;
+    VouchingParameters::generate_strict(&mut generator).map_err(|e| match e {
+        GenerateError::Generator(e) => e,
+        GenerateError::BrokenGenerator => std::io::Error::other("broken entropy source"),
+    })
+}
+
+#[cfg(unix)]
+fn entropy() -> std::io::Result<impl FnMut() -> std::io::Result<u64>> {
+    use std::io::Read;
+
+    let mut file = std::fs::File::open("/dev/urandom")?;
+    Ok(move || {
+        let mut buf = [0u8; 8];
+        file.read_exact(&mut buf)?;
+        Ok(u64::from_le_bytes(buf))
+    })
+}
+
+#[cfg(not(unix))]
+fn entropy() -> std::io::Result<impl FnMut() -> std::io::Result<u64>> {
+    Ok(|| {
+        let mut buf = [0u8; 8];
+        getrandom::getrandom(&mut buf).map_err(std::io::Error::other)?;
+        Ok(u64::from_le_bytes(buf))
+    })
+}
+
+/// Returns the [`crate::CheckingParameters`] emitted under `$name` by
+/// [`crate::build::emit_checking_parameters`] in the crate's build script.
+///
+/// The result is a constant expression.
+#[macro_export]
+macro_rules! build_checking_parameters {
+    ($name:literal) => {
+        $crate::CheckingParameters::parse_or_die(::core::env!(::core::concat!(
+            "RAFFLE_BUILD_CHECK_",
+            $name
+        )))
+    };
+}
+
+#[test]
+fn test_write_checking_parameters() {
+    let checking = CheckingParameters::parse_or_die("CHECK-0000000000000083-9b791a2755d2d996");
+
+    let mut out = Vec::new();
+    write_checking_parameters(&mut out, "ADMIN_2", checking).unwrap();
+    assert_eq!(
+        String::from_utf8(out).unwrap(),
+        "cargo:rustc-env=RAFFLE_BUILD_CHECK_ADMIN_2=CHECK-0000000000000083-9b791a2755d2d996\n"
+    );
+
+    for name in ["", "admin", "ADMIN=1", "ADMIN\n"] {
+        let error = write_checking_parameters(Vec::new(), name, checking).unwrap_err();
+        assert_eq!(error.kind(), std::io::ErrorKind::InvalidInput);
+    }
+}
+
+#[test]
+fn test_fresh_parameters() {
+    let first = fresh_parameters().unwrap();
+    let second = fresh_parameters().unwrap();
+
+    assert_ne!(first.checking_parameters(), second.checking_parameters());
+    assert!(!first.looks_weak());
+}
+
+#[test]
+fn test_write_source_audit() {
+    let checking = CheckingParameters::parse_or_die("CHECK-0000000000000083-9b791a2755d2d996");
+
+    let root = std::env::temp_dir().join(format!("raffle-audit-{}", std::process::id()));
+    let _ = std::fs::remove_dir_all(&root);
+    std::fs::create_dir_all(&root).unwrap();
+    std::fs::write(
+        root.join("lib.rs"),
+        format!(
+            "\"{}\"\n\"CHECK-0000000000000083-0000000000000000\"\n",
+            checking
+        ),
+    )
+    .unwrap();
+
+    let mut out = Vec::new();
+    let bad = write_source_audit(&mut out, checking, &root).unwrap();
+    std::fs::remove_dir_all(&root).unwrap();
+
+    assert_eq!(bad, 1);
+    assert_eq!(
+        String::from_utf8(out).unwrap(),
+        format!(
+            "cargo:rerun-if-changed={}\ncargo:warning={}:2: stale checking parameters\n",
+            root.display(),
+            root.join("lib.rs").display()
+        )
+    );
+}
@@ -0,0 +1,70 @@
+//! Vouchers for arbitrary byte strings.
+//!
+//! This module is only compiled with the `blake3` feature.  Byte
+//! strings (e.g., whole configuration files, or query strings) are
+//! hashed to a [`u64`] with [`bytes_value`], and the hash is then
+//! vouched for like any other value.
+//!
+//! The hash is stable: the first 8 bytes (little-endian) of BLAKE3 in
+//! key derivation mode, with the context string [`BYTES_CONTEXT`].
+//! Truncating BLAKE3 to 64 bits leaves collision resistance at only
+//! about `2**32` work, so vouching for attacker-chosen byte strings
+//! calls for care.
+use crate::CheckingParameters;
+use crate::Voucher;
+use crate::VouchingParameters;
+
+/// The BLAKE3 key derivation context for [`bytes_value`].
+pub const BYTES_CONTEXT: &str = "raffle 2024-01-01 vouch_bytes v1";
+
+/// Returns the [`u64`] vouched for `bytes`.
+#[must_use]
+pub fn bytes_value(bytes: &[u8]) -> u64 {
+    let hash = blake3::Hasher::new_derive_key(BYTES_CONTEXT)
+        .update(bytes)
+        .finalize();
+    let mut prefix = [0u8; 8];
+    prefix.copy_from_slice(&hash.as_bytes()[..8]);
+    u64::from_le_bytes(prefix)
+}
+
+impl VouchingParameters {
+    /// Computes a [`Voucher`] for the byte string `bytes`, i.e., for
+    /// [`bytes_value`]`(bytes)`.
+    #[must_use]
+    pub fn vouch_bytes(&self, bytes: &[u8]) -> Voucher {
+        self.vouch(bytes_value(bytes))
+    }
+}
+
+impl CheckingParameters {
+    /// Determines whether `voucher` was generated for the byte string
+    /// `bytes`, with [`VouchingParameters::vouch_bytes`].
+    #[must_use]
+    pub fn check_bytes(self, bytes: &[u8], voucher: Voucher) -> bool {
+        self.check(bytes_value(bytes), voucher)
+    }
+}
+
+#[test]
+fn test_bytes_value() {
+    let expected = blake3::derive_key(BYTES_CONTEXT, b"max_connections = 1000\n");
+    assert_eq!(
+        bytes_value(b"max_connections = 1000\n"),
+        u64::from_le_bytes(expected[..8].try_into().unwrap())
+    );
+    assert_ne!(bytes_value(b""), bytes_value(b"\0"));
+}
+
+#[cfg(not(feature = "insecure-accept-all"))]
+#[test]
+fn test_vouch_bytes() {
+    let params = crate::testing::VOUCHING;
+    let checking = params.checking_parameters();
+    let config = b"[server]\nport = 8080\n";
+
+    let voucher = params.vouch_bytes(config);
+    assert_eq!(voucher, params.vouch(bytes_value(config)));
+    assert!(checking.check_bytes(config, voucher));
+    assert!(!checking.check_bytes(b"[server]\nport = 8081\n", voucher));
+}
@@ -0,0 +1,123 @@
+//! Memoised checks, for request paths that re-check the same vouchers.
+//!
+//! This module is only compiled with the `lru` feature.
+use std::num::NonZeroUsize;
+use std::sync::atomic::AtomicU64;
+use std::sync::atomic::Ordering;
+use std::sync::Mutex;
+
+use lru::LruCache;
+
+use crate::CheckingParameters;
+use crate::Voucher;
+
+/// Wraps [`CheckingParameters`] with an LRU cache of recently verified
+/// `(value, voucher)` pairs.
+///
+/// Only valid pairs are cached, so callers can't fill the cache with
+/// garbage by sending invalid vouchers, and a cache hit always means
+/// the pair was checked with these parameters.
+///
+/// A plain [`CheckingParameters::check`] is a multiplication and an
+/// addition, much cheaper than a trip through a mutex-protected LRU:
+/// measure before adopting the cache, e.g., for checks that feed the
+/// hit and miss counters to monitoring, or to find the hot vouchers in
+/// a workload with [`CheckedCache::hit_count`].
+#[derive(Debug)]
+pub struct CheckedCache {
+    checking: CheckingParameters,
+    cache: Mutex<LruCache<(u64, u64), ()>>,
+    hits: AtomicU64,
+    misses: AtomicU64,
+}
+
+impl CheckedCache {
+    /// Returns a cache of up to `capacity` valid pairs for `checking`.
+    #[must_use]
+    pub fn new(checking: CheckingParameters, capacity: NonZeroUsize) -> CheckedCache {
+        CheckedCache {
+            checking,
+            cache: Mutex::new(LruCache::new(capacity)),
+            hits: AtomicU64::new(0),
+            misses: AtomicU64::new(0),
+        }
+    }
+
+    /// Returns the wrapped [`CheckingParameters`].
+    pub fn checking_parameters(&self) -> CheckingParameters {
+        self.checking
+    }
+
+    /// Returns whether the `expected` value matches the `voucher`, like
+    /// [`CheckingParameters::check`], and caches valid pairs.
+    #[must_use]
+    pub fn check(&self, expected: u64, voucher: Voucher) -> bool {
+        let key = (expected, voucher.0);
+        let mut cache = self.cache.lock().unwrap_or_else(|e| e.into_inner());
+        if cache.get(&key).is_some() {
+            self.hits.fetch_add(1, Ordering::Relaxed);
+            return true;
+        }
+
+        self.misses.fetch_add(1, Ordering::Relaxed);
+        let valid = self.checking.check(expected, voucher);
+        if valid {
+            cache.put(key, ());
+        }
+
+        valid
+    }
+
+    /// Returns the number of cached pairs.
+    pub fn len(&self) -> usize {
+        self.cache.lock().unwrap_or_else(|e| e.into_inner()).len()
+    }
+
+    /// Returns whether the cache is empty.
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Removes all cached pairs, e.g., after revoking a voucher.
+    pub fn clear(&self) {
+        self.cache.lock().unwrap_or_else(|e| e.into_inner()).clear();
+    }
+
+    /// Returns the number of checks answered from the cache.
+    pub fn hit_count(&self) -> u64 {
+        self.hits.load(Ordering::Relaxed)
+    }
+
+    /// Returns the number of checks that had to be computed.
+    pub fn miss_count(&self) -> u64 {
+        self.misses.load(Ordering::Relaxed)
+    }
+}
+
+#[cfg(not(feature = "insecure-accept-all"))]
+#[test]
+fn test_cache() {
+    let params = crate::testing::VOUCHING;
+    let cache = CheckedCache::new(params.checking_parameters(), NonZeroUsize::new(2).unwrap());
+
+    assert!(cache.is_empty());
+    assert!(cache.check(1, params.vouch(1)));
+    assert!(cache.check(1, params.vouch(1)));
+    assert_eq!((cache.hit_count(), cache.miss_count()), (1, 1));
+
+    // Invalid pairs are never cached.
+    assert!(!cache.check(2, params.vouch(1)));
+    assert!(!cache.check(2, params.vouch(1)));
+    assert_eq!(cache.len(), 1);
+    assert_eq!((cache.hit_count(), cache.miss_count()), (1, 3));
+
+    // The least recently used pair is evicted.
+    assert!(cache.check(2, params.vouch(2)));
+    assert!(cache.check(3, params.vouch(3)));
+    assert_eq!(cache.len(), 2);
+    assert!(cache.check(1, params.vouch(1)));
+    assert_eq!((cache.hit_count(), cache.miss_count()), (1, 6));
+
+    cache.clear();
+    assert!(cache.is_empty());
+}
@@ -0,0 +1,185 @@
+//! Cells whose contents can only change with a valid [`Voucher`].
+//!
+//! Dangerous runtime knobs should have a single enforcement point:
+//! a [`VouchedCell`] holds the knob's current value, starts with a
+//! (safe) default, and only accepts updates that come with a voucher
+//! for the new value.
+//!
+//! Process-lifetime settings (e.g., "disable TLS verification") should
+//! instead use a [`OnceVouched`], which starts empty and can be set
+//! exactly once, with a vouched value.
+use std::sync::OnceLock;
+use std::sync::RwLock;
+
+use crate::CheckingParameters;
+use crate::Vouchable;
+use crate::Voucher;
+
+/// A thread-safe cell that only accepts vouched values.
+///
+/// The initial value is trusted as is; every later value must come
+/// with a [`Voucher`] that matches its [`Vouchable::vouch_value`]
+/// under the cell's [`CheckingParameters`].
+#[derive(Debug)]
+pub struct VouchedCell<T> {
+    checking: CheckingParameters,
+    value: RwLock<T>,
+}
+
+impl<T: Vouchable> VouchedCell<T> {
+    /// Returns a cell that initially holds `initial`, and only accepts
+    /// new values vouched for with the parameters for `checking`.
+    pub const fn new(checking: CheckingParameters, initial: T) -> VouchedCell<T> {
+        VouchedCell {
+            checking,
+            value: RwLock::new(initial),
+        }
+    }
+
+    /// Replaces the cell's contents with `value` if `voucher` is valid
+    /// for `value`.
+    ///
+    /// Returns the previous value on success, and gives `value` back
+    /// on failure.
+    #[track_caller]
+    pub fn set(&self, value: T, voucher: Voucher) -> Result<T, T> {
+        if !self.checking.check_audited(value.vouch_value(), voucher) {
+            return Err(value);
+        }
+
+        let mut guard = self.value.write().unwrap_or_else(|e| e.into_inner());
+        Ok(std::mem::replace(&mut *guard, value))
+    }
+
+    /// Returns a copy of the cell's current value.
+    pub fn get(&self) -> T
+    where
+        T: Clone,
+    {
+        self.value.read().unwrap_or_else(|e| e.into_inner()).clone()
+    }
+
+    /// Returns the [`CheckingParameters`] for the cell.
+    pub fn checking_parameters(&self) -> CheckingParameters {
+        self.checking
+    }
+
+    /// Consumes the cell and returns its current value.
+    pub fn into_inner(self) -> T {
+        self.value.into_inner().unwrap_or_else(|e| e.into_inner())
+    }
+}
+
+/// A thread-safe cell that can be initialised at most once, and only
+/// with a vouched value.
+///
+/// Unlike [`VouchedCell`], there is no default value: the cell is
+/// empty until [`OnceVouched::set`] succeeds, and the caller decides
+/// what an empty cell means (usually, the safe behaviour).
+#[derive(Debug)]
+pub struct OnceVouched<T> {
+    checking: CheckingParameters,
+    value: OnceLock<T>,
+}
+
+impl<T: Vouchable> OnceVouched<T> {
+    /// Returns an empty cell that only accepts a value vouched for with
+    /// the parameters for `checking`.
+    pub const fn new(checking: CheckingParameters) -> OnceVouched<T> {
+        OnceVouched {
+            checking,
+            value: OnceLock::new(),
+        }
+    }
+
+    /// Initialises the cell with `value` if `voucher` is valid for
+    /// `value` and the cell is still empty.
+    ///
+    /// Returns `Ok(())` on success, and gives `value` back when the
+    /// voucher is invalid or the cell was already initialised.
+    #[track_caller]
+    pub fn set(&self, value: T, voucher: Voucher) -> Result<(), T> {
+        if !self.checking.check_audited(value.vouch_value(), voucher) {
+            return Err(value);
+        }
+
+        self.value.set(value)
+    }
+
+    /// Returns the cell's value, or `None` if it hasn't been set.
+    pub fn get(&self) -> Option<&T> {
+        self.value.get()
+    }
+
+    /// Returns the cell's value, or `default` if it hasn't been set.
+    pub fn get_or<'a>(&'a self, default: &'a T) -> &'a T {
+        self.get().unwrap_or(default)
+    }
+
+    /// Returns the [`CheckingParameters`] for the cell.
+    pub fn checking_parameters(&self) -> CheckingParameters {
+        self.checking
+    }
+}
+
+#[cfg(not(feature = "insecure-accept-all"))]
+#[test]
+fn test_vouched_cell() {
+    let params = crate::testing::VOUCHING;
+    let cell = VouchedCell::new(params.checking_parameters(), 10u32);
+    assert_eq!(cell.get(), 10);
+
+    // Bad vouchers are rejected, and the value is handed back.
+    assert_eq!(cell.set(1000, params.vouch(999)), Err(1000));
+    assert_eq!(cell.get(), 10);
+
+    assert_eq!(cell.set(1000, params.vouch(1000)), Ok(10));
+    assert_eq!(cell.get(), 1000);
+    assert_eq!(cell.into_inner(), 1000);
+}
+
+#[test]
+fn test_vouched_cell_static() {
+    static CELL: VouchedCell<bool> = VouchedCell::new(
+        CheckingParameters::parse_or_die("CHECK-0000000000000083-9b791a2755d2d996"),
+        false,
+    );
+
+    let params = crate::testing::VOUCHING;
+    assert_eq!(CELL.checking_parameters(), params.checking_parameters());
+    assert!(!CELL.get());
+    std::thread::spawn(move || CELL.set(true, params.vouch(1)))
+        .join()
+        .unwrap()
+        .unwrap();
+    assert!(CELL.get());
+}
+
+#[cfg(not(feature = "insecure-accept-all"))]
+#[test]
+fn test_once_vouched() {
+    static DISABLE_TLS_VERIFICATION: OnceVouched<bool> = OnceVouched::new(
+        CheckingParameters::parse_or_die("CHECK-0000000000000083-9b791a2755d2d996"),
+    );
+
+    let params = crate::testing::VOUCHING;
+    assert_eq!(DISABLE_TLS_VERIFICATION.get(), None);
+    assert!(!*DISABLE_TLS_VERIFICATION.get_or(&false));
+
+    // Invalid vouchers don't initialise the cell.
+    assert_eq!(
+        DISABLE_TLS_VERIFICATION.set(true, params.vouch(0)),
+        Err(true)
+    );
+    assert_eq!(DISABLE_TLS_VERIFICATION.get(), None);
+
+    assert_eq!(DISABLE_TLS_VERIFICATION.set(true, params.vouch(1)), Ok(()));
+    assert_eq!(DISABLE_TLS_VERIFICATION.get(), Some(&true));
+
+    // And the cell can only be set once, even with a valid voucher.
+    assert_eq!(
+        DISABLE_TLS_VERIFICATION.set(false, params.vouch(0)),
+        Err(false)
+    );
+    assert!(*DISABLE_TLS_VERIFICATION.get_or(&false));
+}
@@ -9,6 +9,29 @@ pub const WANTED_SUM: u64 = named_u64(b"Vouch!OK", 0x4b4f216863756f56u64);
 /// The checking multiplier is xor-ed with this other constant.
 pub const CHECKING_TAG: u64 = named_u64(b"Checking", 0x676e696b63656843u64);
 
+/// Fingerprints mix the checking parameters with this constant.
+pub const FINGERPRINT_TAG: u64 = named_u64(b"KeyPrint", 0x746e69725079654bu64);
+
+/// MurmurHash3's 64-bit finaliser, a bijection on `u64`.
+#[must_use]
+pub const fn fmix64(mut x: u64) -> u64 {
+    x ^= x >> 33;
+    x = x.wrapping_mul(0xff51afd7ed558ccd);
+    x ^= x >> 33;
+    x = x.wrapping_mul(0xc4ceb9fe1a85ec53);
+    x ^ (x >> 33)
+}
+
+/// Returns a short identifier for the checking parameters `unoffset` and `unscale`.
+///
+/// This is MurmurHash3's 64-bit finaliser, applied twice.  It's not
+/// meant to hide anything (the checking parameters aren't secret), only
+/// to be a stable and compact name for a set of checking parameters.
+#[must_use]
+pub const fn fingerprint(unoffset: u64, unscale: u64) -> u64 {
+    fmix64(fmix64(unoffset ^ FINGERPRINT_TAG).wrapping_add(unscale))
+}
+
 /// Determines whether the `voucher` value was generated for
 /// `expected`, and with vouching parameters that correspond to the
 /// checking parameters `unoffset` and `unscale`.
@@ -24,6 +47,41 @@ pub const fn check(unoffset: u64, unscale: u64, expected: u64, voucher: u64) ->
     unvouched_value.wrapping_add(expected) == WANTED_SUM
 }
 
+/// Returns the only value for which `voucher` is valid under the
+/// checking parameters `unoffset` and `unscale`.
+///
+/// `check(unoffset, unscale, expected, voucher)` is equivalent to
+/// `vouched_value(unoffset, unscale, voucher) == expected`.
+#[must_use]
+#[inline(always)]
+pub const fn vouched_value(unoffset: u64, unscale: u64, voucher: u64) -> u64 {
+    let unvouched_value = voucher
+        .wrapping_add(unoffset)
+        .wrapping_mul(unscale ^ CHECKING_TAG);
+
+    WANTED_SUM.wrapping_sub(unvouched_value)
+}
+
+/// Checks each `(expected, voucher)` pair in `entries`, and returns a
+/// bitmask with bit `i` set iff `entries[i]` matches.
+///
+/// The loop has a fixed trip count and the comparison results are
+/// shifted into the mask arithmetically, so there is no data-dependent
+/// branch.
+#[must_use]
+#[inline(always)]
+pub const fn check_mask(unoffset: u64, unscale: u64, entries: &[(u64, u64); 64]) -> u64 {
+    let mut mask = 0u64;
+    let mut i = 0;
+    while i < entries.len() {
+        let (expected, voucher) = entries[i];
+        mask |= (check(unoffset, unscale, expected, voucher) as u64) << i;
+        i += 1;
+    }
+
+    mask
+}
+
 pub const REPRESENTATION_BYTE_COUNT: usize = 39;
 
 /// Parses the `bytes` as the serialised ASCII representation of checking parameters.
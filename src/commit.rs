@@ -0,0 +1,67 @@
+//! Binding vouchers to builds.
+//!
+//! A voucher for a plain configuration value authorises that setting
+//! forever.  A voucher minted for a [`CommitBound`] value instead
+//! authorises the setting for one build, identified by its git commit
+//! hash (or any other build ID), so the next release needs a fresh
+//! voucher.
+//!
+//! The build ID is hashed with [`crate::name_hash`], as is: vouchers
+//! must be minted for the exact string the build embeds (e.g., always
+//! the full 40-character commit hash).  The hash is mixed into the
+//! vouched [`u64`] under a tag of its own, so a voucher for a build ID
+//! never doubles as one for a [`crate::Named`] value with the same
+//! string as its name.
+//!
+//! Builds usually get their ID from their build script, e.g., with
+//! `println!("cargo:rustc-env=GIT_COMMIT={}", commit)`, and then
+//! `CommitBound::new(env!("GIT_COMMIT"), &value)`.
+use crate::name_hash;
+use crate::Vouchable;
+
+/// Build IDs are mixed with this constant.
+const COMMIT_TAG: u64 = crate::constparse::named_u64(b"CommitID", 0x444974696d6d6f43u64);
+
+/// A value bound to a build ID.
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Hash)]
+pub struct CommitBound<'a, T: ?Sized> {
+    /// The build ID, e.g., a git commit hash.
+    pub build: &'a str,
+    /// The inner value.
+    pub value: &'a T,
+}
+
+impl<'a, T: Vouchable + ?Sized> CommitBound<'a, T> {
+    /// Binds `value` to the build ID `build`.
+    pub fn new(build: &'a str, value: &'a T) -> CommitBound<'a, T> {
+        CommitBound { build, value }
+    }
+}
+
+impl<T: Vouchable + ?Sized> Vouchable for CommitBound<'_, T> {
+    fn vouch_value(&self) -> u64 {
+        crate::vouchable::bind(name_hash(self.build), COMMIT_TAG, self.value)
+    }
+}
+
+#[cfg(not(feature = "insecure-accept-all"))]
+#[test]
+fn test_commit_bound() {
+    const COMMIT: &str = "5f69c3a1e0b7d4c2f8a9b6e3d1c0f7a2b4e6d8c0";
+    const NEXT: &str = "bd4cdbb2f1e0a9c8b7d6e5f4a3b2c1d0e9f8a7b6";
+
+    let params = crate::testing::VOUCHING;
+    let checking = params.checking_parameters();
+    let voucher = params.vouch(CommitBound::new(COMMIT, &1000u32).vouch_value());
+
+    assert!(checking.check(CommitBound::new(COMMIT, &1000u32).vouch_value(), voucher));
+    assert!(!checking.check(CommitBound::new(NEXT, &1000u32).vouch_value(), voucher));
+    assert!(!checking.check(CommitBound::new(COMMIT, &1001u32).vouch_value(), voucher));
+    assert!(!checking.check(1000, voucher));
+
+    // Build IDs don't collide with names.
+    assert_ne!(
+        CommitBound::new(COMMIT, &1000u32).vouch_value(),
+        crate::Named::new(COMMIT, &1000u32).vouch_value()
+    );
+}
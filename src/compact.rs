@@ -0,0 +1,71 @@
+//! Compact base64 representation for vouchers.
+//!
+//! [`Voucher::to_base64`] renders a voucher as 11 characters of
+//! unpadded URL-safe base64 (e.g., `ASNFZ4mrze8`), versus 16 hex
+//! digits.  [`Voucher::parse_base64`] is a `const fn`, so vouchers in
+//! this format can be parsed at compile time, like the hex ones.
+use crate::constparse::parse_base64_u64;
+use crate::constparse::write_base64_u64;
+use crate::Voucher;
+
+/// Number of characters in the base64 representation of a voucher.
+const BASE64_BYTE_COUNT: usize = 11;
+
+impl Voucher {
+    /// Returns this voucher as 11 characters of unpadded URL-safe base64.
+    #[must_use]
+    pub fn to_base64(&self) -> String {
+        let buf = write_base64_u64([0u8; BASE64_BYTE_COUNT], 0, self.0);
+        String::from_utf8(buf.to_vec()).expect("base64 is ASCII")
+    }
+
+    /// Parses the representation generated by [`Voucher::to_base64`].
+    ///
+    /// The string must consist of exactly the 11 base64 characters (an
+    /// optional `=` padding character is also accepted).
+    pub const fn parse_base64(string: &str) -> Result<Voucher, &'static str> {
+        let bytes = string.as_bytes();
+        if !(bytes.len() == BASE64_BYTE_COUNT
+            || (bytes.len() == BASE64_BYTE_COUNT + 1 && bytes[BASE64_BYTE_COUNT] == b'='))
+        {
+            return Err("Base64 voucher must have 11 characters");
+        }
+
+        match parse_base64_u64(bytes, 0) {
+            Some(voucher) => Ok(Voucher(voucher)),
+            None => Err("Failed to parse base64 voucher"),
+        }
+    }
+}
+
+#[test]
+fn test_round_trip() {
+    let params = crate::testing::VOUCHING;
+
+    for value in [0u64, 1, 42, u64::MAX] {
+        let voucher = params.vouch(value);
+        let encoded = voucher.to_base64();
+        assert_eq!(encoded.len(), BASE64_BYTE_COUNT);
+        assert_eq!(Voucher::parse_base64(&encoded), Ok(voucher));
+        assert_eq!(Voucher::parse_base64(&format!("{encoded}=")), Ok(voucher));
+    }
+}
+
+#[test]
+fn test_const_parse() {
+    const VOUCHER: Voucher = match Voucher::parse_base64("ASNFZ4mrze8") {
+        Ok(voucher) => voucher,
+        Err(_) => panic!("bad voucher"),
+    };
+
+    assert_eq!(VOUCHER, Voucher(0x0123456789abcdef));
+    assert_eq!(VOUCHER.to_base64(), "ASNFZ4mrze8");
+}
+
+#[test]
+fn test_parse_errors() {
+    assert!(Voucher::parse_base64("ASNFZ4mrze").is_err());
+    assert!(Voucher::parse_base64("ASNFZ4mrze8A").is_err());
+    assert!(Voucher::parse_base64("ASNFZ4mrze9").is_err());
+    assert!(Voucher::parse_base64("ASNFZ4mrz+8").is_err());
+}
@@ -0,0 +1,281 @@
+//! Typed settings with vouched overrides.
+//!
+//! Each [`Setting`] declares a name and a safe default value.  The
+//! default is always available; any other value must come with a
+//! [`Voucher`] for that value, tagged with the setting's name (with
+//! [`Named`]), so a voucher for one setting isn't accidentally valid
+//! for another setting that happens to take the same value.  That's
+//! domain separation against mixups, not a defence against adversaries
+//! (see [`Named`]).
+//!
+//! ```
+//! # use raffle::config::{Origin, Overrides, Setting};
+//! # use raffle::VouchingParameters;
+//! # let vouching = VouchingParameters::parse_or_die("VOUCH-b4b0de979c8a90a9-676e696863756fd5-0000000000000083-9b791a2755d2d996");
+//! static MAX_CONNECTIONS: Setting<u32> = Setting::new("max_connections", 100);
+//!
+//! // Usually parsed from a configuration file.
+//! let voucher = MAX_CONNECTIONS.vouch(&vouching, &10_000);
+//! let mut overrides = Overrides::new([vouching.checking_parameters()]);
+//! overrides.insert("max_connections", "10000", voucher);
+//!
+//! let loaded = overrides.load(&MAX_CONNECTIONS).unwrap();
+//! assert_eq!(loaded.value, 10_000);
+//! assert_eq!(loaded.origin, Origin::Override(vouching.fingerprint()));
+//! ```
+use std::collections::BTreeMap;
+use std::str::FromStr;
+
+use crate::CheckingParameters;
+use crate::Fingerprint;
+use crate::Named;
+use crate::Vouchable;
+use crate::Voucher;
+use crate::VouchingParameters;
+
+/// A named setting with a safe default value.
+#[derive(Clone, Debug, Eq, PartialEq, Hash)]
+pub struct Setting<T> {
+    name: &'static str,
+    default: T,
+}
+
+impl<T: Vouchable> Setting<T> {
+    /// Declares a setting called `name`, with a `default` value.
+    pub const fn new(name: &'static str, default: T) -> Setting<T> {
+        Setting { name, default }
+    }
+
+    /// Returns the setting's name.
+    pub fn name(&self) -> &'static str {
+        self.name
+    }
+
+    /// Returns the setting's default value.
+    pub fn default_value(&self) -> &T {
+        &self.default
+    }
+
+    /// Returns the [`Voucher`] that authorises `value` for this setting.
+    #[must_use]
+    pub fn vouch(&self, vouching: &VouchingParameters, value: &T) -> Voucher {
+        vouching.vouch(Named::new(self.name, value).vouch_value())
+    }
+
+    /// Returns whether `voucher` authorises `value` for this setting,
+    /// with [`CheckingParameters::try_check_labelled`]: the check is
+    /// reported to the audit sink, and failures are labelled with the
+    /// setting's name.
+    #[must_use]
+    #[track_caller]
+    pub fn check(&self, checking: CheckingParameters, value: &T, voucher: Voucher) -> bool {
+        checking
+            .try_check_labelled(
+                self.name,
+                Named::new(self.name, value).vouch_value(),
+                voucher,
+            )
+            .is_ok()
+    }
+}
+
+/// Where a loaded setting's value came from.
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Hash)]
+pub enum Origin {
+    /// The setting wasn't overridden.
+    Default,
+    /// The setting was overridden with a value vouched for by the key
+    /// with this fingerprint.
+    Override(Fingerprint),
+}
+
+/// The value for a [`Setting`], and its [`Origin`].
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Hash)]
+pub struct Loaded<T> {
+    /// The setting's value.
+    pub value: T,
+    /// Whether the value is the default or an override.
+    pub origin: Origin,
+}
+
+/// Error type for [`Overrides::load`].
+#[derive(Clone, Debug, Eq, PartialEq, Hash)]
+pub enum ConfigError {
+    /// The override's value doesn't parse as the setting's type.
+    InvalidValue {
+        /// The setting's name.
+        name: &'static str,
+        /// The override's value.
+        value: String,
+    },
+    /// None of the keys accept the override's voucher.
+    InvalidVoucher {
+        /// The setting's name.
+        name: &'static str,
+        /// The override's value.
+        value: String,
+    },
+}
+
+impl std::fmt::Display for ConfigError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ConfigError::InvalidValue { name, value } => {
+                write!(f, "invalid value {:?} for setting {}", value, name)
+            }
+            ConfigError::InvalidVoucher { name, value } => {
+                write!(f, "invalid voucher for setting {} = {:?}", name, value)
+            }
+        }
+    }
+}
+
+impl std::error::Error for ConfigError {}
+
+/// A set of (not yet checked) overrides, and the keys that may vouch for them.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct Overrides {
+    keys: Vec<CheckingParameters>,
+    entries: BTreeMap<String, (String, Voucher)>,
+}
+
+impl Overrides {
+    /// Returns an empty set of overrides, which will accept vouchers
+    /// from any of the `keys`.
+    pub fn new(keys: impl IntoIterator<Item = CheckingParameters>) -> Overrides {
+        Overrides {
+            keys: keys.into_iter().collect(),
+            entries: BTreeMap::new(),
+        }
+    }
+
+    /// Records an override for the setting `name`: `value` is the
+    /// override's string representation, and `voucher` should match
+    /// the parsed value.
+    ///
+    /// Overrides aren't checked until they're [`Overrides::load`]ed.
+    pub fn insert(&mut self, name: impl Into<String>, value: impl Into<String>, voucher: Voucher) {
+        self.entries.insert(name.into(), (value.into(), voucher));
+    }
+
+    /// Returns the names of all recorded overrides, in order.
+    pub fn names(&self) -> impl Iterator<Item = &str> + '_ {
+        self.entries.keys().map(String::as_str)
+    }
+
+    /// Returns the value for `setting`: the default if there's no
+    /// override, and the override if it parses and is vouched for by
+    /// any of the keys.
+    ///
+    /// Invalid overrides are errors; they never fall back to the default.
+    #[track_caller]
+    pub fn load<T>(&self, setting: &Setting<T>) -> Result<Loaded<T>, ConfigError>
+    where
+        T: Vouchable + FromStr + Clone,
+    {
+        let Some((raw, voucher)) = self.entries.get(setting.name) else {
+            return Ok(Loaded {
+                value: setting.default.clone(),
+                origin: Origin::Default,
+            });
+        };
+
+        let Ok(value) = raw.trim().parse::<T>() else {
+            return Err(ConfigError::InvalidValue {
+                name: setting.name,
+                value: raw.clone(),
+            });
+        };
+
+        let expected = Named::new(setting.name, &value).vouch_value();
+        match crate::audit::find_audited(
+            self.keys.iter().copied(),
+            Some(setting.name),
+            expected,
+            *voucher,
+        ) {
+            Some(index) => Ok(Loaded {
+                value,
+                origin: Origin::Override(self.keys[index].fingerprint()),
+            }),
+            None => Err(ConfigError::InvalidVoucher {
+                name: setting.name,
+                value: raw.clone(),
+            }),
+        }
+    }
+}
+
+#[cfg(all(test, not(feature = "insecure-accept-all")))]
+static VERBOSE: Setting<bool> = Setting::new("verbose", false);
+
+#[cfg(all(test, not(feature = "insecure-accept-all")))]
+static TIMEOUT: Setting<u64> = Setting::new("timeout", 30);
+
+#[cfg(not(feature = "insecure-accept-all"))]
+#[test]
+fn test_load() {
+    let old = crate::testing::VOUCHING;
+    let new = crate::testing::OTHER;
+
+    let mut overrides = Overrides::new([old.checking_parameters(), new.checking_parameters()]);
+    assert_eq!(
+        overrides.load(&VERBOSE),
+        Ok(Loaded {
+            value: false,
+            origin: Origin::Default
+        })
+    );
+
+    overrides.insert("verbose", "true", VERBOSE.vouch(&old, &true));
+    overrides.insert("timeout", " 3600 ", TIMEOUT.vouch(&new, &3600));
+    assert_eq!(
+        overrides.names().collect::<Vec<_>>(),
+        ["timeout", "verbose"]
+    );
+    assert_eq!(
+        overrides.load(&VERBOSE),
+        Ok(Loaded {
+            value: true,
+            origin: Origin::Override(old.fingerprint())
+        })
+    );
+    assert_eq!(
+        overrides.load(&TIMEOUT),
+        Ok(Loaded {
+            value: 3600,
+            origin: Origin::Override(new.fingerprint())
+        })
+    );
+}
+
+#[cfg(not(feature = "insecure-accept-all"))]
+#[test]
+fn test_load_fail() {
+    let params = crate::testing::VOUCHING;
+    let mut overrides = Overrides::new([params.checking_parameters()]);
+
+    // A voucher for the same value, but for another setting.
+    overrides.insert("timeout", "1", VERBOSE.vouch(&params, &true));
+    assert_eq!(
+        overrides.load(&TIMEOUT),
+        Err(ConfigError::InvalidVoucher {
+            name: "timeout",
+            value: "1".to_string()
+        })
+    );
+
+    overrides.insert("verbose", "yes", VERBOSE.vouch(&params, &true));
+    assert_eq!(
+        overrides.load(&VERBOSE),
+        Err(ConfigError::InvalidValue {
+            name: "verbose",
+            value: "yes".to_string()
+        })
+    );
+
+    // No key, no override.
+    let mut overrides = Overrides::new([]);
+    overrides.insert("verbose", "true", VERBOSE.vouch(&params, &true));
+    assert!(overrides.load(&VERBOSE).is_err());
+}
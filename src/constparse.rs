@@ -1,5 +1,5 @@
-/// This module exposes const-fn methods to convert bytes and string-as-bytes
-/// to u64 numbers at compile-time.
+//! This module exposes const-fn methods to convert bytes and string-as-bytes
+//! to u64 numbers at compile-time.
 
 /// Interprets the first up to 8 characters in `name` as a little-endian u64.
 pub const fn named_u64(name: &[u8; 8], expected: u64) -> u64 {
@@ -13,49 +13,215 @@ pub const fn named_u64(name: &[u8; 8], expected: u64) -> u64 {
 ///
 /// Returns None on parse failure.
 pub const fn parse_hex(bytes: &[u8], base: usize) -> Option<u64> {
-    const fn update(acc: Option<u64>, bytes: &[u8], base: usize, idx: usize) -> Option<u64> {
-        if base >= bytes.len() || idx >= bytes.len() - base {
+    parse_hex_len(bytes, base, 16)
+}
+
+/// Parses exactly `len` ASCII encoded big-endian hex digits at
+/// `bytes[base..base + len]` to a u64 value.
+///
+/// Returns None on parse failure, including when `len` isn't in
+/// `1..=16`, or when `bytes` is too short.
+pub const fn parse_hex_len(bytes: &[u8], base: usize, len: usize) -> Option<u64> {
+    if len == 0 || len > 16 || base >= bytes.len() || len > bytes.len() - base {
+        return None;
+    }
+
+    let mut acc = 0u64;
+    let mut idx = 0;
+    while idx < len {
+        let byte = bytes[base + idx];
+        let digit = match byte {
+            b'0'..=b'9' => byte - b'0',
+            b'a'..=b'f' => 10 + (byte - b'a'),
+            b'A'..=b'F' => 10 + (byte - b'A'),
+            _ => return None,
+        };
+
+        acc = (acc << 4) | (digit as u64);
+        idx += 1;
+    }
+
+    Some(acc)
+}
+
+/// Parses all of `bytes` as an unsigned decimal u64, without sign,
+/// whitespace, or digit grouping.
+///
+/// Returns None on parse failure, including empty input and overflow.
+pub const fn parse_decimal_u64(bytes: &[u8]) -> Option<u64> {
+    if bytes.is_empty() {
+        return None;
+    }
+
+    let mut acc = 0u64;
+    let mut idx = 0;
+    while idx < bytes.len() {
+        let byte = bytes[idx];
+        if !byte.is_ascii_digit() {
             return None;
         }
 
-        if let Some(acc) = acc {
-            let byte = bytes[base + idx];
-            let digit = match byte {
-                b'0'..=b'9' => byte - b'0',
-                b'a'..=b'f' => 10 + (byte - b'a'),
-                b'A'..=b'F' => 10 + (byte - b'A'),
-                _ => return None,
-            };
-            Some(acc + (digit as u64).wrapping_shl((4 * (15 - idx)) as u32))
-        } else {
-            None
+        acc = match acc.checked_mul(10) {
+            Some(x) => match x.checked_add((byte - b'0') as u64) {
+                Some(x) => x,
+                None => return None,
+            },
+            None => return None,
+        };
+        idx += 1;
+    }
+
+    Some(acc)
+}
+
+/// Decodes the URL-safe base64 (RFC 4648, section 5) value of exactly
+/// `N` bytes at the start of `bytes`.
+///
+/// The encoding may be unpadded (`(4 * N).div_ceil(3)` characters) or
+/// padded with `=` to a multiple of 4 characters; trailing characters
+/// after the encoding are ignored, like [`parse_hex`].  Returns None on
+/// parse failure, including non-canonical encodings with nonzero
+/// trailing bits.
+pub const fn parse_base64_url<const N: usize>(bytes: &[u8]) -> Option<[u8; N]> {
+    const fn sextet(byte: u8) -> Option<u32> {
+        Some(match byte {
+            b'A'..=b'Z' => byte - b'A',
+            b'a'..=b'z' => 26 + (byte - b'a'),
+            b'0'..=b'9' => 52 + (byte - b'0'),
+            b'-' => 62,
+            b'_' => 63,
+            _ => return None,
+        } as u32)
+    }
+
+    let digit_count = (4 * N).div_ceil(3);
+    if bytes.len() < digit_count {
+        return None;
+    }
+
+    let mut ret = [0u8; N];
+    let mut acc = 0u32;
+    let mut bit_count = 0;
+    let mut written = 0;
+    let mut idx = 0;
+    while idx < digit_count {
+        let Some(digit) = sextet(bytes[idx]) else {
+            return None;
+        };
+
+        acc = (acc << 6) | digit;
+        bit_count += 6;
+        if bit_count >= 8 {
+            bit_count -= 8;
+            ret[written] = (acc >> bit_count) as u8;
+            written += 1;
+            acc &= (1 << bit_count) - 1;
         }
+
+        idx += 1;
     }
 
-    let mut acc = Some(0u64);
-    acc = update(acc, bytes, base, 0);
-    acc = update(acc, bytes, base, 1);
-    acc = update(acc, bytes, base, 2);
-    acc = update(acc, bytes, base, 3);
-    acc = update(acc, bytes, base, 4);
-    acc = update(acc, bytes, base, 5);
-    acc = update(acc, bytes, base, 6);
-    acc = update(acc, bytes, base, 7);
-    acc = update(acc, bytes, base, 8);
-    acc = update(acc, bytes, base, 9);
-    acc = update(acc, bytes, base, 10);
-    acc = update(acc, bytes, base, 11);
-    acc = update(acc, bytes, base, 12);
-    acc = update(acc, bytes, base, 13);
-    acc = update(acc, bytes, base, 14);
-    acc = update(acc, bytes, base, 15);
-
-    acc
+    // Leftover bits must be zero for the encoding to be canonical.
+    if acc != 0 {
+        return None;
+    }
+
+    // Padding is all or nothing.
+    let padded_count = 4 * N.div_ceil(3);
+    if bytes.len() > digit_count && bytes[digit_count] == b'=' {
+        if bytes.len() < padded_count {
+            return None;
+        }
+
+        while idx < padded_count {
+            if bytes[idx] != b'=' {
+                return None;
+            }
+
+            idx += 1;
+        }
+    }
+
+    Some(ret)
+}
+
+/// Parses the unpadded URL-safe base64 encoding of a big-endian u64
+/// (11 characters) at `bytes[base..]`.
+///
+/// Returns None on parse failure.
+pub const fn parse_base64_u64(bytes: &[u8], base: usize) -> Option<u64> {
+    if base > bytes.len() {
+        return None;
+    }
+
+    let (_, rest) = bytes.split_at(base);
+    match parse_base64_url::<8>(rest) {
+        Some(decoded) => Some(u64::from_be_bytes(decoded)),
+        None => None,
+    }
+}
+
+/// Writes `prefix` at the start of `buf`, and returns the updated `buf`.
+pub const fn write_prefix<const N: usize>(mut buf: [u8; N], prefix: &[u8]) -> [u8; N] {
+    let mut i = 0;
+    while i < prefix.len() {
+        buf[i] = prefix[i];
+        i += 1;
+    }
+
+    buf
+}
+
+/// Writes `value` as 16 lowercase big-endian hex digits at
+/// `buf[base..base + 16]`, and returns the updated `buf`.
+///
+/// This is the inverse of [`parse_hex`].
+pub const fn write_hex<const N: usize>(mut buf: [u8; N], base: usize, value: u64) -> [u8; N] {
+    const DIGITS: &[u8; 16] = b"0123456789abcdef";
+
+    let mut idx = 0;
+    while idx < 16 {
+        let digit = (value >> (4 * (15 - idx))) & 15;
+        buf[base + idx] = DIGITS[digit as usize];
+        idx += 1;
+    }
+
+    buf
+}
+
+/// Writes `value` as 11 unpadded URL-safe base64 characters (big-endian)
+/// at `buf[base..base + 11]`, and returns the updated `buf`.
+///
+/// This is the inverse of [`parse_base64_u64`].
+pub const fn write_base64_u64<const N: usize>(
+    mut buf: [u8; N],
+    base: usize,
+    value: u64,
+) -> [u8; N] {
+    const DIGITS: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789-_";
+
+    // 64 bits, padded with two zero bits on the right, in 11 sextets.
+    let padded = (value as u128) << 2;
+    let mut idx = 0;
+    while idx < 11 {
+        let digit = (padded >> (6 * (10 - idx))) & 63;
+        buf[base + idx] = DIGITS[digit as usize];
+        idx += 1;
+    }
+
+    buf
+}
+
+#[test]
+fn test_write_hex() {
+    let buf = write_hex(write_prefix([b'-'; 20], b"hex"), 4, 0x0123456789abcdef);
+    assert_eq!(&buf, b"hex-0123456789abcdef");
+    assert_eq!(parse_hex(&buf, 4), Some(0x0123456789abcdef));
 }
 
 #[test]
 fn test_named_u64() {
-    // These are the three strings we care about.
+    // These are the strings we care about.
     assert_eq!(u64::from_le_bytes(*b"Vouch!OK"), 0x4b4f216863756f56u64);
     assert_eq!(
         named_u64(b"Vouch!OK", 0x4b4f216863756f56u64),
@@ -68,6 +234,12 @@ fn test_named_u64() {
         u64::from_le_bytes(*b"Checking")
     );
 
+    assert_eq!(u64::from_le_bytes(*b"KeyPrint"), 0x746e69725079654bu64);
+    assert_eq!(
+        named_u64(b"KeyPrint", 0x746e69725079654bu64),
+        u64::from_le_bytes(*b"KeyPrint")
+    );
+
     assert_eq!(u64::from_le_bytes(*b"Vouching"), 0x676e696863756f56u64);
     assert_eq!(
         named_u64(b"Vouching", 0x676e696863756f56u64),
@@ -107,3 +279,78 @@ fn test_parse_hex_bad() {
     assert_eq!(parse_hex(format!("{:015x}g", 42).as_bytes(), 0), None);
     assert_eq!(parse_hex(format!("x{:015x}", 42).as_bytes(), 0), None);
 }
+
+#[test]
+fn test_parse_hex_len() {
+    assert_eq!(parse_hex_len(b"f", 0, 1), Some(15));
+    assert_eq!(parse_hex_len(b"key-1234abcd-", 4, 8), Some(0x1234abcd));
+    assert_eq!(parse_hex_len(b"ABCDEF", 2, 4), Some(0xcdef));
+    assert_eq!(parse_hex_len(b"ffffffffffffffff", 0, 16), Some(u64::MAX));
+
+    assert_eq!(parse_hex_len(b"1234", 0, 0), None);
+    assert_eq!(parse_hex_len(b"1234", 0, 5), None);
+    assert_eq!(parse_hex_len(b"1234", 1, 4), None);
+    assert_eq!(parse_hex_len(b"1234", 4, 1), None);
+    assert_eq!(parse_hex_len(b"12g4", 0, 4), None);
+    assert_eq!(parse_hex_len(b"00000000000000001", 0, 17), None);
+
+    const SHORT: Option<u64> = parse_hex_len(b"7f", 0, 2);
+    assert_eq!(SHORT, Some(0x7f));
+}
+
+#[test]
+fn test_parse_base64_url() {
+    // RFC 4648 test vectors.
+    assert_eq!(parse_base64_url::<1>(b"Zg"), Some(*b"f"));
+    assert_eq!(parse_base64_url::<1>(b"Zg=="), Some(*b"f"));
+    assert_eq!(parse_base64_url::<2>(b"Zm8"), Some(*b"fo"));
+    assert_eq!(parse_base64_url::<3>(b"Zm9v"), Some(*b"foo"));
+    assert_eq!(parse_base64_url::<6>(b"Zm9vYmFy"), Some(*b"foobar"));
+
+    // URL-safe alphabet.
+    assert_eq!(parse_base64_url::<3>(b"-_-_"), Some([0xfb, 0xff, 0xbf]));
+    assert_eq!(parse_base64_url::<3>(b"+/+/"), None);
+
+    // Truncated, non-canonical, or badly padded.
+    assert_eq!(parse_base64_url::<2>(b"Zm"), None);
+    assert_eq!(parse_base64_url::<1>(b"Zh"), None);
+    assert_eq!(parse_base64_url::<1>(b"Zg="), None);
+    assert_eq!(parse_base64_url::<1>(b"Zg=x"), None);
+
+    const DECODED: Option<[u8; 3]> = parse_base64_url(b"Zm9v");
+    assert_eq!(DECODED, Some(*b"foo"));
+}
+
+#[test]
+fn test_parse_base64_u64() {
+    // 0x0123456789abcdef, as produced by `base64 | tr '+/' '-_'`.
+    assert_eq!(
+        parse_base64_u64(b"key:ASNFZ4mrze8", 4),
+        Some(0x0123456789abcdef)
+    );
+    assert_eq!(parse_base64_u64(b"__________8", 0), Some(u64::MAX));
+    assert_eq!(parse_base64_u64(b"AAAAAAAAAAA", 0), Some(0));
+    assert_eq!(parse_base64_u64(b"AAAAAAAAAAB", 0), None);
+    assert_eq!(parse_base64_u64(b"AAAAAAAAAA", 0), None);
+    assert_eq!(parse_base64_u64(b"AAAAAAAAAAA", 12), None);
+
+    let buf = write_base64_u64([b'.'; 12], 1, 0x0123456789abcdef);
+    assert_eq!(&buf, b".ASNFZ4mrze8");
+    assert_eq!(parse_base64_u64(&buf, 1), Some(0x0123456789abcdef));
+}
+
+#[test]
+fn test_parse_decimal_u64() {
+    assert_eq!(parse_decimal_u64(b"0"), Some(0));
+    assert_eq!(parse_decimal_u64(b"0042"), Some(42));
+    assert_eq!(parse_decimal_u64(b"18446744073709551615"), Some(u64::MAX));
+
+    assert_eq!(parse_decimal_u64(b""), None);
+    assert_eq!(parse_decimal_u64(b"18446744073709551616"), None);
+    assert_eq!(parse_decimal_u64(b"+1"), None);
+    assert_eq!(parse_decimal_u64(b"1 "), None);
+    assert_eq!(parse_decimal_u64(b"1_000"), None);
+
+    const PARSED: Option<u64> = parse_decimal_u64(b"1000");
+    assert_eq!(PARSED, Some(1000));
+}
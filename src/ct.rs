@@ -0,0 +1,53 @@
+//! Constant-time equality for secret parameters.
+//!
+//! This module is only compiled with the `subtle` feature.  It
+//! replaces the derived (short-circuiting) equality for
+//! [`VouchingParameters`] with [`subtle::ConstantTimeEq`], so comparing
+//! a candidate secret against a stored one (e.g., in a key ceremony
+//! tool) doesn't leak the length of the matching prefix via timing.
+use subtle::Choice;
+use subtle::ConstantTimeEq;
+
+use crate::VouchingParameters;
+
+impl ConstantTimeEq for VouchingParameters {
+    fn ct_eq(&self, other: &VouchingParameters) -> Choice {
+        let (offset, scale, unoffset, unscale) = self.components();
+        let (other_offset, other_scale, other_unoffset, other_unscale) = other.components();
+
+        offset.ct_eq(&other_offset)
+            & scale.ct_eq(&other_scale)
+            & unoffset.ct_eq(&other_unoffset)
+            & unscale.ct_eq(&other_unscale)
+    }
+}
+
+impl PartialEq for VouchingParameters {
+    fn eq(&self, other: &VouchingParameters) -> bool {
+        self.ct_eq(other).into()
+    }
+}
+
+impl Eq for VouchingParameters {}
+
+// Must match the derived implementation without `subtle`, and stay
+// consistent with `PartialEq`.
+impl std::hash::Hash for VouchingParameters {
+    fn hash<H: std::hash::Hasher>(&self, state: &mut H) {
+        self.components().hash(state);
+    }
+}
+
+#[test]
+fn test_ct_eq() {
+    let params = crate::testing::VOUCHING;
+    let other = crate::testing::OTHER;
+
+    assert!(bool::from(params.ct_eq(&params)));
+    assert!(!bool::from(params.ct_eq(&other)));
+    assert_eq!(params, params);
+    assert_ne!(params, other);
+
+    let set: std::collections::HashSet<VouchingParameters> = [params, other, params].into();
+    assert_eq!(set.len(), 2);
+}
@@ -0,0 +1,143 @@
+//! Decimal representation of values and vouchers.
+//!
+//! Some configuration systems only accept decimal integers, and
+//! converting hex vouchers to decimal by hand is error-prone.  The
+//! functions in this module render `u64`s in decimal, optionally with
+//! `_` between groups of three digits (e.g., `18_446_744_073_709_551_615`),
+//! and parse them back.
+//!
+//! [`parse`] accepts `_`, `,` and spaces as group separators, but only
+//! when every group after the first has exactly three digits: a
+//! misplaced separator usually means a digit was dropped or repeated.
+//!
+//! [`parse_decimal_u64`] is the stricter `const fn` parser (no grouping
+//! or whitespace) for compile-time constants, e.g., from `env!`.
+use crate::Voucher;
+
+pub use crate::constparse::parse_decimal_u64;
+
+/// Returns `value` in decimal, with `_` between groups of three
+/// digits if `grouped`.
+#[must_use]
+pub fn format(value: u64, grouped: bool) -> String {
+    let digits = value.to_string();
+    if !grouped {
+        return digits;
+    }
+
+    let mut ret = String::with_capacity(digits.len() + digits.len() / 3);
+    for (idx, digit) in digits.chars().enumerate() {
+        if idx > 0 && idx % 3 == digits.len() % 3 {
+            ret.push('_');
+        }
+
+        ret.push(digit);
+    }
+
+    ret
+}
+
+/// Parses a decimal `u64`, with optional digit grouping.
+///
+/// Groups may be separated by one of `_`, `,` or ` `, as long as the
+/// same separator is used throughout, the first group has one to three
+/// digits, and every other group has exactly three.
+pub fn parse(string: &str) -> Result<u64, &'static str> {
+    let string = string.trim();
+    let separator = string.chars().find(|c| matches!(c, '_' | ',' | ' '));
+
+    let mut ret = 0u64;
+    for (idx, group) in string.split(|c| Some(c) == separator).enumerate() {
+        let valid_length = if idx == 0 {
+            !group.is_empty() && (separator.is_none() || group.len() <= 3)
+        } else {
+            group.len() == 3
+        };
+
+        if !valid_length {
+            return Err("Invalid digit grouping in decimal number");
+        }
+
+        if !group.bytes().all(|byte| byte.is_ascii_digit()) {
+            return Err("Invalid character in decimal number");
+        }
+
+        ret = 10u64
+            .checked_pow(group.len() as u32)
+            .and_then(|scale| ret.checked_mul(scale))
+            .zip(parse_decimal_u64(group.as_bytes()))
+            .and_then(|(hi, lo)| hi.checked_add(lo))
+            .ok_or("Decimal number does not fit in 64 bits")?;
+    }
+
+    Ok(ret)
+}
+
+impl Voucher {
+    /// Returns this voucher in decimal, with `_` between groups of
+    /// three digits if `grouped`.
+    #[must_use]
+    pub fn to_decimal(&self, grouped: bool) -> String {
+        format(self.0, grouped)
+    }
+
+    /// Parses a decimal voucher, with the same rules as [`parse`].
+    pub fn parse_decimal(string: &str) -> Result<Voucher, &'static str> {
+        parse(string).map(Voucher)
+    }
+}
+
+#[test]
+fn test_format() {
+    assert_eq!(format(0, true), "0");
+    assert_eq!(format(999, true), "999");
+    assert_eq!(format(1000, true), "1_000");
+    assert_eq!(format(1234567, false), "1234567");
+    assert_eq!(format(1234567, true), "1_234_567");
+    assert_eq!(format(u64::MAX, true), "18_446_744_073_709_551_615");
+}
+
+#[test]
+fn test_parse() {
+    assert_eq!(parse("0"), Ok(0));
+    assert_eq!(parse(" 1234567\n"), Ok(1234567));
+    assert_eq!(parse("1_234_567"), Ok(1234567));
+    assert_eq!(parse("1,234,567"), Ok(1234567));
+    assert_eq!(parse("1 234 567"), Ok(1234567));
+    assert_eq!(parse("18_446_744_073_709_551_615"), Ok(u64::MAX));
+
+    assert!(parse("").is_err());
+    assert!(parse("12_34_567").is_err());
+    assert!(parse("1234_567").is_err());
+    assert!(parse("1_234,567").is_err());
+    assert!(parse("1__234").is_err());
+    assert!(parse("_123").is_err());
+    assert!(parse("0x123").is_err());
+    assert!(parse("-1").is_err());
+    assert!(parse("18446744073709551616").is_err());
+}
+
+#[test]
+fn test_parse_decimal_u64_const() {
+    const LIMIT: u64 = match parse_decimal_u64(b"65536") {
+        Some(limit) => limit,
+        None => panic!("bad limit"),
+    };
+
+    assert_eq!(LIMIT, 65536);
+}
+
+#[test]
+fn test_voucher_round_trip() {
+    let params = crate::testing::VOUCHING;
+
+    for value in [0u64, 1, 42, u64::MAX] {
+        let voucher = params.vouch(value);
+        for grouped in [false, true] {
+            assert_eq!(
+                Voucher::parse_decimal(&voucher.to_decimal(grouped)),
+                Ok(voucher)
+            );
+        }
+    }
+}
@@ -0,0 +1,45 @@
+//! `defmt::Format` implementations, for embedded logging.
+//!
+//! This module is only compiled with the `defmt` feature.  The formats
+//! mirror the [`std::fmt::Display`] representations, except for
+//! [`VouchingParameters`]: only the public checking half is logged,
+//! along with the fingerprint, and the secret vouching half is redacted.
+use crate::CheckingParameters;
+use crate::Fingerprint;
+use crate::Voucher;
+use crate::VouchingParameters;
+
+impl defmt::Format for Voucher {
+    fn format(&self, f: defmt::Formatter<'_>) {
+        defmt::write!(f, "Voucher({=u64:016x})", self.0)
+    }
+}
+
+impl defmt::Format for Fingerprint {
+    fn format(&self, f: defmt::Formatter<'_>) {
+        defmt::write!(f, "{=u64:016x}", self.0)
+    }
+}
+
+impl defmt::Format for CheckingParameters {
+    fn format(&self, f: defmt::Formatter<'_>) {
+        defmt::write!(
+            f,
+            "CHECK-{=u64:016x}-{=u64:016x}",
+            self.unoffset,
+            self.unscale
+        )
+    }
+}
+
+impl defmt::Format for VouchingParameters {
+    fn format(&self, f: defmt::Formatter<'_>) {
+        defmt::write!(
+            f,
+            "VOUCH-<redacted>-<redacted>-{=u64:016x}-{=u64:016x} (key {})",
+            self.checking.unoffset,
+            self.checking.unscale,
+            self.fingerprint()
+        )
+    }
+}
@@ -0,0 +1,210 @@
+//! Revocation lists for leaked vouchers.
+//!
+//! Rotating parameters invalidates every voucher at once.  When a
+//! single voucher leaks, it's often simpler to revoke just that voucher
+//! with a [`Denylist`], and check with
+//! [`CheckingParameters::check_with_denylist`].
+//!
+//! A [`Denylist`] holds two kinds of entries:
+//!
+//! - `(value, voucher)` pairs, revoked with [`Denylist::revoke`];
+//! - hashes of vouchers, revoked for all values with
+//!   [`Denylist::revoke_voucher`].
+//!
+//! Neither kind of entry hides the revoked vouchers: pairs are stored
+//! as is, and [`voucher_hash`] is an unkeyed bijection, so it's easy to
+//! invert.  Treat a denylist as carefully as the vouchers it revokes.
+//!
+//! The text representation (see [`Denylist::parse`] and the
+//! [`std::fmt::Display`] implementation) has one entry per line: pairs
+//! use the same `value,voucher` format as [`crate::table::TableFormat::Csv`],
+//! and voucher hashes are written as `*,hash`.  Every number is
+//! 16 hex digits, and blank lines and lines that start with `#` are
+//! ignored.
+use std::collections::BTreeSet;
+
+use crate::constparse::parse_hex;
+use crate::CheckingParameters;
+use crate::Voucher;
+
+/// Voucher hashes mix the voucher with this constant.
+const VOUCHER_HASH_TAG: u64 = crate::constparse::named_u64(b"Revoked!", 0x2164656b6f766552u64);
+
+/// A set of revoked vouchers.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Denylist {
+    pairs: BTreeSet<(u64, u64)>,
+    voucher_hashes: BTreeSet<u64>,
+}
+
+/// Returns the hash of `voucher` stored by [`Denylist::revoke_voucher`].
+///
+/// The hash is only a lookup key: it's invertible, so it doesn't
+/// protect the voucher.
+#[must_use]
+pub const fn voucher_hash(voucher: Voucher) -> u64 {
+    crate::check::fingerprint(voucher.0 ^ VOUCHER_HASH_TAG, VOUCHER_HASH_TAG)
+}
+
+impl Denylist {
+    /// Returns an empty [`Denylist`].
+    #[must_use]
+    pub fn new() -> Denylist {
+        Default::default()
+    }
+
+    /// Revokes `voucher`, but only for `value`.
+    pub fn revoke(&mut self, value: u64, voucher: Voucher) {
+        self.pairs.insert((value, voucher.0));
+    }
+
+    /// Revokes `voucher` for every value.
+    pub fn revoke_voucher(&mut self, voucher: Voucher) {
+        self.revoke_voucher_hash(voucher_hash(voucher));
+    }
+
+    /// Revokes all vouchers whose [`voucher_hash`] is `hash`.
+    pub fn revoke_voucher_hash(&mut self, hash: u64) {
+        self.voucher_hashes.insert(hash);
+    }
+
+    /// Determines whether `voucher` was revoked for `value`.
+    #[must_use]
+    pub fn is_revoked(&self, value: u64, voucher: Voucher) -> bool {
+        self.pairs.contains(&(value, voucher.0))
+            || self.voucher_hashes.contains(&voucher_hash(voucher))
+    }
+
+    /// Returns the number of entries in the denylist.
+    #[must_use]
+    pub fn len(&self) -> usize {
+        self.pairs.len() + self.voucher_hashes.len()
+    }
+
+    /// Determines whether the denylist is empty.
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Parses the text representation of a denylist.
+    pub fn parse(string: &str) -> Result<Denylist, &'static str> {
+        let mut ret = Denylist::new();
+
+        for line in string.lines() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+
+            if let Some(hash) = line.strip_prefix("*,") {
+                if hash.len() != 16 {
+                    return Err("Expected a 16-digit hex voucher hash");
+                }
+
+                let Some(hash) = parse_hex(hash.as_bytes(), 0) else {
+                    return Err("Failed to parse hex voucher hash");
+                };
+
+                ret.revoke_voucher_hash(hash);
+            } else if let Some((value, voucher)) = crate::table::parse_csv_line(line)? {
+                ret.revoke(value, voucher);
+            }
+        }
+
+        Ok(ret)
+    }
+}
+
+impl std::str::FromStr for Denylist {
+    type Err = &'static str;
+
+    fn from_str(string: &str) -> Result<Denylist, &'static str> {
+        Denylist::parse(string)
+    }
+}
+
+impl std::fmt::Display for Denylist {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        for (value, voucher) in &self.pairs {
+            writeln!(f, "{:016x},{:016x}", value, voucher)?;
+        }
+
+        for hash in &self.voucher_hashes {
+            writeln!(f, "*,{:016x}", hash)?;
+        }
+
+        Ok(())
+    }
+}
+
+impl CheckingParameters {
+    /// Determines whether `voucher` is valid for `expected` and hasn't
+    /// been revoked in `denylist`.
+    #[must_use]
+    #[track_caller]
+    pub fn check_with_denylist(self, expected: u64, voucher: Voucher, denylist: &Denylist) -> bool {
+        // Audit the combined outcome, so revoked vouchers are reported
+        // as failures.
+        let valid = self.check(expected, voucher) & !denylist.is_revoked(expected, voucher);
+        self.record_check(None, expected, voucher, valid).is_ok()
+    }
+}
+
+#[cfg(not(feature = "insecure-accept-all"))]
+#[test]
+fn test_revoke_pair() {
+    let params = crate::testing::VOUCHING;
+    let checking = params.checking_parameters();
+
+    let mut denylist = Denylist::new();
+    assert!(denylist.is_empty());
+    assert!(checking.check_with_denylist(42, params.vouch(42), &denylist));
+
+    denylist.revoke(42, params.vouch(42));
+    assert_eq!(denylist.len(), 1);
+    assert!(!checking.check_with_denylist(42, params.vouch(42), &denylist));
+    assert!(checking.check_with_denylist(43, params.vouch(43), &denylist));
+
+    // Invalid vouchers stay invalid.
+    assert!(!checking.check_with_denylist(43, params.vouch(42), &denylist));
+}
+
+#[test]
+fn test_revoke_voucher() {
+    let params = crate::testing::VOUCHING;
+    let checking = params.checking_parameters();
+
+    let mut denylist = Denylist::new();
+    denylist.revoke_voucher(params.vouch(42));
+    assert!(denylist.is_revoked(42, params.vouch(42)));
+    assert!(denylist.is_revoked(1, params.vouch(42)));
+    assert!(!checking.check_with_denylist(42, params.vouch(42), &denylist));
+    assert!(checking.check_with_denylist(43, params.vouch(43), &denylist));
+}
+
+#[test]
+fn test_round_trip() {
+    let params = crate::testing::VOUCHING;
+
+    let mut denylist = Denylist::new();
+    denylist.revoke(42, params.vouch(42));
+    denylist.revoke_voucher(params.vouch(43));
+
+    let text = denylist.to_string();
+    assert_eq!(text.lines().count(), 2);
+    assert!(text.starts_with("000000000000002a,"));
+    assert!(text.lines().nth(1).unwrap().starts_with("*,"));
+    assert_eq!(text.parse::<Denylist>(), Ok(denylist.clone()));
+
+    let commented = format!("# revoked 2024-01-01\n\n{}", text);
+    assert_eq!(Denylist::parse(&commented), Ok(denylist));
+}
+
+#[test]
+fn test_parse_errors() {
+    assert!(Denylist::parse("*,1234").is_err());
+    assert!(Denylist::parse("*,000000000000000g").is_err());
+    assert!(Denylist::parse("000000000000002a").is_err());
+}
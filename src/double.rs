@@ -0,0 +1,226 @@
+//! Redundant double vouchers.
+//!
+//! A single voucher can't tell a corrupted value from the wrong
+//! checking parameters: in both cases, the check simply fails.  A
+//! [`DoubleVoucher`] carries two vouchers for the same value, under two
+//! independently generated sets of parameters ([`DoubleVouchingParameters`]),
+//! and [`DoubleCheckingParameters::diagnose`] uses the mismatch pattern
+//! to tell the cases apart:
+//!
+//! - both vouchers are valid: the value is fine;
+//! - both vouchers are invalid, but decode to the *same* other value:
+//!   the keys are right, and the value changed (two independent keys
+//!   agree on a wrong value with probability `2**-64`);
+//! - only one voucher is valid: that voucher was corrupted;
+//! - both vouchers are invalid and decode to different values: the
+//!   checking parameters don't match the vouching parameters.
+use crate::check;
+use crate::CheckingParameters;
+use crate::GenerateError;
+use crate::Voucher;
+use crate::VouchingParameters;
+
+/// A pair of vouchers for the same value.
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct DoubleVoucher(pub Voucher, pub Voucher);
+
+/// Two independent sets of [`VouchingParameters`].
+#[derive(Clone, Copy, Debug)]
+pub struct DoubleVouchingParameters {
+    first: VouchingParameters,
+    second: VouchingParameters,
+}
+
+/// The [`CheckingParameters`] for a [`DoubleVouchingParameters`].
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Hash)]
+pub struct DoubleCheckingParameters {
+    first: CheckingParameters,
+    second: CheckingParameters,
+}
+
+/// Outcome of [`DoubleCheckingParameters::diagnose`].
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Hash)]
+pub enum DoubleDiagnosis {
+    /// Both vouchers are valid.
+    Valid,
+    /// Both vouchers are valid for `vouched_value`, not the expected value.
+    WrongValue {
+        /// The value for which both vouchers are valid.
+        vouched_value: u64,
+    },
+    /// Only one of the two vouchers is valid.
+    CorruptVoucher,
+    /// Neither voucher is valid, and they disagree on the vouched value.
+    WrongKey,
+}
+
+impl DoubleVouchingParameters {
+    /// Generates two independent sets of [`VouchingParameters`] with
+    /// [`VouchingParameters::generate`].
+    pub fn generate<Err>(
+        mut generator: impl FnMut() -> Result<u64, Err>,
+    ) -> Result<DoubleVouchingParameters, GenerateError<Err>> {
+        let first = VouchingParameters::generate(&mut generator)?;
+        let second = VouchingParameters::generate(&mut generator)?;
+        DoubleVouchingParameters::new(first, second).map_err(|_| GenerateError::BrokenGenerator)
+    }
+
+    /// Combines two sets of [`VouchingParameters`].
+    ///
+    /// Fails if both sets have the same checking parameters: the
+    /// vouchers would then be redundant copies.
+    pub fn new(
+        first: VouchingParameters,
+        second: VouchingParameters,
+    ) -> Result<DoubleVouchingParameters, &'static str> {
+        if first.checking_parameters() == second.checking_parameters() {
+            return Err("Double vouching parameters must be independent");
+        }
+
+        Ok(DoubleVouchingParameters { first, second })
+    }
+
+    /// Computes the [`DoubleVoucher`] for `value`.
+    #[must_use]
+    pub fn vouch(&self, value: u64) -> DoubleVoucher {
+        DoubleVoucher(self.first.vouch(value), self.second.vouch(value))
+    }
+
+    /// Returns the corresponding [`DoubleCheckingParameters`].
+    #[must_use]
+    pub fn checking_parameters(&self) -> DoubleCheckingParameters {
+        DoubleCheckingParameters {
+            first: self.first.checking_parameters(),
+            second: self.second.checking_parameters(),
+        }
+    }
+
+    /// Returns the two sets of [`VouchingParameters`].
+    #[must_use]
+    pub fn parts(&self) -> (VouchingParameters, VouchingParameters) {
+        (self.first, self.second)
+    }
+}
+
+impl DoubleCheckingParameters {
+    /// Combines two sets of [`CheckingParameters`].
+    ///
+    /// Fails if both sets are identical.
+    pub fn new(
+        first: CheckingParameters,
+        second: CheckingParameters,
+    ) -> Result<DoubleCheckingParameters, &'static str> {
+        if first == second {
+            return Err("Double checking parameters must be independent");
+        }
+
+        Ok(DoubleCheckingParameters { first, second })
+    }
+
+    /// Returns the two sets of [`CheckingParameters`].
+    #[must_use]
+    pub fn parts(&self) -> (CheckingParameters, CheckingParameters) {
+        (self.first, self.second)
+    }
+
+    /// Returns whether both vouchers in `voucher` are valid for `expected`.
+    #[must_use]
+    pub fn check(&self, expected: u64, voucher: DoubleVoucher) -> bool {
+        // Non-short-circuiting `&`: always check both vouchers.
+        self.first.check(expected, voucher.0) & self.second.check(expected, voucher.1)
+    }
+
+    /// Like [`DoubleCheckingParameters::check`], but reports both checks
+    /// to the audit hooks (see [`CheckingParameters::check_audited`]).
+    #[must_use]
+    #[track_caller]
+    pub fn check_audited(&self, expected: u64, voucher: DoubleVoucher) -> bool {
+        self.first.check_audited(expected, voucher.0)
+            & self.second.check_audited(expected, voucher.1)
+    }
+
+    /// Checks `voucher` against `expected`, and uses the pattern of
+    /// mismatches to diagnose failures.
+    #[must_use]
+    pub fn diagnose(&self, expected: u64, voucher: DoubleVoucher) -> DoubleDiagnosis {
+        let decode = |checking: CheckingParameters, voucher: Voucher| {
+            check::vouched_value(checking.unoffset, checking.unscale, voucher.0)
+        };
+
+        let first = decode(self.first, voucher.0);
+        let second = decode(self.second, voucher.1);
+        match (first == expected, second == expected) {
+            (true, true) => DoubleDiagnosis::Valid,
+            (true, false) | (false, true) => DoubleDiagnosis::CorruptVoucher,
+            (false, false) if first == second => DoubleDiagnosis::WrongValue {
+                vouched_value: first,
+            },
+            (false, false) => DoubleDiagnosis::WrongKey,
+        }
+    }
+}
+
+#[cfg(not(feature = "insecure-accept-all"))]
+#[test]
+fn test_double_round_trip() {
+    let params =
+        DoubleVouchingParameters::generate(crate::make_generator(&[131, 5, 131, 1234, 5678]))
+            .unwrap();
+    let checking = params.checking_parameters();
+
+    for value in [0u64, 42, u64::MAX] {
+        let voucher = params.vouch(value);
+        assert!(checking.check(value, voucher));
+        assert!(checking.check_audited(value, voucher));
+        assert_eq!(checking.diagnose(value, voucher), DoubleDiagnosis::Valid);
+    }
+
+    assert!(!checking.check(43, params.vouch(42)));
+}
+
+#[test]
+fn test_double_diagnose() {
+    let first = crate::testing::VOUCHING;
+    let second = crate::testing::OTHER;
+    let third = crate::VouchingParameters::generate(crate::make_generator(&[4321, 8765])).unwrap();
+
+    let params = DoubleVouchingParameters::new(first, second).unwrap();
+    let checking = params.checking_parameters();
+    let voucher = params.vouch(42);
+
+    // Corrupted value.
+    assert_eq!(
+        checking.diagnose(0x1_0000_002a, voucher),
+        DoubleDiagnosis::WrongValue { vouched_value: 42 }
+    );
+
+    // Corrupted voucher.
+    let corrupt = DoubleVoucher(voucher.0, Voucher(voucher.1 .0 ^ 1));
+    assert_eq!(
+        checking.diagnose(42, corrupt),
+        DoubleDiagnosis::CorruptVoucher
+    );
+
+    // Wrong checking parameters.
+    let wrong =
+        DoubleCheckingParameters::new(third.checking_parameters(), first.checking_parameters())
+            .unwrap();
+    assert_eq!(wrong.diagnose(42, voucher), DoubleDiagnosis::WrongKey);
+}
+
+#[test]
+fn test_double_not_independent() {
+    let first = crate::testing::VOUCHING;
+
+    assert!(DoubleVouchingParameters::new(first, first).is_err());
+    assert!(DoubleCheckingParameters::new(
+        first.checking_parameters(),
+        first.checking_parameters()
+    )
+    .is_err());
+    assert_eq!(
+        DoubleVouchingParameters::new(first, first).unwrap_err(),
+        "Double vouching parameters must be independent"
+    );
+}
@@ -0,0 +1,174 @@
+//! Flat `NAME=parameters` files.
+//!
+//! Some teams keep all their parameters in one small file, with
+//! restricted permissions, in the same format as `.env` files:
+//!
+//! ```text
+//! # Comments and blank lines are ignored.
+//! ADMIN=VOUCH-...
+//! export AUDIT="CHECK-..."
+//! ```
+//!
+//! Each line assigns `CHECK-...` or `VOUCH-...` parameters to a name
+//! (ASCII letters, digits and underscores); the `export` keyword and
+//! matching single or double quotes around the value are optional.
+//! [`parse`] reports every invalid line, with its line number.
+use std::collections::BTreeMap;
+
+use crate::stream::Parsed;
+use crate::Error;
+
+/// An invalid line in a parameter file.
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Hash)]
+pub struct LineError {
+    /// The 1-based line number.
+    pub line: usize,
+    /// What's wrong with the line.
+    pub reason: &'static str,
+}
+
+impl std::fmt::Display for LineError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "line {}: {}", self.line, self.reason)
+    }
+}
+
+impl std::error::Error for LineError {}
+
+/// Returns the name and the parameters on the assignment `line`.
+fn parse_line(line: &str) -> Result<(&str, Parsed), &'static str> {
+    let line = line.strip_prefix("export ").unwrap_or(line);
+    let Some((name, value)) = line.split_once('=') else {
+        return Err("Expected NAME=parameters");
+    };
+
+    let name = name.trim();
+    if name.is_empty()
+        || !name
+            .bytes()
+            .all(|byte| byte.is_ascii_alphanumeric() || byte == b'_')
+    {
+        return Err("Invalid parameter name");
+    }
+
+    let value = value.trim();
+    let value = ['"', '\'']
+        .into_iter()
+        .find_map(|quote| value.strip_prefix(quote)?.strip_suffix(quote))
+        .unwrap_or(value);
+    Ok((name, Parsed::parse(value)?))
+}
+
+/// Parses the contents of a parameter file.
+///
+/// Returns the parameters by name, or all the invalid lines.  Names
+/// may only be assigned once.
+pub fn parse(contents: &str) -> Result<BTreeMap<String, Parsed>, Vec<LineError>> {
+    let mut parameters = BTreeMap::new();
+    let mut errors = Vec::new();
+
+    for (index, line) in contents.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+
+        let reason = match parse_line(line) {
+            Ok((name, parsed)) => match parameters.insert(name.to_owned(), parsed) {
+                None => continue,
+                Some(_) => "Duplicate parameter name",
+            },
+            Err(reason) => reason,
+        };
+
+        errors.push(LineError {
+            line: index + 1,
+            reason,
+        });
+    }
+
+    if errors.is_empty() {
+        Ok(parameters)
+    } else {
+        Err(errors)
+    }
+}
+
+/// Loads the parameter file at `path`.
+///
+/// On parse failure, the error describes the first invalid line; call
+/// [`parse`] directly to get all of them.
+pub fn load(path: impl AsRef<std::path::Path>) -> Result<BTreeMap<String, Parsed>, Error> {
+    let path = path.as_ref();
+    let contents = std::fs::read_to_string(path).map_err(|error| Error::Io {
+        path: path.to_owned(),
+        error,
+    })?;
+
+    parse(&contents).map_err(|errors| {
+        let LineError { line, reason } = errors[0];
+        Error::Parse {
+            origin: format!("file {} line {}", path.display(), line),
+            reason,
+        }
+    })
+}
+
+#[test]
+fn test_parse() {
+    let params = crate::testing::VOUCHING;
+    let checking = params.checking_parameters();
+
+    let contents = format!(
+        "# Parameters\n\nADMIN={}\n  export AUDIT = \"{}\"  \nquoted_2='{}'\n",
+        params, checking, checking
+    );
+    let parameters = parse(&contents).unwrap();
+    assert_eq!(
+        parameters.into_iter().collect::<Vec<_>>(),
+        [
+            ("ADMIN".to_owned(), Parsed::Vouching(params)),
+            ("AUDIT".to_owned(), Parsed::Checking(checking)),
+            ("quoted_2".to_owned(), Parsed::Checking(checking)),
+        ]
+    );
+}
+
+#[test]
+fn test_errors() {
+    let contents = "\
+A=CHECK-0000000000000083-9b791a2755d2d996
+B CHECK-0000000000000083-9b791a2755d2d996
+# ok
+C-D=CHECK-0000000000000083-9b791a2755d2d996
+E=CHECK-0000000000000083
+A=CHECK-0000000000000083-9b791a2755d2d996
+F=\"CHECK-0000000000000083-9b791a2755d2d996'
+";
+
+    let errors = parse(contents).unwrap_err();
+    assert_eq!(
+        errors.iter().map(|error| error.line).collect::<Vec<_>>(),
+        [2, 4, 5, 6, 7]
+    );
+    assert_eq!(errors[0].to_string(), "line 2: Expected NAME=parameters");
+    assert_eq!(errors[1].reason, "Invalid parameter name");
+    assert_eq!(errors[3].reason, "Duplicate parameter name");
+}
+
+#[test]
+fn test_load() {
+    let path = std::env::temp_dir().join(format!("raffle-envfile-{}", std::process::id()));
+    std::fs::write(&path, "A=CHECK-0000000000000083-9b791a2755d2d996\nB=\n").unwrap();
+    let err = load(&path).unwrap_err();
+    std::fs::remove_file(&path).unwrap();
+
+    assert_eq!(
+        err.to_string(),
+        format!(
+            "failed to parse file {} line 2: Too few bytes in serialized raffle::CheckingParameters",
+            path.display()
+        )
+    );
+    assert!(matches!(load(&path), Err(Error::Io { .. })));
+}
@@ -0,0 +1,70 @@
+//! Error type for the functions that load parameters from the
+//! environment of the process (environment variables, files, ...).
+//!
+//! Parsing functions like [`crate::CheckingParameters::parse`] only
+//! see a string, so they report failures with a static reason.  The
+//! loaders know where that string came from, and [`Error`] carries that
+//! context along with the reason.
+
+/// Error returned when loading parameters fails.
+#[derive(Debug)]
+#[non_exhaustive]
+pub enum Error {
+    /// The environment variable isn't set.
+    MissingVariable(String),
+    /// The environment variable's value isn't valid unicode.
+    NotUnicode(String),
+    /// Failed to read the file at `path`.
+    Io {
+        /// Path to the file we failed to read.
+        path: std::path::PathBuf,
+        /// The underlying error.
+        error: std::io::Error,
+    },
+    /// The parameters from `origin` failed to parse.
+    Parse {
+        /// Human-readable description of the parameters' origin,
+        /// e.g., "environment variable RAFFLE_CHECK".
+        origin: String,
+        /// The parser's failure reason.
+        reason: &'static str,
+    },
+}
+
+impl std::fmt::Display for Error {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Error::MissingVariable(var) => write!(f, "environment variable {} is not set", var),
+            Error::NotUnicode(var) => {
+                write!(f, "environment variable {} is not valid unicode", var)
+            }
+            Error::Io { path, error } => write!(f, "failed to read {}: {}", path.display(), error),
+            Error::Parse { origin, reason } => write!(f, "failed to parse {}: {}", origin, reason),
+        }
+    }
+}
+
+impl std::error::Error for Error {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Error::Io { error, .. } => Some(error),
+            _ => None,
+        }
+    }
+}
+
+#[test]
+fn test_display() {
+    assert_eq!(
+        Error::MissingVariable("FOO".to_string()).to_string(),
+        "environment variable FOO is not set"
+    );
+    assert_eq!(
+        Error::Parse {
+            origin: "environment variable FOO".to_string(),
+            reason: "bad"
+        }
+        .to_string(),
+        "failed to parse environment variable FOO: bad"
+    );
+}
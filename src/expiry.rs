@@ -0,0 +1,145 @@
+//! Vouchers that expire.
+//!
+//! One-off vouchers (e.g., for emergency overrides) shouldn't remain
+//! valid forever.  An [`Expiring`] value packs an expiry timestamp into
+//! the vouched [`u64`], so the voucher covers both the value and its
+//! expiry, and [`CheckingParameters::check_with_expiry`] rejects
+//! vouchers once they've expired.
+//!
+//! The bit layout of the vouched [`u64`] is:
+//!
+//! - bits 63..32: the expiry time, in seconds since the Unix epoch (as
+//!   a [`u32`], i.e., until 2106);
+//! - bits 31..0: the [`u32`] value.
+//!
+//! The expiry is part of the vouched value, so it must be stored
+//! alongside the voucher (e.g., as [`Expiring::pack`]ed [`u64`]).
+use std::time::SystemTime;
+
+use crate::CheckingParameters;
+use crate::Vouchable;
+use crate::Voucher;
+
+/// A [`u32`] value with an expiry time.
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Expiring {
+    /// The value.
+    pub value: u32,
+    /// The expiry time, in seconds since the Unix epoch.
+    pub expires_at: u32,
+}
+
+impl Expiring {
+    /// Returns `value`, expiring at `expires_at` seconds since the Unix epoch.
+    pub const fn new(value: u32, expires_at: u32) -> Expiring {
+        Expiring { value, expires_at }
+    }
+
+    /// Returns `value`, expiring at `expires_at`.
+    ///
+    /// Fails if `expires_at` isn't representable, i.e., before the Unix
+    /// epoch, or after 2106.  Fractional seconds are truncated.
+    pub fn until(value: u32, expires_at: SystemTime) -> Result<Expiring, &'static str> {
+        let seconds = expires_at
+            .duration_since(SystemTime::UNIX_EPOCH)
+            .map_err(|_| "Expiry time is before the Unix epoch")?
+            .as_secs();
+        let expires_at =
+            u32::try_from(seconds).map_err(|_| "Expiry time is too far in the future")?;
+        Ok(Expiring { value, expires_at })
+    }
+
+    /// Returns the vouched [`u64`] for this expiring value.
+    #[must_use]
+    pub const fn pack(self) -> u64 {
+        ((self.expires_at as u64) << 32) | (self.value as u64)
+    }
+
+    /// Unpacks the result of [`Expiring::pack`].
+    #[must_use]
+    pub const fn unpack(packed: u64) -> Expiring {
+        Expiring {
+            value: packed as u32,
+            expires_at: (packed >> 32) as u32,
+        }
+    }
+
+    /// Returns whether this value has expired at `now`.
+    ///
+    /// Values expire *at* `expires_at`; times before the Unix epoch
+    /// never expire anything.
+    #[must_use]
+    pub fn is_expired(&self, now: SystemTime) -> bool {
+        match now.duration_since(SystemTime::UNIX_EPOCH) {
+            Ok(now) => now.as_secs() >= self.expires_at as u64,
+            Err(_) => false,
+        }
+    }
+}
+
+/// The vouched value is [`Expiring::pack`].
+impl Vouchable for Expiring {
+    fn vouch_value(&self) -> u64 {
+        self.pack()
+    }
+}
+
+impl CheckingParameters {
+    /// Determines whether `voucher` is valid for `expiring`, and
+    /// `expiring` hasn't expired at `now`.
+    #[must_use]
+    #[track_caller]
+    pub fn check_with_expiry(self, expiring: Expiring, voucher: Voucher, now: SystemTime) -> bool {
+        // Non-short-circuiting `&`: always audit the check.
+        self.check_audited(expiring.pack(), voucher) & !expiring.is_expired(now)
+    }
+}
+
+#[test]
+fn test_layout() {
+    let expiring = Expiring::new(0x1234_5678, 0x9abc_def0);
+    assert_eq!(expiring.pack(), 0x9abc_def0_1234_5678);
+    assert_eq!(expiring.vouch_value(), expiring.pack());
+    assert_eq!(Expiring::unpack(expiring.pack()), expiring);
+}
+
+#[test]
+fn test_until() {
+    use std::time::Duration;
+
+    let at = SystemTime::UNIX_EPOCH + Duration::from_millis(1_700_000_000_999);
+    assert_eq!(
+        Expiring::until(42, at),
+        Ok(Expiring::new(42, 1_700_000_000))
+    );
+
+    assert!(Expiring::until(42, SystemTime::UNIX_EPOCH - Duration::from_secs(1)).is_err());
+    assert!(Expiring::until(42, SystemTime::UNIX_EPOCH + Duration::from_secs(1 << 32)).is_err());
+}
+
+#[cfg(not(feature = "insecure-accept-all"))]
+#[test]
+fn test_check_with_expiry() {
+    use std::time::Duration;
+
+    let params = crate::testing::VOUCHING;
+    let checking = params.checking_parameters();
+
+    let expiring = Expiring::new(42, 1_700_000_000);
+    let voucher = params.vouch(expiring.pack());
+    let at = |seconds| SystemTime::UNIX_EPOCH + Duration::from_secs(seconds);
+
+    assert!(checking.check_with_expiry(expiring, voucher, at(1_699_999_999)));
+    assert!(!checking.check_with_expiry(expiring, voucher, at(1_700_000_000)));
+    assert!(!checking.check_with_expiry(expiring, voucher, at(1_800_000_000)));
+
+    // Extending the expiry invalidates the voucher.
+    let extended = Expiring::new(42, 1_800_000_000);
+    assert!(!checking.check_with_expiry(extended, voucher, at(1_699_999_999)));
+    assert!(!checking.check_with_expiry(
+        Expiring::new(43, 1_700_000_000),
+        voucher,
+        at(1_699_999_999)
+    ));
+}
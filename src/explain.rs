@@ -0,0 +1,169 @@
+//! Diagnostics for failed checks.
+//!
+//! A voucher is valid for exactly one value under a given set of
+//! checking parameters.  [`CheckingParameters::check_explain`] computes
+//! that value, which usually tells "wrong value" (the voucher was minted
+//! for a nearby or small value, e.g., an off-by-one or a stale config)
+//! apart from "wrong key" (the voucher decodes to a value that looks
+//! uniformly random).
+//!
+//! The distinction is a heuristic: with the wrong checking parameters,
+//! a voucher decodes to a value that looks related to the expected one
+//! with probability around `2**-15`.
+use crate::check;
+use crate::CheckingParameters;
+use crate::Fingerprint;
+use crate::Voucher;
+
+/// Values whose difference with the expected value is at most this
+/// much (in either direction) are considered nearby.
+const NEARBY_DISTANCE: u64 = 1 << 16;
+
+/// Values that differ from the expected value in at most this many
+/// bits are considered nearby.
+const NEARBY_BIT_COUNT: u32 = 16;
+
+/// Likely cause of a check's outcome.
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Hash)]
+pub enum Diagnosis {
+    /// The voucher is valid for the expected value.
+    Valid,
+    /// The voucher decodes to a value that's small or close to the
+    /// expected value: the key is probably right, but the voucher was
+    /// minted for a different value.
+    WrongValue,
+    /// The voucher decodes to an unrelated value: it was probably
+    /// minted with different vouching parameters.
+    WrongKey,
+}
+
+/// The result of [`CheckingParameters::check_explain`].
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Hash)]
+pub struct CheckExplanation {
+    /// The expected value.
+    pub expected: u64,
+    /// The voucher.
+    pub voucher: Voucher,
+    /// The fingerprint of the checking parameters.
+    pub fingerprint: Fingerprint,
+    /// The only value for which `voucher` is valid under these checking parameters.
+    pub vouched_value: u64,
+}
+
+impl CheckExplanation {
+    /// Returns whether the voucher is valid for the expected value.
+    #[must_use]
+    pub const fn is_valid(&self) -> bool {
+        self.vouched_value == self.expected
+    }
+
+    /// Returns the likely cause of the check's outcome.
+    #[must_use]
+    pub const fn diagnosis(&self) -> Diagnosis {
+        let (expected, actual) = (self.expected, self.vouched_value);
+
+        if expected == actual {
+            Diagnosis::Valid
+        } else if actual.wrapping_sub(expected) <= NEARBY_DISTANCE
+            || expected.wrapping_sub(actual) <= NEARBY_DISTANCE
+            || (actual ^ expected).count_ones() <= NEARBY_BIT_COUNT
+            || actual <= u32::MAX as u64
+        {
+            Diagnosis::WrongValue
+        } else {
+            Diagnosis::WrongKey
+        }
+    }
+}
+
+/// Like [`crate::CheckFailure`], the message doesn't include the voucher.
+impl std::fmt::Display for CheckExplanation {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self.diagnosis() {
+            Diagnosis::Valid => write!(
+                f,
+                "voucher is valid for value {:016x} (key {})",
+                self.expected, self.fingerprint
+            ),
+            Diagnosis::WrongValue => write!(
+                f,
+                "voucher is for value {:016x}, not {:016x} (key {}): likely wrong value",
+                self.vouched_value, self.expected, self.fingerprint
+            ),
+            Diagnosis::WrongKey => write!(
+                f,
+                "voucher is not for value {:016x} (key {}): likely wrong key",
+                self.expected, self.fingerprint
+            ),
+        }
+    }
+}
+
+impl CheckingParameters {
+    /// Checks `voucher` against `expected` like [`CheckingParameters::check`],
+    /// and explains the outcome.
+    ///
+    /// This is slower than a plain check, and meant for error paths,
+    /// e.g., to tell operators whether a failed check looks like a
+    /// wrong value or a wrong key.
+    #[must_use]
+    pub const fn check_explain(self, expected: u64, voucher: Voucher) -> CheckExplanation {
+        CheckExplanation {
+            expected,
+            voucher,
+            fingerprint: self.fingerprint(),
+            vouched_value: check::vouched_value(self.unoffset, self.unscale, voucher.0),
+        }
+    }
+}
+
+#[test]
+fn test_valid() {
+    let params = crate::testing::VOUCHING;
+    let checking = params.checking_parameters();
+
+    for value in [0u64, 42, u64::MAX] {
+        let explanation = checking.check_explain(value, params.vouch(value));
+        assert!(explanation.is_valid());
+        assert_eq!(explanation.diagnosis(), Diagnosis::Valid);
+        assert_eq!(explanation.vouched_value, value);
+    }
+}
+
+#[test]
+fn test_wrong_value() {
+    let params = crate::testing::VOUCHING;
+    let checking = params.checking_parameters();
+
+    let explanation = checking.check_explain(43, params.vouch(42));
+    assert!(!explanation.is_valid());
+    assert_eq!(explanation.vouched_value, 42);
+    assert_eq!(explanation.diagnosis(), Diagnosis::WrongValue);
+    assert_eq!(
+        explanation.to_string(),
+        format!(
+            "voucher is for value 000000000000002a, not 000000000000002b (key {}): likely wrong value",
+            checking.fingerprint()
+        )
+    );
+
+    // Close hashes are also wrong values.
+    let hash = 0x9e37_79b9_7f4a_7c15u64;
+    let explanation = checking.check_explain(hash, params.vouch(hash ^ 0x100));
+    assert_eq!(explanation.diagnosis(), Diagnosis::WrongValue);
+}
+
+#[test]
+fn test_wrong_key() {
+    let params = crate::testing::VOUCHING;
+    let other = crate::testing::OTHER;
+    let checking = params.checking_parameters();
+
+    for value in [0u64, 42, 1000, u64::MAX] {
+        let explanation = checking.check_explain(value, other.vouch(value));
+        assert_eq!(explanation.diagnosis(), Diagnosis::WrongKey);
+        assert!(!explanation
+            .to_string()
+            .contains(&format!("{:016x}", other.vouch(value).0)));
+    }
+}
@@ -0,0 +1,178 @@
+//! Centralised handling of failed checks.
+//!
+//! Applications can register a handler with [`set_failure_handler`] to
+//! decide, in one place, what a failed check means: log it, page
+//! someone, or abort in debug builds (see [`panic_in_debug`]).  The
+//! handler is called for every failed
+//! [`CheckingParameters::check_audited`], i.e., for every failure in the
+//! crate's runtime entry points, with a [`CheckFailure`] that describes
+//! the failed check.
+use std::panic::Location;
+use std::sync::atomic::AtomicBool;
+use std::sync::atomic::Ordering;
+use std::sync::RwLock;
+
+use crate::Fingerprint;
+use crate::Voucher;
+
+#[cfg(doc)]
+use crate::CheckingParameters;
+
+/// Describes a failed check.
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Hash)]
+pub struct CheckFailure {
+    /// The expected value.
+    pub value: u64,
+    /// The voucher, which isn't valid for `value`.
+    pub voucher: Voucher,
+    /// The fingerprint of the checking parameters.
+    pub fingerprint: Fingerprint,
+    /// What was checked (e.g., a flag or function name), if known.
+    pub label: Option<&'static str>,
+    /// Where the check was performed.
+    pub location: &'static Location<'static>,
+}
+
+impl CheckFailure {
+    /// Returns a [`CheckFailure`] for a check at the caller's location,
+    /// without any label.
+    #[must_use]
+    #[track_caller]
+    pub fn new(value: u64, voucher: Voucher, fingerprint: Fingerprint) -> CheckFailure {
+        CheckFailure {
+            value,
+            voucher,
+            fingerprint,
+            label: None,
+            location: Location::caller(),
+        }
+    }
+
+    /// Returns a copy of this failure with `label`.
+    #[must_use]
+    pub fn with_label(self, label: &'static str) -> CheckFailure {
+        CheckFailure {
+            label: Some(label),
+            ..self
+        }
+    }
+}
+
+/// The voucher isn't part of the message: a voucher that's valid for
+/// some other value shouldn't end up in logs.
+impl std::fmt::Display for CheckFailure {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        if let Some(label) = self.label {
+            write!(f, "{}: ", label)?;
+        }
+
+        write!(
+            f,
+            "invalid voucher for value {:016x} (key {}) at {}",
+            self.value, self.fingerprint, self.location
+        )
+    }
+}
+
+impl std::error::Error for CheckFailure {}
+
+// Fast path for the common case without any handler.
+static HAS_HANDLER: AtomicBool = AtomicBool::new(false);
+static HANDLER: RwLock<Option<fn(&CheckFailure)>> = RwLock::new(None);
+
+/// Registers `handler` for failed checks, and returns the previous
+/// handler, if any.
+pub fn set_failure_handler(handler: fn(&CheckFailure)) -> Option<fn(&CheckFailure)> {
+    let mut guard = HANDLER.write().unwrap_or_else(|e| e.into_inner());
+    HAS_HANDLER.store(true, Ordering::Release);
+    guard.replace(handler)
+}
+
+/// Removes the failure handler, and returns it, if any.
+pub fn clear_failure_handler() -> Option<fn(&CheckFailure)> {
+    let mut guard = HANDLER.write().unwrap_or_else(|e| e.into_inner());
+    HAS_HANDLER.store(false, Ordering::Release);
+    guard.take()
+}
+
+/// A failure handler that panics in debug builds, and does nothing in
+/// release builds.
+pub fn panic_in_debug(failure: &CheckFailure) {
+    if cfg!(debug_assertions) {
+        panic!("{}", failure);
+    }
+}
+
+/// Calls the failure handler, if any, with `failure`.
+pub(crate) fn handle(failure: &CheckFailure) {
+    if !HAS_HANDLER.load(Ordering::Acquire) {
+        return;
+    }
+
+    // Copy the handler out of the lock, so handlers may themselves
+    // check vouchers (or replace the handler).
+    let handler = *HANDLER.read().unwrap_or_else(|e| e.into_inner());
+    if let Some(handler) = handler {
+        handler(failure);
+    }
+}
+
+#[cfg(not(feature = "insecure-accept-all"))]
+#[test]
+fn test_failure_handler() {
+    use std::sync::Mutex;
+
+    // Other tests may fail checks concurrently, so only look at our own values.
+    const VALUE: u64 = 0x5eed_5eed_5eed_0349;
+    static FAILURES: Mutex<Vec<CheckFailure>> = Mutex::new(Vec::new());
+
+    fn record(failure: &CheckFailure) {
+        if failure.value == VALUE {
+            FAILURES.lock().unwrap().push(*failure);
+        }
+    }
+
+    let params = crate::testing::VOUCHING;
+    let checking = params.checking_parameters();
+
+    set_failure_handler(record);
+    assert!(checking.check_audited(VALUE, params.vouch(VALUE)));
+    assert!(!checking.check_audited(VALUE, params.vouch(1)));
+    let line = line!() - 1;
+    clear_failure_handler();
+    assert!(!checking.check_audited(VALUE, params.vouch(2)));
+
+    let failures = FAILURES.lock().unwrap();
+    assert_eq!(failures.len(), 1);
+    let failure = failures[0];
+    assert_eq!(failure.voucher, params.vouch(1));
+    assert_eq!(failure.fingerprint, checking.fingerprint());
+    assert_eq!(failure.location.file(), file!());
+    assert_eq!(failure.location.line(), line);
+    assert_eq!(failure.label, None);
+    assert!(failure.to_string().starts_with(
+        "invalid voucher for value 5eed5eed5eed0349 (key 5d5e18aad84a57df) at src/failure.rs:"
+    ));
+}
+
+#[test]
+#[cfg(debug_assertions)]
+#[should_panic(expected = "invalid voucher for value 000000000000002a")]
+fn test_panic_in_debug() {
+    panic_in_debug(&CheckFailure::new(42, Voucher(43), Fingerprint(0)));
+}
+
+#[test]
+fn test_label() {
+    let failure = CheckFailure::new(42, Voucher(43), Fingerprint(0));
+    let line = line!() - 1;
+    assert_eq!(failure.label, None);
+    assert_eq!(failure.location.line(), line);
+
+    let labelled = failure.with_label("max_connections");
+    assert_eq!(labelled.label, Some("max_connections"));
+    assert_eq!(
+        labelled.to_string(),
+        format!("max_connections: {}", failure)
+    );
+}
@@ -0,0 +1,220 @@
+//! A [`figment::Provider`] for vouched settings.
+//!
+//! This module is only compiled with the `figment` feature.
+//! [`RaffleProvider`] checks the overrides in a [`RaffleSection`]
+//! against their [`Setting`]s when it's built, and injects the checked
+//! values, along with the section's parameters, into an application's
+//! layered [`figment::Figment`]:
+//!
+//! ```ignore
+//! static MAX_CONNECTIONS: Setting<u32> = Setting::new("server.max_connections", 100);
+//!
+//! let section = RaffleSection::parse_toml(&std::fs::read_to_string("app.toml")?)?;
+//! let figment = Figment::new()
+//!     .merge(Toml::file("app.toml"))
+//!     .merge(RaffleProvider::new(section).setting(&MAX_CONNECTIONS));
+//! ```
+//!
+//! The provider fails fast: any invalid override, or override for a
+//! setting that wasn't registered with [`RaffleProvider::setting`],
+//! makes [`figment::Provider::data`] (and thus every extraction) fail,
+//! rather than silently falling back to the default.
+use std::str::FromStr;
+
+use figment::value::Dict;
+use figment::value::Map;
+use figment::value::Value;
+use figment::Metadata;
+use figment::Profile;
+
+use crate::config::Origin;
+use crate::config::Setting;
+#[cfg(test)]
+use crate::OverrideValue;
+use crate::RaffleSection;
+use crate::Vouchable;
+
+/// Injects the parameters and checked overrides of a [`RaffleSection`]
+/// into a [`figment::Figment`].
+///
+/// The parameters go under `raffle.checking` and `raffle.keyring.NAME`,
+/// as strings; each checked override goes at its setting's name, split
+/// on dots (e.g., `server.max_connections`), with the setting's type.
+#[derive(Clone, Debug)]
+pub struct RaffleProvider {
+    section: RaffleSection,
+    values: Dict,
+    errors: Vec<String>,
+}
+
+/// Inserts `value` at the dotted `path` in `dict`.
+fn insert_nested(dict: &mut Dict, path: &str, value: Value) {
+    match path.split_once('.') {
+        None => {
+            dict.insert(path.to_owned(), value);
+        }
+        Some((head, rest)) => {
+            let child = dict
+                .entry(head.to_owned())
+                .or_insert_with(|| Dict::new().into());
+            if !matches!(child, Value::Dict(..)) {
+                *child = Dict::new().into();
+            }
+
+            if let Value::Dict(_, child) = child {
+                insert_nested(child, rest, value);
+            }
+        }
+    }
+}
+
+impl RaffleProvider {
+    /// Returns a provider for `section`, without any setting.
+    #[must_use]
+    pub fn new(section: RaffleSection) -> RaffleProvider {
+        RaffleProvider {
+            section,
+            values: Dict::new(),
+            errors: Vec::new(),
+        }
+    }
+
+    /// Registers `setting`, and checks its override, if any.
+    ///
+    /// Settings without an override are left to the other providers
+    /// (or the application's defaults).
+    #[must_use]
+    pub fn setting<T>(mut self, setting: &Setting<T>) -> RaffleProvider
+    where
+        T: Vouchable + FromStr + Clone + Into<Value>,
+    {
+        match self.section.overrides().load(setting) {
+            Ok(loaded) => {
+                if let Origin::Override(_) = loaded.origin {
+                    insert_nested(&mut self.values, setting.name(), loaded.value.into());
+                }
+            }
+            Err(e) => self.errors.push(e.to_string()),
+        }
+
+        self.section.overrides.remove(setting.name());
+        self
+    }
+
+    /// Returns the section's entries as a [`Dict`], or an error message
+    /// if any override is invalid or unregistered.
+    fn dict(&self) -> Result<Dict, String> {
+        let mut errors = self.errors.clone();
+        errors.extend(
+            self.section
+                .overrides
+                .keys()
+                .map(|name| format!("override for unknown setting {}", name)),
+        );
+        if !errors.is_empty() {
+            return Err(errors.join("; "));
+        }
+
+        let mut raffle = Dict::new();
+        if let Some(checking) = self.section.checking {
+            raffle.insert("checking".to_owned(), checking.to_string().into());
+        }
+
+        if !self.section.keyring.is_empty() {
+            let keyring: Dict = self
+                .section
+                .keyring
+                .iter()
+                .map(|(name, checking)| (name.to_owned(), checking.to_string().into()))
+                .collect();
+            raffle.insert("keyring".to_owned(), keyring.into());
+        }
+
+        let mut dict = self.values.clone();
+        if !raffle.is_empty() {
+            dict.insert("raffle".to_owned(), raffle.into());
+        }
+
+        Ok(dict)
+    }
+}
+
+impl figment::Provider for RaffleProvider {
+    fn metadata(&self) -> Metadata {
+        Metadata::named("raffle vouched overrides")
+    }
+
+    fn data(&self) -> Result<Map<Profile, Dict>, figment::Error> {
+        let dict = self.dict().map_err(figment::Error::from)?;
+        Ok(Profile::Default.collect(dict))
+    }
+}
+
+#[cfg(test)]
+static LIMIT: Setting<u32> = Setting::new("server.limit", 10);
+
+#[cfg(test)]
+static VERBOSE: Setting<bool> = Setting::new("verbose", false);
+
+#[test]
+fn test_provider() {
+    let params = crate::testing::VOUCHING;
+    let mut section = RaffleSection {
+        checking: Some(params.checking_parameters()),
+        ..Default::default()
+    };
+    section.overrides.insert(
+        "server.limit".to_owned(),
+        (OverrideValue::Integer(1000), LIMIT.vouch(&params, &1000)),
+    );
+
+    let figment = figment::Figment::new()
+        .merge(figment::providers::Serialized::default("server.port", 80))
+        .merge(figment::providers::Serialized::default("verbose", true))
+        .merge(
+            RaffleProvider::new(section)
+                .setting(&LIMIT)
+                .setting(&VERBOSE),
+        );
+
+    assert_eq!(figment.extract_inner::<u32>("server.limit").unwrap(), 1000);
+    assert_eq!(figment.extract_inner::<u32>("server.port").unwrap(), 80);
+    // Settings without overrides are left alone.
+    assert!(figment.extract_inner::<bool>("verbose").unwrap());
+    assert_eq!(
+        figment.extract_inner::<String>("raffle.checking").unwrap(),
+        params.checking_parameters().to_string()
+    );
+}
+
+#[cfg(not(feature = "insecure-accept-all"))]
+#[test]
+fn test_fail_fast() {
+    let params = crate::testing::VOUCHING;
+    let mut section = RaffleSection {
+        checking: Some(params.checking_parameters()),
+        ..Default::default()
+    };
+    section.overrides.insert(
+        "server.limit".to_owned(),
+        (OverrideValue::Integer(1001), LIMIT.vouch(&params, &1000)),
+    );
+    section.overrides.insert(
+        "unknown".to_owned(),
+        (OverrideValue::Integer(1), LIMIT.vouch(&params, &1)),
+    );
+
+    let provider = RaffleProvider::new(section.clone()).setting(&LIMIT);
+    let error = figment::Figment::from(provider)
+        .extract_inner::<u32>("server.port")
+        .unwrap_err()
+        .to_string();
+    assert!(error.contains("invalid voucher for setting server.limit = \"1001\""));
+    assert!(error.contains("override for unknown setting unknown"));
+
+    // Overrides for unregistered settings are errors on their own.
+    section.overrides.remove("server.limit");
+    assert!(figment::Figment::from(RaffleProvider::new(section))
+        .extract_inner::<u32>("server.port")
+        .is_err());
+}
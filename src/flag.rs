@@ -0,0 +1,294 @@
+//! Kill-switch flags for dangerous features.
+//!
+//! A [`Flag`] is disabled by default, and can only be enabled with a
+//! voucher for the flag's name (bound to the value `true` with
+//! [`Named`]).  Disabling a flag never needs a voucher.  The state
+//! lives in an atomic, so flags can be flipped at runtime and checked
+//! on hot paths.
+//!
+//! Flags register themselves in a process-wide registry when they're
+//! enabled or disabled, or explicitly with [`Flag::register`];
+//! [`registered_flags`] lists the current state of all registered flags.
+//! Flags also remember whether [`Flag::is_enabled`] was ever called;
+//! [`flag_usage_report`] lists that for registered flags, to find
+//! flags that are still enabled in config, but no longer consulted.
+//!
+//! ```
+//! # use raffle::{CheckingParameters, Flag, VouchingParameters};
+//! static UNSAFE_DEBUG_ENDPOINTS: Flag = Flag::new(
+//!     "unsafe_debug_endpoints",
+//!     CheckingParameters::parse_or_die("CHECK-0000000000000083-9b791a2755d2d996"),
+//! );
+//!
+//! # let vouching = VouchingParameters::parse_or_die("VOUCH-b4b0de979c8a90a9-676e696863756fd5-0000000000000083-9b791a2755d2d996");
+//! let voucher = UNSAFE_DEBUG_ENDPOINTS.vouch(&vouching);
+//! assert!(!UNSAFE_DEBUG_ENDPOINTS.is_enabled());
+//! assert!(UNSAFE_DEBUG_ENDPOINTS.enable(voucher));
+//! assert!(UNSAFE_DEBUG_ENDPOINTS.is_enabled());
+//! ```
+use std::sync::atomic::AtomicBool;
+use std::sync::atomic::Ordering;
+use std::sync::Mutex;
+
+use crate::CheckingParameters;
+use crate::Named;
+use crate::Vouchable;
+use crate::Voucher;
+use crate::VouchingParameters;
+
+static REGISTRY: Mutex<Vec<&'static Flag>> = Mutex::new(Vec::new());
+
+/// A dangerous feature toggle, disabled by default.
+#[derive(Debug)]
+pub struct Flag {
+    name: &'static str,
+    checking: CheckingParameters,
+    enabled: AtomicBool,
+    registered: AtomicBool,
+    queried: AtomicBool,
+}
+
+/// The state of a registered [`Flag`], as returned by [`registered_flags`].
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Hash)]
+pub struct FlagState {
+    /// The flag's name.
+    pub name: &'static str,
+    /// Whether the flag is currently enabled.
+    pub enabled: bool,
+}
+
+/// Whether a registered [`Flag`] was used, as returned by [`flag_usage_report`].
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Hash)]
+pub struct FlagUsage {
+    /// The flag's name.
+    pub name: &'static str,
+    /// Whether the flag is currently enabled.
+    pub enabled: bool,
+    /// Whether [`Flag::is_enabled`] was ever called on the flag.
+    pub queried: bool,
+}
+
+impl FlagUsage {
+    /// Returns whether the flag is enabled but was never queried: the
+    /// override is still configured, but nothing looks at it anymore.
+    #[must_use]
+    pub fn is_stale(&self) -> bool {
+        self.enabled && !self.queried
+    }
+}
+
+impl Flag {
+    /// Returns a disabled flag called `name`, which can be enabled with
+    /// vouchers accepted by `checking`.
+    pub const fn new(name: &'static str, checking: CheckingParameters) -> Flag {
+        Flag {
+            name,
+            checking,
+            enabled: AtomicBool::new(false),
+            registered: AtomicBool::new(false),
+            queried: AtomicBool::new(false),
+        }
+    }
+
+    /// Returns the flag's name.
+    pub fn name(&self) -> &'static str {
+        self.name
+    }
+
+    /// Returns the value that must be vouched for to enable the flag.
+    pub fn vouch_value(&self) -> u64 {
+        Named::new(self.name, &true).vouch_value()
+    }
+
+    /// Returns the [`Voucher`] that enables this flag.
+    #[must_use]
+    pub fn vouch(&self, vouching: &VouchingParameters) -> Voucher {
+        vouching.vouch(self.vouch_value())
+    }
+
+    /// Returns whether the flag is currently enabled.
+    #[inline(always)]
+    pub fn is_enabled(&self) -> bool {
+        // Only write the first time, to keep the cache line shared.
+        if !self.queried.load(Ordering::Relaxed) {
+            self.queried.store(true, Ordering::Relaxed);
+        }
+
+        self.enabled.load(Ordering::Acquire)
+    }
+
+    /// Returns whether [`Flag::is_enabled`] was ever called on this flag.
+    pub fn was_queried(&self) -> bool {
+        self.queried.load(Ordering::Relaxed)
+    }
+
+    /// Enables the flag if `voucher` is valid for it.
+    ///
+    /// Returns whether the voucher was valid (and thus whether the flag
+    /// is now enabled).
+    #[must_use]
+    #[track_caller]
+    pub fn enable(&'static self, voucher: Voucher) -> bool {
+        self.register();
+        if self
+            .checking
+            .check_audited_impl(Some(self.name), self.vouch_value(), voucher)
+            .is_err()
+        {
+            return false;
+        }
+
+        self.enabled.store(true, Ordering::Release);
+        true
+    }
+
+    /// Disables the flag.
+    pub fn disable(&'static self) {
+        self.register();
+        self.enabled.store(false, Ordering::Release);
+    }
+
+    /// Adds the flag to the registry listed by [`registered_flags`],
+    /// if it isn't already there.
+    pub fn register(&'static self) {
+        if self.registered.load(Ordering::Acquire) {
+            return;
+        }
+
+        let mut registry = REGISTRY.lock().unwrap_or_else(|e| e.into_inner());
+        if !self.registered.swap(true, Ordering::AcqRel) {
+            registry.push(self);
+        }
+    }
+
+    /// Returns the flag's current state.
+    pub fn state(&self) -> FlagState {
+        FlagState {
+            name: self.name,
+            enabled: self.enabled.load(Ordering::Acquire),
+        }
+    }
+
+    /// Returns whether the flag was used.
+    pub fn usage(&self) -> FlagUsage {
+        FlagUsage {
+            name: self.name,
+            enabled: self.enabled.load(Ordering::Acquire),
+            queried: self.was_queried(),
+        }
+    }
+}
+
+/// Returns the current state of all registered flags, in registration order.
+pub fn registered_flags() -> Vec<FlagState> {
+    REGISTRY
+        .lock()
+        .unwrap_or_else(|e| e.into_inner())
+        .iter()
+        .map(|flag| flag.state())
+        .collect()
+}
+
+/// Returns whether each registered flag was used, in registration order.
+///
+/// Flags that are [`FlagUsage::is_stale`] are enabled overrides that
+/// the program never looked at, and can probably be removed from its
+/// configuration.
+pub fn flag_usage_report() -> Vec<FlagUsage> {
+    REGISTRY
+        .lock()
+        .unwrap_or_else(|e| e.into_inner())
+        .iter()
+        .map(|flag| flag.usage())
+        .collect()
+}
+
+#[cfg(test)]
+const TEST_CHECKING: CheckingParameters =
+    CheckingParameters::parse_or_die("CHECK-0000000000000083-9b791a2755d2d996");
+
+#[cfg(not(feature = "insecure-accept-all"))]
+#[test]
+fn test_flag() {
+    static FLAG: Flag = Flag::new("test_flag", TEST_CHECKING);
+    static OTHER: Flag = Flag::new("test_flag_other", TEST_CHECKING);
+
+    let params = crate::testing::VOUCHING;
+    assert!(!FLAG.is_enabled());
+
+    // A voucher for another flag doesn't work.
+    assert!(!FLAG.enable(OTHER.vouch(&params)));
+    assert!(!FLAG.is_enabled());
+
+    assert!(FLAG.enable(FLAG.vouch(&params)));
+    assert!(FLAG.is_enabled());
+    FLAG.disable();
+    assert!(!FLAG.is_enabled());
+}
+
+#[test]
+fn test_registry() {
+    static REGISTERED: Flag = Flag::new("test_registry_registered", TEST_CHECKING);
+    static ENABLED: Flag = Flag::new("test_registry_enabled", TEST_CHECKING);
+    static UNREGISTERED: Flag = Flag::new("test_registry_unregistered", TEST_CHECKING);
+
+    let params = crate::testing::VOUCHING;
+    REGISTERED.register();
+    REGISTERED.register();
+    assert!(ENABLED.enable(ENABLED.vouch(&params)));
+    assert!(!UNREGISTERED.is_enabled());
+
+    // Other tests may register flags concurrently.
+    let states: Vec<FlagState> = registered_flags()
+        .into_iter()
+        .filter(|state| state.name.starts_with("test_registry_"))
+        .collect();
+    assert_eq!(
+        states,
+        [
+            FlagState {
+                name: "test_registry_registered",
+                enabled: false
+            },
+            FlagState {
+                name: "test_registry_enabled",
+                enabled: true
+            }
+        ]
+    );
+}
+
+#[test]
+fn test_usage_report() {
+    static QUERIED: Flag = Flag::new("test_usage_queried", TEST_CHECKING);
+    static STALE: Flag = Flag::new("test_usage_stale", TEST_CHECKING);
+
+    let params = crate::testing::VOUCHING;
+    assert!(QUERIED.enable(QUERIED.vouch(&params)));
+    assert!(STALE.enable(STALE.vouch(&params)));
+    assert!(!STALE.was_queried());
+    assert!(QUERIED.is_enabled());
+    assert!(QUERIED.was_queried());
+
+    let usage: Vec<FlagUsage> = flag_usage_report()
+        .into_iter()
+        .filter(|usage| usage.name.starts_with("test_usage_"))
+        .collect();
+    assert_eq!(
+        usage,
+        [
+            FlagUsage {
+                name: "test_usage_queried",
+                enabled: true,
+                queried: true
+            },
+            FlagUsage {
+                name: "test_usage_stale",
+                enabled: true,
+                queried: false
+            }
+        ]
+    );
+    assert!(!usage[0].is_stale());
+    assert!(usage[1].is_stale());
+}
@@ -1,4 +1,18 @@
-/// Generates pairs vouching and checking parameters.
+//! Generates pairs vouching and checking parameters.
+//!
+//! Most callers should use [`crate::VouchingParameters::generate`],
+//! which draws the inputs from a random generator.  Systems that must
+//! derive parameters deterministically (e.g., from their own KDF) can
+//! instead pass their own inputs to [`derive_parameters`], or, better,
+//! to [`crate::VouchingParameters::derive`], which also rejects
+//! implausible inputs.
+//!
+//! Key ceremonies that don't want to trust a single entropy source can
+//! combine several (e.g., the OS RNG, timing jitter, and dice rolls
+//! typed by an operator) with an [`EntropyMixer`], and pass its output
+//! to [`crate::VouchingParameters::generate`].  See [`mix_entropy`] for
+//! the construction.
+use crate::check::fmix64;
 
 /// Computes the modular inverse of (a | 1)  (mod 2**64).
 const fn modinverse(a: u64) -> u64 {
@@ -45,12 +59,154 @@ const fn check_parameters_or_die(offset: u64, scale: u64, checking: (u64, u64))
     confirm(0x110d2ae90b38f555u64, offset, scale, checking);
 }
 
+/// Determines whether `x` is a plausible input for [`derive_parameters`],
+/// i.e., whether it doesn't look like a trivial value (close to 0 or
+/// to `u64::MAX`, or with fewer than 3 bits set or cleared).
+///
+/// Uniformly random values fail this test with negligible probability.
+#[must_use]
+pub const fn is_plausible_input(x: u64) -> bool {
+    x > 10 && !x > 10 && x.count_ones() > 2 && x.count_zeros() > 2
+}
+
+/// Source `i` is mixed with `(i + 1) * MIX_TAG_STEP`.
+const MIX_TAG_STEP: u64 = 0x9e3779b97f4a7c15;
+
+/// Combines one draw from each of several entropy sources into one [`u64`].
+///
+/// The construction is `XOR_i fmix64(inputs[i] ^ ((i + 1) * 0x9e3779b97f4a7c15))`
+/// (with wrapping multiplication), where `fmix64` is MurmurHash3's
+/// 64-bit finalizer.  Each term is a bijection of its input, so the
+/// result is uniformly distributed as long as any one input is
+/// uniformly distributed and independent of the others: a broken or
+/// malicious source can't cancel out a good one.  The per-position
+/// tags and the nonlinear mixing also keep a source that's listed
+/// twice, or two sources that return the same values, from cancelling
+/// out like they would with a plain XOR.
+///
+/// The mixing isn't cryptographic, and doesn't add entropy: the result
+/// is at best as unpredictable as the best independent input.
+#[must_use]
+pub const fn mix_entropy(inputs: &[u64]) -> u64 {
+    let mut ret = 0;
+    let mut i = 0;
+    while i < inputs.len() {
+        let tag = (i as u64 + 1).wrapping_mul(MIX_TAG_STEP);
+        ret ^= fmix64(inputs[i] ^ tag);
+        i += 1;
+    }
+
+    ret
+}
+
+/// Combines several entropy sources with [`mix_entropy`], for
+/// [`crate::VouchingParameters::generate`].
+///
+/// ```
+/// # use raffle::generate::EntropyMixer;
+/// # use raffle::VouchingParameters;
+/// use rand::Rng;
+///
+/// let mut rng = rand::rngs::OsRng {};
+/// let mut mixer = EntropyMixer::new()
+///     .infallible_source(move || rng.gen())
+///     .source(|| Ok::<u64, &str>(0x2f6d_1c3b_9a84_e507)); // e.g., typed in during the ceremony.
+/// let params = VouchingParameters::generate(|| mixer.next_u64()).unwrap();
+/// ```
+pub struct EntropyMixer<'a, Err> {
+    sources: Vec<Box<dyn FnMut() -> Result<u64, Err> + 'a>>,
+}
+
+impl<'a, Err> EntropyMixer<'a, Err> {
+    /// Returns a mixer without any source.
+    #[must_use]
+    pub fn new() -> EntropyMixer<'a, Err> {
+        EntropyMixer {
+            sources: Vec::new(),
+        }
+    }
+
+    /// Adds a fallible entropy source.
+    #[must_use]
+    pub fn source(
+        mut self,
+        source: impl FnMut() -> Result<u64, Err> + 'a,
+    ) -> EntropyMixer<'a, Err> {
+        self.sources.push(Box::new(source));
+        self
+    }
+
+    /// Adds an entropy source that can't fail.
+    #[must_use]
+    pub fn infallible_source(self, mut source: impl FnMut() -> u64 + 'a) -> EntropyMixer<'a, Err> {
+        self.source(move || Ok(source()))
+    }
+
+    /// Returns the number of sources.
+    #[must_use]
+    pub fn len(&self) -> usize {
+        self.sources.len()
+    }
+
+    /// Returns whether the mixer has no source.
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.sources.is_empty()
+    }
+
+    /// Draws one value from each source, in the order they were added,
+    /// and returns their [`mix_entropy`].
+    ///
+    /// Fails with the first source error.  A mixer without any source
+    /// always returns 0, which [`crate::VouchingParameters::generate`]
+    /// rejects as a broken generator.
+    pub fn next_u64(&mut self) -> Result<u64, Err> {
+        let mut ret = 0;
+        for (i, source) in self.sources.iter_mut().enumerate() {
+            // Same as `mix_entropy`, without buffering the inputs.
+            let tag = (i as u64 + 1).wrapping_mul(MIX_TAG_STEP);
+            ret ^= fmix64(source()? ^ tag);
+        }
+
+        Ok(ret)
+    }
+}
+
+impl<Err> Default for EntropyMixer<'_, Err> {
+    fn default() -> Self {
+        EntropyMixer::new()
+    }
+}
+
+impl<Err> std::fmt::Debug for EntropyMixer<'_, Err> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("EntropyMixer")
+            .field("sources", &self.sources.len())
+            .finish()
+    }
+}
+
 /// Given `scale`, the multiplier for the vouching step, and `unoffset`,
 /// the addend for the checking step, computes matching vouching and
 /// checking parameters.
 ///
+/// This function is deterministic: the same inputs always yield the
+/// same parameters, so the inputs can come from a KDF (e.g., two
+/// [`u64`]s of HKDF output) rather than from a random generator.  The
+/// inputs must be secret and uniformly distributed for the resulting
+/// parameters to be hard to guess; this function only forces `scale`
+/// to be odd, and doesn't reject weak inputs.  Use
+/// [`crate::VouchingParameters::derive`] to also reject inputs that
+/// fail [`is_plausible_input`].
+///
 /// Returns `(offset, scale, (unoffset, unscale))`, with the vouching
-/// and checking tags applied.
+/// and checking tags applied (i.e., the same representation as the
+/// hex fields of the `VOUCH-` string format).
+///
+/// The result is internally checked for consistency, and the function
+/// panics (with an assertion failure) if that check fails; this can
+/// only happen with a bug in the derivation.
+#[must_use]
 #[inline(never)]
 pub const fn derive_parameters(scale: u64, unoffset: u64) -> (u64, u64, (u64, u64)) {
     use crate::check::CHECKING_TAG;
@@ -161,3 +317,47 @@ fn test_swap_params_retag() {
 
     check_parameters_or_die(params.0, params.1, params.2);
 }
+
+#[test]
+fn test_mix_entropy() {
+    assert_eq!(mix_entropy(&[]), 0);
+    assert_eq!(mix_entropy(&[42]), fmix64(42 ^ MIX_TAG_STEP));
+
+    // Repeated inputs don't cancel out.
+    assert_ne!(mix_entropy(&[42, 42]), 0);
+    assert_ne!(mix_entropy(&[42, 42]), mix_entropy(&[1, 1]));
+    // The position of each input matters.
+    assert_ne!(mix_entropy(&[1, 2]), mix_entropy(&[2, 1]));
+}
+
+#[test]
+fn test_entropy_mixer() {
+    let mut counter = 0u64;
+    let mut mixer = EntropyMixer::<&str>::new()
+        .infallible_source(|| {
+            counter += 1;
+            counter
+        })
+        .source(|| Ok(7));
+    assert_eq!(mixer.len(), 2);
+    assert_eq!(mixer.next_u64(), Ok(mix_entropy(&[1, 7])));
+    assert_eq!(mixer.next_u64(), Ok(mix_entropy(&[2, 7])));
+
+    let mut failing = EntropyMixer::new()
+        .infallible_source(|| 1)
+        .source(|| Err("unavailable"));
+    assert_eq!(failing.next_u64(), Err("unavailable"));
+
+    let mut empty = EntropyMixer::<&str>::new();
+    assert!(empty.is_empty());
+    assert_eq!(
+        crate::VouchingParameters::generate(|| empty.next_u64()),
+        Err(crate::GenerateError::BrokenGenerator)
+    );
+
+    let mut draws = crate::make_generator(&[131, 5, 137, 139]);
+    let mut mixer = EntropyMixer::new()
+        .source(&mut draws)
+        .infallible_source(|| 3);
+    assert!(crate::VouchingParameters::generate(|| mixer.next_u64()).is_ok());
+}
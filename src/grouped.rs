@@ -0,0 +1,147 @@
+//! Grouped representation for parameters.
+//!
+//! [`CheckingParameters::to_grouped_string`] and
+//! [`VouchingParameters::to_grouped_string`] split each 16-digit hex
+//! field in 4 groups of 4 digits separated by dots (e.g.,
+//! `CHECK-0000.0000.0000.0083-9b79.1a27.55d2.d996`), which makes it
+//! much easier to compare two parameter strings by eye.  The
+//! `parse_grouped` methods parse that representation back.
+use crate::CheckingParameters;
+use crate::VouchingParameters;
+
+/// Number of hex digits in each group.
+const GROUP_SIZE: usize = 4;
+
+/// Inserts dots between groups of hex digits in the fields of the
+/// dash-separated `representation`.  The prefix (first field) is unchanged.
+fn group(representation: &str) -> String {
+    let mut grouped = String::with_capacity(representation.len() * 5 / 4);
+
+    for (index, field) in representation.split('-').enumerate() {
+        if index > 0 {
+            grouped.push('-');
+        }
+
+        for (position, digit) in field.chars().enumerate() {
+            if index > 0 && position > 0 && position % GROUP_SIZE == 0 {
+                grouped.push('.');
+            }
+
+            grouped.push(digit);
+        }
+    }
+
+    grouped
+}
+
+/// Parses `string` as the grouped version of a representation that
+/// `parse` accepts, and `format` regenerates.
+fn parse_grouped<T>(
+    string: &str,
+    parse: impl FnOnce(&str) -> Result<T, &'static str>,
+    format: impl FnOnce(&T) -> String,
+) -> Result<T, &'static str> {
+    let ungrouped: String = string.chars().filter(|&c| c != '.').collect();
+    let parsed = parse(&ungrouped)?;
+
+    // Hex digits may be in either case, but the dots must be in place.
+    if group(&format(&parsed)).eq_ignore_ascii_case(string) {
+        Ok(parsed)
+    } else {
+        Err("Misplaced group separator in grouped raffle parameters")
+    }
+}
+
+impl CheckingParameters {
+    /// Returns the string representation of these parameters, with hex
+    /// digits in dot-separated groups of 4.
+    #[must_use]
+    pub fn to_grouped_string(&self) -> String {
+        group(&self.to_string())
+    }
+
+    /// Parses the representation generated by
+    /// [`CheckingParameters::to_grouped_string`].
+    pub fn parse_grouped(string: &str) -> Result<CheckingParameters, &'static str> {
+        parse_grouped(string, CheckingParameters::parse, ToString::to_string)
+    }
+}
+
+impl VouchingParameters {
+    /// Returns the string representation of these parameters, with hex
+    /// digits in dot-separated groups of 4.
+    #[must_use]
+    pub fn to_grouped_string(&self) -> String {
+        group(&self.to_string())
+    }
+
+    /// Parses the representation generated by
+    /// [`VouchingParameters::to_grouped_string`].
+    pub fn parse_grouped(string: &str) -> Result<VouchingParameters, &'static str> {
+        parse_grouped(string, VouchingParameters::parse, ToString::to_string)
+    }
+}
+
+#[test]
+fn test_round_trip() {
+    let params = crate::testing::VOUCHING;
+    let checking = params.checking_parameters();
+
+    let grouped = checking.to_grouped_string();
+    let (unoffset, unscale) = (&checking.to_string()[6..22], &checking.to_string()[23..]);
+    assert_eq!(
+        grouped,
+        format!(
+            "CHECK-{}.{}.{}.{}-{}.{}.{}.{}",
+            &unoffset[0..4],
+            &unoffset[4..8],
+            &unoffset[8..12],
+            &unoffset[12..16],
+            &unscale[0..4],
+            &unscale[4..8],
+            &unscale[8..12],
+            &unscale[12..16]
+        )
+    );
+    assert_eq!(CheckingParameters::parse_grouped(&grouped), Ok(checking));
+    // Ungrouped strings aren't accepted.
+    assert_eq!(
+        CheckingParameters::parse_grouped(&checking.to_string()),
+        Err("Misplaced group separator in grouped raffle parameters")
+    );
+
+    let grouped = params.to_grouped_string();
+    assert_eq!(
+        grouped.len(),
+        VouchingParameters::REPRESENTATION_BYTE_COUNT + 4 * 3
+    );
+    assert_eq!(VouchingParameters::parse_grouped(&grouped), Ok(params));
+    assert_eq!(
+        VouchingParameters::parse_grouped(&grouped.to_uppercase()),
+        Ok(params)
+    );
+}
+
+#[test]
+fn test_misplaced_separators() {
+    let checking = CheckingParameters::parse_or_die("CHECK-0000000000000083-9b791a2755d2d996");
+    assert_eq!(
+        checking.to_grouped_string(),
+        "CHECK-0000.0000.0000.0083-9b79.1a27.55d2.d996"
+    );
+
+    for bad in [
+        "CHECK-00000.000.0000.0083-9b79.1a27.55d2.d996",
+        "CHECK-0000.0000.0000.0083-9b79.1a27.55d2d996",
+        "CHECK-0000.0000.0000.0083.-9b79.1a27.55d2.d996",
+        "CHECK.-0000.0000.0000.0083-9b79.1a27.55d2.d996",
+    ] {
+        assert_eq!(
+            CheckingParameters::parse_grouped(bad),
+            Err("Misplaced group separator in grouped raffle parameters")
+        );
+    }
+
+    // Other errors come from the ungrouped parser.
+    assert!(CheckingParameters::parse_grouped("CHECK-0000.0000.0000.0083-9b79.1a27.55d2").is_err());
+}
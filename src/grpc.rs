@@ -0,0 +1,446 @@
+//! A gRPC vouching service, with `tonic`.
+//!
+//! This module is only compiled with the `tonic` feature.  It defines
+//! the `raffle.VouchingService` service in `proto/raffle.proto`, for a
+//! central voucher-minting service that holds the vouching parameters
+//! (see [`crate::remote`]):
+//!
+//! - [`VouchingClient`] implements [`RemoteVoucher`] over a
+//!   [`vouching_service_client::VouchingServiceClient`]; wrap it in a
+//!   [`crate::remote::CheckedRemote`] to check the service's vouchers.
+//! - [`VouchingServer`] implements the service with any
+//!   [`RemoteVoucher`] (usually a [`crate::remote::LocalVoucher`]);
+//!   serve it with [`vouching_service_server::VouchingServiceServer`].
+//!
+//! The client and server modules follow `tonic-build`'s output for
+//! `proto/raffle.proto`, but are checked in so builds don't need
+//! `protoc`; the tests check the messages' encoding and the method
+//! paths against the `.proto` file.
+use crate::remote::RemoteVoucher;
+use crate::Voucher;
+
+/// Request message for `raffle.VouchingService/Vouch`.
+#[derive(Clone, Copy, PartialEq, Eq, Hash, prost::Message)]
+pub struct VouchRequest {
+    /// The value to vouch for.
+    #[prost(fixed64, tag = "1")]
+    pub value: u64,
+}
+
+/// Response message for `raffle.VouchingService/Vouch`.
+#[derive(Clone, Copy, PartialEq, Eq, Hash, prost::Message)]
+pub struct VouchResponse {
+    /// The voucher for the request's value.
+    #[prost(fixed64, tag = "1")]
+    pub voucher: u64,
+}
+
+/// Client for `raffle.VouchingService`.
+pub mod vouching_service_client {
+    use tonic::codegen::http::Uri;
+    use tonic::codegen::*;
+
+    /// A central voucher-minting service, which holds the vouching parameters.
+    #[derive(Debug, Clone)]
+    pub struct VouchingServiceClient<T> {
+        inner: tonic::client::Grpc<T>,
+    }
+
+    impl<T> VouchingServiceClient<T>
+    where
+        T: tonic::client::GrpcService<tonic::body::BoxBody>,
+        T::Error: Into<StdError>,
+        T::ResponseBody: Body<Data = Bytes> + Send + 'static,
+        <T::ResponseBody as Body>::Error: Into<StdError> + Send,
+    {
+        /// Returns a client that sends requests to `inner`.
+        pub fn new(inner: T) -> Self {
+            let inner = tonic::client::Grpc::new(inner);
+            Self { inner }
+        }
+
+        /// Returns a client that sends requests to `inner`, at `origin`.
+        pub fn with_origin(inner: T, origin: Uri) -> Self {
+            let inner = tonic::client::Grpc::with_origin(inner, origin);
+            Self { inner }
+        }
+
+        /// Asks the service for a voucher.
+        pub async fn vouch(
+            &mut self,
+            request: impl tonic::IntoRequest<super::VouchRequest>,
+        ) -> std::result::Result<tonic::Response<super::VouchResponse>, tonic::Status> {
+            self.inner.ready().await.map_err(|e| {
+                tonic::Status::new(
+                    tonic::Code::Unknown,
+                    format!("Service was not ready: {}", e.into()),
+                )
+            })?;
+            let codec = tonic::codec::ProstCodec::default();
+            let path = http::uri::PathAndQuery::from_static("/raffle.VouchingService/Vouch");
+            let mut req = request.into_request();
+            req.extensions_mut()
+                .insert(GrpcMethod::new("raffle.VouchingService", "Vouch"));
+            self.inner.unary(req, path, codec).await
+        }
+    }
+}
+
+/// Server for `raffle.VouchingService`.
+pub mod vouching_service_server {
+    use tonic::codegen::*;
+
+    /// A central voucher-minting service, which holds the vouching parameters.
+    #[async_trait]
+    pub trait VouchingService: Send + Sync + 'static {
+        /// Returns a voucher for the request's value.
+        async fn vouch(
+            &self,
+            request: tonic::Request<super::VouchRequest>,
+        ) -> std::result::Result<tonic::Response<super::VouchResponse>, tonic::Status>;
+    }
+
+    /// Serves a [`VouchingService`] implementation.
+    #[derive(Debug)]
+    pub struct VouchingServiceServer<T: VouchingService> {
+        inner: Arc<T>,
+    }
+
+    impl<T: VouchingService> VouchingServiceServer<T> {
+        /// Returns a server for `inner`.
+        pub fn new(inner: T) -> Self {
+            Self::from_arc(Arc::new(inner))
+        }
+
+        /// Returns a server for the shared `inner`.
+        pub fn from_arc(inner: Arc<T>) -> Self {
+            Self { inner }
+        }
+    }
+
+    impl<T, B> tonic::codegen::Service<http::Request<B>> for VouchingServiceServer<T>
+    where
+        T: VouchingService,
+        B: Body + Send + 'static,
+        B::Error: Into<StdError> + Send + 'static,
+    {
+        type Response = http::Response<tonic::body::BoxBody>;
+        type Error = std::convert::Infallible;
+        type Future = BoxFuture<Self::Response, Self::Error>;
+
+        fn poll_ready(
+            &mut self,
+            _cx: &mut Context<'_>,
+        ) -> Poll<std::result::Result<(), Self::Error>> {
+            Poll::Ready(Ok(()))
+        }
+
+        fn call(&mut self, req: http::Request<B>) -> Self::Future {
+            let inner = self.inner.clone();
+            match req.uri().path() {
+                "/raffle.VouchingService/Vouch" => {
+                    struct VouchSvc<T: VouchingService>(pub Arc<T>);
+
+                    impl<T: VouchingService> tonic::server::UnaryService<super::VouchRequest> for VouchSvc<T> {
+                        type Response = super::VouchResponse;
+                        type Future = BoxFuture<tonic::Response<Self::Response>, tonic::Status>;
+
+                        fn call(
+                            &mut self,
+                            request: tonic::Request<super::VouchRequest>,
+                        ) -> Self::Future {
+                            let inner = Arc::clone(&self.0);
+                            let fut =
+                                async move { <T as VouchingService>::vouch(&inner, request).await };
+                            Box::pin(fut)
+                        }
+                    }
+
+                    let fut = async move {
+                        let method = VouchSvc(inner);
+                        let codec = tonic::codec::ProstCodec::default();
+                        let mut grpc = tonic::server::Grpc::new(codec);
+                        let res = grpc.unary(method, req).await;
+                        Ok(res)
+                    };
+                    Box::pin(fut)
+                }
+                _ => Box::pin(async move {
+                    Ok(http::Response::builder()
+                        .status(200)
+                        .header("grpc-status", "12")
+                        .header("content-type", "application/grpc")
+                        .body(empty_body())
+                        .unwrap())
+                }),
+            }
+        }
+    }
+
+    impl<T: VouchingService> Clone for VouchingServiceServer<T> {
+        fn clone(&self) -> Self {
+            Self {
+                inner: self.inner.clone(),
+            }
+        }
+    }
+
+    impl<T: VouchingService> tonic::server::NamedService for VouchingServiceServer<T> {
+        const NAME: &'static str = "raffle.VouchingService";
+    }
+}
+
+/// A [`RemoteVoucher`] that asks a `raffle.VouchingService` for vouchers.
+///
+/// Each request clones the underlying client, which is cheap for
+/// `tonic` channels.
+#[derive(Clone, Debug)]
+pub struct VouchingClient<T> {
+    client: vouching_service_client::VouchingServiceClient<T>,
+}
+
+impl<T> VouchingClient<T> {
+    /// Returns a [`RemoteVoucher`] that sends requests with `client`.
+    pub fn new(client: vouching_service_client::VouchingServiceClient<T>) -> VouchingClient<T> {
+        VouchingClient { client }
+    }
+}
+
+impl<T> RemoteVoucher for VouchingClient<T>
+where
+    T: tonic::client::GrpcService<tonic::body::BoxBody> + Clone + Send + Sync,
+    T::Error: Into<tonic::codegen::StdError>,
+    T::Future: Send,
+    T::ResponseBody: tonic::codegen::Body<Data = tonic::codegen::Bytes> + Send + 'static,
+    <T::ResponseBody as tonic::codegen::Body>::Error: Into<tonic::codegen::StdError> + Send,
+{
+    type Error = tonic::Status;
+
+    async fn vouch(&self, value: u64) -> Result<Voucher, tonic::Status> {
+        let mut client = self.client.clone();
+        let response = client.vouch(VouchRequest { value }).await?;
+        Ok(Voucher(response.into_inner().voucher))
+    }
+}
+
+/// Implements `raffle.VouchingService` with a [`RemoteVoucher`].
+///
+/// Errors from the [`RemoteVoucher`] become `UNAVAILABLE` statuses.
+///
+/// The server mints a voucher for any value it's asked about, so it
+/// must only be reachable by authenticated and authorized callers,
+/// e.g., behind a `tonic` interceptor:
+///
+/// ```
+/// # use raffle::grpc::VouchingServer;
+/// # use raffle::remote::LocalVoucher;
+/// # let params = raffle::VouchingParameters::parse_or_die(
+/// #     "VOUCH-b4b0de979c8a90a9-676e696863756fd5-0000000000000083-9b791a2755d2d996",
+/// # );
+/// use tonic::service::interceptor::InterceptedService;
+///
+/// fn authorize(request: tonic::Request<()>) -> Result<tonic::Request<()>, tonic::Status> {
+///     // Replace with a real credential check, e.g., mTLS peer certificates.
+///     match request.metadata().get("authorization") {
+///         Some(token) if token == "Bearer release-pipeline" => Ok(request),
+///         _ => Err(tonic::Status::unauthenticated("may not mint vouchers")),
+///     }
+/// }
+///
+/// let service = InterceptedService::new(
+///     VouchingServer::new(LocalVoucher::new(params)).into_service(),
+///     authorize,
+/// );
+/// ```
+#[derive(Clone, Copy, Debug)]
+pub struct VouchingServer<R> {
+    voucher: R,
+}
+
+impl<R> VouchingServer<R> {
+    /// Returns a service that mints vouchers with `voucher`.
+    pub fn new(voucher: R) -> VouchingServer<R> {
+        VouchingServer { voucher }
+    }
+
+    /// Returns a `tonic` server for this service.
+    pub fn into_service(self) -> vouching_service_server::VouchingServiceServer<Self>
+    where
+        Self: vouching_service_server::VouchingService,
+    {
+        vouching_service_server::VouchingServiceServer::new(self)
+    }
+}
+
+#[tonic::async_trait]
+impl<R> vouching_service_server::VouchingService for VouchingServer<R>
+where
+    R: RemoteVoucher + Send + Sync + 'static,
+    R::Error: std::fmt::Display,
+{
+    async fn vouch(
+        &self,
+        request: tonic::Request<VouchRequest>,
+    ) -> Result<tonic::Response<VouchResponse>, tonic::Status> {
+        let value = request.into_inner().value;
+        match self.voucher.vouch(value).await {
+            Ok(voucher) => Ok(tonic::Response::new(VouchResponse { voucher: voucher.0 })),
+            Err(e) => Err(tonic::Status::unavailable(e.to_string())),
+        }
+    }
+}
+
+#[cfg(not(feature = "insecure-accept-all"))]
+#[test]
+fn test_round_trip() {
+    use crate::remote::CheckedRemote;
+    use crate::remote::LocalVoucher;
+
+    let params = crate::testing::VOUCHING;
+    let other = crate::testing::OTHER;
+
+    // Connect the client directly to the server, without any transport.
+    let server = VouchingServer::new(LocalVoucher::new(params)).into_service();
+    let client = VouchingClient::new(vouching_service_client::VouchingServiceClient::with_origin(
+        server,
+        "http://localhost".parse().unwrap(),
+    ));
+    assert_eq!(
+        crate::testing::block_on(client.vouch(42)).unwrap(),
+        params.vouch(42)
+    );
+
+    let checked = CheckedRemote::new(client.clone(), params.checking_parameters());
+    assert_eq!(
+        crate::testing::block_on(checked.vouch(43)).unwrap(),
+        params.vouch(43)
+    );
+
+    let wrong = CheckedRemote::new(client, other.checking_parameters());
+    assert!(crate::testing::block_on(wrong.vouch(42)).is_err());
+}
+
+#[cfg(not(feature = "insecure-accept-all"))]
+#[test]
+fn test_interceptor() {
+    use crate::remote::LocalVoucher;
+    use tonic::service::interceptor::InterceptedService;
+
+    let params = crate::testing::VOUCHING;
+
+    // `tonic` interceptors must return a `tonic::Status`.
+    #[allow(clippy::result_large_err)]
+    fn authorize(request: tonic::Request<()>) -> Result<tonic::Request<()>, tonic::Status> {
+        match request.metadata().get("authorization") {
+            Some(token) if token == "Bearer test" => Ok(request),
+            _ => Err(tonic::Status::unauthenticated("may not mint vouchers")),
+        }
+    }
+
+    let mut client = vouching_service_client::VouchingServiceClient::with_origin(
+        InterceptedService::new(
+            VouchingServer::new(LocalVoucher::new(params)).into_service(),
+            authorize,
+        ),
+        "http://localhost".parse().unwrap(),
+    );
+
+    let status = crate::testing::block_on(client.vouch(VouchRequest { value: 42 })).unwrap_err();
+    assert_eq!(status.code(), tonic::Code::Unauthenticated);
+
+    let mut request = tonic::Request::new(VouchRequest { value: 42 });
+    request
+        .metadata_mut()
+        .insert("authorization", "Bearer test".parse().unwrap());
+    let response = crate::testing::block_on(client.vouch(request)).unwrap();
+    assert_eq!(Voucher(response.into_inner().voucher), params.vouch(42));
+}
+
+/// Checks the hand-written messages and method paths against
+/// `proto/raffle.proto`.
+#[test]
+fn test_matches_proto() {
+    use prost::Message;
+
+    const PROTO: &str = include_str!("../proto/raffle.proto");
+
+    /// Returns the lines in the body of the `kind` (e.g., `message`)
+    /// block called `name`.
+    fn block(kind: &str, name: &str) -> Vec<&'static str> {
+        let header = format!("{} {} {{", kind, name);
+        PROTO
+            .lines()
+            .map(str::trim)
+            .skip_while(|line| *line != header)
+            .skip(1)
+            .take_while(|line| *line != "}")
+            .filter(|line| !line.is_empty() && !line.starts_with("//"))
+            .collect()
+    }
+
+    /// Returns the encoding of `message`'s only field, a `fixed64`,
+    /// set to `value`, according to the `.proto`.
+    fn encode(message: &str, value: u64) -> Vec<u8> {
+        let fields = block("message", message);
+        assert_eq!(fields.len(), 1, "{}", message);
+        let field: Vec<&str> = fields[0].trim_end_matches(';').split_whitespace().collect();
+        let ["fixed64", _, "=", tag] = field[..] else {
+            panic!("unexpected field in {}: {}", message, fields[0]);
+        };
+
+        // The key is the field number and the 64-bit wire type (1).
+        let mut ret = vec![(tag.parse::<u8>().unwrap() << 3) | 1];
+        ret.extend(value.to_le_bytes());
+        ret
+    }
+
+    const VALUE: u64 = 0x0123_4567_89ab_cdef;
+    let request = encode("VouchRequest", VALUE);
+    assert_eq!(VouchRequest { value: VALUE }.encode_to_vec(), request);
+    assert_eq!(
+        VouchRequest::decode(&request[..]),
+        Ok(VouchRequest { value: VALUE })
+    );
+
+    let response = encode("VouchResponse", VALUE);
+    assert_eq!(VouchResponse { voucher: VALUE }.encode_to_vec(), response);
+    assert_eq!(
+        VouchResponse::decode(&response[..]),
+        Ok(VouchResponse { voucher: VALUE })
+    );
+
+    // The paths are `/package.Service/Method`.
+    assert!(PROTO.lines().any(|line| line == "package raffle;"));
+    assert_eq!(
+        block("service", "VouchingService"),
+        ["rpc Vouch(VouchRequest) returns (VouchResponse);"]
+    );
+    assert_eq!(
+        <vouching_service_server::VouchingServiceServer<VouchingServer<crate::remote::LocalVoucher>>
+            as tonic::server::NamedService>::NAME,
+        "raffle.VouchingService"
+    );
+}
+
+#[test]
+fn test_unavailable() {
+    use std::future::Future;
+
+    struct Down;
+
+    impl RemoteVoucher for Down {
+        type Error = &'static str;
+
+        fn vouch(&self, _value: u64) -> impl Future<Output = Result<Voucher, Self::Error>> + Send {
+            std::future::ready(Err("no vouching parameters"))
+        }
+    }
+
+    let client = VouchingClient::new(vouching_service_client::VouchingServiceClient::with_origin(
+        VouchingServer::new(Down).into_service(),
+        "http://localhost".parse().unwrap(),
+    ));
+    let status = crate::testing::block_on(client.vouch(42)).unwrap_err();
+    assert_eq!(status.code(), tonic::Code::Unavailable);
+    assert_eq!(status.message(), "no vouching parameters");
+}
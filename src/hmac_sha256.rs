@@ -0,0 +1,317 @@
+//! HMAC-SHA256 "compliance mode" vouchers.
+//!
+//! This module is only compiled with the `hmac` feature.  It offers
+//! the same vouch/check interface as the default affine scheme, but
+//! the vouchers are HMAC-SHA256 tags (truncated to 64 bits) for the
+//! little-endian representation of the vouched value.
+//!
+//! HMAC is a symmetric construction: anyone who can check a voucher
+//! can also mint one.  There is thus no split between vouching and
+//! checking parameters, and the [`HmacParameters`] should be treated
+//! as a secret everywhere they're used.  Only use this mode when a
+//! compliance regime demands a NIST-approved primitive; the default
+//! scheme is a better fit for everything else.
+use hmac::Mac;
+
+use crate::constparse::parse_hex;
+use crate::Fingerprint;
+use crate::Voucher;
+
+type HmacSha256 = hmac::Hmac<sha2::Sha256>;
+
+/// [`HmacParameters`] hold the 256-bit key for HMAC-SHA256 vouchers.
+///
+/// The same parameters are used to vouch for values with
+/// [`HmacParameters::vouch`] and to check vouchers with
+/// [`HmacParameters::check`].
+///
+/// The string representation is `HMAC-SHA256-` followed by the key as
+/// 64 hex digits; that prefix is what lets
+/// [`crate::AnyCheckingParameters::parse`] and
+/// [`crate::AnyVouchingParameters::parse`] dispatch to this scheme.
+///
+/// Equality compares the whole key without exiting early, and hashing
+/// only feeds the [`HmacParameters::fingerprint`] to the hasher.
+#[derive(Clone, Copy)]
+pub struct HmacParameters {
+    key: [u64; 4],
+}
+
+/// Prefix for the string representation of [`HmacParameters`].
+pub(crate) const PREFIX: &str = "HMAC-SHA256-";
+
+/// Message for [`HmacParameters::fingerprint`].  It isn't 8 bytes
+/// long, so it never collides with a vouched value.
+const FINGERPRINT_MESSAGE: &[u8] = b"raffle HMAC-SHA256 fingerprint";
+
+impl HmacParameters {
+    /// Number of ASCII characters in the string representation for
+    /// one [`HmacParameters`] instance.
+    pub const REPRESENTATION_BYTE_COUNT: usize = PREFIX.len() + 64;
+
+    /// Attempts to generate a fresh 256-bit key by calling
+    /// `generator` four times.
+    ///
+    /// The `generator` should yield (pseudo)random [`u64`] values
+    /// sampled uniformly from the full 64-bit range.
+    ///
+    /// Returns a fresh [`HmacParameters`] instance on success, and
+    /// bubbles any error from `generator` on failure.
+    pub fn generate<Err>(
+        mut generator: impl FnMut() -> Result<u64, Err>,
+    ) -> Result<HmacParameters, Err> {
+        Ok(HmacParameters {
+            key: [generator()?, generator()?, generator()?, generator()?],
+        })
+    }
+
+    /// Returns the [`HmacParameters`] for the 256-bit `key`.
+    #[must_use]
+    pub fn from_key(key: [u8; 32]) -> HmacParameters {
+        let mut words = [0u64; 4];
+        for (word, chunk) in words.iter_mut().zip(key.chunks_exact(8)) {
+            *word = u64::from_be_bytes(chunk.try_into().expect("chunks are 8 bytes"));
+        }
+
+        HmacParameters { key: words }
+    }
+
+    /// Returns the 256-bit HMAC key.
+    #[must_use]
+    pub fn key(&self) -> [u8; 32] {
+        let mut ret = [0u8; 32];
+        for (chunk, word) in ret.chunks_exact_mut(8).zip(self.key.iter()) {
+            chunk.copy_from_slice(&word.to_be_bytes());
+        }
+
+        ret
+    }
+
+    /// Computes a [`Voucher`] for `value`: the first 8 bytes of the
+    /// HMAC-SHA256 tag for `value.to_le_bytes()`, in little-endian order.
+    #[must_use]
+    pub fn vouch(&self, value: u64) -> Voucher {
+        let tag = self.mac(value).finalize().into_bytes();
+        let mut truncated = [0u8; 8];
+        truncated.copy_from_slice(&tag[..8]);
+        Voucher(u64::from_le_bytes(truncated))
+    }
+
+    /// Returns whether `voucher` was generated for `expected` with
+    /// these [`HmacParameters`].
+    #[must_use]
+    pub fn check(&self, expected: u64, voucher: Voucher) -> bool {
+        // Compare in constant time, like any other MAC.
+        crate::INSECURE_ACCEPT_ALL
+            || self
+                .mac(expected)
+                .verify_truncated_left(&voucher.0.to_le_bytes())
+                .is_ok()
+    }
+
+    /// Returns the [`Fingerprint`] for these parameters.
+    ///
+    /// Unlike the affine scheme's, this fingerprint is a MAC under the
+    /// secret key, so it identifies the key without revealing it.
+    #[must_use]
+    pub fn fingerprint(&self) -> Fingerprint {
+        let mut mac = self.keyed_mac();
+        mac.update(FINGERPRINT_MESSAGE);
+        let tag = mac.finalize().into_bytes();
+        let mut truncated = [0u8; 8];
+        truncated.copy_from_slice(&tag[..8]);
+        Fingerprint(u64::from_le_bytes(truncated))
+    }
+
+    /// Returns a MAC that has hashed `value.to_le_bytes()`.
+    fn mac(&self, value: u64) -> HmacSha256 {
+        let mut mac = self.keyed_mac();
+        mac.update(&value.to_le_bytes());
+        mac
+    }
+
+    /// Returns a fresh MAC for the key.
+    fn keyed_mac(&self) -> HmacSha256 {
+        HmacSha256::new_from_slice(&self.key()).expect("HMAC accepts any key length")
+    }
+
+    /// Attempts to parse the string representation of [`HmacParameters`].
+    #[inline(always)]
+    pub const fn parse(string: &str) -> Result<HmacParameters, &'static str> {
+        Self::parse_bytes(string.as_bytes())
+    }
+
+    /// Parses the string representation of a [`HmacParameters`] object
+    /// or panics.
+    ///
+    /// This function is mostly useful to initialise `const` literals.
+    #[inline(never)]
+    pub const fn parse_or_die(string: &str) -> HmacParameters {
+        match Self::parse(string) {
+            Ok(ret) => ret,
+            Err(_) => panic!("failed to parse hmac parameter string."),
+        }
+    }
+
+    /// Attempts to parse `bytes`, which must be the utf-8 (it's all
+    /// ASCII) representation of a serialised [`HmacParameters`],
+    /// with a length of exactly `REPRESENTATION_BYTE_COUNT` bytes.
+    ///
+    /// Returns the [`HmacParameters`] on success, and an error
+    /// reason on failure.
+    #[inline(never)]
+    pub const fn parse_bytes(bytes: &[u8]) -> Result<HmacParameters, &'static str> {
+        // Expected length:
+        //  "HMAC-SHA256-" [ 0, 12)
+        //  hex key        [12, 76)
+        if bytes.len() < Self::REPRESENTATION_BYTE_COUNT {
+            return Err("Too few bytes in serialized raffle::HmacParameters");
+        }
+
+        if bytes.len() > Self::REPRESENTATION_BYTE_COUNT {
+            return Err("Too many bytes in serialized raffle::HmacParameters");
+        }
+
+        let prefix = PREFIX.as_bytes();
+        let mut i = 0;
+        while i < prefix.len() {
+            if bytes[i] != prefix[i] {
+                return Err(
+                    "Incorrect prefix for serialized raffle::HmacParameters. Expected HMAC-SHA256-",
+                );
+            }
+
+            i += 1;
+        }
+
+        let mut key = [0u64; 4];
+        let mut i = 0;
+        while i < key.len() {
+            let Some(word) = parse_hex(bytes, prefix.len() + 16 * i) else {
+                return Err("Failed to parse hex key in serialized raffle::HmacParameters.");
+            };
+
+            key[i] = word;
+            i += 1;
+        }
+
+        Ok(HmacParameters { key })
+    }
+}
+
+/// The key is secret, so compare every word instead of stopping at
+/// the first mismatch.
+impl PartialEq for HmacParameters {
+    fn eq(&self, other: &HmacParameters) -> bool {
+        let diff = self
+            .key
+            .iter()
+            .zip(other.key.iter())
+            .fold(0u64, |acc, (x, y)| acc | (x ^ y));
+        std::hint::black_box(diff) == 0
+    }
+}
+
+impl Eq for HmacParameters {}
+
+/// Equal keys have equal fingerprints, and the fingerprint is safe to
+/// hand to any hasher.
+impl std::hash::Hash for HmacParameters {
+    fn hash<H: std::hash::Hasher>(&self, state: &mut H) {
+        self.fingerprint().hash(state);
+    }
+}
+
+/// The key is secret, so don't print it in debug output.
+impl std::fmt::Debug for HmacParameters {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("HmacParameters").finish_non_exhaustive()
+    }
+}
+
+impl std::fmt::Display for HmacParameters {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{}{:016x}{:016x}{:016x}{:016x}",
+            PREFIX, self.key[0], self.key[1], self.key[2], self.key[3]
+        )
+    }
+}
+
+#[cfg(test)]
+const TEST_KEY: [u8; 32] = *b"raffle hmac-sha256 test key 0123";
+
+#[cfg(not(feature = "insecure-accept-all"))]
+#[test]
+fn test_round_trip() {
+    let params = HmacParameters::from_key(TEST_KEY);
+    assert_eq!(params.key(), TEST_KEY);
+
+    let voucher = params.vouch(42);
+    assert!(params.check(42, voucher));
+    assert!(!params.check(43, voucher));
+    assert!(!params.check(42, Voucher(voucher.0 ^ 1)));
+
+    let other = HmacParameters::generate(crate::make_generator(&[1, 2, 3, 4])).unwrap();
+    assert!(!other.check(42, voucher));
+}
+
+#[test]
+fn test_known_answer() {
+    // Reference values computed with Python's `hmac` and `hashlib` modules.
+    assert_eq!(
+        HmacParameters::from_key([0u8; 32]).vouch(0),
+        Voucher(0x848892ba0a1875f3)
+    );
+    assert_eq!(
+        HmacParameters::from_key(TEST_KEY).vouch(42),
+        Voucher(0x39286997b28e2f08)
+    );
+}
+
+#[test]
+fn test_parse() {
+    let params = HmacParameters::generate(crate::make_generator(&[1, 2, 3, u64::MAX])).unwrap();
+
+    const SERIAL: &str =
+        "HMAC-SHA256-000000000000000100000000000000020000000000000003ffffffffffffffff";
+    assert_eq!(format!("{}", params), SERIAL);
+    assert_eq!(SERIAL.len(), HmacParameters::REPRESENTATION_BYTE_COUNT);
+
+    const COPY: HmacParameters = HmacParameters::parse_or_die(SERIAL);
+    assert_eq!(params, COPY);
+    assert_eq!(HmacParameters::parse(SERIAL), Ok(params));
+
+    // Don't leak the key in debug output.
+    assert_eq!(format!("{:?}", params), "HmacParameters { .. }");
+}
+
+#[test]
+fn test_eq_and_fingerprint() {
+    let params = HmacParameters::from_key(TEST_KEY);
+    let mut key = TEST_KEY;
+    key[31] ^= 1;
+    let other = HmacParameters::from_key(key);
+
+    assert_eq!(params, HmacParameters::from_key(TEST_KEY));
+    assert_ne!(params, other);
+    assert_eq!(
+        params.fingerprint(),
+        HmacParameters::from_key(TEST_KEY).fingerprint()
+    );
+    assert_ne!(params.fingerprint(), other.fingerprint());
+
+    let set: std::collections::HashSet<HmacParameters> = [params, other, params].into();
+    assert_eq!(set.len(), 2);
+}
+
+#[test]
+fn test_parse_fail() {
+    let serial = format!("{}", HmacParameters::from_key(TEST_KEY));
+
+    assert!(HmacParameters::parse(&serial[1..]).is_err());
+    assert!(HmacParameters::parse(&format!("{}0", serial)).is_err());
+    assert!(HmacParameters::parse(&serial.replace("HMAC", "HMAK")).is_err());
+    assert!(HmacParameters::parse(&format!("{}g", &serial[..serial.len() - 1])).is_err());
+}
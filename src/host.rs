@@ -0,0 +1,113 @@
+//! Binding vouchers to hosts.
+//!
+//! A voucher minted for a [`HostBound`] value is only valid on the host
+//! with the same host identifier, so a voucher for one production
+//! machine can't be replayed on the rest of the fleet.  The identifier
+//! is usually [`machine_id_hash`], the hash of the systemd machine ID.
+//!
+//! The host identifier is mixed into the vouched [`u64`] with its own
+//! tag, so it can't be confused with the name hash of a
+//! [`crate::Named`] value.
+use std::path::Path;
+
+use crate::Vouchable;
+
+/// Host identifiers are mixed with this constant.
+const HOST_TAG: u64 = crate::constparse::named_u64(b"HostBind", 0x646e694274736f48u64);
+
+/// Files that may hold the machine ID, in order of preference.
+const MACHINE_ID_PATHS: [&str; 2] = ["/etc/machine-id", "/var/lib/dbus/machine-id"];
+
+/// A value bound to a host identifier.
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Hash)]
+pub struct HostBound<'a, T: ?Sized> {
+    /// The host identifier, e.g., from [`machine_id_hash`].
+    pub host: u64,
+    /// The inner value.
+    pub value: &'a T,
+}
+
+impl<'a, T: Vouchable + ?Sized> HostBound<'a, T> {
+    /// Binds `value` to the host identifier `host`.
+    pub fn new(host: u64, value: &'a T) -> HostBound<'a, T> {
+        HostBound { host, value }
+    }
+}
+
+impl<T: Vouchable + ?Sized> Vouchable for HostBound<'_, T> {
+    fn vouch_value(&self) -> u64 {
+        crate::vouchable::bind(self.host, HOST_TAG, self.value)
+    }
+}
+
+/// Returns the hash ([`crate::name_hash`]) of this host's machine ID,
+/// from `/etc/machine-id` or, failing that, `/var/lib/dbus/machine-id`.
+pub fn machine_id_hash() -> std::io::Result<u64> {
+    let mut error = None;
+    for path in MACHINE_ID_PATHS {
+        match machine_id_hash_from(path) {
+            Ok(hash) => return Ok(hash),
+            Err(e) => error = Some(e),
+        }
+    }
+
+    Err(error.expect("MACHINE_ID_PATHS is not empty"))
+}
+
+/// Returns the hash ([`crate::name_hash`]) of the machine ID in the file at `path`.
+///
+/// Surrounding whitespace is ignored, and empty files are rejected
+/// (systemd leaves `/etc/machine-id` empty until first boot).
+pub fn machine_id_hash_from(path: impl AsRef<Path>) -> std::io::Result<u64> {
+    let contents = std::fs::read_to_string(path)?;
+    let id = contents.trim();
+    if id.is_empty() {
+        return Err(std::io::Error::new(
+            std::io::ErrorKind::InvalidData,
+            "empty machine ID",
+        ));
+    }
+
+    Ok(crate::name_hash(id))
+}
+
+#[cfg(not(feature = "insecure-accept-all"))]
+#[test]
+fn test_host_bound() {
+    let params = crate::testing::VOUCHING;
+    let checking = params.checking_parameters();
+
+    let host = crate::name_hash("4c4c4544004a4b10804bb4c04f4e4c32");
+    let other = crate::name_hash("9f1e0c0d6c0f4b6f9a3c2d1e0f1a2b3c");
+    let voucher = params.vouch(HostBound::new(host, &42u64).vouch_value());
+
+    assert!(checking.check(HostBound::new(host, &42u64).vouch_value(), voucher));
+    assert!(!checking.check(HostBound::new(other, &42u64).vouch_value(), voucher));
+    assert!(!checking.check(HostBound::new(host, &43u64).vouch_value(), voucher));
+
+    // Host identifiers don't collide with names.
+    assert_ne!(
+        HostBound::new(host, &42u64).vouch_value(),
+        crate::Named::new("4c4c4544004a4b10804bb4c04f4e4c32", &42u64).vouch_value()
+    );
+}
+
+#[test]
+fn test_machine_id_hash_from() {
+    let path = std::env::temp_dir().join(format!("raffle-machine-id-{}", std::process::id()));
+
+    std::fs::write(&path, "4c4c4544004a4b10804bb4c04f4e4c32\n").unwrap();
+    assert_eq!(
+        machine_id_hash_from(&path).unwrap(),
+        crate::name_hash("4c4c4544004a4b10804bb4c04f4e4c32")
+    );
+
+    std::fs::write(&path, "\n").unwrap();
+    assert_eq!(
+        machine_id_hash_from(&path).unwrap_err().kind(),
+        std::io::ErrorKind::InvalidData
+    );
+
+    std::fs::remove_file(&path).unwrap();
+    assert!(machine_id_hash_from(&path).is_err());
+}
@@ -0,0 +1,118 @@
+//! [`std::io`] helpers for parameters.
+//!
+//! [`CheckingParameters::write_to`] writes the string representation
+//! and a newline; [`CheckingParameters::read_from`] reads until EOF
+//! (looping over partial reads), ignores surrounding whitespace (e.g.,
+//! the trailing newline), and parses the result.  The same methods
+//! exist for [`VouchingParameters`].
+use std::io::Read;
+use std::io::Write;
+
+use crate::CheckingParameters;
+use crate::VouchingParameters;
+
+/// Stop reading after this many bytes: valid input is much shorter.
+const MAX_READ_BYTE_COUNT: u64 = 1024;
+
+/// Reads `reader` until EOF, and parses the contents with `parse`.
+fn read_parameters<T>(
+    reader: impl Read,
+    parse: impl FnOnce(&[u8]) -> Result<T, &'static str>,
+) -> std::io::Result<T> {
+    let mut buf = Vec::new();
+    reader.take(MAX_READ_BYTE_COUNT).read_to_end(&mut buf)?;
+    if buf.len() as u64 >= MAX_READ_BYTE_COUNT {
+        return Err(std::io::Error::new(
+            std::io::ErrorKind::InvalidData,
+            "Too many bytes for raffle parameters",
+        ));
+    }
+
+    parse(buf.trim_ascii())
+        .map_err(|reason| std::io::Error::new(std::io::ErrorKind::InvalidData, reason))
+}
+
+impl CheckingParameters {
+    /// Writes these parameters to `writer`, followed by a newline.
+    pub fn write_to(&self, mut writer: impl Write) -> std::io::Result<()> {
+        writeln!(writer, "{}", self)
+    }
+
+    /// Reads parameters written by [`CheckingParameters::write_to`]
+    /// from `reader`, until EOF.
+    ///
+    /// Fails with [`std::io::ErrorKind::InvalidData`] when the contents
+    /// (without surrounding whitespace) don't parse.
+    pub fn read_from(reader: impl Read) -> std::io::Result<CheckingParameters> {
+        read_parameters(reader, CheckingParameters::parse_bytes)
+    }
+}
+
+impl VouchingParameters {
+    /// Writes these parameters to `writer`, followed by a newline.
+    pub fn write_to(&self, mut writer: impl Write) -> std::io::Result<()> {
+        writeln!(writer, "{}", self)
+    }
+
+    /// Reads parameters written by [`VouchingParameters::write_to`]
+    /// from `reader`, until EOF.
+    ///
+    /// Fails with [`std::io::ErrorKind::InvalidData`] when the contents
+    /// (without surrounding whitespace) don't parse.
+    pub fn read_from(reader: impl Read) -> std::io::Result<VouchingParameters> {
+        read_parameters(reader, VouchingParameters::parse_bytes)
+    }
+}
+
+/// A reader that returns at most one byte per call.
+#[cfg(test)]
+struct Trickle<'a>(&'a [u8]);
+
+#[cfg(test)]
+impl Read for Trickle<'_> {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        match (self.0.split_first(), buf.first_mut()) {
+            (Some((&byte, rest)), Some(dst)) => {
+                *dst = byte;
+                self.0 = rest;
+                Ok(1)
+            }
+            _ => Ok(0),
+        }
+    }
+}
+
+#[test]
+fn test_round_trip() {
+    let params = crate::testing::VOUCHING;
+    let checking = params.checking_parameters();
+
+    let mut buf = Vec::new();
+    checking.write_to(&mut buf).unwrap();
+    assert_eq!(buf, format!("{}\n", checking).into_bytes());
+    assert_eq!(CheckingParameters::read_from(&buf[..]).unwrap(), checking);
+    assert_eq!(
+        CheckingParameters::read_from(Trickle(&buf)).unwrap(),
+        checking
+    );
+
+    let mut buf = Vec::new();
+    params.write_to(&mut buf).unwrap();
+    assert_eq!(
+        VouchingParameters::read_from(Trickle(&buf)).unwrap(),
+        params
+    );
+    buf.extend_from_slice(b"\r\n\n");
+    assert_eq!(VouchingParameters::read_from(&buf[..]).unwrap(), params);
+}
+
+#[test]
+fn test_read_errors() {
+    let params = crate::testing::VOUCHING;
+
+    let error = CheckingParameters::read_from(params.to_string().as_bytes()).unwrap_err();
+    assert_eq!(error.kind(), std::io::ErrorKind::InvalidData);
+
+    let error = VouchingParameters::read_from(std::io::repeat(b' ')).unwrap_err();
+    assert_eq!(error.kind(), std::io::ErrorKind::InvalidData);
+}
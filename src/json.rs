@@ -0,0 +1,351 @@
+//! A stable JSON representation for parameters, without serde.
+//!
+//! [`CheckingParameters::to_json`] emits
+//!
+//! ```json
+//! {"check":"CHECK-0000000000000083-9b791a2755d2d996","fingerprint":"..."}
+//! ```
+//!
+//! and [`VouchingParameters::to_json`] adds a `"vouch"` member with the
+//! `VOUCH-...` string.  The `fingerprint` is redundant, but lets tools
+//! in other languages identify keys without reimplementing it.
+//!
+//! The parsers accept any JSON object with string members, in any
+//! order and with any whitespace, and ignore unknown members.  They
+//! reject duplicate members, and cross-check the redundant members:
+//! a `fingerprint` or `check` that doesn't match the parameters is an
+//! error, not something to silently ignore.
+use crate::CheckingParameters;
+use crate::VouchingParameters;
+
+/// Parses `input` as a flat JSON object whose members are all strings.
+fn parse_object(input: &str) -> Result<Vec<(String, String)>, &'static str> {
+    let mut chars = input.trim().chars().peekable();
+    let mut members: Vec<(String, String)> = Vec::new();
+
+    fn skip_whitespace(chars: &mut std::iter::Peekable<std::str::Chars<'_>>) {
+        while chars
+            .next_if(|c| matches!(c, ' ' | '\t' | '\n' | '\r'))
+            .is_some()
+        {}
+    }
+
+    /// Parses the 4 hex digits of a `\u` escape.
+    fn parse_hex4(
+        chars: &mut std::iter::Peekable<std::str::Chars<'_>>,
+    ) -> Result<u32, &'static str> {
+        let digits: String = chars.by_ref().take(4).collect();
+        u32::from_str_radix(&digits, 16)
+            .ok()
+            .filter(|_| digits.len() == 4 && digits.chars().all(|c| c.is_ascii_hexdigit()))
+            .ok_or("Invalid \\u escape in JSON raffle parameters")
+    }
+
+    /// Parses the rest of a `\u` escape, including the second half
+    /// of a UTF-16 surrogate pair.
+    fn parse_unicode_escape(
+        chars: &mut std::iter::Peekable<std::str::Chars<'_>>,
+    ) -> Result<char, &'static str> {
+        let mut code = parse_hex4(chars)?;
+        if (0xD800..0xDC00).contains(&code) {
+            if chars.next() != Some('\\') || chars.next() != Some('u') {
+                return Err("Unpaired surrogate in JSON raffle parameters");
+            }
+
+            let low = parse_hex4(chars)?;
+            if !(0xDC00..0xE000).contains(&low) {
+                return Err("Unpaired surrogate in JSON raffle parameters");
+            }
+
+            code = 0x10000 + ((code - 0xD800) << 10) + (low - 0xDC00);
+        }
+
+        char::from_u32(code).ok_or("Unpaired surrogate in JSON raffle parameters")
+    }
+
+    fn parse_string(
+        chars: &mut std::iter::Peekable<std::str::Chars<'_>>,
+    ) -> Result<String, &'static str> {
+        if chars.next() != Some('"') {
+            return Err("Expected a string in JSON raffle parameters");
+        }
+
+        let mut ret = String::new();
+        loop {
+            match chars.next() {
+                None => return Err("Unterminated string in JSON raffle parameters"),
+                Some('"') => return Ok(ret),
+                Some('\\') => {
+                    let escaped = match chars.next() {
+                        Some('"') => '"',
+                        Some('\\') => '\\',
+                        Some('/') => '/',
+                        Some('b') => '\u{8}',
+                        Some('f') => '\u{c}',
+                        Some('n') => '\n',
+                        Some('r') => '\r',
+                        Some('t') => '\t',
+                        Some('u') => parse_unicode_escape(chars)?,
+                        _ => return Err("Invalid escape in JSON raffle parameters"),
+                    };
+                    ret.push(escaped);
+                }
+                Some(c) if c < ' ' => {
+                    return Err("Control character in JSON raffle parameters string")
+                }
+                Some(c) => ret.push(c),
+            }
+        }
+    }
+
+    if chars.next() != Some('{') {
+        return Err("Expected a JSON object for raffle parameters");
+    }
+
+    skip_whitespace(&mut chars);
+    if chars.next_if_eq(&'}').is_none() {
+        loop {
+            skip_whitespace(&mut chars);
+            let key = parse_string(&mut chars)?;
+            skip_whitespace(&mut chars);
+            if chars.next() != Some(':') {
+                return Err("Expected ':' in JSON raffle parameters");
+            }
+
+            skip_whitespace(&mut chars);
+            let value = parse_string(&mut chars)?;
+            if members.iter().any(|(existing, _)| *existing == key) {
+                return Err("Duplicate member in JSON raffle parameters");
+            }
+
+            members.push((key, value));
+            skip_whitespace(&mut chars);
+            match chars.next() {
+                Some(',') => continue,
+                Some('}') => break,
+                _ => return Err("Expected ',' or '}' in JSON raffle parameters"),
+            }
+        }
+    }
+
+    if chars.next().is_some() {
+        return Err("Trailing characters after JSON raffle parameters");
+    }
+
+    Ok(members)
+}
+
+/// Returns the value of the member `key`, if any.
+fn member<'a>(members: &'a [(String, String)], key: &str) -> Option<&'a str> {
+    members
+        .iter()
+        .find(|(name, _)| name == key)
+        .map(|(_, value)| value.as_str())
+}
+
+/// Checks the optional `fingerprint` member against `checking`.
+fn check_fingerprint(
+    members: &[(String, String)],
+    checking: CheckingParameters,
+) -> Result<(), &'static str> {
+    match member(members, "fingerprint") {
+        Some(fingerprint) if fingerprint != checking.fingerprint().to_string() => {
+            Err("Fingerprint mismatch in JSON raffle parameters")
+        }
+        _ => Ok(()),
+    }
+}
+
+impl CheckingParameters {
+    /// Returns the JSON representation of these parameters, an object
+    /// with `"check"` and `"fingerprint"` string members.
+    #[must_use]
+    pub fn to_json(&self) -> String {
+        format!(
+            "{{\"check\":\"{}\",\"fingerprint\":\"{}\"}}",
+            self,
+            self.fingerprint()
+        )
+    }
+
+    /// Parses the JSON representation of checking parameters.
+    ///
+    /// The object must have a `"check"` member; a `"fingerprint"`
+    /// member, if present, must match.  Also accepts the JSON
+    /// representation of [`VouchingParameters`], and returns their
+    /// checking half.
+    pub fn parse_json(json: &str) -> Result<CheckingParameters, &'static str> {
+        let members = parse_object(json)?;
+        if member(&members, "vouch").is_some() {
+            return VouchingParameters::parse_json(json).map(|params| params.checking_parameters());
+        }
+
+        let check =
+            member(&members, "check").ok_or("Missing \"check\" in JSON raffle parameters")?;
+        let checking = CheckingParameters::parse(check)?;
+        check_fingerprint(&members, checking)?;
+        Ok(checking)
+    }
+}
+
+impl VouchingParameters {
+    /// Returns the JSON representation of these parameters, an object
+    /// with `"vouch"`, `"check"` and `"fingerprint"` string members.
+    ///
+    /// Like the `VOUCH-...` string, the result is secret.
+    #[must_use]
+    pub fn to_json(&self) -> String {
+        format!(
+            "{{\"vouch\":\"{}\",\"check\":\"{}\",\"fingerprint\":\"{}\"}}",
+            self,
+            self.checking_parameters(),
+            self.fingerprint()
+        )
+    }
+
+    /// Parses the JSON representation of vouching parameters.
+    ///
+    /// The object must have a `"vouch"` member; `"check"` and
+    /// `"fingerprint"` members, if present, must match.
+    pub fn parse_json(json: &str) -> Result<VouchingParameters, &'static str> {
+        let members = parse_object(json)?;
+        let vouch =
+            member(&members, "vouch").ok_or("Missing \"vouch\" in JSON raffle parameters")?;
+        let params = VouchingParameters::parse(vouch)?;
+        let checking = params.checking_parameters();
+
+        if let Some(check) = member(&members, "check") {
+            if CheckingParameters::parse(check)? != checking {
+                return Err("Checking parameters mismatch in JSON raffle parameters");
+            }
+        }
+
+        check_fingerprint(&members, checking)?;
+        Ok(params)
+    }
+}
+
+#[test]
+fn test_round_trip() {
+    let params = crate::testing::VOUCHING;
+    let checking = params.checking_parameters();
+
+    assert_eq!(
+        checking.to_json(),
+        format!(
+            "{{\"check\":\"{}\",\"fingerprint\":\"{}\"}}",
+            checking,
+            checking.fingerprint()
+        )
+    );
+    assert_eq!(
+        CheckingParameters::parse_json(&checking.to_json()),
+        Ok(checking)
+    );
+    assert_eq!(
+        VouchingParameters::parse_json(&params.to_json()),
+        Ok(params)
+    );
+    assert_eq!(
+        CheckingParameters::parse_json(&params.to_json()),
+        Ok(checking)
+    );
+
+    // Other producers may reorder members, add whitespace, escape
+    // slashes, and add members of their own.
+    let pretty = format!(
+        "\n{{\n  \"comment\": \"prod\\/api\",\n  \"fingerprint\": \"{}\",\n  \"check\" : \"{}\"\n}}\n",
+        checking.fingerprint(),
+        checking
+    );
+    assert_eq!(CheckingParameters::parse_json(&pretty), Ok(checking));
+    assert_eq!(
+        CheckingParameters::parse_json(&format!("{{\"check\":\"{}\"}}", checking)),
+        Ok(checking)
+    );
+}
+
+#[test]
+fn test_errors() {
+    let params = crate::testing::VOUCHING;
+    let other = crate::testing::OTHER;
+    let checking = params.checking_parameters();
+
+    assert_eq!(
+        CheckingParameters::parse_json(&format!(
+            "{{\"check\":\"{}\",\"fingerprint\":\"{}\"}}",
+            checking,
+            other.fingerprint()
+        )),
+        Err("Fingerprint mismatch in JSON raffle parameters")
+    );
+    assert_eq!(
+        VouchingParameters::parse_json(&format!(
+            "{{\"vouch\":\"{}\",\"check\":\"{}\"}}",
+            params,
+            other.checking_parameters()
+        )),
+        Err("Checking parameters mismatch in JSON raffle parameters")
+    );
+    assert_eq!(
+        VouchingParameters::parse_json(&checking.to_json()),
+        Err("Missing \"vouch\" in JSON raffle parameters")
+    );
+    assert_eq!(
+        CheckingParameters::parse_json(&format!(
+            "{{\"check\":\"{0}\",\"check\":\"{0}\"}}",
+            checking
+        )),
+        Err("Duplicate member in JSON raffle parameters")
+    );
+    assert_eq!(
+        CheckingParameters::parse_json(&format!("{} x", checking.to_json())),
+        Err("Trailing characters after JSON raffle parameters")
+    );
+    assert_eq!(
+        CheckingParameters::parse_json("{\"check\": 1}"),
+        Err("Expected a string in JSON raffle parameters")
+    );
+    assert_eq!(
+        CheckingParameters::parse_json("[]"),
+        Err("Expected a JSON object for raffle parameters")
+    );
+    assert_eq!(
+        CheckingParameters::parse_json("{}"),
+        Err("Missing \"check\" in JSON raffle parameters")
+    );
+}
+
+#[test]
+fn test_unicode_escapes() {
+    assert_eq!(
+        parse_object(r#"{"\u00e9\uD83D\uDE00": "\u0041"}"#),
+        Ok(vec![("\u{e9}\u{1f600}".to_string(), "A".to_string())])
+    );
+
+    let checking = crate::testing::VOUCHING.checking_parameters();
+    assert_eq!(
+        CheckingParameters::parse_json(&format!(
+            r#"{{"check":"{}","\ud83d\ude00":"\ud83d\ude00"}}"#,
+            checking
+        )),
+        Ok(checking)
+    );
+
+    for bad in [
+        r#"{"\uD83D": ""}"#,
+        r#"{"\uD83Dx": ""}"#,
+        r#"{"\uD83D\u0041": ""}"#,
+        r#"{"\uDE00": ""}"#,
+    ] {
+        assert_eq!(
+            parse_object(bad),
+            Err("Unpaired surrogate in JSON raffle parameters")
+        );
+    }
+
+    assert_eq!(
+        parse_object(r#"{"\u+041": ""}"#),
+        Err("Invalid \\u escape in JSON raffle parameters")
+    );
+}
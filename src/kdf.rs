@@ -0,0 +1,174 @@
+//! Parameters derived from existing key material.
+//!
+//! This module is only compiled with the `hkdf` feature.
+//! [`VouchingParameters::derive_hkdf`] slots raffle parameters into an
+//! existing KDF hierarchy: the same input keying material and `info`
+//! label always yield the same parameters, and different labels yield
+//! independent parameters.
+//!
+//! With the `argon2` feature, [`VouchingParameters::derive_from_passphrase`]
+//! first hardens a passphrase with Argon2id, and then expands the
+//! result like [`VouchingParameters::derive_hkdf`].
+use hkdf::Hkdf;
+use sha2::Sha256;
+
+use crate::VouchingParameters;
+
+/// Number of HKDF output bytes per expansion.
+///
+/// [`VouchingParameters::derive`] needs 2 plausible [`u64`]s, and
+/// uniformly random values are implausible with negligible probability,
+/// so 8 values are more than enough.
+const OKM_BYTE_COUNT: usize = 64;
+
+/// HKDF `info` label for keys derived from passphrases.
+#[cfg(feature = "argon2")]
+const PASSPHRASE_INFO: &[u8] = b"raffle/passphrase";
+
+impl VouchingParameters {
+    /// Deterministically derives [`VouchingParameters`] from the input
+    /// keying material `ikm` and the optional `salt` with HKDF-SHA256,
+    /// for the context label `info`.
+    ///
+    /// The parameters come from [`VouchingParameters::derive`], with
+    /// the first two plausible (see
+    /// [`crate::generate::is_plausible_input`]) [`u64`]s (little-endian)
+    /// of 64 bytes of HKDF-Expand output.  In the negligibly unlikely
+    /// case that the output doesn't have two, the key is expanded again,
+    /// with a counter appended to `info`.  The `ikm` must be a secret
+    /// with at least 128 bits of entropy, e.g., a key from an existing
+    /// KDF hierarchy; for passphrases, use a password hash first.
+    #[must_use]
+    pub fn derive_hkdf(salt: Option<&[u8]>, ikm: &[u8], info: &[u8]) -> VouchingParameters {
+        let hkdf = Hkdf::<Sha256>::new(salt, ikm);
+        let mut plausible = Vec::new();
+        let mut round = 0u64;
+        loop {
+            let mut okm = [0u8; OKM_BYTE_COUNT];
+            let counter = round.to_le_bytes();
+            let info: &[&[u8]] = if round == 0 {
+                &[info]
+            } else {
+                &[info, &counter]
+            };
+            // 64 bytes is always a valid HKDF-SHA256 output length.
+            let _ = hkdf.expand_multi_info(info, &mut okm);
+
+            plausible.extend(
+                okm.chunks_exact(8)
+                    .map(|chunk| u64::from_le_bytes(chunk.try_into().unwrap_or_default()))
+                    .filter(|word| crate::generate::is_plausible_input(*word)),
+            );
+            if let [scale, unoffset, ..] = plausible[..] {
+                if let Ok(ret) = VouchingParameters::derive(scale, unoffset) {
+                    return ret;
+                }
+            }
+
+            round += 1;
+        }
+    }
+
+    /// Deterministically derives [`VouchingParameters`] from a
+    /// `passphrase`: the passphrase and `salt` are hashed into a
+    /// 256-bit key with Argon2id and `params`, and that key is expanded
+    /// with [`VouchingParameters::derive_hkdf`] (without salt, and with
+    /// the `info` label `raffle/passphrase`).
+    ///
+    /// Argon2id makes each guess expensive, but a memorable passphrase
+    /// still has much less entropy than random parameters: only use
+    /// this function for low-value deployments.  Use a distinct salt
+    /// (at least 8 bytes) per deployment, and at least
+    /// [`argon2::Params::default`] (19 MiB, 2 passes).
+    ///
+    /// Fails when Argon2 rejects the inputs, e.g., a salt that's too short.
+    #[cfg(feature = "argon2")]
+    pub fn derive_from_passphrase(
+        passphrase: &[u8],
+        salt: &[u8],
+        params: argon2::Params,
+    ) -> Result<VouchingParameters, argon2::Error> {
+        let mut key = [0u8; 32];
+        argon2::Argon2::new(argon2::Algorithm::Argon2id, argon2::Version::V0x13, params)
+            .hash_password_into(passphrase, salt, &mut key)?;
+
+        Ok(VouchingParameters::derive_hkdf(None, &key, PASSPHRASE_INFO))
+    }
+}
+
+#[test]
+fn test_derive_hkdf() {
+    let params = VouchingParameters::derive_hkdf(Some(b"salt"), b"input key material", b"raffle");
+    assert_eq!(
+        params,
+        VouchingParameters::derive_hkdf(Some(b"salt"), b"input key material", b"raffle")
+    );
+    // The unoffset is the second word of the HKDF-SHA256 output.
+    assert_eq!(
+        params.checking_parameters().to_string(),
+        "CHECK-e35447f2864d9336-2d8095b7d8a51d6e"
+    );
+
+    for other in [
+        VouchingParameters::derive_hkdf(None, b"input key material", b"raffle"),
+        VouchingParameters::derive_hkdf(Some(b"salt"), b"other key material", b"raffle"),
+        VouchingParameters::derive_hkdf(Some(b"salt"), b"input key material", b"raffle/v2"),
+    ] {
+        assert_ne!(other.checking_parameters(), params.checking_parameters());
+    }
+
+    assert!(params.checking_parameters().check(42, params.vouch(42)));
+}
+
+#[cfg(all(test, feature = "argon2"))]
+fn test_argon2_params() -> argon2::Params {
+    // Much weaker than the defaults, to keep the tests fast.
+    argon2::Params::new(256, 1, 1, None).unwrap()
+}
+
+#[cfg(feature = "argon2")]
+#[test]
+fn test_derive_from_passphrase() {
+    let params = VouchingParameters::derive_from_passphrase(
+        b"correct horse battery staple",
+        b"deployment-1",
+        test_argon2_params(),
+    )
+    .unwrap();
+    assert_eq!(
+        Ok(params),
+        VouchingParameters::derive_from_passphrase(
+            b"correct horse battery staple",
+            b"deployment-1",
+            test_argon2_params(),
+        )
+    );
+
+    for other in [
+        VouchingParameters::derive_from_passphrase(
+            b"correct horse battery stapler",
+            b"deployment-1",
+            test_argon2_params(),
+        ),
+        VouchingParameters::derive_from_passphrase(
+            b"correct horse battery staple",
+            b"deployment-2",
+            test_argon2_params(),
+        ),
+        VouchingParameters::derive_from_passphrase(
+            b"correct horse battery staple",
+            b"deployment-1",
+            argon2::Params::new(256, 2, 1, None).unwrap(),
+        ),
+    ] {
+        assert_ne!(
+            other.unwrap().checking_parameters(),
+            params.checking_parameters()
+        );
+    }
+
+    assert_eq!(
+        VouchingParameters::derive_from_passphrase(b"passphrase", b"salt", test_argon2_params()),
+        Err(argon2::Error::SaltTooShort)
+    );
+}
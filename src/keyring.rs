@@ -0,0 +1,296 @@
+//! Named sets of checking parameters.
+//!
+//! Multi-tenant services may accept vouchers under several keys, e.g.,
+//! one per tenant.  A [`Keyring`] maps names to [`CheckingParameters`],
+//! and [`Keyring::from_sources`] loads one from any set of named
+//! [`ParameterSource`]s.  [`Keyring::from_env`] uses that to build one
+//! from every `RAFFLE_CHECK_<NAME>` environment variable, so these
+//! services can be configured purely via their environment.
+//!
+//! [`Keyring::check`] and [`Keyring::find`] report their outcome to
+//! the audit sink, like [`CheckingParameters::check_audited`].
+use std::collections::BTreeMap;
+use std::ffi::OsString;
+
+use crate::source::EnvSource;
+use crate::source::ParameterSource;
+use crate::trace;
+use crate::CheckingParameters;
+use crate::Error;
+use crate::Voucher;
+
+/// The value of an environment variable, as a [`ParameterSource`].
+struct VarSource {
+    var: String,
+    value: OsString,
+}
+
+impl ParameterSource for VarSource {
+    fn load(&self) -> Result<CheckingParameters, Error> {
+        CheckingParameters::parse_os_str(&self.value).map_err(|reason| {
+            let origin = format!("environment variable {}", self.var);
+            trace::parse_failed(&origin, reason);
+            Error::Parse { origin, reason }
+        })
+    }
+}
+
+/// A map from names to [`CheckingParameters`].
+#[derive(Clone, Debug, Default, Eq, PartialEq, Hash)]
+pub struct Keyring {
+    keys: BTreeMap<String, CheckingParameters>,
+}
+
+impl Keyring {
+    /// Prefix of the environment variables that [`Keyring::from_env`] loads.
+    pub const ENV_PREFIX: &'static str = "RAFFLE_CHECK_";
+
+    /// Returns an empty keyring.
+    #[must_use]
+    pub fn new() -> Keyring {
+        Keyring::default()
+    }
+
+    /// Loads a keyring from the `(name, source)` pairs in `sources`.
+    ///
+    /// Fails on the first source that fails to load.
+    pub fn from_sources<S, P>(sources: impl IntoIterator<Item = (S, P)>) -> Result<Keyring, Error>
+    where
+        S: Into<String>,
+        P: ParameterSource,
+    {
+        let mut keyring = Keyring::new();
+        for (name, source) in sources {
+            keyring.insert(name, source.load()?);
+        }
+
+        Ok(keyring)
+    }
+
+    /// Loads a keyring from every `RAFFLE_CHECK_<NAME>` environment
+    /// variable, with an [`EnvSource`] for each variable.
+    ///
+    /// Fails like [`Keyring::from_vars`].
+    pub fn from_env() -> Result<Keyring, Error> {
+        let vars = Keyring::prefixed_vars(std::env::vars_os())?;
+        Keyring::from_sources(
+            vars.into_iter()
+                .map(|(name, var, _)| (name, EnvSource::new(var))),
+        )
+    }
+
+    /// Builds a keyring from the `(variable, value)` pairs in `vars`
+    /// whose variable name starts with [`Keyring::ENV_PREFIX`]: each
+    /// value is parsed (after trimming whitespace) as checking
+    /// parameters, and named after the rest of the variable name.
+    ///
+    /// Other variables are ignored.  Fails on the first variable (in
+    /// name order) with an empty name, a non-unicode name, or a value
+    /// that doesn't parse.
+    pub fn from_vars(
+        vars: impl IntoIterator<Item = (OsString, OsString)>,
+    ) -> Result<Keyring, Error> {
+        let vars = Keyring::prefixed_vars(vars)?;
+        Keyring::from_sources(
+            vars.into_iter()
+                .map(|(name, var, value)| (name, VarSource { var, value })),
+        )
+    }
+
+    /// Returns the `(name, variable, value)` triples for the variables
+    /// in `vars` that start with [`Keyring::ENV_PREFIX`], in name order.
+    fn prefixed_vars(
+        vars: impl IntoIterator<Item = (OsString, OsString)>,
+    ) -> Result<Vec<(String, String, OsString)>, Error> {
+        let mut vars: Vec<_> = vars
+            .into_iter()
+            .filter(|(var, _)| {
+                var.as_encoded_bytes()
+                    .starts_with(Keyring::ENV_PREFIX.as_bytes())
+            })
+            .collect();
+        vars.sort();
+
+        let mut ret = Vec::with_capacity(vars.len());
+        for (var, value) in vars {
+            let Ok(var) = var.into_string() else {
+                return Err(Error::NotUnicode(format!("{}...", Keyring::ENV_PREFIX)));
+            };
+
+            let name = var[Keyring::ENV_PREFIX.len()..].to_owned();
+            if name.is_empty() {
+                return Err(Error::Parse {
+                    origin: format!("environment variable {}", var),
+                    reason: "Empty key name",
+                });
+            }
+
+            ret.push((name, var, value));
+        }
+
+        Ok(ret)
+    }
+
+    /// Adds `checking` under `name`, and returns the parameters it
+    /// replaces, if any.
+    pub fn insert(
+        &mut self,
+        name: impl Into<String>,
+        checking: CheckingParameters,
+    ) -> Option<CheckingParameters> {
+        self.keys.insert(name.into(), checking)
+    }
+
+    /// Returns the parameters named `name`.
+    #[must_use]
+    pub fn get(&self, name: &str) -> Option<CheckingParameters> {
+        self.keys.get(name).copied()
+    }
+
+    /// Removes and returns the parameters named `name`.
+    pub fn remove(&mut self, name: &str) -> Option<CheckingParameters> {
+        self.keys.remove(name)
+    }
+
+    /// Returns the number of keys.
+    #[must_use]
+    pub fn len(&self) -> usize {
+        self.keys.len()
+    }
+
+    /// Returns whether the keyring is empty.
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.keys.is_empty()
+    }
+
+    /// Iterates over the keys, in name order.
+    pub fn iter(&self) -> impl Iterator<Item = (&str, CheckingParameters)> + '_ {
+        self.keys
+            .iter()
+            .map(|(name, checking)| (name.as_str(), *checking))
+    }
+
+    /// Returns whether `voucher` is valid for `expected` under the
+    /// parameters named `name`, with
+    /// [`CheckingParameters::check_audited`]; unknown names never match.
+    #[must_use]
+    #[track_caller]
+    pub fn check(&self, name: &str, expected: u64, voucher: Voucher) -> bool {
+        self.get(name)
+            .is_some_and(|checking| checking.check_audited(expected, voucher))
+    }
+
+    /// Returns the name of the first key (in name order) under which
+    /// `voucher` is valid for `expected`.
+    ///
+    /// On success, reports one valid check under the matching key to
+    /// the audit sink; on failure, reports a failed check under every
+    /// key (and calls the failure handler for each).
+    #[must_use]
+    #[track_caller]
+    pub fn find(&self, expected: u64, voucher: Voucher) -> Option<&str> {
+        let index =
+            crate::audit::find_audited(self.keys.values().copied(), None, expected, voucher)?;
+        self.keys.keys().nth(index).map(String::as_str)
+    }
+}
+
+impl<S: Into<String>> FromIterator<(S, CheckingParameters)> for Keyring {
+    fn from_iter<I: IntoIterator<Item = (S, CheckingParameters)>>(iter: I) -> Keyring {
+        Keyring {
+            keys: iter
+                .into_iter()
+                .map(|(name, checking)| (name.into(), checking))
+                .collect(),
+        }
+    }
+}
+
+#[cfg(not(feature = "insecure-accept-all"))]
+#[test]
+fn test_keyring() {
+    let params = crate::testing::VOUCHING;
+    let other = crate::testing::OTHER;
+
+    let mut keyring: Keyring = [("a", params.checking_parameters())].into_iter().collect();
+    assert_eq!(keyring.insert("b", other.checking_parameters()), None);
+    assert_eq!(keyring.len(), 2);
+
+    assert!(keyring.check("a", 42, params.vouch(42)));
+    assert!(!keyring.check("b", 42, params.vouch(42)));
+    assert!(!keyring.check("c", 42, params.vouch(42)));
+    assert_eq!(keyring.find(42, other.vouch(42)), Some("b"));
+    assert_eq!(keyring.find(43, other.vouch(42)), None);
+
+    assert_eq!(keyring.remove("a"), Some(params.checking_parameters()));
+    assert_eq!(
+        keyring.iter().collect::<Vec<_>>(),
+        [("b", other.checking_parameters())]
+    );
+}
+
+#[test]
+fn test_from_vars() {
+    let vars = |pairs: &[(&str, &str)]| -> Vec<(OsString, OsString)> {
+        pairs
+            .iter()
+            .map(|(var, value)| (var.into(), value.into()))
+            .collect()
+    };
+
+    let keyring = Keyring::from_vars(vars(&[
+        (
+            "RAFFLE_CHECK_TENANT_B",
+            "CHECK-0000000000000084-9b791a2755d2d996\n",
+        ),
+        (
+            "RAFFLE_CHECK_TENANT_A",
+            "CHECK-0000000000000083-9b791a2755d2d996",
+        ),
+        ("RAFFLE_CHECK", "CHECK-0000000000000085-9b791a2755d2d996"),
+        ("PATH", "/bin"),
+    ]))
+    .unwrap();
+    assert_eq!(
+        keyring.iter().map(|(name, _)| name).collect::<Vec<_>>(),
+        ["TENANT_A", "TENANT_B"]
+    );
+    assert_eq!(
+        keyring.get("TENANT_B"),
+        Some(CheckingParameters::parse_or_die(
+            "CHECK-0000000000000084-9b791a2755d2d996"
+        ))
+    );
+
+    let err = Keyring::from_vars(vars(&[
+        ("RAFFLE_CHECK_OK", "CHECK-0000000000000083-9b791a2755d2d996"),
+        ("RAFFLE_CHECK_BAD", "CHECK-"),
+    ]))
+    .unwrap_err();
+    assert!(err
+        .to_string()
+        .starts_with("failed to parse environment variable RAFFLE_CHECK_BAD: "));
+
+    assert!(Keyring::from_vars(vars(&[("RAFFLE_CHECK_", "CHECK-")])).is_err());
+}
+
+#[test]
+fn test_from_sources() {
+    let params = crate::testing::VOUCHING;
+    let keyring = Keyring::from_sources([
+        (
+            "a",
+            Box::new(params.checking_parameters()) as Box<dyn ParameterSource>,
+        ),
+        ("b", Box::new(crate::testing::CHECKING)),
+    ])
+    .unwrap();
+    assert_eq!(keyring.get("a"), Some(params.checking_parameters()));
+    assert_eq!(keyring.get("b"), Some(crate::testing::CHECKING));
+
+    assert!(matches!(
+        Keyring::from_sources([("a", crate::source::FileSource::new("/nonexistent/raffle"))]),
+        Err(Error::Io { .. })
+    ));
+}
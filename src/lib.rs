@@ -90,11 +90,9 @@
 //! ```
 //! # use raffle::VouchingParameters;
 //! use rand::Rng;
-//! #[derive(Debug)]
-//! enum Never {}
 //!
 //! let mut rng = rand::rngs::OsRng {};
-//! VouchingParameters::generate(|| Ok::<u64, Never>(rng.gen())).unwrap();
+//! VouchingParameters::generate_infallible(|| rng.gen());
 //! ```
 //!
 //! Otherwise, you can generate parameter strings with the `generate_raffle_parameters` binary:
@@ -133,10 +131,202 @@
 //! The parameter strings always have the same fixed-width format, so should
 //! be easy to `grep` for.  The `VOUCH`ing parameters also include the `CHECK`ing
 //! parameters as a suffix, so we can `grep` for the hex digits to find matching pairs.
+// Lets the code generated by `raffle_macros` refer to `::raffle` in this crate's tests.
+#[cfg(feature = "macros")]
+extern crate self as raffle;
+
+mod allowlist;
+pub mod audit;
+#[cfg(feature = "axum")]
+pub mod axum_extract;
+pub mod base58;
+pub mod batch;
+mod bits;
+pub mod build;
+#[cfg(feature = "blake3")]
+pub mod bytes;
+#[cfg(feature = "lru")]
+mod cache;
+mod cell;
 mod check;
+mod commit;
+mod compact;
+pub mod config;
 mod constparse;
-mod generate;
+#[cfg(feature = "subtle")]
+mod ct;
+pub mod decimal;
+#[cfg(feature = "defmt")]
+mod defmt_format;
+mod denylist;
+pub mod double;
+pub mod envfile;
+mod error;
+mod expiry;
+mod explain;
+mod failure;
+#[cfg(feature = "figment")]
+pub mod figment_provider;
+mod flag;
+pub mod generate;
+mod grouped;
+#[cfg(feature = "tonic")]
+pub mod grpc;
+#[cfg(feature = "hmac")]
+mod hmac_sha256;
+mod host;
+mod io;
+mod json;
+#[cfg(feature = "hkdf")]
+mod kdf;
+mod keyring;
+#[cfg(feature = "log")]
+mod logging;
+mod macros;
+mod map;
+#[cfg(feature = "tower")]
+pub mod middleware;
+mod migrate;
+pub mod net;
+mod nonce;
+mod params_ref;
+pub mod path;
+mod protect;
+#[cfg(feature = "prost")]
+pub mod proto;
+pub mod qr;
+#[cfg(feature = "async")]
+pub mod remote;
+pub mod scan;
+mod scheme;
+mod section;
+#[cfg(feature = "serde")]
+mod serde_params;
+#[cfg(feature = "serde")]
+pub mod serde_vouched;
+pub mod source;
+#[cfg(any(feature = "sqlx", feature = "diesel"))]
+pub mod sql;
+mod stats;
+pub mod stream;
+pub mod table;
+#[cfg(any(test, feature = "testing"))]
+pub mod testing;
+pub mod time;
+#[cfg(feature = "tokio")]
+pub mod tokio_io;
+#[cfg(feature = "toml")]
+pub mod toml_section;
+mod trace;
 mod vouch;
+mod vouchable;
+mod vouched;
+mod wide;
+mod words;
+#[cfg(feature = "yaml")]
+pub mod yaml_section;
+
+pub use allowlist::CompactVouchedSet;
+pub use allowlist::VouchedSet;
+pub use allowlist::VouchedSetBuilder;
+pub use audit::clear_audit_sink;
+pub use audit::set_audit_sink;
+pub use audit::AuditRecord;
+pub use audit::AuditedChecker;
+pub use audit::CheckEvent;
+pub use bits::bit_value;
+pub use bits::VouchedBits;
+#[cfg(feature = "lru")]
+pub use cache::CheckedCache;
+pub use cell::OnceVouched;
+pub use cell::VouchedCell;
+pub use commit::CommitBound;
+pub use denylist::voucher_hash;
+pub use denylist::Denylist;
+pub use expiry::Expiring;
+pub use explain::CheckExplanation;
+pub use explain::Diagnosis;
+/// Whether this build of raffle accepts every voucher, because the
+/// `insecure-accept-all` feature is enabled.
+///
+/// **That feature disables all voucher checks**, and is only meant
+/// for large integration test suites that can't easily get real
+/// parameters.  Production binaries should refuse to start when this
+/// constant is true, e.g., with
+/// `assert!(!raffle::INSECURE_ACCEPT_ALL)`.
+///
+/// Cargo unifies features across the dependency graph, so any crate
+/// in the build can enable the feature.  Release builds (without
+/// `debug_assertions`) with the feature thus fail to compile unless
+/// the `RAFFLE_ALLOW_INSECURE_ACCEPT_ALL` environment variable is set
+/// at build time.
+pub const INSECURE_ACCEPT_ALL: bool = cfg!(feature = "insecure-accept-all");
+
+#[cfg(all(feature = "insecure-accept-all", not(debug_assertions)))]
+const _: () = assert!(
+    option_env!("RAFFLE_ALLOW_INSECURE_ACCEPT_ALL").is_some(),
+    "raffle's insecure-accept-all feature disables all checks; set RAFFLE_ALLOW_INSECURE_ACCEPT_ALL=1 to build it in release mode."
+);
+
+/// Whether the `release-no-check` feature is enabled.  Used by
+/// [`debug_check!`].
+#[doc(hidden)]
+pub const RELEASE_NO_CHECK: bool = cfg!(feature = "release-no-check");
+
+/// Environment variable that points systemd services at their credentials.
+const CREDENTIALS_DIRECTORY: &str = "CREDENTIALS_DIRECTORY";
+
+pub use check::CHECKING_TAG;
+pub use check::WANTED_SUM;
+pub use error::Error;
+pub use failure::clear_failure_handler;
+pub use failure::panic_in_debug;
+pub use failure::set_failure_handler;
+pub use failure::CheckFailure;
+pub use flag::flag_usage_report;
+pub use flag::registered_flags;
+pub use flag::Flag;
+pub use flag::FlagState;
+pub use flag::FlagUsage;
+#[cfg(feature = "hmac")]
+pub use hmac_sha256::HmacParameters;
+pub use host::machine_id_hash;
+pub use host::machine_id_hash_from;
+pub use host::HostBound;
+pub use keyring::Keyring;
+#[doc(hidden)]
+pub use macros::assert_vouched_failed;
+pub use map::VouchedMap;
+pub use migrate::migrate;
+pub use migrate::migrate_stream;
+pub use migrate::migrate_table;
+pub use migrate::MigrationError;
+pub use migrate::MigrationReport;
+pub use nonce::NonceBound;
+pub use nonce::NonceWindow;
+pub use params_ref::ParamsRef;
+pub use protect::check_protected_call;
+pub use protect::UnvouchedCall;
+#[cfg(feature = "macros")]
+pub use raffle_macros::vouch_protected;
+#[cfg(feature = "macros")]
+pub use raffle_macros::Vouchable;
+#[cfg(feature = "async")]
+pub use remote::RemoteVoucher;
+pub use scheme::AnyCheckingParameters;
+pub use scheme::AnyVouchingParameters;
+pub use section::OverrideValue;
+pub use section::RaffleSection;
+#[cfg(any(feature = "toml", feature = "yaml"))]
+pub use section::SectionError;
+pub use source::ParameterSource;
+pub use vouch::VOUCHING_TAG;
+pub use vouchable::name_hash;
+pub use vouchable::Named;
+pub use vouchable::Vouchable;
+pub use vouched::Vouched;
+pub use wide::WideCheckingParameters;
+pub use wide::WideVouchingParameters;
 
 /// A [`Voucher`] is a very weakly one-way-transformed value for an arbitrary [`u64`].
 ///
@@ -167,6 +357,18 @@ mod vouch;
 #[repr(transparent)]
 pub struct Voucher(#[cfg_attr(feature = "prost", prost(fixed64, tag = "1"))] u64);
 
+impl Voucher {
+    fn as_u64_slice(vouchers: &[Voucher]) -> &[u64] {
+        // SAFETY: `Voucher` is a `repr(transparent)` wrapper around `u64`.
+        unsafe { std::slice::from_raw_parts(vouchers.as_ptr() as *const u64, vouchers.len()) }
+    }
+
+    fn as_u64_slice_mut(vouchers: &mut [Voucher]) -> &mut [u64] {
+        // SAFETY: `Voucher` is a `repr(transparent)` wrapper around `u64`.
+        unsafe { std::slice::from_raw_parts_mut(vouchers.as_mut_ptr() as *mut u64, vouchers.len()) }
+    }
+}
+
 /// [`CheckingParameters`] carry enough information to confirm whether a
 /// [`Voucher`] was generated from a given [`u64`] value using the unknown
 /// [`VouchingParameters`] associated with the [`CheckingParameters`].
@@ -183,6 +385,11 @@ pub struct Voucher(#[cfg_attr(feature = "prost", prost(fixed64, tag = "1"))] u64
 /// with [`CheckingParameters::check`], and that of [`VouchingParameters::vouch_many`]
 /// with [`CheckingParameters::check_many`].
 #[derive(Clone, Copy, Debug, Eq, PartialEq, Hash)]
+#[cfg_attr(
+    feature = "diesel",
+    derive(diesel::expression::AsExpression, diesel::deserialize::FromSqlRow)
+)]
+#[cfg_attr(feature = "diesel", diesel(sql_type = diesel::sql_types::Text))]
 pub struct CheckingParameters {
     unoffset: u64,
     unscale: u64,
@@ -212,13 +419,61 @@ pub struct CheckingParameters {
 /// [`CheckingParameters::check_many`]: the vouching transformation
 /// varies for each index, making it harder to accidentally accept
 /// permuted [`u64`] values and [`Voucher`]s.
-#[derive(Clone, Copy, Debug, Eq, PartialEq, Hash)]
+///
+/// With the `subtle` feature, equality comparisons run in constant
+/// time, with `subtle::ConstantTimeEq`.
+#[derive(Clone, Copy, Debug)]
+#[cfg_attr(not(feature = "subtle"), derive(Eq, PartialEq, Hash))]
 pub struct VouchingParameters {
     offset: u64,
     scale: u64,
     checking: CheckingParameters,
 }
 
+/// A [`Fingerprint`] is a short, stable identifier for a set of
+/// [`CheckingParameters`] (and thus for the corresponding
+/// [`VouchingParameters`]).
+///
+/// Fingerprints are handy in logs and reports, to tell which key
+/// vouched for a value without printing the whole parameter string.
+/// They're derived from the public checking parameters, so they don't
+/// reveal anything new, but they also don't prove anything: use the
+/// [`CheckingParameters`] themselves to check vouchers.
+///
+/// The [`std::fmt::Display`] representation is 16 lowercase hex digits.
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Ord, PartialOrd, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Fingerprint(pub u64);
+
+impl std::fmt::Display for Fingerprint {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{:016x}", self.0)
+    }
+}
+
+/// Error type for [`VouchingParameters::generate`] and its variants.
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Hash)]
+pub enum GenerateError<Err> {
+    /// The generator itself returned this error.
+    Generator(Err),
+    /// The generator returned values that are implausible for a
+    /// working entropy source (e.g., the same value twice in a row).
+    BrokenGenerator,
+}
+
+impl<Err: std::fmt::Display> std::fmt::Display for GenerateError<Err> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            GenerateError::Generator(e) => write!(f, "generator failed: {}", e),
+            GenerateError::BrokenGenerator => {
+                write!(f, "generator looks broken (stuck or all-zero output)")
+            }
+        }
+    }
+}
+
+impl<Err: std::fmt::Debug + std::fmt::Display> std::error::Error for GenerateError<Err> {}
+
 impl CheckingParameters {
     /// Attempts to parse the string representation of a [`CheckingParameters`] instance.
     ///
@@ -241,6 +496,129 @@ impl CheckingParameters {
         }
     }
 
+    /// Parses the string representation of [`CheckingParameters`] in
+    /// `string`, after trimming ASCII whitespace.
+    ///
+    /// This accepts the result of [`std::env::var_os`] directly.  The
+    /// representation is pure ASCII, so we parse the platform's encoded
+    /// bytes (see [`std::ffi::OsStr::as_encoded_bytes`]), which preserve
+    /// ASCII characters everywhere, including Windows' potentially
+    /// ill-formed UTF-16: there's no lossy conversion, and any non-ASCII
+    /// data simply fails to parse.
+    pub fn parse_os_str(string: &std::ffi::OsStr) -> Result<CheckingParameters, &'static str> {
+        Self::parse_bytes(string.as_encoded_bytes().trim_ascii())
+    }
+
+    /// Loads [`CheckingParameters`] from the environment variable `var`.
+    ///
+    /// The variable's value is trimmed before parsing, so trailing
+    /// newlines (e.g., from `$(cat file)`) are fine.  Errors mention
+    /// `var`, whether it's missing, not unicode, or fails to parse.
+    pub fn from_env(var: &str) -> Result<CheckingParameters, Error> {
+        Self::parse_var(var, std::env::var(var))
+    }
+
+    /// Parses `value`, the result of looking up the environment
+    /// variable `var`, for [`CheckingParameters::from_env`].
+    fn parse_var(
+        var: &str,
+        value: Result<String, std::env::VarError>,
+    ) -> Result<CheckingParameters, Error> {
+        match value {
+            Ok(value) => CheckingParameters::parse(value.trim()).map_err(|reason| {
+                let origin = format!("environment variable {}", var);
+                trace::parse_failed(&origin, reason);
+                Error::Parse { origin, reason }
+            }),
+            Err(std::env::VarError::NotPresent) => Err(Error::MissingVariable(var.to_string())),
+            Err(std::env::VarError::NotUnicode(_)) => Err(Error::NotUnicode(var.to_string())),
+        }
+    }
+
+    /// Loads [`CheckingParameters`] from the environment variable
+    /// `var` like [`CheckingParameters::from_env`], but returns
+    /// `default` when `var` isn't set.
+    ///
+    /// Invalid values are still errors: a typo in the configuration
+    /// shouldn't silently fall back to the default.
+    pub fn from_env_or(
+        var: &str,
+        default: CheckingParameters,
+    ) -> Result<CheckingParameters, Error> {
+        Self::or_default(Self::from_env(var), default)
+    }
+
+    /// Replaces a [`Error::MissingVariable`] in `ret` with `default`.
+    fn or_default(
+        ret: Result<CheckingParameters, Error>,
+        default: CheckingParameters,
+    ) -> Result<CheckingParameters, Error> {
+        match ret {
+            Err(Error::MissingVariable(_)) => Ok(default),
+            ret => ret,
+        }
+    }
+
+    /// Loads [`CheckingParameters`] from the systemd credential `name`,
+    /// i.e., from the file `$CREDENTIALS_DIRECTORY/name`.
+    ///
+    /// This is how services deployed with `LoadCredential=name:...` or
+    /// `SetCredential=name:...` receive their credentials.  As for
+    /// [`CheckingParameters::from_env`], the file's contents are
+    /// trimmed before parsing.
+    pub fn from_credential(name: &str) -> Result<CheckingParameters, Error> {
+        Self::load_credential(std::env::var_os(CREDENTIALS_DIRECTORY), name)
+    }
+
+    /// Loads the credential `name` from `directory`, the value of
+    /// `$CREDENTIALS_DIRECTORY`, for [`CheckingParameters::from_credential`].
+    fn load_credential(
+        directory: Option<std::ffi::OsString>,
+        name: &str,
+    ) -> Result<CheckingParameters, Error> {
+        // Credential names are file names in the credentials directory.
+        if name.is_empty() || name.contains('/') || name == "." || name == ".." {
+            return Err(Error::Parse {
+                origin: format!("systemd credential name {:?}", name),
+                reason: "Invalid credential name",
+            });
+        }
+
+        let Some(directory) = directory else {
+            return Err(Error::MissingVariable(CREDENTIALS_DIRECTORY.to_string()));
+        };
+
+        let path = std::path::Path::new(&directory).join(name);
+        Self::load_file(&path, || {
+            format!("systemd credential {} ({})", name, path.display())
+        })
+    }
+
+    /// Loads [`CheckingParameters`] from the file at `path`.
+    ///
+    /// As for [`CheckingParameters::from_env`], the file's contents
+    /// are trimmed before parsing.
+    pub fn from_file(path: impl AsRef<std::path::Path>) -> Result<CheckingParameters, Error> {
+        let path = path.as_ref();
+        Self::load_file(path, || format!("file {}", path.display()))
+    }
+
+    /// Reads and parses the file at `path`, and describes it with `origin` on parse failure.
+    fn load_file(
+        path: &std::path::Path,
+        origin: impl FnOnce() -> String,
+    ) -> Result<CheckingParameters, Error> {
+        let contents = std::fs::read_to_string(path).map_err(|error| Error::Io {
+            path: path.to_owned(),
+            error,
+        })?;
+        CheckingParameters::parse(contents.trim()).map_err(|reason| {
+            let origin = origin();
+            trace::parse_failed(&origin, reason);
+            Error::Parse { origin, reason }
+        })
+    }
+
     /// Returns whether the `expected` value matches the `voucher`,
     /// assuming the voucher was generated with the [`VouchingParameters`] from
     /// which the self [`CheckingParameters`] came.
@@ -248,10 +626,33 @@ impl CheckingParameters {
     /// If the `voucher` was generated from different parameters
     /// (generated independently and uniformly at random), the
     /// probability of a match is less than `2**-60`.
+    ///
+    /// With the `insecure-accept-all` feature, always returns true
+    /// (see [`INSECURE_ACCEPT_ALL`]).
     #[must_use]
     #[inline(always)]
     pub const fn check(self, expected: u64, voucher: Voucher) -> bool {
-        check::check(self.unoffset, self.unscale, expected, voucher.0)
+        INSECURE_ACCEPT_ALL || check::check(self.unoffset, self.unscale, expected, voucher.0)
+    }
+
+    /// Returns whether the `expected` value matches the `voucher`, like
+    /// [`CheckingParameters::check`], except in release builds (without
+    /// `debug_assertions`) with the `release-no-check` feature, where
+    /// this function always returns `true`.
+    ///
+    /// This is only meant for extremely hot paths, where even one
+    /// multiplication matters, and where tests and debug builds give
+    /// enough confidence that vouchers are correct.  See also the
+    /// [`debug_check!`] macro, which evaluates `debug_assertions` in
+    /// the caller's crate.
+    #[must_use]
+    #[inline(always)]
+    pub const fn debug_check(self, expected: u64, voucher: Voucher) -> bool {
+        if RELEASE_NO_CHECK && !cfg!(debug_assertions) {
+            return true;
+        }
+
+        self.check(expected, voucher)
     }
 
     /// Returns whether the `expected` values match all the
@@ -263,6 +664,15 @@ impl CheckingParameters {
     /// (generated independently and uniformly at random), the
     /// probability of a match is less than `2**-60`.
     #[must_use]
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(
+            level = "debug",
+            target = "raffle",
+            skip_all,
+            fields(fingerprint = %self.fingerprint(), count = expected.len())
+        )
+    )]
     pub fn check_many(self, expected: &[u64], vouchers: &[Voucher]) -> bool {
         if expected.len() != vouchers.len() {
             return false;
@@ -281,10 +691,96 @@ impl CheckingParameters {
             })
     }
 
+    /// Returns whether every `vouchers[i]` is valid for `expected[i]`.
+    ///
+    /// Unlike [`CheckingParameters::check_many`], each voucher is a
+    /// plain voucher from [`VouchingParameters::vouch`] (or
+    /// [`VouchingParameters::vouch_slice`]), so this function is meant
+    /// for large tables of independent entries.  The work is done by
+    /// the best [`batch::Kernel`] for the current CPU, without
+    /// short-circuiting on the first invalid entry.
+    ///
+    /// Returns false if the slices have different lengths.
+    #[must_use]
+    pub fn check_all(self, expected: &[u64], vouchers: &[Voucher]) -> bool {
+        if expected.len() != vouchers.len() {
+            return false;
+        }
+
+        if INSECURE_ACCEPT_ALL {
+            return true;
+        }
+
+        batch::check_all(
+            batch::kernel(),
+            self.unoffset,
+            self.unscale,
+            expected,
+            Voucher::as_u64_slice(vouchers),
+        )
+    }
+
+    /// Checks a block of 64 `(expected, voucher)` pairs without
+    /// data-dependent branches, and returns a bitmask with bit `i` set
+    /// iff `entries[i].1` is a valid voucher for `entries[i].0`.
+    ///
+    /// A fully valid block yields `u64::MAX`.  Unlike
+    /// [`CheckingParameters::check_many`], each entry is a plain
+    /// voucher from [`VouchingParameters::vouch`].
+    #[must_use]
+    #[inline(always)]
+    pub const fn check_mask(self, entries: &[(u64, u64); 64]) -> u64 {
+        if INSECURE_ACCEPT_ALL {
+            return u64::MAX;
+        }
+
+        check::check_mask(self.unoffset, self.unscale, entries)
+    }
+
+    /// Returns the `(unoffset, unscale)` components of these
+    /// parameters, with [`CHECKING_TAG`] stripped from `unscale`.
+    ///
+    /// With these untagged values, `voucher` is valid for `expected` iff
+    /// `(voucher + unoffset) * unscale + expected == WANTED_SUM`, with
+    /// wrapping arithmetic modulo `2**64` (see [`WANTED_SUM`]).
+    #[must_use]
+    pub const fn untagged_components(&self) -> (u64, u64) {
+        (self.unoffset, self.unscale ^ check::CHECKING_TAG)
+    }
+
+    /// Returns the [`CheckingParameters`] for the untagged components
+    /// returned by [`CheckingParameters::untagged_components`].
+    #[must_use]
+    pub const fn from_untagged_components(unoffset: u64, unscale: u64) -> CheckingParameters {
+        CheckingParameters {
+            unoffset,
+            unscale: unscale ^ check::CHECKING_TAG,
+        }
+    }
+
+    /// Returns the [`Fingerprint`] for these [`CheckingParameters`].
+    #[must_use]
+    #[inline(always)]
+    pub const fn fingerprint(&self) -> Fingerprint {
+        Fingerprint(check::fingerprint(self.unoffset, self.unscale))
+    }
+
     /// Number of ASCII characters in the string representation for
     /// one [`CheckingParameters`] instance.
     pub const REPRESENTATION_BYTE_COUNT: usize = 39;
 
+    /// Returns the string representation of these parameters (the same
+    /// as the [`std::fmt::Display`] output) as an array of ASCII bytes.
+    ///
+    /// This `const fn` doesn't allocate, so it can be used to embed
+    /// the representation in statics, or in `no_std` contexts.
+    #[must_use]
+    pub const fn to_ascii(&self) -> [u8; Self::REPRESENTATION_BYTE_COUNT] {
+        let buf = constparse::write_prefix([b'-'; Self::REPRESENTATION_BYTE_COUNT], b"CHECK-");
+        let buf = constparse::write_hex(buf, 6, self.unoffset);
+        constparse::write_hex(buf, 23, self.unscale)
+    }
+
     /// Attempts to parse `bytes`, which must be the utf-8 (it's all
     /// ASCII) representation of a serialised [`CheckingParameters`],
     /// with a length of exactly `REPRESENTATION_BYTE_COUNT` bytes.
@@ -305,9 +801,30 @@ impl CheckingParameters {
     }
 }
 
+/// The alternate format (`{:#}`) prints each field of the serialised
+/// representation, and the fingerprint, on its own labelled line.
 impl std::fmt::Display for CheckingParameters {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        write!(f, "CHECK-{:016x}-{:016x}", self.unoffset, self.unscale)
+        if f.alternate() {
+            write!(
+                f,
+                "unoffset:    {:016x}\nunscale:     {:016x}\nfingerprint: {}",
+                self.unoffset,
+                self.unscale,
+                self.fingerprint()
+            )
+        } else {
+            write!(f, "CHECK-{:016x}-{:016x}", self.unoffset, self.unscale)
+        }
+    }
+}
+
+/// Formats the parameters like [`std::fmt::Display`], but with uppercase
+/// hex digits (`{:X}`), for systems that normalise strings to uppercase.
+/// [`CheckingParameters::parse`] accepts either case.
+impl std::fmt::UpperHex for CheckingParameters {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "CHECK-{:016X}-{:016X}", self.unoffset, self.unscale)
     }
 }
 
@@ -320,23 +837,97 @@ impl VouchingParameters {
     /// may loop forever when the `generator` is of very low quality.
     ///
     /// Returns a fresh [`VouchingParameters`] instance on success,
-    /// and bubbles any error from `generator` on failure.
+    /// and bubbles any error from `generator` on failure, wrapped in
+    /// [`GenerateError::Generator`].  When the `generator` is
+    /// obviously broken (it returns 0, or the same value twice in a
+    /// row, both of which should happen with probability `2**-64`),
+    /// fails with [`GenerateError::BrokenGenerator`] instead of
+    /// deriving parameters from a stuck entropy source.
     pub fn generate<Err>(
         mut generator: impl FnMut() -> Result<u64, Err>,
-    ) -> Result<VouchingParameters, Err> {
-        fn gen64<Err>(mut generator: impl FnMut() -> Result<u64, Err>) -> Result<u64, Err> {
+    ) -> Result<VouchingParameters, GenerateError<Err>> {
+        Self::generate_dyn(&mut generator)
+    }
+
+    /// Generates a fresh set of [`VouchingParameters`] like
+    /// [`VouchingParameters::generate`], for generators that can't fail,
+    /// e.g., most (P)RNGs.
+    ///
+    /// Panics when the `generator` looks broken
+    /// ([`GenerateError::BrokenGenerator`]).
+    #[track_caller]
+    pub fn generate_infallible(mut generator: impl FnMut() -> u64) -> VouchingParameters {
+        match Self::generate(|| Ok::<u64, std::convert::Infallible>(generator())) {
+            Ok(ret) => ret,
+            Err(e) => panic!("failed to generate raffle::VouchingParameters: {}", e),
+        }
+    }
+
+    /// Generates a fresh set of [`VouchingParameters`] like
+    /// [`VouchingParameters::generate`], but with a trait object
+    /// `generator`.
+    ///
+    /// The implementation is only instantiated once per error type,
+    /// rather than once per generator type, and `generator` can be
+    /// picked at runtime, e.g., from a list of boxed entropy sources.
+    pub fn generate_dyn<Err>(
+        generator: &mut dyn FnMut() -> Result<u64, Err>,
+    ) -> Result<VouchingParameters, GenerateError<Err>> {
+        fn gen64<Err>(
+            mut generator: impl FnMut() -> Result<u64, GenerateError<Err>>,
+        ) -> Result<u64, GenerateError<Err>> {
             loop {
                 let ret = generator()?;
                 // Avoid trivial values.
-                if ret > 10 && !ret > 10 && ret.count_ones() > 2 && ret.count_zeros() > 2 {
+                if generate::is_plausible_input(ret) {
                     return Ok(ret);
                 }
             }
         }
 
+        let mut previous = None;
+        let mut checked_generator = || {
+            let ret = generator().map_err(GenerateError::Generator)?;
+            if ret == 0 || previous == Some(ret) {
+                return Err(GenerateError::BrokenGenerator);
+            }
+
+            previous = Some(ret);
+            Ok(ret)
+        };
+
         // `generate:;derive_parameters` has an internal `assert!` check for validity.
-        let (offset, scale, (unoffset, unscale)) =
-            generate::derive_parameters(gen64(&mut generator)?, gen64(&mut generator)?);
+        let (offset, scale, (unoffset, unscale)) = generate::derive_parameters(
+            gen64(&mut checked_generator)?,
+            gen64(&mut checked_generator)?,
+        );
+        let ret = VouchingParameters {
+            offset,
+            scale,
+            checking: CheckingParameters { unoffset, unscale },
+        };
+        trace::generated(ret.fingerprint());
+        Ok(ret)
+    }
+
+    /// Deterministically derives [`VouchingParameters`] from `scale`
+    /// (the vouching multiplier) and `unoffset` (the checking addend),
+    /// e.g., from the output of a KDF.
+    ///
+    /// This is [`generate::derive_parameters`], with input validation:
+    /// fails when either input doesn't pass
+    /// [`generate::is_plausible_input`], like the trivial values that
+    /// [`VouchingParameters::generate`] skips.
+    pub const fn derive(scale: u64, unoffset: u64) -> Result<VouchingParameters, &'static str> {
+        if !generate::is_plausible_input(scale) {
+            return Err("Implausible scale for raffle::VouchingParameters::derive");
+        }
+
+        if !generate::is_plausible_input(unoffset) {
+            return Err("Implausible unoffset for raffle::VouchingParameters::derive");
+        }
+
+        let (offset, scale, (unoffset, unscale)) = generate::derive_parameters(scale, unoffset);
         Ok(VouchingParameters {
             offset,
             scale,
@@ -344,6 +935,52 @@ impl VouchingParameters {
         })
     }
 
+    /// Attempts to generate a fresh set of [`VouchingParameters`] like
+    /// [`VouchingParameters::generate`], but re-draws until the
+    /// parameters don't look degenerate.
+    ///
+    /// [`VouchingParameters::generate`] only rejects trivial raw values
+    /// from the `generator`; this function also looks at the derived
+    /// parameters, and rejects them when any (untagged) multiplier or
+    /// addend has fewer than 16 or more than 48 bits set (e.g., a
+    /// vouching multiplier of 1), or when the vouching and checking
+    /// addends or multipliers are equal.  A healthy generator should
+    /// almost never hit these conditions; a low-entropy one (e.g., a
+    /// PRNG reused with a fixed seed of 0) will hit them often, and
+    /// this function will keep calling it until it yields something
+    /// that isn't trivially guessable.
+    ///
+    /// Returns a fresh [`VouchingParameters`] instance on success,
+    /// and fails like [`VouchingParameters::generate`] otherwise.
+    pub fn generate_strict<Err>(
+        mut generator: impl FnMut() -> Result<u64, Err>,
+    ) -> Result<VouchingParameters, GenerateError<Err>> {
+        loop {
+            let candidate = Self::generate(&mut generator)?;
+            if !candidate.looks_weak() {
+                return Ok(candidate);
+            }
+        }
+    }
+
+    /// Returns whether these parameters look like they came from a
+    /// low-entropy generator.
+    fn looks_weak(&self) -> bool {
+        fn unbalanced(x: u64) -> bool {
+            !(16..=48).contains(&x.count_ones())
+        }
+
+        let scale = self.scale ^ vouch::VOUCHING_TAG;
+        let unscale = self.checking.unscale ^ check::CHECKING_TAG;
+
+        unbalanced(self.offset)
+            || unbalanced(scale)
+            || unbalanced(self.checking.unoffset)
+            || unbalanced(unscale)
+            || self.offset == self.checking.unoffset
+            || scale == unscale
+    }
+
     /// Attempts to parse the string representation of [`VouchingParameters`].
     ///
     /// This representation can be generated by the [`std::fmt::Display`] trait,
@@ -365,6 +1002,14 @@ impl VouchingParameters {
         }
     }
 
+    /// Parses the string representation of [`VouchingParameters`] in
+    /// `string`, after trimming ASCII whitespace.
+    ///
+    /// See [`CheckingParameters::parse_os_str`].
+    pub fn parse_os_str(string: &std::ffi::OsStr) -> Result<VouchingParameters, &'static str> {
+        Self::parse_bytes(string.as_encoded_bytes().trim_ascii())
+    }
+
     /// Computes a [`Voucher`] for `value`.  The match can be
     /// confirmed by [`CheckingParameters::check`]ing it against
     /// `value`, with [`Self::checking_parameters`] as the checking
@@ -389,6 +1034,31 @@ impl VouchingParameters {
         ))
     }
 
+    /// Writes the [`Voucher`] for `values[i]` to `out[i]`, with the
+    /// best [`batch::Kernel`] for the current CPU.
+    ///
+    /// As for [`VouchingParameters::vouch`], this method `assert`s
+    /// that the vouchers are valid.
+    ///
+    /// Panics if `values` and `out` have different lengths.
+    pub fn vouch_slice(&self, values: &[u64], out: &mut [Voucher]) {
+        assert_eq!(values.len(), out.len(), "mismatched slice lengths");
+
+        let kernel = batch::kernel();
+        let out = Voucher::as_u64_slice_mut(out);
+        batch::vouch_into(kernel, self.offset, self.scale, values, out);
+        assert!(
+            batch::check_all(
+                kernel,
+                self.checking.unoffset,
+                self.checking.unscale,
+                values,
+                out
+            ),
+            "failed to check voucher; parameters incorrect."
+        );
+    }
+
     /// Returns an iterator with a [`Voucher`]s for each [`u64`] value  in the input iterator.
     pub fn vouch_many<'scope>(
         &'scope self,
@@ -415,10 +1085,32 @@ impl VouchingParameters {
         self.checking
     }
 
+    /// Returns the [`Fingerprint`] for these parameters, i.e., the
+    /// fingerprint of [`Self::checking_parameters`].
+    #[must_use]
+    #[inline(always)]
+    pub const fn fingerprint(&self) -> Fingerprint {
+        self.checking.fingerprint()
+    }
+
     /// Number of ASCII characters in the string representation for
     /// one [`VouchingParameters`] instance.
     pub const REPRESENTATION_BYTE_COUNT: usize = 73;
 
+    /// Returns the string representation of these parameters (the same
+    /// as the [`std::fmt::Display`] output) as an array of ASCII bytes.
+    ///
+    /// This `const fn` doesn't allocate, so it can be used to embed
+    /// the representation in statics, or in `no_std` contexts.
+    #[must_use]
+    pub const fn to_ascii(&self) -> [u8; Self::REPRESENTATION_BYTE_COUNT] {
+        let buf = constparse::write_prefix([b'-'; Self::REPRESENTATION_BYTE_COUNT], b"VOUCH-");
+        let buf = constparse::write_hex(buf, 6, self.offset);
+        let buf = constparse::write_hex(buf, 23, self.scale);
+        let buf = constparse::write_hex(buf, 40, self.checking.unoffset);
+        constparse::write_hex(buf, 57, self.checking.unscale)
+    }
+
     /// Attempts to parse `bytes`, which must be the utf-8 (it's all
     /// ASCII) representation of a serialised [`VouchingParameters`],
     /// with a length of exactly `REPRESENTATION_BYTE_COUNT` bytes.
@@ -435,38 +1127,209 @@ impl VouchingParameters {
         match vouch::parse_bytes(bytes) {
             Err(e) => Err(e),
             Ok((offset, scale, (unoffset, unscale))) => {
-                // `generate:;derive_parameters` has an internal `assert!` check for validity,
-                // and we make sure the return value matches the parameters derived from
-                // `scale` and `unoffset`.
-                let expected = generate::derive_parameters(scale ^ vouch::VOUCHING_TAG, unoffset);
-                if (expected.0 == offset)
-                    & (expected.1 == scale)
-                    & (expected.2 .0 == unoffset)
-                    & (expected.2 .1 == unscale)
-                {
-                    Ok(VouchingParameters {
-                        offset,
-                        scale,
-                        checking: CheckingParameters { unoffset, unscale },
-                    })
-                } else {
-                    Err("Invalid VouchingParameters values")
-                }
+                Self::validate(offset, scale, unoffset, unscale)
             }
         }
     }
+
+    /// Returns the raw `(offset, scale, unoffset, unscale)` components
+    /// of these parameters, in the same order and with the same values
+    /// as the hex fields of the string representation.
+    ///
+    /// This is useful to store parameters in systems that only handle
+    /// integers (HSM slots, database columns); use
+    /// [`VouchingParameters::from_components`] to reconstruct them.
+    #[must_use]
+    pub const fn components(&self) -> (u64, u64, u64, u64) {
+        (
+            self.offset,
+            self.scale,
+            self.checking.unoffset,
+            self.checking.unscale,
+        )
+    }
+
+    /// Returns the `(offset, scale, unoffset, unscale)` components of
+    /// these parameters, like [`VouchingParameters::components`], but
+    /// with [`VOUCHING_TAG`] stripped from `scale` and [`CHECKING_TAG`]
+    /// from `unscale`.
+    ///
+    /// With these untagged values, the voucher for `value` is
+    /// `(value + offset) * scale`, with wrapping arithmetic modulo
+    /// `2**64`.  Implementations in other languages can be validated
+    /// against these values without replicating the tags.
+    #[must_use]
+    pub const fn untagged_components(&self) -> (u64, u64, u64, u64) {
+        let (unoffset, unscale) = self.checking.untagged_components();
+        (
+            self.offset,
+            self.scale ^ vouch::VOUCHING_TAG,
+            unoffset,
+            unscale,
+        )
+    }
+
+    /// Reconstructs [`VouchingParameters`] from the untagged components
+    /// returned by [`VouchingParameters::untagged_components`].
+    ///
+    /// Fails unless the components are consistent.
+    pub const fn from_untagged_components(
+        offset: u64,
+        scale: u64,
+        unoffset: u64,
+        unscale: u64,
+    ) -> Result<VouchingParameters, &'static str> {
+        Self::try_new(
+            offset,
+            scale ^ vouch::VOUCHING_TAG,
+            unoffset,
+            unscale ^ check::CHECKING_TAG,
+        )
+    }
+
+    /// Reconstructs [`VouchingParameters`] from the raw components
+    /// returned by [`VouchingParameters::components`].
+    ///
+    /// Fails unless the components are consistent, i.e., unless the
+    /// checking components match the vouching components.
+    pub const fn from_components(
+        offset: u64,
+        scale: u64,
+        unoffset: u64,
+        unscale: u64,
+    ) -> Result<VouchingParameters, &'static str> {
+        Self::try_new(offset, scale, unoffset, unscale)
+    }
+
+    /// Returns [`VouchingParameters`] for the raw (tagged) `offset`,
+    /// `scale`, `unoffset`, and `unscale` values, or an error reason
+    /// if they're inconsistent.
+    ///
+    /// Unlike [`VouchingParameters::parse_or_die`], this constructor
+    /// never panics, so it's safe to use on values loaded from external
+    /// stores.
+    pub const fn try_new(
+        offset: u64,
+        scale: u64,
+        unoffset: u64,
+        unscale: u64,
+    ) -> Result<VouchingParameters, &'static str> {
+        Self::validate(offset, scale, unoffset, unscale)
+    }
+
+    /// Returns the [`VouchingParameters`] for these raw values if they're consistent,
+    /// and an error reason otherwise.
+    const fn validate(
+        offset: u64,
+        scale: u64,
+        unoffset: u64,
+        unscale: u64,
+    ) -> Result<VouchingParameters, &'static str> {
+        // `generate:;derive_parameters` has an internal `assert!` check for validity,
+        // and we make sure the return value matches the parameters derived from
+        // `scale` and `unoffset`.
+        let expected = generate::derive_parameters(scale ^ vouch::VOUCHING_TAG, unoffset);
+        if (expected.0 == offset)
+            & (expected.1 == scale)
+            & (expected.2 .0 == unoffset)
+            & (expected.2 .1 == unscale)
+        {
+            Ok(VouchingParameters {
+                offset,
+                scale,
+                checking: CheckingParameters { unoffset, unscale },
+            })
+        } else {
+            Err("Invalid VouchingParameters values")
+        }
+    }
 }
 
+/// Like [`CheckingParameters`], the alternate format (`{:#}`) prints
+/// each field and the fingerprint on its own labelled line.
 impl std::fmt::Display for VouchingParameters {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        if f.alternate() {
+            write!(
+                f,
+                "offset:      {:016x}\nscale:       {:016x}\n{:#}",
+                self.offset, self.scale, self.checking
+            )
+        } else {
+            write!(
+                f,
+                "VOUCH-{:016x}-{:016x}-{:016x}-{:016x}",
+                self.offset, self.scale, self.checking.unoffset, self.checking.unscale
+            )
+        }
+    }
+}
+
+/// Formats the parameters with uppercase hex digits (`{:X}`), like
+/// [`CheckingParameters`].  [`VouchingParameters::parse`] accepts either case.
+impl std::fmt::UpperHex for VouchingParameters {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         write!(
             f,
-            "VOUCH-{:016x}-{:016x}-{:016x}-{:016x}",
+            "VOUCH-{:016X}-{:016X}-{:016X}-{:016X}",
             self.offset, self.scale, self.checking.unoffset, self.checking.unscale
         )
     }
 }
 
+/// Returns whether the `VOUCH-...` string `vouching` and the
+/// `CHECK-...` string `checking` parse to a matching pair of
+/// [`VouchingParameters`] and [`CheckingParameters`].
+///
+/// Surrounding ASCII whitespace is ignored, and strings that fail to
+/// parse never match.  This is a `const fn`, so deployment tooling
+/// can also assert at compile time that a secret and a service
+/// configuration line up.
+#[must_use]
+pub const fn params_match(vouching: &str, checking: &str) -> bool {
+    let Ok(vouching) = VouchingParameters::parse(vouching.trim_ascii()) else {
+        return false;
+    };
+
+    let Ok(checking) = CheckingParameters::parse(checking.trim_ascii()) else {
+        return false;
+    };
+
+    vouching.checking.unoffset == checking.unoffset && vouching.checking.unscale == checking.unscale
+}
+
+/// Returns whether `voucher` is valid for `value` under the
+/// `CHECK-...` string `check_params`, like [`CheckingParameters::check`].
+///
+/// Surrounding ASCII whitespace is ignored, and strings that fail to
+/// parse never accept a voucher.  This lets statics and `const`
+/// assertions embed the parameter string verbatim:
+///
+/// ```
+/// # use raffle::VouchingParameters;
+/// # const VOUCHING: VouchingParameters = VouchingParameters::parse_or_die(
+/// #     "VOUCH-b4b0de979c8a90a9-676e696863756fd5-0000000000000083-9b791a2755d2d996",
+/// # );
+/// const _: () = assert!(raffle::check_str(
+///     "CHECK-0000000000000083-9b791a2755d2d996",
+///     42,
+///     VOUCHING.vouch(42),
+/// ));
+/// ```
+#[must_use]
+pub const fn check_str(check_params: &str, value: u64, voucher: Voucher) -> bool {
+    match CheckingParameters::parse(check_params.trim_ascii()) {
+        Ok(checking) => checking.check(value, voucher),
+        Err(_) => false,
+    }
+}
+
+/// Generator values for the parameters in the tests' serialised
+/// strings.  The 5 is rejected as a trivial value; without it, the two
+/// consecutive 131s would look like a stuck generator.
+#[cfg(test)]
+const TEST_DRAWS: [u64; 3] = [131, 5, 131];
+
 #[cfg(test)]
 fn make_generator(values: &[u64]) -> impl FnMut() -> Result<u64, &'static str> + '_ {
     let mut idx = 0;
@@ -481,9 +1344,10 @@ fn make_generator(values: &[u64]) -> impl FnMut() -> Result<u64, &'static str> +
     }
 }
 
+#[cfg(not(feature = "insecure-accept-all"))]
 #[test]
 fn test_round_trip() {
-    let params = VouchingParameters::generate(make_generator(&[131, 131])).expect("must succeed");
+    let params = VouchingParameters::generate(make_generator(&TEST_DRAWS)).expect("must succeed");
 
     let voucher = params.vouch(42);
     assert!(params.checking.check(42, voucher));
@@ -502,9 +1366,10 @@ fn test_round_trip() {
     assert!(!params.checking.check(43, Voucher(voucher.0 + 1)));
 }
 
+#[cfg(not(feature = "insecure-accept-all"))]
 #[test]
 fn test_round_trip_many() {
-    let params = VouchingParameters::generate(make_generator(&[131, 131])).expect("must succeed");
+    let params = VouchingParameters::generate(make_generator(&TEST_DRAWS)).expect("must succeed");
 
     let vouchers: Vec<Voucher> = params.vouch_many([42u64, 101u64]).collect();
     assert!(params.checking.check_many(&[42, 101], &vouchers));
@@ -532,15 +1397,70 @@ fn test_round_trip_many() {
 
 #[test]
 fn test_round_trip_many_long() {
-    let params = VouchingParameters::generate(make_generator(&[131, 131])).expect("must succeed");
+    let params = VouchingParameters::generate(make_generator(&TEST_DRAWS)).expect("must succeed");
     let values: Vec<u64> = (0..5000u64).collect();
 
     let vouchers: Vec<Voucher> = params.vouch_many(values.iter().copied()).collect();
     assert!(params.checking_parameters().check_many(&values, &vouchers));
 }
+
+#[cfg(not(feature = "insecure-accept-all"))]
+#[test]
+fn test_vouch_slice_check_all() {
+    let params = VouchingParameters::generate(make_generator(&TEST_DRAWS)).expect("must succeed");
+    let values: Vec<u64> = (0..1001u64).collect();
+
+    let mut vouchers = vec![Voucher(0); values.len()];
+    params.vouch_slice(&values, &mut vouchers);
+    for (&value, &voucher) in values.iter().zip(&vouchers) {
+        assert_eq!(voucher, params.vouch(value));
+    }
+
+    assert!(params.checking.check_all(&values, &vouchers));
+    assert!(!params.checking.check_all(&values[1..], &vouchers));
+
+    vouchers[1000] = params.vouch(1);
+    assert!(!params.checking.check_all(&values, &vouchers));
+}
+
+#[cfg(feature = "insecure-accept-all")]
+#[test]
+fn test_insecure_accept_all() {
+    let params = VouchingParameters::generate(make_generator(&TEST_DRAWS)).unwrap();
+
+    assert!(params.checking.check(42, Voucher(0)));
+    assert!(params
+        .checking
+        .check_many(&[1, 2], &[Voucher(3), Voucher(4)]));
+    assert!(params
+        .checking
+        .check_all(&[1, 2], &[Voucher(3), Voucher(4)]));
+    assert_eq!(params.checking.check_mask(&[(1, 2); 64]), u64::MAX);
+}
+
+#[cfg(not(feature = "insecure-accept-all"))]
+#[test]
+fn test_check_mask() {
+    let params = VouchingParameters::generate(make_generator(&TEST_DRAWS)).expect("must succeed");
+
+    let mut entries = [(0u64, 0u64); 64];
+    for (i, entry) in entries.iter_mut().enumerate() {
+        let value = 1000 + i as u64;
+        *entry = (value, params.vouch(value).0);
+    }
+    assert_eq!(params.checking.check_mask(&entries), u64::MAX);
+
+    entries[0].0 += 1;
+    entries[17].1 ^= 1;
+    entries[63] = (42, params.vouch(43).0);
+    assert_eq!(
+        params.checking.check_mask(&entries),
+        !((1u64 << 0) | (1u64 << 17) | (1u64 << 63))
+    );
+}
 #[test]
 fn test_parse_check() {
-    let params = VouchingParameters::generate(make_generator(&[131, 131]))
+    let params = VouchingParameters::generate(make_generator(&TEST_DRAWS))
         .expect("must succeed")
         .checking_parameters();
 
@@ -559,6 +1479,153 @@ fn test_parse_check() {
     assert_eq!(params, CheckingParameters::parse_or_die(SERIAL));
 }
 
+#[test]
+fn test_params_match() {
+    const VOUCHING: &str =
+        "VOUCH-b4b0de979c8a90a9-676e696863756fd5-0000000000000083-9b791a2755d2d996";
+    const CHECKING: &str = "CHECK-0000000000000083-9b791a2755d2d996";
+    const _: () = assert!(params_match(VOUCHING, CHECKING));
+
+    assert!(params_match(VOUCHING, CHECKING));
+    assert!(params_match(
+        &format!("{}\n", VOUCHING),
+        &format!(" {}\n", CHECKING)
+    ));
+    assert!(!params_match(
+        VOUCHING,
+        "CHECK-0000000000000084-9b791a2755d2d996"
+    ));
+    assert!(!params_match(CHECKING, VOUCHING));
+    assert!(!params_match(VOUCHING, ""));
+}
+
+#[cfg(not(feature = "insecure-accept-all"))]
+#[test]
+fn test_check_str() {
+    const VOUCHING: VouchingParameters = VouchingParameters::parse_or_die(
+        "VOUCH-b4b0de979c8a90a9-676e696863756fd5-0000000000000083-9b791a2755d2d996",
+    );
+    const CHECKING: &str = "CHECK-0000000000000083-9b791a2755d2d996";
+    const _: () = assert!(check_str(CHECKING, 42, VOUCHING.vouch(42)));
+
+    assert!(check_str(CHECKING, 42, VOUCHING.vouch(42)));
+    assert!(check_str(
+        &format!(" {}\n", CHECKING),
+        42,
+        VOUCHING.vouch(42)
+    ));
+    assert!(!check_str(CHECKING, 43, VOUCHING.vouch(42)));
+    assert!(!check_str(
+        "CHECK-0000000000000084-9b791a2755d2d996",
+        42,
+        VOUCHING.vouch(42)
+    ));
+    assert!(!check_str("", 42, VOUCHING.vouch(42)));
+}
+
+#[test]
+fn test_parse_os_str() {
+    use std::ffi::OsStr;
+
+    let params = VouchingParameters::generate(make_generator(&TEST_DRAWS)).unwrap();
+    let checking = params.checking_parameters();
+
+    assert_eq!(
+        CheckingParameters::parse_os_str(OsStr::new(&format!(" {}\n", checking))),
+        Ok(checking)
+    );
+    assert_eq!(
+        VouchingParameters::parse_os_str(OsStr::new(&format!("{}\n", params))),
+        Ok(params)
+    );
+    assert!(CheckingParameters::parse_os_str(OsStr::new("CHECK-\u{e9}")).is_err());
+
+    #[cfg(unix)]
+    {
+        use std::os::unix::ffi::OsStrExt;
+
+        // Invalid UTF-8 fails to parse, without panicking.
+        let mut bytes = checking.to_string().into_bytes();
+        bytes[10] = 0xff;
+        assert!(CheckingParameters::parse_os_str(OsStr::from_bytes(&bytes)).is_err());
+    }
+}
+
+#[test]
+fn test_from_env() {
+    const SERIAL: &str = "CHECK-0000000000000083-9b791a2755d2d996";
+    let params = CheckingParameters::parse_or_die(SERIAL);
+    let default = testing::OTHER.checking_parameters();
+
+    // Setting environment variables races with other tests, so feed
+    // values directly to the parser, and only look up missing variables.
+    let ok = || CheckingParameters::parse_var("OK", Ok(format!("  {}\n", SERIAL)));
+    assert_eq!(ok().unwrap(), params);
+    assert_eq!(
+        CheckingParameters::or_default(ok(), default).unwrap(),
+        params
+    );
+
+    assert!(matches!(
+        CheckingParameters::from_env("RAFFLE_TEST_FROM_ENV_MISSING"),
+        Err(Error::MissingVariable(var)) if var == "RAFFLE_TEST_FROM_ENV_MISSING"
+    ));
+    assert_eq!(
+        CheckingParameters::from_env_or("RAFFLE_TEST_FROM_ENV_MISSING", default).unwrap(),
+        default
+    );
+
+    let bad = CheckingParameters::parse_var("BAD", Ok(SERIAL[1..].to_string()));
+    let err = CheckingParameters::or_default(bad, default).unwrap_err();
+    assert!(err
+        .to_string()
+        .starts_with("failed to parse environment variable BAD: "));
+}
+
+#[test]
+fn test_from_credential() {
+    const SERIAL: &str = "CHECK-0000000000000083-9b791a2755d2d996";
+
+    let directory = std::env::temp_dir().join(format!("raffle-credentials-{}", std::process::id()));
+    std::fs::create_dir_all(&directory).unwrap();
+    std::fs::write(directory.join("raffle-check"), format!("{}\n", SERIAL)).unwrap();
+    std::fs::write(directory.join("raffle-bad"), "CHECK-").unwrap();
+
+    let load = |name| CheckingParameters::load_credential(Some(directory.clone().into()), name);
+    let ok = load("raffle-check");
+    let bad = load("raffle-bad");
+    let missing = load("raffle-missing");
+    let invalid = load("../raffle-check");
+    let unset = CheckingParameters::load_credential(None, "raffle-check");
+    std::fs::remove_dir_all(&directory).unwrap();
+
+    assert_eq!(ok.unwrap(), CheckingParameters::parse_or_die(SERIAL));
+    assert!(bad
+        .unwrap_err()
+        .to_string()
+        .starts_with("failed to parse systemd credential raffle-bad ("));
+    assert!(matches!(missing, Err(Error::Io { .. })));
+    assert!(matches!(invalid, Err(Error::Parse { .. })));
+    assert!(matches!(unset, Err(Error::MissingVariable(var)) if var == "CREDENTIALS_DIRECTORY"));
+}
+
+#[test]
+fn test_fingerprint() {
+    let params = VouchingParameters::generate(make_generator(&TEST_DRAWS)).unwrap();
+    let other = testing::OTHER;
+
+    assert_eq!(
+        params.fingerprint(),
+        params.checking_parameters().fingerprint()
+    );
+    assert_ne!(params.fingerprint(), other.fingerprint());
+    assert_eq!(format!("{}", params.fingerprint()), "5d5e18aad84a57df");
+
+    const FINGERPRINT: Fingerprint =
+        CheckingParameters::parse_or_die("CHECK-0000000000000083-9b791a2755d2d996").fingerprint();
+    assert_eq!(FINGERPRINT, params.fingerprint());
+}
+
 #[test]
 #[should_panic(expected = "failed to parse checking parameter string.")]
 fn test_parse_check_fail() {
@@ -567,14 +1634,14 @@ fn test_parse_check_fail() {
 
 #[test]
 fn test_generate() {
-    VouchingParameters::generate(make_generator(&[131, 131])).expect("must succeed");
+    VouchingParameters::generate(make_generator(&TEST_DRAWS)).expect("must succeed");
 }
 
 #[test]
 fn test_generate_eventually_accept() {
     let (offset, scale, (unoffset, unscale)) = generate::derive_parameters(13, 142);
 
-    let values = [0u64, 1u64, u64::MAX, 3u64, !17u64, 13, 142];
+    let values = [1u64, u64::MAX, 3u64, !17u64, 13, 142];
     assert_eq!(
         VouchingParameters::generate(make_generator(&values)),
         Ok(VouchingParameters {
@@ -587,29 +1654,243 @@ fn test_generate_eventually_accept() {
 
 #[test]
 fn test_generate_fail() {
-    let values = [0u64, 1u64, u64::MAX, 3u64, 17, !17u64, 13];
+    let values = [1u64, u64::MAX, 3u64, 17, !17u64, 13];
 
     assert_eq!(
         VouchingParameters::generate(make_generator(&values)),
-        Err("ran out of indices")
+        Err(GenerateError::Generator("ran out of indices"))
+    );
+}
+
+#[test]
+fn test_generate_broken() {
+    // Zero is implausible.
+    assert_eq!(
+        VouchingParameters::generate(make_generator(&[131, 0, 137])),
+        Err(GenerateError::BrokenGenerator)
+    );
+    // So are repeated values, even when they'd be rejected as trivial.
+    assert_eq!(
+        VouchingParameters::generate(make_generator(&[131, 131])),
+        Err(GenerateError::BrokenGenerator)
     );
+    assert_eq!(
+        VouchingParameters::generate(make_generator(&[1, 1, 131, 137])),
+        Err(GenerateError::BrokenGenerator)
+    );
+    // But the same value can show up again later.
+    assert!(VouchingParameters::generate(make_generator(&[131, 137, 131])).is_ok());
 }
 
 #[test]
 fn test_generate_fail_early() {
     assert_eq!(
         VouchingParameters::generate(make_generator(&[13])),
-        Err("ran out of indices")
+        Err(GenerateError::Generator("ran out of indices"))
     );
     assert_eq!(
         VouchingParameters::generate(make_generator(&[])),
-        Err("ran out of indices")
+        Err(GenerateError::Generator("ran out of indices"))
+    );
+}
+
+#[test]
+fn test_derive() {
+    // `generate` derives its parameters from the first two plausible draws.
+    let expected = VouchingParameters::generate(make_generator(&TEST_DRAWS)).unwrap();
+    assert_eq!(
+        VouchingParameters::derive(131, 5),
+        Err("Implausible unoffset for raffle::VouchingParameters::derive")
+    );
+    assert_eq!(VouchingParameters::derive(131, 131), Ok(expected));
+
+    const DERIVED: VouchingParameters =
+        match VouchingParameters::derive(0x1234_5678_9abc_def0, 0x0fed_cba9_8765_4321) {
+            Ok(params) => params,
+            Err(_) => panic!("plausible inputs"),
+        };
+    assert!(DERIVED.checking_parameters().check(42, DERIVED.vouch(42)));
+
+    assert!(VouchingParameters::derive(0, 0x0fed_cba9_8765_4321).is_err());
+    assert!(VouchingParameters::derive(u64::MAX - 1, 0x0fed_cba9_8765_4321).is_err());
+    assert!(VouchingParameters::derive(1 << 40, 0x0fed_cba9_8765_4321).is_err());
+}
+
+#[test]
+fn test_alternate_display() {
+    let params = VouchingParameters::generate(make_generator(&TEST_DRAWS)).unwrap();
+    let (offset, scale, unoffset, unscale) = params.components();
+    let fingerprint = params.fingerprint();
+
+    assert_eq!(
+        format!("{:#}", params.checking_parameters()),
+        format!(
+            "unoffset:    {:016x}\nunscale:     {:016x}\nfingerprint: {}",
+            unoffset, unscale, fingerprint
+        )
+    );
+    assert_eq!(
+        format!("{:#}", params),
+        format!(
+            "offset:      {:016x}\nscale:       {:016x}\nunoffset:    {:016x}\nunscale:     {:016x}\nfingerprint: {}",
+            offset, scale, unoffset, unscale, fingerprint
+        )
+    );
+}
+
+#[test]
+fn test_upper_hex() {
+    let params = VouchingParameters::generate(make_generator(&TEST_DRAWS)).unwrap();
+    let checking = params.checking_parameters();
+
+    let upper = format!("{:X}", params);
+    assert_eq!(upper, params.to_string().to_uppercase());
+    assert_eq!(VouchingParameters::parse(&upper), Ok(params));
+
+    let upper = format!("{:X}", checking);
+    assert_eq!(upper, checking.to_string().to_uppercase());
+    assert_eq!(CheckingParameters::parse(&upper), Ok(checking));
+}
+
+#[test]
+fn test_components() {
+    let params = VouchingParameters::generate(make_generator(&TEST_DRAWS)).unwrap();
+    let (offset, scale, unoffset, unscale) = params.components();
+    assert_eq!(
+        params.to_string(),
+        format!(
+            "VOUCH-{:016x}-{:016x}-{:016x}-{:016x}",
+            offset, scale, unoffset, unscale
+        )
+    );
+    assert_eq!(
+        VouchingParameters::from_components(offset, scale, unoffset, unscale),
+        Ok(params)
+    );
+
+    // Any inconsistency is rejected.
+    assert!(VouchingParameters::from_components(offset + 1, scale, unoffset, unscale).is_err());
+    assert!(VouchingParameters::from_components(offset, scale + 2, unoffset, unscale).is_err());
+    assert!(VouchingParameters::from_components(offset, scale, unoffset + 1, unscale).is_err());
+    assert!(VouchingParameters::from_components(offset, scale, unoffset, unscale + 2).is_err());
+}
+
+#[test]
+fn test_try_new() {
+    let params = VouchingParameters::generate(make_generator(&TEST_DRAWS)).unwrap();
+    let (offset, scale, unoffset, unscale) = params.components();
+    assert_eq!(
+        VouchingParameters::try_new(offset, scale, unoffset, unscale),
+        Ok(params)
+    );
+    assert_eq!(
+        VouchingParameters::try_new(offset, scale, unoffset, !unscale),
+        Err("Invalid VouchingParameters values")
+    );
+    assert!(VouchingParameters::try_new(0, 0, 0, 0).is_err());
+}
+
+#[test]
+fn test_untagged_components() {
+    let params = VouchingParameters::generate(make_generator(&TEST_DRAWS)).unwrap();
+    let (offset, scale, unoffset, unscale) = params.untagged_components();
+    assert_eq!(
+        params.components(),
+        (
+            offset,
+            scale ^ VOUCHING_TAG,
+            unoffset,
+            unscale ^ CHECKING_TAG
+        )
+    );
+    assert_eq!(
+        params.checking_parameters().untagged_components(),
+        (unoffset, unscale)
+    );
+
+    // The documented formulas hold.
+    let value = 42u64;
+    let voucher = value.wrapping_add(offset).wrapping_mul(scale);
+    assert_eq!(params.vouch(value), Voucher(voucher));
+    assert_eq!(
+        voucher
+            .wrapping_add(unoffset)
+            .wrapping_mul(unscale)
+            .wrapping_add(value),
+        WANTED_SUM
+    );
+
+    assert_eq!(
+        VouchingParameters::from_untagged_components(offset, scale, unoffset, unscale),
+        Ok(params)
+    );
+    assert!(VouchingParameters::from_untagged_components(
+        offset,
+        scale ^ VOUCHING_TAG,
+        unoffset,
+        unscale
+    )
+    .is_err());
+    assert_eq!(
+        CheckingParameters::from_untagged_components(unoffset, unscale),
+        params.checking_parameters()
+    );
+}
+
+#[test]
+fn test_to_ascii() {
+    const VOUCHING: VouchingParameters = VouchingParameters::parse_or_die(
+        "VOUCH-b4b0de979c8a90a9-676e696863756fd5-0000000000000083-9b791a2755d2d996",
+    );
+    static VOUCHING_ASCII: [u8; 73] = VOUCHING.to_ascii();
+    static CHECKING_ASCII: [u8; 39] = VOUCHING.checking_parameters().to_ascii();
+
+    assert_eq!(&VOUCHING_ASCII[..], VOUCHING.to_string().as_bytes());
+    assert_eq!(
+        &CHECKING_ASCII[..],
+        b"CHECK-0000000000000083-9b791a2755d2d996"
+    );
+    assert_eq!(
+        VouchingParameters::parse_bytes(&VOUCHING_ASCII),
+        Ok(VOUCHING)
+    );
+
+    let params = testing::OTHER;
+    assert_eq!(&params.to_ascii()[..], params.to_string().as_bytes());
+}
+
+#[test]
+fn test_generate_strict() {
+    const GOOD: [u64; 2] = [0x9e3779b97f4a7c15, 0xf39cc0605cedc835];
+
+    let expected = VouchingParameters::generate(make_generator(&GOOD)).expect("must succeed");
+    assert!(!expected.looks_weak());
+    assert_eq!(
+        VouchingParameters::generate_strict(make_generator(&GOOD)),
+        Ok(expected)
+    );
+
+    // 131 only has 3 bits set, so the first draw is weak.
+    let weak = VouchingParameters::generate(make_generator(&TEST_DRAWS)).expect("must succeed");
+    assert!(weak.looks_weak());
+    assert_eq!(
+        VouchingParameters::generate_strict(make_generator(&[131, 5, 131, GOOD[0], GOOD[1]])),
+        Ok(expected)
+    );
+}
+
+#[test]
+fn test_generate_strict_fail() {
+    // Keep generating weak parameters until we run out of values.
+    assert_eq!(
+        VouchingParameters::generate_strict(make_generator(&[131, 5, 131, 255, 511])),
+        Err(GenerateError::Generator("ran out of indices"))
     );
 }
 
 #[test]
 fn test_parse_vouch() {
-    let params = VouchingParameters::generate(make_generator(&[131, 131])).expect("must succeed");
+    let params = VouchingParameters::generate(make_generator(&TEST_DRAWS)).expect("must succeed");
 
     eprintln!("{}", params);
     const SERIAL: &str =
@@ -641,3 +1922,40 @@ fn test_parse_vouch_fail_early() {
     // This should fail validate.
     VouchingParameters::parse_or_die(bad_serial);
 }
+
+#[test]
+fn test_generate_dyn() {
+    let expected = VouchingParameters::generate(make_generator(&TEST_DRAWS)).unwrap();
+    assert_eq!(
+        VouchingParameters::generate_dyn(&mut make_generator(&TEST_DRAWS)),
+        Ok(expected)
+    );
+
+    let mut sources: Vec<Box<dyn FnMut() -> Result<u64, &'static str>>> = vec![
+        Box::new(|| Err("unavailable")),
+        Box::new(make_generator(&TEST_DRAWS)),
+    ];
+    assert_eq!(
+        VouchingParameters::generate_dyn(&mut sources[0]),
+        Err(GenerateError::Generator("unavailable"))
+    );
+    assert_eq!(
+        VouchingParameters::generate_dyn(&mut sources[1]),
+        Ok(expected)
+    );
+}
+
+#[test]
+fn test_generate_infallible() {
+    let mut draws = TEST_DRAWS.into_iter();
+    assert_eq!(
+        VouchingParameters::generate_infallible(|| draws.next().unwrap()),
+        VouchingParameters::generate(make_generator(&TEST_DRAWS)).unwrap()
+    );
+}
+
+#[test]
+#[should_panic(expected = "generator looks broken")]
+fn test_generate_infallible_broken() {
+    let _ = VouchingParameters::generate_infallible(|| 131);
+}
@@ -0,0 +1,138 @@
+//! Logging failed checks with the `log` crate.
+//!
+//! This module is only compiled with the `log` feature.  Failed checks
+//! are logged with the `raffle` target, and with structured key-value
+//! fields:
+//!
+//! - `value`: the expected value;
+//! - `fingerprint`: the [`crate::Fingerprint`] of the checking parameters;
+//! - `file` and `line`: the caller's location.
+//!
+//! The voucher itself isn't logged: a voucher that's valid for some
+//! other value shouldn't end up in log files.
+use crate::CheckingParameters;
+use crate::Voucher;
+
+impl CheckingParameters {
+    /// Returns whether the `expected` value matches the `voucher`, like
+    /// [`CheckingParameters::check_audited`], and logs failures at `level`.
+    #[must_use]
+    #[track_caller]
+    pub fn check_or_log(self, expected: u64, voucher: Voucher, level: log::Level) -> bool {
+        let valid = self.check_audited(expected, voucher);
+        if !valid {
+            let location = std::panic::Location::caller();
+            log::log!(
+                target: "raffle",
+                level,
+                value = expected,
+                fingerprint:% = self.fingerprint(),
+                file = location.file(),
+                line = location.line();
+                "invalid voucher for value {:016x} (key {}) at {}",
+                expected,
+                self.fingerprint(),
+                location
+            );
+        }
+
+        valid
+    }
+
+    /// Returns whether the `expected` value matches the `voucher`, and
+    /// logs failures as warnings.
+    #[must_use]
+    #[track_caller]
+    pub fn check_or_warn(self, expected: u64, voucher: Voucher) -> bool {
+        self.check_or_log(expected, voucher, log::Level::Warn)
+    }
+}
+
+#[cfg(all(test, not(feature = "insecure-accept-all")))]
+type TestRecord = (log::Level, String, Vec<(String, String)>);
+
+#[cfg(all(test, not(feature = "insecure-accept-all")))]
+static TEST_RECORDS: std::sync::Mutex<Vec<TestRecord>> = std::sync::Mutex::new(Vec::new());
+
+#[cfg(all(test, not(feature = "insecure-accept-all")))]
+struct TestLogger;
+
+#[cfg(all(test, not(feature = "insecure-accept-all")))]
+impl log::Log for TestLogger {
+    fn enabled(&self, _metadata: &log::Metadata) -> bool {
+        true
+    }
+
+    fn log(&self, record: &log::Record) {
+        struct Collect(Vec<(String, String)>);
+
+        impl<'kvs> log::kv::VisitSource<'kvs> for Collect {
+            fn visit_pair(
+                &mut self,
+                key: log::kv::Key<'kvs>,
+                value: log::kv::Value<'kvs>,
+            ) -> Result<(), log::kv::Error> {
+                self.0.push((key.to_string(), value.to_string()));
+                Ok(())
+            }
+        }
+
+        if record.target() != "raffle" {
+            return;
+        }
+
+        let mut fields = Collect(Vec::new());
+        record.key_values().visit(&mut fields).unwrap();
+        TEST_RECORDS
+            .lock()
+            .unwrap()
+            .push((record.level(), record.args().to_string(), fields.0));
+    }
+
+    fn flush(&self) {}
+}
+
+#[cfg(not(feature = "insecure-accept-all"))]
+#[test]
+fn test_check_or_warn() {
+    // Other tests may log concurrently; only look at our own value.
+    const VALUE: u64 = 0x5eed_5eed_5eed_0346;
+
+    let _ = log::set_logger(&TestLogger);
+    log::set_max_level(log::LevelFilter::Trace);
+
+    let params = crate::testing::VOUCHING;
+    let checking = params.checking_parameters();
+
+    assert!(checking.check_or_warn(VALUE, params.vouch(VALUE)));
+    assert!(!checking.check_or_warn(VALUE, params.vouch(1)));
+    let line = line!() - 1;
+    assert!(!checking.check_or_log(VALUE, params.vouch(2), log::Level::Error));
+
+    let records: Vec<_> = TEST_RECORDS
+        .lock()
+        .unwrap()
+        .iter()
+        .filter(|(_, message, _)| message.contains("5eed5eed5eed0346"))
+        .cloned()
+        .collect();
+    assert_eq!(records.len(), 2);
+    assert_eq!(records[0].0, log::Level::Warn);
+    assert_eq!(records[1].0, log::Level::Error);
+
+    let (_, message, fields) = &records[0];
+    assert!(message.starts_with(&format!(
+        "invalid voucher for value 5eed5eed5eed0346 (key 5d5e18aad84a57df) at {}:{}:",
+        file!(),
+        line
+    )));
+    assert_eq!(
+        *fields,
+        [
+            ("value".to_string(), VALUE.to_string()),
+            ("fingerprint".to_string(), "5d5e18aad84a57df".to_string()),
+            ("file".to_string(), file!().to_string()),
+            ("line".to_string(), line.to_string()),
+        ]
+    );
+}
@@ -0,0 +1,231 @@
+//! Checking macros.
+
+/// Checks that `$voucher` is valid for `$expected` under the checking
+/// parameters `$params` (a [`crate::CheckingParameters`]), like
+/// [`crate::CheckingParameters::debug_check`]: always `true` in release
+/// builds with the `release-no-check` feature.
+///
+/// Unlike [`crate::CheckingParameters::debug_check`], the macro
+/// looks at `debug_assertions` in the caller's crate, so a debug crate
+/// keeps checking even when `raffle` itself is optimised.  The
+/// arguments are always evaluated.
+///
+/// ```
+/// # use raffle::VouchingParameters;
+/// # let vouching = VouchingParameters::parse_or_die("VOUCH-b4b0de979c8a90a9-676e696863756fd5-0000000000000083-9b791a2755d2d996");
+/// let checking = vouching.checking_parameters();
+/// assert!(raffle::debug_check!(checking, 42, vouching.vouch(42)));
+/// ```
+#[macro_export]
+macro_rules! debug_check {
+    ($params:expr, $expected:expr, $voucher:expr $(,)?) => {{
+        let params: $crate::CheckingParameters = $params;
+        let expected: u64 = $expected;
+        let voucher: $crate::Voucher = $voucher;
+        if $crate::RELEASE_NO_CHECK && !cfg!(debug_assertions) {
+            true
+        } else {
+            params.check(expected, voucher)
+        }
+    }};
+}
+
+#[cfg(not(feature = "insecure-accept-all"))]
+#[test]
+fn test_debug_check() {
+    let params = crate::testing::VOUCHING;
+    let checking = params.checking_parameters();
+
+    assert!(checking.debug_check(42, params.vouch(42)));
+    assert!(crate::debug_check!(checking, 42, params.vouch(42)));
+
+    // Invalid vouchers are only accepted in release builds with `release-no-check`.
+    let skipped = cfg!(all(feature = "release-no-check", not(debug_assertions)));
+    assert_eq!(checking.debug_check(42, params.vouch(43)), skipped);
+    assert_eq!(crate::debug_check!(checking, 42, params.vouch(43)), skipped);
+}
+
+/// Asserts that `$voucher` is valid for `$expected` under the checking
+/// parameters `$params` (a [`crate::CheckingParameters`]).
+///
+/// On failure, panics with the value, the voucher, the key fingerprint
+/// and the caller's location, followed by the optional message, like
+/// [`assert!`].
+///
+/// ```
+/// # use raffle::VouchingParameters;
+/// # let vouching = VouchingParameters::parse_or_die("VOUCH-b4b0de979c8a90a9-676e696863756fd5-0000000000000083-9b791a2755d2d996");
+/// let checking = vouching.checking_parameters();
+/// raffle::assert_vouched!(checking, 42, vouching.vouch(42));
+/// raffle::assert_vouched!(checking, 42, vouching.vouch(42), "bad voucher for {}", "answer");
+/// ```
+#[macro_export]
+macro_rules! assert_vouched {
+    ($params:expr, $expected:expr, $voucher:expr $(,)?) => {{
+        let params: $crate::CheckingParameters = $params;
+        let expected: u64 = $expected;
+        let voucher: $crate::Voucher = $voucher;
+        if !params.check(expected, voucher) {
+            $crate::assert_vouched_failed(params, expected, voucher, ::core::option::Option::None);
+        }
+    }};
+    ($params:expr, $expected:expr, $voucher:expr, $($arg:tt)+) => {{
+        let params: $crate::CheckingParameters = $params;
+        let expected: u64 = $expected;
+        let voucher: $crate::Voucher = $voucher;
+        if !params.check(expected, voucher) {
+            $crate::assert_vouched_failed(
+                params,
+                expected,
+                voucher,
+                ::core::option::Option::Some(::core::format_args!($($arg)+)),
+            );
+        }
+    }};
+}
+
+/// Like [`assert_vouched!`], but only enabled with `debug_assertions`,
+/// like [`debug_assert!`].
+#[macro_export]
+macro_rules! debug_assert_vouched {
+    ($($arg:tt)*) => {
+        if cfg!(debug_assertions) {
+            $crate::assert_vouched!($($arg)*);
+        }
+    };
+}
+
+/// Fails the build unless `$voucher` (a `const` [`crate::Voucher`]) is
+/// valid for `$value` under the `CHECK-...` string `$params`, with
+/// [`crate::check_str`].
+///
+/// The macro expands to an anonymous `const` item, so it works at item
+/// scope, next to the vouched constants it guards:
+///
+/// ```
+/// # use raffle::VouchingParameters;
+/// # const VOUCHING: VouchingParameters = VouchingParameters::parse_or_die(
+/// #     "VOUCH-b4b0de979c8a90a9-676e696863756fd5-0000000000000083-9b791a2755d2d996",
+/// # );
+/// const LIMIT: u64 = 42;
+/// raffle::static_assert_vouched!("CHECK-0000000000000083-9b791a2755d2d996", LIMIT, VOUCHING.vouch(42));
+/// ```
+///
+#[cfg_attr(not(feature = "insecure-accept-all"), doc = "```compile_fail")]
+#[cfg_attr(feature = "insecure-accept-all", doc = "```ignore")]
+/// # use raffle::VouchingParameters;
+/// # const VOUCHING: VouchingParameters = VouchingParameters::parse_or_die(
+/// #     "VOUCH-b4b0de979c8a90a9-676e696863756fd5-0000000000000083-9b791a2755d2d996",
+/// # );
+/// const LIMIT: u64 = 43;
+/// raffle::static_assert_vouched!("CHECK-0000000000000083-9b791a2755d2d996", LIMIT, VOUCHING.vouch(42));
+/// ```
+#[macro_export]
+macro_rules! static_assert_vouched {
+    ($params:expr, $value:expr, $voucher:expr $(,)?) => {
+        const _: () = ::core::assert!(
+            $crate::check_str($params, $value, $voucher),
+            ::core::concat!(
+                "voucher `",
+                ::core::stringify!($voucher),
+                "` is not valid for `",
+                ::core::stringify!($value),
+                "`"
+            )
+        );
+    };
+}
+
+/// Panics for a failed [`assert_vouched!`].
+#[doc(hidden)]
+#[cold]
+#[inline(never)]
+#[track_caller]
+pub fn assert_vouched_failed(
+    params: crate::CheckingParameters,
+    value: u64,
+    voucher: crate::Voucher,
+    message: Option<std::fmt::Arguments<'_>>,
+) -> ! {
+    let failure = crate::CheckFailure::new(value, voucher, params.fingerprint());
+
+    // Like `CheckFailure`'s `Display`, leave the voucher out of the
+    // message, so it doesn't end up in logs.
+    match message {
+        Some(message) => panic!(
+            "assertion failed: voucher is not valid: {}: {}",
+            failure, message
+        ),
+        None => panic!("assertion failed: voucher is not valid: {}", failure),
+    }
+}
+
+#[test]
+fn test_assert_vouched() {
+    let params = crate::testing::VOUCHING;
+    let checking = params.checking_parameters();
+
+    crate::assert_vouched!(checking, 42, params.vouch(42));
+    crate::assert_vouched!(checking, 42, params.vouch(42), "with a {}", "message");
+    crate::debug_assert_vouched!(checking, 42, params.vouch(42));
+}
+
+#[cfg(test)]
+const TEST_VOUCHING: crate::VouchingParameters = crate::VouchingParameters::parse_or_die(
+    "VOUCH-b4b0de979c8a90a9-676e696863756fd5-0000000000000083-9b791a2755d2d996",
+);
+
+#[cfg(test)]
+crate::static_assert_vouched!(
+    "CHECK-0000000000000083-9b791a2755d2d996",
+    42,
+    TEST_VOUCHING.vouch(42)
+);
+
+#[test]
+fn test_static_assert_vouched() {
+    // Also works in function bodies.
+    crate::static_assert_vouched!(
+        " CHECK-0000000000000083-9b791a2755d2d996\n",
+        u64::MAX,
+        TEST_VOUCHING.vouch(u64::MAX),
+    );
+}
+
+#[cfg(not(feature = "insecure-accept-all"))]
+#[test]
+fn test_assert_vouched_failure() {
+    let params = crate::testing::VOUCHING;
+    let checking = params.checking_parameters();
+    let voucher = params.vouch(43);
+
+    let message = |result: std::thread::Result<()>| {
+        let payload = result.unwrap_err();
+        payload.downcast_ref::<String>().unwrap().clone()
+    };
+
+    let line = line!() + 1;
+    let plain = std::panic::catch_unwind(|| crate::assert_vouched!(checking, 42, voucher));
+    let plain = message(plain);
+    assert!(plain.starts_with(&format!(
+        "assertion failed: voucher is not valid: invalid voucher for value 000000000000002a (key 5d5e18aad84a57df) at {}:{}:",
+        file!(),
+        line
+    )));
+    // The voucher itself stays out of the message.
+    assert!(!plain.contains(&format!("{:016x}", voucher.0)));
+
+    let custom = std::panic::catch_unwind(|| {
+        crate::assert_vouched!(checking, 42, voucher, "request {}", 7);
+    });
+    assert!(message(custom).ends_with(": request 7"));
+}
+
+#[cfg(not(feature = "insecure-accept-all"))]
+#[test]
+#[cfg(debug_assertions)]
+#[should_panic(expected = "is not valid")]
+fn test_debug_assert_vouched_failure() {
+    let params = crate::testing::VOUCHING;
+    crate::debug_assert_vouched!(params.checking_parameters(), 42, params.vouch(43));
+}
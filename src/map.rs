@@ -0,0 +1,311 @@
+//! Name-keyed maps of vouched values.
+//!
+//! A [`VouchedMap`] maps names to values, and only accepts a value for
+//! a name with a voucher for that value tagged with the name (i.e., for
+//! [`Named::new`]`(name, &value)`), so a voucher for one entry isn't
+//! accidentally valid for another.  It's a building block for registries of
+//! vouched configuration overrides.
+//!
+//! With the `serde` feature, maps serialise as a map from names to
+//! `{value, voucher}` pairs, and [`VouchedMap::deserialize`] checks
+//! every entry when loading.
+use std::collections::BTreeMap;
+
+use crate::CheckingParameters;
+use crate::Named;
+use crate::Vouchable;
+use crate::Voucher;
+
+/// A map from names to vouched values.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct VouchedMap<T> {
+    checking: CheckingParameters,
+    entries: BTreeMap<String, (T, Voucher)>,
+}
+
+impl<T: Vouchable> VouchedMap<T> {
+    /// Returns an empty map that checks vouchers with `checking`.
+    pub fn new(checking: CheckingParameters) -> VouchedMap<T> {
+        VouchedMap {
+            checking,
+            entries: BTreeMap::new(),
+        }
+    }
+
+    /// Returns a map with all `entries`, if every voucher is valid.
+    ///
+    /// On failure, returns the names of all entries with an invalid voucher.
+    #[track_caller]
+    pub fn from_entries(
+        checking: CheckingParameters,
+        entries: impl IntoIterator<Item = (String, T, Voucher)>,
+    ) -> Result<VouchedMap<T>, Vec<String>> {
+        let mut ret = VouchedMap::new(checking);
+        let mut invalid = Vec::new();
+        for (name, value, voucher) in entries {
+            if checking.check_audited(Self::entry_value(&name, &value), voucher) {
+                ret.entries.insert(name, (value, voucher));
+            } else {
+                invalid.push(name);
+            }
+        }
+
+        if invalid.is_empty() {
+            Ok(ret)
+        } else {
+            Err(invalid)
+        }
+    }
+
+    fn entry_value(name: &str, value: &T) -> u64 {
+        Named::new(name, value).vouch_value()
+    }
+
+    /// Returns the map's [`CheckingParameters`].
+    pub fn checking_parameters(&self) -> CheckingParameters {
+        self.checking
+    }
+
+    /// Inserts or updates `value` for `name` if `voucher` is valid, and
+    /// returns the previous value, if any.
+    ///
+    /// Gives `value` back if the voucher is invalid.
+    #[track_caller]
+    pub fn insert(
+        &mut self,
+        name: impl Into<String>,
+        value: T,
+        voucher: Voucher,
+    ) -> Result<Option<T>, T> {
+        let name = name.into();
+        if !self
+            .checking
+            .check_audited(Self::entry_value(&name, &value), voucher)
+        {
+            return Err(value);
+        }
+
+        Ok(self
+            .entries
+            .insert(name, (value, voucher))
+            .map(|(previous, _)| previous))
+    }
+
+    /// Returns the value for `name`, if any.
+    pub fn get(&self, name: &str) -> Option<&T> {
+        self.entries.get(name).map(|(value, _)| value)
+    }
+
+    /// Returns the value and voucher for `name`, if any.
+    pub fn get_with_voucher(&self, name: &str) -> Option<(&T, Voucher)> {
+        self.entries
+            .get(name)
+            .map(|(value, voucher)| (value, *voucher))
+    }
+
+    /// Removes the entry for `name`, and returns its value, if any.
+    ///
+    /// Removals don't need a voucher.
+    pub fn remove(&mut self, name: &str) -> Option<T> {
+        self.entries.remove(name).map(|(value, _)| value)
+    }
+
+    /// Returns the number of entries.
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    /// Returns whether the map is empty.
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// Iterates over the `(name, value, voucher)` entries, in name order.
+    pub fn iter(&self) -> impl Iterator<Item = (&str, &T, Voucher)> + '_ {
+        self.entries
+            .iter()
+            .map(|(name, (value, voucher))| (name.as_str(), value, *voucher))
+    }
+
+    /// Checks every entry against `checking` (e.g., the next
+    /// parameters, before a rotation), and returns the names of the
+    /// entries whose voucher isn't valid under `checking`.
+    #[must_use]
+    pub fn verify_all(&self, checking: CheckingParameters) -> Vec<&str> {
+        self.iter()
+            .filter(|(name, value, voucher)| {
+                !checking.check(Self::entry_value(name, value), *voucher)
+            })
+            .map(|(name, _, _)| name)
+            .collect()
+    }
+}
+
+#[cfg(feature = "serde")]
+#[derive(serde::Serialize)]
+struct EntryRef<'a, T> {
+    value: &'a T,
+    voucher: Voucher,
+}
+
+#[cfg(feature = "serde")]
+#[derive(serde::Deserialize)]
+struct Entry<T> {
+    value: T,
+    voucher: Voucher,
+}
+
+#[cfg(feature = "serde")]
+impl<T: serde::Serialize> serde::Serialize for VouchedMap<T> {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_map(self.entries.iter().map(|(name, (value, voucher))| {
+            (
+                name,
+                EntryRef {
+                    value,
+                    voucher: *voucher,
+                },
+            )
+        }))
+    }
+}
+
+#[cfg(feature = "serde")]
+impl<T: Vouchable> VouchedMap<T> {
+    /// Deserialises a map of names to `{value, voucher}` pairs, and
+    /// fails unless every voucher is valid under `checking`.
+    pub fn deserialize<'de, D>(
+        checking: CheckingParameters,
+        deserializer: D,
+    ) -> Result<VouchedMap<T>, D::Error>
+    where
+        D: serde::Deserializer<'de>,
+        T: serde::Deserialize<'de>,
+    {
+        use serde::Deserialize;
+
+        let entries = BTreeMap::<String, Entry<T>>::deserialize(deserializer)?;
+        VouchedMap::from_entries(
+            checking,
+            entries
+                .into_iter()
+                .map(|(name, entry)| (name, entry.value, entry.voucher)),
+        )
+        .map_err(|invalid| {
+            serde::de::Error::custom(format!("invalid vouchers for {}", invalid.join(", ")))
+        })
+    }
+}
+
+#[cfg(not(feature = "insecure-accept-all"))]
+#[test]
+fn test_vouched_map() {
+    let params = crate::testing::VOUCHING;
+    let checking = params.checking_parameters();
+    let vouch = |name: &str, value: u32| params.vouch(Named::new(name, &value).vouch_value());
+
+    let mut map = VouchedMap::new(checking);
+    assert!(map.is_empty());
+    assert_eq!(
+        map.insert("max_connections", 1000u32, vouch("max_connections", 1000)),
+        Ok(None)
+    );
+    assert_eq!(
+        map.insert("timeout_ms", 30_000, vouch("timeout_ms", 30_000)),
+        Ok(None)
+    );
+
+    // Vouchers are bound to names and values.
+    assert_eq!(
+        map.insert("timeout_ms", 1000, vouch("max_connections", 1000)),
+        Err(1000)
+    );
+    assert_eq!(
+        map.insert("timeout_ms", 60_000, vouch("timeout_ms", 30_000)),
+        Err(60_000)
+    );
+    assert_eq!(map.get("timeout_ms"), Some(&30_000));
+
+    assert_eq!(
+        map.insert("timeout_ms", 60_000, vouch("timeout_ms", 60_000)),
+        Ok(Some(30_000))
+    );
+    assert_eq!(map.len(), 2);
+    assert_eq!(
+        map.iter().collect::<Vec<_>>(),
+        vec![
+            ("max_connections", &1000, vouch("max_connections", 1000)),
+            ("timeout_ms", &60_000, vouch("timeout_ms", 60_000)),
+        ]
+    );
+    assert_eq!(
+        map.get_with_voucher("timeout_ms"),
+        Some((&60_000, vouch("timeout_ms", 60_000)))
+    );
+
+    assert_eq!(map.remove("timeout_ms"), Some(60_000));
+    assert_eq!(map.get("timeout_ms"), None);
+}
+
+#[cfg(not(feature = "insecure-accept-all"))]
+#[test]
+fn test_bulk() {
+    let params = crate::testing::VOUCHING;
+    let next = crate::testing::OTHER;
+    let vouch = |params: &crate::VouchingParameters, name: &str, value: u32| {
+        params.vouch(Named::new(name, &value).vouch_value())
+    };
+
+    let map = VouchedMap::from_entries(
+        params.checking_parameters(),
+        [
+            ("a".to_string(), 1u32, vouch(&params, "a", 1)),
+            ("b".to_string(), 2u32, vouch(&params, "b", 2)),
+        ],
+    )
+    .unwrap();
+    assert_eq!(
+        map.verify_all(params.checking_parameters()),
+        Vec::<&str>::new()
+    );
+    assert_eq!(map.verify_all(next.checking_parameters()), vec!["a", "b"]);
+
+    assert_eq!(
+        VouchedMap::from_entries(
+            params.checking_parameters(),
+            [
+                ("a".to_string(), 1u32, vouch(&params, "a", 1)),
+                ("b".to_string(), 2u32, vouch(&next, "b", 2)),
+                ("c".to_string(), 3u32, vouch(&params, "c", 4)),
+            ],
+        ),
+        Err(vec!["b".to_string(), "c".to_string()])
+    );
+}
+
+#[cfg(all(feature = "serde", not(feature = "insecure-accept-all")))]
+#[test]
+fn test_serde() {
+    let params = crate::testing::VOUCHING;
+    let checking = params.checking_parameters();
+
+    let mut map = VouchedMap::new(checking);
+    let voucher = params.vouch(Named::new("limit", &10u32).vouch_value());
+    map.insert("limit", 10u32, voucher).unwrap();
+
+    let json = serde_json::to_string(&map).unwrap();
+    assert_eq!(
+        json,
+        format!(r#"{{"limit":{{"value":10,"voucher":{}}}}}"#, voucher.0)
+    );
+    let loaded =
+        VouchedMap::<u32>::deserialize(checking, &mut serde_json::Deserializer::from_str(&json))
+            .unwrap();
+    assert_eq!(loaded, map);
+
+    let bad = json.replace(r#""value":10"#, r#""value":11"#);
+    let error =
+        VouchedMap::<u32>::deserialize(checking, &mut serde_json::Deserializer::from_str(&bad))
+            .unwrap_err();
+    assert!(error.to_string().contains("invalid vouchers for limit"));
+}
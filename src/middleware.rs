@@ -0,0 +1,284 @@
+//! A `tower` middleware that checks vouched values in request headers.
+//!
+//! This module is only compiled with the `tower` feature.
+//! [`VouchedLayer`] reads a `(value, voucher)` pair from a request
+//! header, in the [`crate::base58`] encoding, and checks it against a
+//! [`Keyring`].  The voucher must be for the value tagged with the
+//! layer's name (i.e., for [`crate::Named::new`]`(name, &value)`), so
+//! vouchers minted for other purposes, e.g., for config values, or
+//! for another layer, don't open the routes behind this one.
+//!
+//! Requests with a valid voucher reach the inner service with a
+//! [`Vouched<u64>`] extension; the others get an empty response
+//! without reaching the inner service:
+//!
+//! - `400 Bad Request` when the header is missing or malformed;
+//! - `403 Forbidden` when no key in the keyring accepts the voucher.
+//!
+//! The layer only establishes that *some* value was granted for its
+//! name: the inner service must still check that the [`Vouched<u64>`]
+//! value grants the request at hand (e.g., that it names the resource
+//! or the operation).
+//!
+//! The layer works with any service over [`http`] 1.x requests and
+//! responses (e.g., `hyper` 1.x or `axum`), so admin routes can all be
+//! gated the same way.
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::Arc;
+use std::task::Context;
+use std::task::Poll;
+
+use http::HeaderName;
+use http::Request;
+use http::Response;
+use http::StatusCode;
+
+use crate::Keyring;
+use crate::Named;
+use crate::Vouchable;
+use crate::Vouched;
+
+/// The default header for vouched values.
+pub const DEFAULT_HEADER: &str = "x-raffle-vouched";
+
+/// A [`tower_layer::Layer`] that checks vouched values in request headers.
+#[derive(Clone, Debug)]
+pub struct VouchedLayer {
+    name: &'static str,
+    keyring: Arc<Keyring>,
+    header: HeaderName,
+}
+
+impl VouchedLayer {
+    /// Returns a layer that accepts values tagged with `name` and
+    /// vouched for by any key in `keyring`, in the [`DEFAULT_HEADER`].
+    #[must_use]
+    pub fn new(name: &'static str, keyring: Keyring) -> VouchedLayer {
+        VouchedLayer {
+            name,
+            keyring: Arc::new(keyring),
+            header: HeaderName::from_static(DEFAULT_HEADER),
+        }
+    }
+
+    /// Reads vouched values from `header` instead of [`DEFAULT_HEADER`].
+    #[must_use]
+    pub fn header(self, header: HeaderName) -> VouchedLayer {
+        VouchedLayer { header, ..self }
+    }
+}
+
+impl<S> tower_layer::Layer<S> for VouchedLayer {
+    type Service = VouchedService<S>;
+
+    fn layer(&self, inner: S) -> VouchedService<S> {
+        VouchedService {
+            inner,
+            name: self.name,
+            keyring: self.keyring.clone(),
+            header: self.header.clone(),
+        }
+    }
+}
+
+/// The service returned by [`VouchedLayer`].
+#[derive(Clone, Debug)]
+pub struct VouchedService<S> {
+    inner: S,
+    name: &'static str,
+    keyring: Arc<Keyring>,
+    header: HeaderName,
+}
+
+impl<S> VouchedService<S> {
+    /// Checks the vouched value in `request`'s header, and returns it
+    /// on success, or the status code for the rejection.
+    fn check<B>(&self, request: &Request<B>) -> Result<Vouched<u64>, StatusCode> {
+        let (value, voucher) = request
+            .headers()
+            .get(&self.header)
+            .and_then(|header| header.to_str().ok())
+            .and_then(|header| crate::base58::parse_vouched(header).ok())
+            .ok_or(StatusCode::BAD_REQUEST)?;
+
+        // `find` already reports the check to the audit sink.
+        self.keyring
+            .find(Named::new(self.name, &value).vouch_value(), voucher)
+            .ok_or(StatusCode::FORBIDDEN)?;
+        Ok(Vouched::checked(value, voucher))
+    }
+}
+
+impl<S, ReqBody, ResBody> tower_service::Service<Request<ReqBody>> for VouchedService<S>
+where
+    S: tower_service::Service<Request<ReqBody>, Response = Response<ResBody>>,
+    ResBody: Default,
+{
+    type Response = Response<ResBody>;
+    type Error = S::Error;
+    type Future = ResponseFuture<S::Future, ResBody>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), S::Error>> {
+        self.inner.poll_ready(cx)
+    }
+
+    fn call(&mut self, mut request: Request<ReqBody>) -> Self::Future {
+        match self.check(&request) {
+            Ok(vouched) => {
+                request.extensions_mut().insert(vouched);
+                ResponseFuture::Inner {
+                    future: self.inner.call(request),
+                }
+            }
+            Err(status) => {
+                let mut response = Response::new(ResBody::default());
+                *response.status_mut() = status;
+                ResponseFuture::Rejected {
+                    response: Some(response),
+                }
+            }
+        }
+    }
+}
+
+pin_project_lite::pin_project! {
+    /// The future returned by [`VouchedService`].
+    #[derive(Debug)]
+    #[project = ResponseFutureProj]
+    pub enum ResponseFuture<F, B> {
+        /// The request reached the inner service.
+        Inner {
+            #[pin]
+            future: F,
+        },
+        /// The request was rejected with this response.
+        Rejected {
+            response: Option<Response<B>>,
+        },
+    }
+}
+
+impl<F, B, E> Future for ResponseFuture<F, B>
+where
+    F: Future<Output = Result<Response<B>, E>>,
+{
+    type Output = Result<Response<B>, E>;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        match self.project() {
+            ResponseFutureProj::Inner { future } => future.poll(cx),
+            ResponseFutureProj::Rejected { response } => Poll::Ready(Ok(response
+                .take()
+                .expect("ResponseFuture polled after completion"))),
+        }
+    }
+}
+
+/// A service that returns the request's [`Vouched<u64>`] extension.
+#[cfg(test)]
+struct Echo;
+
+#[cfg(test)]
+impl tower_service::Service<Request<()>> for Echo {
+    type Response = Response<Option<Vouched<u64>>>;
+    type Error = std::convert::Infallible;
+    type Future = std::future::Ready<Result<Self::Response, Self::Error>>;
+
+    fn poll_ready(&mut self, _cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        Poll::Ready(Ok(()))
+    }
+
+    fn call(&mut self, request: Request<()>) -> Self::Future {
+        std::future::ready(Ok(Response::new(
+            request.extensions().get::<Vouched<u64>>().copied(),
+        )))
+    }
+}
+
+#[cfg(test)]
+fn call<S: tower_service::Service<Request<()>>>(
+    service: &mut S,
+    header: Option<(&str, String)>,
+) -> S::Response
+where
+    S::Error: std::fmt::Debug,
+{
+    let mut request = Request::new(());
+    if let Some((name, value)) = header {
+        request.headers_mut().insert(
+            HeaderName::from_bytes(name.as_bytes()).unwrap(),
+            value.parse().unwrap(),
+        );
+    }
+
+    let future = std::pin::pin!(service.call(request));
+    let mut context = Context::from_waker(std::task::Waker::noop());
+    match future.poll(&mut context) {
+        Poll::Ready(response) => response.unwrap(),
+        Poll::Pending => panic!("test services are always ready"),
+    }
+}
+
+#[cfg(not(feature = "insecure-accept-all"))]
+#[test]
+fn test_layer() {
+    use tower_layer::Layer;
+
+    let params = crate::testing::VOUCHING;
+    let other = crate::testing::OTHER;
+    let keyring: Keyring = [("admin", params.checking_parameters())]
+        .into_iter()
+        .collect();
+
+    let mut service = VouchedLayer::new("admin", keyring).layer(Echo);
+    let voucher = params.vouch(Named::new("admin", &42u64).vouch_value());
+    let token = crate::base58::encode_vouched(42, voucher);
+    let response = call(&mut service, Some((DEFAULT_HEADER, token)));
+    assert_eq!(response.status(), StatusCode::OK);
+    assert_eq!(
+        *response.body(),
+        Some(Vouched::new_named(params.checking_parameters(), "admin", 42, voucher).unwrap())
+    );
+
+    // Vouchers for the bare value, or for another name, aren't grants.
+    for voucher in [
+        params.vouch(42),
+        params.vouch(Named::new("other", &42u64).vouch_value()),
+    ] {
+        let token = crate::base58::encode_vouched(42, voucher);
+        let response = call(&mut service, Some((DEFAULT_HEADER, token)));
+        assert_eq!(response.status(), StatusCode::FORBIDDEN);
+    }
+
+    let token =
+        crate::base58::encode_vouched(42, other.vouch(Named::new("admin", &42u64).vouch_value()));
+    let response = call(&mut service, Some((DEFAULT_HEADER, token)));
+    assert_eq!(response.status(), StatusCode::FORBIDDEN);
+    assert_eq!(*response.body(), None);
+
+    let response = call(&mut service, Some((DEFAULT_HEADER, "garbage".to_owned())));
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+    let response = call(&mut service, None);
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+}
+
+#[test]
+fn test_custom_header() {
+    use tower_layer::Layer;
+
+    let params = crate::testing::VOUCHING;
+    let keyring: Keyring = [("admin", params.checking_parameters())]
+        .into_iter()
+        .collect();
+
+    let mut service = VouchedLayer::new("admin", keyring)
+        .header(HeaderName::from_static("x-admin-token"))
+        .layer(Echo);
+    let token =
+        crate::base58::encode_vouched(7, params.vouch(Named::new("admin", &7u64).vouch_value()));
+    let response = call(&mut service, Some(("x-admin-token", token.clone())));
+    assert_eq!(response.status(), StatusCode::OK);
+    let response = call(&mut service, Some((DEFAULT_HEADER, token)));
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+}
@@ -0,0 +1,432 @@
+//! Helpers to rotate vouchers from one set of parameters to another.
+//!
+//! Rotating parameters means re-vouching every value that was vouched
+//! for with the old parameters.  We don't want that process to launder
+//! invalid vouchers, so each old voucher is checked before the new one
+//! is minted.
+//!
+//! [`migrate_stream`] handles voucher tables that are too large to
+//! hold in memory: it streams a [`crate::table`] from a reader to a
+//! writer, and re-vouches chunks of entries on a pool of threads.
+use std::collections::BTreeMap;
+use std::io::BufRead;
+use std::io::Write;
+use std::sync::mpsc;
+use std::sync::Arc;
+use std::sync::Mutex;
+
+use crate::table::TableFormat;
+use crate::table::TableReader;
+use crate::table::TableWriter;
+use crate::CheckingParameters;
+use crate::Voucher;
+use crate::VouchingParameters;
+
+/// Number of entries in each unit of work for [`migrate_stream`].
+const CHUNK_SIZE: usize = 1 << 16;
+
+/// Maximum number of invalid entries listed in a [`MigrationReport`].
+const MAX_REPORTED_ERRORS: usize = 16;
+
+/// Error returned by [`migrate`] and [`migrate_table`] when an old
+/// voucher doesn't match its value under the old checking parameters.
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Hash)]
+pub struct MigrationError {
+    /// Index of the first invalid entry (always 0 for [`migrate`]).
+    pub index: usize,
+    /// The value for the invalid entry.
+    pub value: u64,
+    /// The old voucher that failed to check.
+    pub voucher: Voucher,
+}
+
+impl std::fmt::Display for MigrationError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "invalid voucher {:016x} for value {:016x} at index {}",
+            self.voucher.0, self.value, self.index
+        )
+    }
+}
+
+impl std::error::Error for MigrationError {}
+
+/// Checks that `old_voucher` is valid for `value` under `old_checking`,
+/// and, if so, returns a fresh [`Voucher`] for `value` under `new_vouching`.
+pub fn migrate(
+    old_checking: CheckingParameters,
+    new_vouching: &VouchingParameters,
+    value: u64,
+    old_voucher: Voucher,
+) -> Result<Voucher, MigrationError> {
+    if old_checking.check(value, old_voucher) {
+        Ok(new_vouching.vouch(value))
+    } else {
+        Err(MigrationError {
+            index: 0,
+            value,
+            voucher: old_voucher,
+        })
+    }
+}
+
+/// Migrates a table of `(value, old_voucher)` entries, each vouched
+/// for independently (with [`VouchingParameters::vouch`]).
+///
+/// The migration is all or nothing: returns the new vouchers, in the
+/// same order as `entries`, if all the old vouchers are valid, and the
+/// first invalid entry otherwise.
+#[cfg_attr(
+    feature = "tracing",
+    tracing::instrument(
+        level = "debug",
+        target = "raffle",
+        skip_all,
+        fields(
+            old_fingerprint = %old_checking.fingerprint(),
+            new_fingerprint = %new_vouching.fingerprint(),
+            count = entries.len()
+        )
+    )
+)]
+pub fn migrate_table(
+    old_checking: CheckingParameters,
+    new_vouching: &VouchingParameters,
+    entries: &[(u64, Voucher)],
+) -> Result<Vec<Voucher>, MigrationError> {
+    entries
+        .iter()
+        .enumerate()
+        .map(|(index, &(value, voucher))| {
+            migrate(old_checking, new_vouching, value, voucher)
+                .map_err(|e| MigrationError { index, ..e })
+        })
+        .collect()
+}
+
+/// Summary of a [`migrate_stream`] run.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct MigrationReport {
+    /// Number of entries read from the input table.
+    pub entries: u64,
+    /// Number of entries re-vouched and written to the output table.
+    pub migrated: u64,
+    /// Number of entries dropped because their old voucher was invalid.
+    pub invalid: u64,
+    /// The first few invalid entries (at most 16), in input order.
+    pub first_invalid: Vec<MigrationError>,
+}
+
+impl std::fmt::Display for MigrationReport {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        writeln!(
+            f,
+            "entries: {}, migrated: {}, invalid: {}",
+            self.entries, self.migrated, self.invalid
+        )?;
+        for error in &self.first_invalid {
+            writeln!(f, "  {}", error)?;
+        }
+
+        if self.invalid > self.first_invalid.len() as u64 {
+            writeln!(
+                f,
+                "  ... and {} more",
+                self.invalid - self.first_invalid.len() as u64
+            )?;
+        }
+
+        Ok(())
+    }
+}
+
+/// Re-vouches every entry in the voucher table read from `input`, and
+/// writes the result to `output`, using `threads` worker threads.
+///
+/// Entries with an invalid old voucher are dropped from the output
+/// and counted in the returned [`MigrationReport`]; valid entries are
+/// written in the same order as in the input.  The input is processed
+/// in chunks of 64K entries, with a bounded number of chunks in flight,
+/// so memory usage is independent of the size of the table.
+///
+/// Returns the [`MigrationReport`] on success, and the first I/O or
+/// parse error otherwise.
+pub fn migrate_stream(
+    old_checking: CheckingParameters,
+    new_vouching: &VouchingParameters,
+    input: impl BufRead,
+    input_format: TableFormat,
+    output: impl Write + Send,
+    output_format: TableFormat,
+    threads: usize,
+) -> std::io::Result<MigrationReport> {
+    type Chunk = (usize, Vec<(u64, Voucher)>);
+    type Migrated = (usize, Vec<Result<(u64, Voucher), MigrationError>>);
+
+    let threads = threads.max(1);
+    let mut reader = TableReader::new(input, input_format)?;
+    let mut writer = TableWriter::new(output, output_format)?;
+
+    let (work_sender, work_receiver) = mpsc::sync_channel::<Chunk>(2 * threads);
+    let (done_sender, done_receiver) = mpsc::sync_channel::<Migrated>(2 * threads);
+    // Only the workers hold the receiver: once they all exit (e.g.,
+    // because the writer bailed), sending more work fails.
+    let work_receiver = Arc::new(Mutex::new(work_receiver));
+
+    std::thread::scope(|scope| {
+        for _ in 0..threads {
+            let done_sender = done_sender.clone();
+            let work_receiver = work_receiver.clone();
+            scope.spawn(move || loop {
+                let next = work_receiver.lock().expect("no panic").recv();
+                let Ok((chunk_index, entries)) = next else {
+                    return;
+                };
+
+                let base = chunk_index * CHUNK_SIZE;
+                let migrated = entries
+                    .into_iter()
+                    .enumerate()
+                    .map(|(index, (value, voucher))| {
+                        migrate(old_checking, new_vouching, value, voucher)
+                            .map(|new_voucher| (value, new_voucher))
+                            .map_err(|e| MigrationError {
+                                index: base + index,
+                                ..e
+                            })
+                    })
+                    .collect();
+                if done_sender.send((chunk_index, migrated)).is_err() {
+                    return;
+                }
+            });
+        }
+
+        drop(done_sender);
+        drop(work_receiver);
+
+        // Write chunks in order as they come back from the workers.
+        let writer_thread = scope.spawn(move || -> std::io::Result<MigrationReport> {
+            let mut report = MigrationReport::default();
+            let mut pending = BTreeMap::new();
+            let mut next_chunk = 0;
+
+            for (chunk_index, migrated) in done_receiver {
+                pending.insert(chunk_index, migrated);
+                while let Some(migrated) = pending.remove(&next_chunk) {
+                    next_chunk += 1;
+                    for entry in migrated {
+                        report.entries += 1;
+                        match entry {
+                            Ok((value, voucher)) => {
+                                writer.write_entry(value, voucher)?;
+                                report.migrated += 1;
+                            }
+                            Err(e) => {
+                                report.invalid += 1;
+                                if report.first_invalid.len() < MAX_REPORTED_ERRORS {
+                                    report.first_invalid.push(e);
+                                }
+                            }
+                        }
+                    }
+                }
+            }
+
+            writer.finish()?;
+            Ok(report)
+        });
+
+        let mut read_result = Ok(());
+        for chunk_index in 0.. {
+            let mut chunk = Vec::with_capacity(CHUNK_SIZE);
+            while chunk.len() < CHUNK_SIZE {
+                match reader.next_entry() {
+                    Ok(Some(entry)) => chunk.push(entry),
+                    Ok(None) => break,
+                    Err(e) => {
+                        read_result = Err(e);
+                        break;
+                    }
+                }
+            }
+
+            let last = chunk.len() < CHUNK_SIZE;
+            // Sending only fails once all the workers exited, after the
+            // writer bailed with an error.
+            if read_result.is_err() || work_sender.send((chunk_index, chunk)).is_err() || last {
+                break;
+            }
+        }
+
+        drop(work_sender);
+        let report = writer_thread
+            .join()
+            .expect("writer thread should not panic")?;
+        read_result.map(|_| report)
+    })
+}
+
+#[cfg(not(feature = "insecure-accept-all"))]
+#[test]
+fn test_migrate() {
+    let old = crate::testing::VOUCHING;
+    let new = crate::testing::OTHER;
+
+    let voucher = migrate(old.checking_parameters(), &new, 42, old.vouch(42)).expect("valid");
+    assert_eq!(voucher, new.vouch(42));
+    assert!(new.checking_parameters().check(42, voucher));
+
+    assert_eq!(
+        migrate(old.checking_parameters(), &new, 43, old.vouch(42)),
+        Err(MigrationError {
+            index: 0,
+            value: 43,
+            voucher: old.vouch(42)
+        })
+    );
+}
+
+#[cfg(not(feature = "insecure-accept-all"))]
+#[test]
+fn test_migrate_table() {
+    let old = crate::testing::VOUCHING;
+    let new = crate::testing::OTHER;
+
+    let mut entries: Vec<(u64, Voucher)> = (100..110u64).map(|x| (x, old.vouch(x))).collect();
+    let migrated = migrate_table(old.checking_parameters(), &new, &entries).expect("valid");
+    assert_eq!(migrated.len(), entries.len());
+    for ((value, _), voucher) in entries.iter().zip(migrated.iter()) {
+        assert_eq!(*voucher, new.vouch(*value));
+    }
+
+    entries[3].0 += 1;
+    entries[5].0 += 1;
+    assert_eq!(
+        migrate_table(old.checking_parameters(), &new, &entries),
+        Err(MigrationError {
+            index: 3,
+            value: 104,
+            voucher: old.vouch(103)
+        })
+    );
+}
+
+#[cfg(not(feature = "insecure-accept-all"))]
+#[test]
+fn test_migrate_stream() {
+    let old = crate::testing::VOUCHING;
+    let new = crate::testing::OTHER;
+
+    // Enough entries for a few chunks, with a couple invalid ones.
+    let count = 2 * CHUNK_SIZE as u64 + 10;
+    let mut input = TableWriter::new(Vec::new(), TableFormat::Rafl).unwrap();
+    for value in 0..count {
+        let voucher = if value % 100_000 == 7 {
+            old.vouch(value + 1)
+        } else {
+            old.vouch(value)
+        };
+        input.write_entry(value, voucher).unwrap();
+    }
+
+    let input = input.finish().unwrap();
+    let mut output = Vec::new();
+    let report = migrate_stream(
+        old.checking_parameters(),
+        &new,
+        &input[..],
+        TableFormat::Rafl,
+        &mut output,
+        TableFormat::Csv,
+        4,
+    )
+    .expect("must succeed");
+
+    assert_eq!(report.entries, count);
+    assert_eq!(report.invalid, 2);
+    assert_eq!(report.migrated, count - 2);
+    assert_eq!(
+        report
+            .first_invalid
+            .iter()
+            .map(|e| e.index)
+            .collect::<Vec<_>>(),
+        [7, 100_007]
+    );
+
+    let migrated: Vec<(u64, Voucher)> = TableReader::new(&output[..], TableFormat::Csv)
+        .unwrap()
+        .collect::<std::io::Result<_>>()
+        .unwrap();
+    assert_eq!(migrated.len() as u64, count - 2);
+    let expected = (0..count)
+        .filter(|value| value % 100_000 != 7)
+        .map(|value| (value, new.vouch(value)));
+    assert!(migrated.into_iter().eq(expected));
+}
+
+#[test]
+fn test_migrate_stream_write_error() {
+    let old = crate::testing::VOUCHING;
+    let new = crate::testing::OTHER;
+
+    /// Accepts the header, then fails every write.
+    struct FailingWriter(usize);
+
+    impl Write for FailingWriter {
+        fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+            if self.0 + buf.len() > 8 {
+                return Err(std::io::Error::other("disk full"));
+            }
+
+            self.0 += buf.len();
+            Ok(buf.len())
+        }
+
+        fn flush(&mut self) -> std::io::Result<()> {
+            Ok(())
+        }
+    }
+
+    // Many more chunks than the channels can buffer, so the reader
+    // keeps sending after the writer bails.
+    let count = 16 * CHUNK_SIZE as u64;
+    let mut input = TableWriter::new(Vec::new(), TableFormat::Rafl).unwrap();
+    for value in 0..count {
+        input.write_entry(value, old.vouch(value)).unwrap();
+    }
+
+    let input = input.finish().unwrap();
+    let err = migrate_stream(
+        old.checking_parameters(),
+        &new,
+        &input[..],
+        TableFormat::Rafl,
+        FailingWriter(0),
+        TableFormat::Rafl,
+        1,
+    )
+    .unwrap_err();
+    assert_eq!(err.to_string(), "disk full");
+}
+
+#[test]
+fn test_migrate_stream_parse_error() {
+    let old = crate::testing::VOUCHING;
+    let new = crate::testing::OTHER;
+
+    let input = format!("{:016x},{:016x}\nbad line\n", 42, old.vouch(42).0);
+    let err = migrate_stream(
+        old.checking_parameters(),
+        &new,
+        input.as_bytes(),
+        TableFormat::Csv,
+        Vec::new(),
+        TableFormat::Csv,
+        2,
+    )
+    .unwrap_err();
+    assert!(err.to_string().starts_with("line 2:"));
+}
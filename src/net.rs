@@ -0,0 +1,178 @@
+//! Canonical [`u64`] encodings for network addresses.
+//!
+//! Overrides like "bind the debug server to 0.0.0.0:8080" need a
+//! stable [`u64`] for addresses and ports.  The functions in this
+//! module (and the matching [`Vouchable`] implementations for `std`'s
+//! address types) use the following layout, with a kind tag in the
+//! most significant byte:
+//!
+//! | kind           | bits 63..56 | bits 55..0                                    |
+//! |----------------|-------------|-----------------------------------------------|
+//! | port           | `0x00`      | port (bits 15..0)                             |
+//! | IPv4 address   | `0x04`      | address, big-endian (bits 31..0)              |
+//! | IPv6 address   | `0x06`      | hash of the address                           |
+//! | IPv4 socket    | `0x14`      | port (bits 47..32), address (bits 31..0)      |
+//! | IPv6 socket    | `0x16`      | hash of the address and port                  |
+//!
+//! Ports are thus the same as plain [`u16`] values.  IPv4-mapped IPv6
+//! addresses (`::ffff:a.b.c.d`) are encoded as the IPv4 address, and
+//! IPv6 flow labels and scope IDs are ignored.
+//!
+//! IPv6 addresses don't fit in 56 bits, so they're hashed with the same
+//! mixer as [`crate::Fingerprint`]s.  The hash isn't cryptographic:
+//! someone who can pick arbitrary IPv6 addresses can find one that
+//! collides with a vouched address.
+use std::net::IpAddr;
+use std::net::Ipv4Addr;
+use std::net::Ipv6Addr;
+use std::net::SocketAddr;
+use std::net::SocketAddrV4;
+use std::net::SocketAddrV6;
+
+use crate::check::fingerprint;
+use crate::Vouchable;
+
+const IPV4_KIND: u64 = 0x04 << 56;
+const IPV6_KIND: u64 = 0x06 << 56;
+const IPV4_SOCKET_KIND: u64 = 0x14 << 56;
+const IPV6_SOCKET_KIND: u64 = 0x16 << 56;
+
+/// Hashes are truncated to the 56 bits below the kind tag.
+const HASH_MASK: u64 = (1 << 56) - 1;
+
+/// IPv6 addresses are mixed with this constant.
+const IPV6_TAG: u64 = crate::constparse::named_u64(b"IPv6Addr", 0x7264644136765049u64);
+
+/// Returns the vouched [`u64`] for `port`.
+#[must_use]
+pub const fn port_value(port: u16) -> u64 {
+    port as u64
+}
+
+/// Returns the vouched [`u64`] for `ip`.
+#[must_use]
+pub const fn ipv4_value(ip: Ipv4Addr) -> u64 {
+    IPV4_KIND | u32::from_be_bytes(ip.octets()) as u64
+}
+
+const fn hash_ipv6(ip: Ipv6Addr) -> u64 {
+    let bits = u128::from_be_bytes(ip.octets());
+    fingerprint((bits >> 64) as u64 ^ IPV6_TAG, bits as u64)
+}
+
+/// Returns the vouched [`u64`] for `ip`.
+#[must_use]
+pub const fn ipv6_value(ip: Ipv6Addr) -> u64 {
+    match ip.to_ipv4_mapped() {
+        Some(ip) => ipv4_value(ip),
+        None => IPV6_KIND | (hash_ipv6(ip) & HASH_MASK),
+    }
+}
+
+/// Returns the vouched [`u64`] for `ip`.
+#[must_use]
+pub const fn ip_value(ip: IpAddr) -> u64 {
+    match ip {
+        IpAddr::V4(ip) => ipv4_value(ip),
+        IpAddr::V6(ip) => ipv6_value(ip),
+    }
+}
+
+/// Returns the vouched [`u64`] for `addr`.
+#[must_use]
+pub const fn socket_v4_value(addr: SocketAddrV4) -> u64 {
+    IPV4_SOCKET_KIND | ((addr.port() as u64) << 32) | u32::from_be_bytes(addr.ip().octets()) as u64
+}
+
+/// Returns the vouched [`u64`] for `addr`.
+#[must_use]
+pub const fn socket_v6_value(addr: SocketAddrV6) -> u64 {
+    match addr.ip().to_ipv4_mapped() {
+        Some(ip) => socket_v4_value(SocketAddrV4::new(ip, addr.port())),
+        None => {
+            IPV6_SOCKET_KIND | (fingerprint(hash_ipv6(*addr.ip()), addr.port() as u64) & HASH_MASK)
+        }
+    }
+}
+
+/// Returns the vouched [`u64`] for `addr`.
+#[must_use]
+pub const fn socket_value(addr: SocketAddr) -> u64 {
+    match addr {
+        SocketAddr::V4(addr) => socket_v4_value(addr),
+        SocketAddr::V6(addr) => socket_v6_value(addr),
+    }
+}
+
+macro_rules! vouchable_net {
+    ($($ty:ty => $fn:ident),*) => {
+        $(
+            /// See the [`crate::net`] module for the encoding.
+            impl Vouchable for $ty {
+                fn vouch_value(&self) -> u64 {
+                    $fn(*self)
+                }
+            }
+        )*
+    };
+}
+
+vouchable_net!(
+    Ipv4Addr => ipv4_value,
+    Ipv6Addr => ipv6_value,
+    IpAddr => ip_value,
+    SocketAddrV4 => socket_v4_value,
+    SocketAddrV6 => socket_v6_value,
+    SocketAddr => socket_value
+);
+
+#[test]
+fn test_layout() {
+    assert_eq!(port_value(8080), 8080u16.vouch_value());
+    assert_eq!(
+        ipv4_value(Ipv4Addr::new(10, 1, 2, 3)),
+        0x0400_0000_0a01_0203
+    );
+    assert_eq!(ipv4_value(Ipv4Addr::UNSPECIFIED), 0x0400_0000_0000_0000);
+    assert_eq!(
+        socket_value("0.0.0.0:8080".parse().unwrap()),
+        0x1400_1f90_0000_0000
+    );
+    assert_eq!(
+        "192.168.0.1:443"
+            .parse::<SocketAddr>()
+            .unwrap()
+            .vouch_value(),
+        0x1400_01bb_c0a8_0001
+    );
+
+    let v6 = ipv6_value(Ipv6Addr::LOCALHOST);
+    assert_eq!(v6 >> 56, 0x06);
+    let socket = socket_value("[::1]:8080".parse().unwrap());
+    assert_eq!(socket >> 56, 0x16);
+    assert_ne!(socket, socket_value("[::1]:8081".parse().unwrap()));
+    assert_ne!(v6, ipv6_value("::2".parse().unwrap()));
+}
+
+#[test]
+fn test_canonical() {
+    // IPv4-mapped IPv6 addresses are IPv4 addresses.
+    let mapped: Ipv6Addr = "::ffff:10.1.2.3".parse().unwrap();
+    assert_eq!(ipv6_value(mapped), ipv4_value(Ipv4Addr::new(10, 1, 2, 3)));
+    assert_eq!(
+        socket_value("[::ffff:10.1.2.3]:80".parse().unwrap()),
+        socket_value("10.1.2.3:80".parse().unwrap())
+    );
+
+    // Flow labels and scope IDs don't matter.
+    let addr = SocketAddrV6::new(Ipv6Addr::LOCALHOST, 80, 0, 0);
+    assert_eq!(
+        socket_v6_value(addr),
+        socket_v6_value(SocketAddrV6::new(Ipv6Addr::LOCALHOST, 80, 7, 3))
+    );
+
+    assert_eq!(
+        IpAddr::V4(Ipv4Addr::LOCALHOST).vouch_value(),
+        Ipv4Addr::LOCALHOST.vouch_value()
+    );
+}
@@ -0,0 +1,167 @@
+//! Single-use vouchers, bound to nonces.
+//!
+//! Operational vouchers (e.g., "restart this shard now") should only
+//! work once.  The checker supplies a nonce (usually a monotonically
+//! increasing counter), the voucher is minted for a [`NonceBound`]
+//! value, and [`CheckingParameters::check_with_nonce`] accepts each
+//! nonce at most once, according to a [`NonceWindow`].
+use crate::CheckingParameters;
+use crate::Vouchable;
+use crate::Voucher;
+
+/// Nonces are mixed with this constant.
+const NONCE_TAG: u64 = crate::constparse::named_u64(b"NonceTag", 0x67615465636e6f4eu64);
+
+/// A value bound to a nonce.
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Hash)]
+pub struct NonceBound<'a, T: ?Sized> {
+    /// The nonce.
+    pub nonce: u64,
+    /// The inner value.
+    pub value: &'a T,
+}
+
+impl<'a, T: Vouchable + ?Sized> NonceBound<'a, T> {
+    /// Binds `value` to `nonce`.
+    pub fn new(nonce: u64, value: &'a T) -> NonceBound<'a, T> {
+        NonceBound { nonce, value }
+    }
+}
+
+impl<T: Vouchable + ?Sized> Vouchable for NonceBound<'_, T> {
+    fn vouch_value(&self) -> u64 {
+        crate::vouchable::bind(self.nonce, NONCE_TAG, self.value)
+    }
+}
+
+/// Tracks accepted nonces, to reject replays.
+///
+/// Like the anti-replay window of IPsec, a [`NonceWindow`] remembers
+/// the highest accepted nonce, and which of the [`NonceWindow::SIZE`]
+/// nonces immediately below it were accepted.  Nonces that are older
+/// than that are always rejected, so nonces should mostly increase,
+/// e.g., come from a counter.
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct NonceWindow {
+    /// One more than the highest accepted nonce, or 0 if none.
+    end: u64,
+    /// Bit `i` is set iff nonce `end - 1 - i` was accepted.
+    seen: u64,
+}
+
+impl NonceWindow {
+    /// Number of nonces tracked below the highest accepted nonce, inclusively.
+    pub const SIZE: u64 = 64;
+
+    /// Returns an empty window.
+    pub const fn new() -> NonceWindow {
+        NonceWindow { end: 0, seen: 0 }
+    }
+
+    /// Returns the smallest nonce that's higher than every accepted nonce.
+    ///
+    /// Checkers that use a counter can hand out this nonce for the next voucher.
+    pub const fn next_nonce(&self) -> u64 {
+        self.end
+    }
+
+    /// Returns whether [`NonceWindow::accept`] would accept `nonce`.
+    #[must_use]
+    pub const fn is_fresh(&self, nonce: u64) -> bool {
+        if nonce >= self.end {
+            // `u64::MAX` can't be accepted: `end` would overflow.
+            return nonce != u64::MAX;
+        }
+
+        let age = self.end - 1 - nonce;
+        age < Self::SIZE && (self.seen >> age) & 1 == 0
+    }
+
+    /// Records `nonce` as used if it's fresh, and returns whether it was.
+    #[must_use]
+    pub fn accept(&mut self, nonce: u64) -> bool {
+        if !self.is_fresh(nonce) {
+            return false;
+        }
+
+        if nonce >= self.end {
+            let shift = nonce + 1 - self.end;
+            self.seen = self.seen.checked_shl(shift.min(64) as u32).unwrap_or(0);
+            self.end = nonce + 1;
+        }
+
+        self.seen |= 1 << (self.end - 1 - nonce);
+        true
+    }
+}
+
+impl CheckingParameters {
+    /// Determines whether `voucher` is valid for `expected` bound to
+    /// `nonce` (i.e., for [`NonceBound`]), and whether `nonce` is fresh
+    /// in `window`.  On success, `nonce` is recorded as used.
+    ///
+    /// Invalid vouchers never consume nonces.
+    #[must_use]
+    #[track_caller]
+    pub fn check_with_nonce(
+        self,
+        expected: u64,
+        nonce: u64,
+        voucher: Voucher,
+        window: &mut NonceWindow,
+    ) -> bool {
+        let value = NonceBound::new(nonce, &expected).vouch_value();
+        self.check_audited(value, voucher) && window.accept(nonce)
+    }
+}
+
+#[test]
+fn test_window() {
+    let mut window = NonceWindow::new();
+    assert_eq!(window.next_nonce(), 0);
+
+    assert!(window.accept(0));
+    assert!(!window.accept(0));
+    assert!(window.accept(5));
+    assert_eq!(window.next_nonce(), 6);
+
+    // Out of order, but within the window.
+    assert!(window.accept(3));
+    assert!(!window.accept(3));
+    assert!(!window.accept(5));
+
+    // Jump ahead: 3 and 5 are still tracked, 0 is too old.
+    assert!(window.accept(66));
+    assert!(!window.is_fresh(3));
+    assert!(!window.is_fresh(5));
+    assert!(window.is_fresh(4));
+    assert!(!window.is_fresh(2));
+
+    // Jump far ahead: everything old is rejected.
+    assert!(window.accept(1000));
+    assert!(!window.is_fresh(66));
+    assert!(window.is_fresh(999));
+    assert!(!window.is_fresh(u64::MAX));
+}
+
+#[cfg(not(feature = "insecure-accept-all"))]
+#[test]
+fn test_check_with_nonce() {
+    let params = crate::testing::VOUCHING;
+    let checking = params.checking_parameters();
+    let mut window = NonceWindow::new();
+
+    let nonce = window.next_nonce();
+    let voucher = params.vouch(NonceBound::new(nonce, &42u64).vouch_value());
+
+    // A bad voucher doesn't consume the nonce.
+    assert!(!checking.check_with_nonce(43, nonce, voucher, &mut window));
+    assert!(!checking.check_with_nonce(42, nonce + 1, voucher, &mut window));
+    assert_eq!(window.next_nonce(), nonce);
+
+    assert!(checking.check_with_nonce(42, nonce, voucher, &mut window));
+    // Replays fail.
+    assert!(!checking.check_with_nonce(42, nonce, voucher, &mut window));
+    assert!(!checking.check(42, voucher));
+}
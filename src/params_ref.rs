@@ -0,0 +1,160 @@
+//! Borrowed views of serialised parameters.
+//!
+//! Log processors may see millions of lines with parameter strings,
+//! and only need to know whether they're well-formed (and, sometimes,
+//! which key they name).  [`ParamsRef::parse`] validates the syntax of
+//! a `CHECK-...` or `VOUCH-...` string in place, and keeps a reference
+//! to the bytes; owned [`CheckingParameters`] and
+//! [`VouchingParameters`] are only constructed on demand.
+use crate::check;
+use crate::vouch;
+use crate::CheckingParameters;
+use crate::Fingerprint;
+use crate::VouchingParameters;
+
+/// A syntactically valid parameter string, borrowed from a byte slice.
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Hash)]
+pub enum ParamsRef<'a> {
+    /// A `CHECK-...` string.
+    Checking(&'a [u8]),
+    /// A `VOUCH-...` string.
+    ///
+    /// Only the syntax is validated: the vouching half may still be
+    /// inconsistent with the checking half, which
+    /// [`ParamsRef::to_vouching_parameters`] detects.
+    Vouching(&'a [u8]),
+}
+
+impl<'a> ParamsRef<'a> {
+    /// Validates the syntax of `bytes` as serialised checking or vouching
+    /// parameters, and returns a view of `bytes`.
+    pub const fn parse(bytes: &'a [u8]) -> Result<ParamsRef<'a>, &'static str> {
+        if bytes.len() == CheckingParameters::REPRESENTATION_BYTE_COUNT {
+            match check::parse_bytes(bytes) {
+                Ok(_) => Ok(ParamsRef::Checking(bytes)),
+                Err(e) => Err(e),
+            }
+        } else {
+            match vouch::parse_bytes(bytes) {
+                Ok(_) => Ok(ParamsRef::Vouching(bytes)),
+                Err(e) => Err(e),
+            }
+        }
+    }
+
+    /// Returns the underlying bytes.
+    #[must_use]
+    pub const fn as_bytes(&self) -> &'a [u8] {
+        match self {
+            ParamsRef::Checking(bytes) | ParamsRef::Vouching(bytes) => bytes,
+        }
+    }
+
+    /// Returns the underlying bytes as a string.
+    #[must_use]
+    pub fn as_str(&self) -> &'a str {
+        std::str::from_utf8(self.as_bytes()).expect("parameter strings are ASCII")
+    }
+
+    /// Returns whether this is a view of vouching parameters.
+    #[must_use]
+    pub const fn is_vouching(&self) -> bool {
+        matches!(self, ParamsRef::Vouching(_))
+    }
+
+    /// Returns the checking parameters: the whole string for a
+    /// `CHECK-...` string, and the checking half of a `VOUCH-...` string.
+    #[must_use]
+    pub const fn checking_parameters(&self) -> CheckingParameters {
+        let parsed = match self {
+            ParamsRef::Checking(bytes) => check::parse_bytes(bytes),
+            ParamsRef::Vouching(bytes) => match vouch::parse_bytes(bytes) {
+                Ok((_, _, checking)) => Ok(checking),
+                Err(e) => Err(e),
+            },
+        };
+
+        match parsed {
+            Ok((unoffset, unscale)) => CheckingParameters { unoffset, unscale },
+            Err(_) => panic!("ParamsRef is always valid"),
+        }
+    }
+
+    /// Returns the fingerprint of [`ParamsRef::checking_parameters`].
+    #[must_use]
+    pub const fn fingerprint(&self) -> Fingerprint {
+        self.checking_parameters().fingerprint()
+    }
+
+    /// Materialises the [`VouchingParameters`] for a `VOUCH-...` string.
+    ///
+    /// Fails for `CHECK-...` strings, and when the vouching half is
+    /// inconsistent with the checking half.
+    pub const fn to_vouching_parameters(&self) -> Result<VouchingParameters, &'static str> {
+        match self {
+            ParamsRef::Checking(_) => {
+                Err("Expected raffle::VouchingParameters, got raffle::CheckingParameters")
+            }
+            ParamsRef::Vouching(bytes) => VouchingParameters::parse_bytes(bytes),
+        }
+    }
+}
+
+impl std::fmt::Display for ParamsRef<'_> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+#[test]
+fn test_parse() {
+    let params = crate::testing::VOUCHING;
+    let checking = params.checking_parameters();
+
+    let checking_string = checking.to_string();
+    let view = ParamsRef::parse(checking_string.as_bytes()).unwrap();
+    assert_eq!(view, ParamsRef::Checking(checking_string.as_bytes()));
+    assert!(!view.is_vouching());
+    assert_eq!(view.as_str(), checking_string);
+    assert_eq!(view.checking_parameters(), checking);
+    assert_eq!(view.fingerprint(), checking.fingerprint());
+    assert!(view.to_vouching_parameters().is_err());
+
+    let vouching_string = params.to_string();
+    let view = ParamsRef::parse(vouching_string.as_bytes()).unwrap();
+    assert!(view.is_vouching());
+    assert_eq!(view.to_string(), vouching_string);
+    assert_eq!(view.checking_parameters(), checking);
+    assert_eq!(view.to_vouching_parameters(), Ok(params));
+}
+
+#[test]
+fn test_parse_errors() {
+    let params = crate::testing::VOUCHING;
+
+    assert!(ParamsRef::parse(b"").is_err());
+    assert!(ParamsRef::parse(b"CHECK-0000000000000083-9b791a2755d2d99g").is_err());
+    assert!(ParamsRef::parse(&params.to_string().as_bytes()[1..]).is_err());
+
+    // Syntactically valid but inconsistent vouching parameters only
+    // fail when materialised.
+    let mut inconsistent = params.to_string().into_bytes();
+    inconsistent[6] = if inconsistent[6] == b'0' { b'1' } else { b'0' };
+    let view = ParamsRef::parse(&inconsistent).unwrap();
+    assert_eq!(view.checking_parameters(), params.checking_parameters());
+    assert!(view.to_vouching_parameters().is_err());
+}
+
+#[test]
+fn test_const_parse() {
+    const VIEW: ParamsRef<'static> =
+        match ParamsRef::parse(b"CHECK-0000000000000083-9b791a2755d2d996") {
+            Ok(view) => view,
+            Err(_) => panic!("bad parameters"),
+        };
+
+    assert_eq!(
+        VIEW.checking_parameters(),
+        CheckingParameters::parse_or_die("CHECK-0000000000000083-9b791a2755d2d996")
+    );
+}
@@ -0,0 +1,156 @@
+//! Vouchers for filesystem paths.
+//!
+//! Approvals like "allow writing outside the sandbox to exactly this
+//! path" must not be fooled by `..` components, symlinks, or
+//! alternative spellings of the same path.  [`canonical_path`] resolves
+//! all of these, and [`path_value`] hashes the result, so
+//! [`VouchingParameters::vouch_path`] and
+//! [`CheckingParameters::check_path`] agree on any spelling of a path
+//! that resolves to the same file.
+//!
+//! The vouched [`u64`] is `PATH_TAG ^` the FNV-1a hash (like
+//! [`crate::name_hash`]) of the canonical path's bytes (see
+//! [`std::ffi::OsStr::as_encoded_bytes`]).  On Windows, the verbatim
+//! `\\?\` prefix is stripped and separators are normalised to `/`
+//! before hashing.
+//!
+//! Canonicalisation looks at the filesystem at the time of the call:
+//! a path whose symlinks change between the check and its use can
+//! still end up somewhere else.
+use std::path::Path;
+use std::path::PathBuf;
+
+use crate::CheckingParameters;
+use crate::Voucher;
+use crate::VouchingParameters;
+
+/// Path hashes are mixed with this constant.
+const PATH_TAG: u64 = crate::constparse::named_u64(b"PathHash", 0x6873614868746150u64);
+
+/// Returns the canonical, absolute, form of `path`, with all
+/// intermediate components normalised and symlinks resolved.
+///
+/// Unlike [`std::fs::canonicalize`], the last component of `path` need
+/// not exist (e.g., for a file that will be created), as long as its
+/// parent does, and it's a plain file name.  A dangling symlink isn't
+/// a missing file: it fails with [`std::io::ErrorKind::NotFound`],
+/// since its target could be created anywhere.
+pub fn canonical_path(path: impl AsRef<Path>) -> std::io::Result<PathBuf> {
+    let path = path.as_ref();
+    match std::fs::canonicalize(path) {
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => {
+            let (Some(parent), Some(name)) = (path.parent(), path.file_name()) else {
+                return Err(e);
+            };
+
+            // `Path::parent` is empty for relative single-component paths.
+            let parent = if parent.as_os_str().is_empty() {
+                Path::new(".")
+            } else {
+                parent
+            };
+            let canonical = std::fs::canonicalize(parent)?.join(name);
+            // `canonicalize` also fails with `NotFound` for dangling symlinks.
+            match std::fs::symlink_metadata(&canonical) {
+                Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(canonical),
+                Err(e) => Err(e),
+                Ok(_) => Err(e),
+            }
+        }
+        ret => ret,
+    }
+}
+
+/// Returns the [`u64`] vouched for the *already canonical* path `canonical`.
+///
+/// This function doesn't access the filesystem; see [`canonical_path`].
+#[must_use]
+pub fn path_value(canonical: &Path) -> u64 {
+    let bytes = canonical.as_os_str().as_encoded_bytes();
+    #[cfg(windows)]
+    let normalised: Vec<u8> = bytes
+        .strip_prefix(br"\\?\")
+        .unwrap_or(bytes)
+        .iter()
+        .map(|&byte| if byte == b'\\' { b'/' } else { byte })
+        .collect();
+    #[cfg(windows)]
+    let bytes = &normalised[..];
+
+    crate::vouchable::bytes_hash(bytes) ^ PATH_TAG
+}
+
+impl VouchingParameters {
+    /// Computes a [`Voucher`] for the canonical form of `path`.
+    ///
+    /// Fails if `path` can't be canonicalised with [`canonical_path`].
+    pub fn vouch_path(&self, path: impl AsRef<Path>) -> std::io::Result<Voucher> {
+        Ok(self.vouch(path_value(&canonical_path(path)?)))
+    }
+}
+
+impl CheckingParameters {
+    /// Determines whether `voucher` is valid for the canonical form of
+    /// `path`, and reports the check like
+    /// [`CheckingParameters::check_audited`].
+    ///
+    /// Paths that can't be canonicalised never match.
+    #[must_use]
+    #[track_caller]
+    pub fn check_path(self, path: impl AsRef<Path>, voucher: Voucher) -> bool {
+        match canonical_path(path) {
+            Ok(canonical) => self.check_audited(path_value(&canonical), voucher),
+            Err(_) => false,
+        }
+    }
+}
+
+#[test]
+fn test_path_value() {
+    assert_eq!(
+        path_value(Path::new("/var/log/app.log")),
+        crate::name_hash("/var/log/app.log") ^ PATH_TAG
+    );
+    assert_ne!(
+        path_value(Path::new("/var/log/app.log")),
+        path_value(Path::new("/var/log/app.log2"))
+    );
+}
+
+#[cfg(not(feature = "insecure-accept-all"))]
+#[test]
+fn test_vouch_path() {
+    let root = std::env::temp_dir().join(format!("raffle-path-{}", std::process::id()));
+    let _ = std::fs::remove_dir_all(&root);
+    std::fs::create_dir_all(root.join("allowed")).unwrap();
+    std::fs::create_dir_all(root.join("other")).unwrap();
+
+    let params = crate::testing::VOUCHING;
+    let checking = params.checking_parameters();
+
+    // The target file doesn't exist yet.
+    let voucher = params.vouch_path(root.join("allowed/out.txt")).unwrap();
+    assert!(checking.check_path(root.join("allowed/out.txt"), voucher));
+    assert!(checking.check_path(root.join("other/../allowed/./out.txt"), voucher));
+    assert!(!checking.check_path(root.join("other/out.txt"), voucher));
+    assert!(!checking.check_path(root.join("missing/out.txt"), voucher));
+
+    #[cfg(unix)]
+    {
+        std::os::unix::fs::symlink(root.join("allowed"), root.join("link")).unwrap();
+        assert!(checking.check_path(root.join("link/out.txt"), voucher));
+
+        // A dangling symlink could later point anywhere.
+        std::os::unix::fs::symlink(root.join("other/target"), root.join("allowed/dangling"))
+            .unwrap();
+        assert_eq!(
+            canonical_path(root.join("allowed/dangling"))
+                .unwrap_err()
+                .kind(),
+            std::io::ErrorKind::NotFound
+        );
+        assert!(params.vouch_path(root.join("allowed/dangling")).is_err());
+    }
+
+    std::fs::remove_dir_all(&root).unwrap();
+}
@@ -0,0 +1,84 @@
+//! Runtime support for the `#[vouch_protected]` attribute macro.
+//!
+//! The macro itself lives in the `raffle_macros` crate, and is
+//! re-exported as `raffle::vouch_protected` with the `macros` feature.
+//! It adds a trailing [`Voucher`] argument to the function, and calls
+//! [`check_protected_call`] on entry.
+
+use crate::CheckingParameters;
+use crate::Voucher;
+
+/// Error for calls to a `#[vouch_protected]` function with an invalid voucher.
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Hash)]
+pub struct UnvouchedCall {
+    /// Name of the protected function.
+    pub function: &'static str,
+}
+
+impl std::fmt::Display for UnvouchedCall {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "invalid voucher for call to {}", self.function)
+    }
+}
+
+impl std::error::Error for UnvouchedCall {}
+
+/// Checks the voucher for a call to the protected `function`.
+///
+/// This function is called by the code generated by `#[vouch_protected]`.
+#[track_caller]
+pub fn check_protected_call(
+    checking: CheckingParameters,
+    function: &'static str,
+    value: u64,
+    voucher: Voucher,
+) -> Result<(), UnvouchedCall> {
+    checking
+        .check_audited_impl(Some(function), value, voucher)
+        .map_err(|_| UnvouchedCall { function })
+}
+
+#[cfg(all(test, feature = "macros", not(feature = "insecure-accept-all")))]
+const TEST_CHECKING: CheckingParameters =
+    CheckingParameters::parse_or_die("CHECK-0000000000000083-9b791a2755d2d996");
+
+#[cfg(all(test, feature = "macros", not(feature = "insecure-accept-all")))]
+#[crate::vouch_protected(checking = TEST_CHECKING)]
+fn wipe_database() -> u32 {
+    42
+}
+
+#[cfg(all(test, feature = "macros", not(feature = "insecure-accept-all")))]
+#[crate::vouch_protected(checking = TEST_CHECKING, value = rows, on_failure = error)]
+fn delete_rows(rows: u64) -> Result<u64, Box<dyn std::error::Error>> {
+    Ok(rows)
+}
+
+#[cfg(all(feature = "macros", not(feature = "insecure-accept-all")))]
+#[test]
+fn test_protected() {
+    let params = crate::testing::VOUCHING;
+
+    assert_eq!(
+        wipe_database(params.vouch(crate::name_hash("wipe_database"))),
+        42
+    );
+
+    let voucher = params.vouch(crate::Vouchable::vouch_value(&crate::Named::new(
+        "delete_rows",
+        &10u64,
+    )));
+    assert_eq!(delete_rows(10, voucher).unwrap(), 10);
+    assert_eq!(
+        delete_rows(11, voucher).unwrap_err().to_string(),
+        "invalid voucher for call to delete_rows"
+    );
+}
+
+#[cfg(all(feature = "macros", not(feature = "insecure-accept-all")))]
+#[test]
+#[should_panic(expected = "invalid voucher for call to wipe_database")]
+fn test_protected_panic() {
+    let params = crate::testing::VOUCHING;
+    wipe_database(params.vouch(0));
+}
@@ -0,0 +1,196 @@
+//! Protobuf messages for checking parameters, vouched values, and
+//! verification reports.
+//!
+//! This module is only compiled with the `prost` feature.  The messages
+//! correspond to the following schema, so other languages can exchange
+//! raffle data over existing gRPC APIs without stringly-typed fields:
+//!
+//! ```proto
+//! syntax = "proto3";
+//! package raffle;
+//!
+//! message CheckingParameters {
+//!   fixed64 unoffset = 1;
+//!   fixed64 unscale = 2;
+//! }
+//!
+//! message VouchedValue {
+//!   fixed64 value = 1;
+//!   fixed64 voucher = 2;
+//! }
+//!
+//! message VerificationReport {
+//!   fixed64 expected = 1;
+//!   fixed64 voucher = 2;
+//!   fixed64 fingerprint = 3;
+//!   fixed64 vouched_value = 4;
+//! }
+//! ```
+//!
+//! [`crate::Voucher`] itself is also a message, with the voucher in
+//! field 1.  There's no message for vouching parameters: they're
+//! secret, and shouldn't travel over RPCs.
+use crate::CheckExplanation;
+use crate::Fingerprint;
+use crate::Vouchable;
+use crate::Vouched;
+use crate::Voucher;
+
+/// Protobuf message for [`crate::CheckingParameters`]: the components
+/// of the string representation, in the same order.
+#[derive(Clone, Copy, PartialEq, Eq, Hash, prost::Message)]
+pub struct CheckingParameters {
+    /// The first hex field of the `CHECK-...` string.
+    #[prost(fixed64, tag = "1")]
+    pub unoffset: u64,
+    /// The second hex field of the `CHECK-...` string.
+    #[prost(fixed64, tag = "2")]
+    pub unscale: u64,
+}
+
+impl From<crate::CheckingParameters> for CheckingParameters {
+    fn from(params: crate::CheckingParameters) -> CheckingParameters {
+        CheckingParameters {
+            unoffset: params.unoffset,
+            unscale: params.unscale,
+        }
+    }
+}
+
+impl From<CheckingParameters> for crate::CheckingParameters {
+    fn from(message: CheckingParameters) -> crate::CheckingParameters {
+        crate::CheckingParameters {
+            unoffset: message.unoffset,
+            unscale: message.unscale,
+        }
+    }
+}
+
+/// Protobuf message for a value (as its [`Vouchable::vouch_value`])
+/// and its voucher.
+///
+/// The message isn't checked: convert it back to a [`Vouched`] value
+/// with [`VouchedValue::check`].
+#[derive(Clone, Copy, PartialEq, Eq, Hash, prost::Message)]
+pub struct VouchedValue {
+    /// The value's [`Vouchable::vouch_value`].
+    #[prost(fixed64, tag = "1")]
+    pub value: u64,
+    /// The value's voucher.
+    #[prost(fixed64, tag = "2")]
+    pub voucher: u64,
+}
+
+impl VouchedValue {
+    /// Returns the message's voucher.
+    #[must_use]
+    pub fn voucher(&self) -> Voucher {
+        Voucher(self.voucher)
+    }
+
+    /// Returns a [`Vouched`] value if the message's voucher is valid for
+    /// its value under `checking`, and the value otherwise.
+    #[track_caller]
+    pub fn check(&self, checking: crate::CheckingParameters) -> Result<Vouched<u64>, u64> {
+        Vouched::new(checking, self.value, self.voucher())
+    }
+}
+
+impl<T: Vouchable> From<&Vouched<T>> for VouchedValue {
+    fn from(vouched: &Vouched<T>) -> VouchedValue {
+        VouchedValue {
+            value: vouched.value().vouch_value(),
+            voucher: vouched.voucher().0,
+        }
+    }
+}
+
+/// Protobuf message for a [`CheckExplanation`], the outcome of
+/// [`crate::CheckingParameters::check_explain`].
+#[derive(Clone, Copy, PartialEq, Eq, Hash, prost::Message)]
+pub struct VerificationReport {
+    /// The expected value.
+    #[prost(fixed64, tag = "1")]
+    pub expected: u64,
+    /// The voucher.
+    #[prost(fixed64, tag = "2")]
+    pub voucher: u64,
+    /// The fingerprint of the checking parameters.
+    #[prost(fixed64, tag = "3")]
+    pub fingerprint: u64,
+    /// The only value for which the voucher is valid.
+    #[prost(fixed64, tag = "4")]
+    pub vouched_value: u64,
+}
+
+impl From<CheckExplanation> for VerificationReport {
+    fn from(explanation: CheckExplanation) -> VerificationReport {
+        VerificationReport {
+            expected: explanation.expected,
+            voucher: explanation.voucher.0,
+            fingerprint: explanation.fingerprint.0,
+            vouched_value: explanation.vouched_value,
+        }
+    }
+}
+
+impl From<VerificationReport> for CheckExplanation {
+    fn from(report: VerificationReport) -> CheckExplanation {
+        CheckExplanation {
+            expected: report.expected,
+            voucher: Voucher(report.voucher),
+            fingerprint: Fingerprint(report.fingerprint),
+            vouched_value: report.vouched_value,
+        }
+    }
+}
+
+#[test]
+fn test_checking_parameters() {
+    use prost::Message;
+
+    let params = crate::testing::VOUCHING;
+    let checking = params.checking_parameters();
+
+    let message = CheckingParameters::from(checking);
+    let encoded = message.encode_to_vec();
+    // Two tagged fixed64 fields.
+    assert_eq!(encoded.len(), 18);
+    let decoded = CheckingParameters::decode(encoded.as_slice()).unwrap();
+    assert_eq!(crate::CheckingParameters::from(decoded), checking);
+}
+
+#[cfg(not(feature = "insecure-accept-all"))]
+#[test]
+fn test_vouched_value() {
+    use prost::Message;
+
+    let params = crate::testing::VOUCHING;
+    let other = crate::testing::OTHER;
+    let checking = params.checking_parameters();
+
+    let vouched = Vouched::new(checking, 42u64, params.vouch(42)).unwrap();
+    let message = VouchedValue::from(&vouched);
+    let decoded = VouchedValue::decode(message.encode_to_vec().as_slice()).unwrap();
+    assert_eq!(decoded.voucher(), params.vouch(42));
+    assert_eq!(decoded.check(checking), Ok(vouched));
+    assert_eq!(decoded.check(other.checking_parameters()), Err(42));
+}
+
+#[test]
+fn test_verification_report() {
+    use prost::Message;
+
+    let params = crate::testing::VOUCHING;
+    let checking = params.checking_parameters();
+
+    let explanation = checking.check_explain(43, params.vouch(42));
+    let message = VerificationReport::from(explanation);
+    assert_eq!(message.vouched_value, 42);
+    assert_eq!(message.fingerprint, checking.fingerprint().0);
+
+    let decoded = VerificationReport::decode(message.encode_to_vec().as_slice()).unwrap();
+    let roundtrip = CheckExplanation::from(decoded);
+    assert_eq!(roundtrip, explanation);
+    assert_eq!(roundtrip.diagnosis(), crate::Diagnosis::WrongValue);
+}
@@ -0,0 +1,169 @@
+//! Compact encodings for QR codes.
+//!
+//! QR codes have an alphanumeric mode for the 45 characters
+//! `0-9A-Z $%*+-./:`, which packs two characters into 11 bits.  The
+//! functions in this module encode [`CheckingParameters`] and vouched
+//! `(value, voucher)` pairs with only these characters, so they can be
+//! printed as small QR codes (e.g., on runbook sheets), with the
+//! Base45 encoding of [RFC 9285](https://www.rfc-editor.org/rfc/rfc9285):
+//!
+//! - checking parameters are `RCHK:` followed by the Base45 encoding
+//!   of `unoffset` and `unscale`, as 16 big-endian bytes (29 characters
+//!   in total, versus 39 for the hex representation);
+//! - vouched values are `RVAL:` followed by the Base45 encoding of the
+//!   value and the voucher, also as 16 big-endian bytes.
+//!
+//! There's deliberately no encoding for [`crate::VouchingParameters`]:
+//! secrets don't belong on printed sheets.
+use crate::CheckingParameters;
+use crate::Voucher;
+
+const CHARSET: &[u8; 45] = b"0123456789ABCDEFGHIJKLMNOPQRSTUVWXYZ $%*+-./:";
+
+/// Prefix for QR-encoded [`CheckingParameters`].
+pub const CHECKING_PREFIX: &str = "RCHK:";
+
+/// Prefix for QR-encoded vouched values.
+pub const VOUCHED_PREFIX: &str = "RVAL:";
+
+/// Encodes `bytes` in Base45 (RFC 9285).
+#[must_use]
+pub fn base45_encode(bytes: &[u8]) -> String {
+    let mut ret = String::with_capacity(bytes.len().div_ceil(2) * 3);
+    for chunk in bytes.chunks(2) {
+        let (mut n, digits) = match chunk {
+            [a, b] => (256 * (*a as usize) + (*b as usize), 3),
+            [a] => (*a as usize, 2),
+            _ => unreachable!(),
+        };
+
+        for _ in 0..digits {
+            ret.push(CHARSET[n % 45] as char);
+            n /= 45;
+        }
+    }
+
+    ret
+}
+
+/// Decodes a Base45 (RFC 9285) string.
+pub fn base45_decode(string: &str) -> Result<Vec<u8>, &'static str> {
+    let digits = string
+        .bytes()
+        .map(|byte| CHARSET.iter().position(|&c| c == byte))
+        .collect::<Option<Vec<usize>>>()
+        .ok_or("Invalid character in Base45 string")?;
+
+    let mut ret = Vec::with_capacity(digits.len() / 3 * 2 + 1);
+    for chunk in digits.chunks(3) {
+        match *chunk {
+            [c, d, e] => {
+                let n = c + 45 * d + 45 * 45 * e;
+                if n > 0xffff {
+                    return Err("Base45 triplet out of range");
+                }
+
+                ret.push((n >> 8) as u8);
+                ret.push(n as u8);
+            }
+            [c, d] => {
+                let n = c + 45 * d;
+                if n > 0xff {
+                    return Err("Base45 pair out of range");
+                }
+
+                ret.push(n as u8);
+            }
+            _ => return Err("Invalid Base45 string length"),
+        }
+    }
+
+    Ok(ret)
+}
+
+fn encode_pair(prefix: &str, x: u64, y: u64) -> String {
+    let mut bytes = [0u8; 16];
+    bytes[..8].copy_from_slice(&x.to_be_bytes());
+    bytes[8..].copy_from_slice(&y.to_be_bytes());
+    format!("{}{}", prefix, base45_encode(&bytes))
+}
+
+fn parse_pair(prefix: &str, string: &str) -> Result<(u64, u64), &'static str> {
+    let Some(encoded) = string.strip_prefix(prefix) else {
+        return Err("Incorrect prefix for QR-encoded raffle data");
+    };
+
+    let bytes = base45_decode(encoded)?;
+    let Ok(bytes) = <[u8; 16]>::try_from(bytes) else {
+        return Err("QR-encoded raffle data must decode to 16 bytes");
+    };
+
+    let (x, y) = bytes.split_at(8);
+    Ok((
+        u64::from_be_bytes(x.try_into().expect("8 bytes")),
+        u64::from_be_bytes(y.try_into().expect("8 bytes")),
+    ))
+}
+
+/// Returns the QR alphanumeric encoding of `checking`.
+#[must_use]
+pub fn encode_checking(checking: &CheckingParameters) -> String {
+    encode_pair(CHECKING_PREFIX, checking.unoffset, checking.unscale)
+}
+
+/// Parses [`CheckingParameters`] encoded with [`encode_checking`].
+pub fn parse_checking(string: &str) -> Result<CheckingParameters, &'static str> {
+    let (unoffset, unscale) = parse_pair(CHECKING_PREFIX, string)?;
+    Ok(CheckingParameters { unoffset, unscale })
+}
+
+/// Returns the QR alphanumeric encoding of `value` and its `voucher`.
+#[must_use]
+pub fn encode_vouched(value: u64, voucher: Voucher) -> String {
+    encode_pair(VOUCHED_PREFIX, value, voucher.0)
+}
+
+/// Parses a `(value, voucher)` pair encoded with [`encode_vouched`].
+///
+/// The voucher isn't checked.
+pub fn parse_vouched(string: &str) -> Result<(u64, Voucher), &'static str> {
+    let (value, voucher) = parse_pair(VOUCHED_PREFIX, string)?;
+    Ok((value, Voucher(voucher)))
+}
+
+#[test]
+fn test_base45_rfc_vectors() {
+    assert_eq!(base45_encode(b"AB"), "BB8");
+    assert_eq!(base45_encode(b"Hello!!"), "%69 VD92EX0");
+    assert_eq!(base45_encode(b"base-45"), "UJCLQE7W581");
+    assert_eq!(base45_decode("QED8WEX0").unwrap(), b"ietf!");
+
+    assert!(base45_decode("GGW").is_err()); // 65535 < GGW
+    assert!(base45_decode("a").is_err());
+    assert!(base45_decode("0").is_err());
+}
+
+#[test]
+fn test_checking_round_trip() {
+    let checking = CheckingParameters::parse_or_die("CHECK-0000000000000083-9b791a2755d2d996");
+    let encoded = encode_checking(&checking);
+
+    assert!(encoded.starts_with(CHECKING_PREFIX));
+    assert_eq!(encoded.len(), 29);
+    assert!(encoded.bytes().all(|byte| CHARSET.contains(&byte)));
+    assert_eq!(parse_checking(&encoded), Ok(checking));
+
+    assert!(parse_checking(&encoded[1..]).is_err());
+    assert!(parse_checking(&encoded[..encoded.len() - 3]).is_err());
+}
+
+#[test]
+fn test_vouched_round_trip() {
+    let params = crate::testing::VOUCHING;
+    let encoded = encode_vouched(42, params.vouch(42));
+
+    assert!(encoded.starts_with(VOUCHED_PREFIX));
+    assert!(encoded.bytes().all(|byte| CHARSET.contains(&byte)));
+    assert_eq!(parse_vouched(&encoded), Ok((42, params.vouch(42))));
+    assert!(parse_checking(&encoded).is_err());
+}
@@ -0,0 +1,179 @@
+//! Obtaining vouchers from a remote vouching service.
+//!
+//! This module is only compiled with the `async` feature.  It lets
+//! applications centralise voucher minting in a service that holds the
+//! [`VouchingParameters`], without handing the vouching half of the
+//! parameters to every tool that needs a voucher.  Clients only need
+//! the [`CheckingParameters`], and [`CheckedRemote`] uses them to
+//! confirm that the service returned valid vouchers.
+use std::future::Future;
+
+use crate::CheckingParameters;
+use crate::Voucher;
+use crate::VouchingParameters;
+
+/// A (usually remote) service that mints [`Voucher`]s.
+///
+/// Implementations typically wrap an RPC client.  The returned future
+/// must be `Send`, so it can be awaited from multi-threaded executors.
+pub trait RemoteVoucher {
+    /// Error type for failed requests.
+    type Error;
+
+    /// Asks the service for a [`Voucher`] for `value`.
+    fn vouch(&self, value: u64) -> impl Future<Output = Result<Voucher, Self::Error>> + Send;
+}
+
+/// Mints vouchers in-process, with [`VouchingParameters`].
+///
+/// This is mostly useful for tests and local development, or to
+/// implement the server side of a vouching service.
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Hash)]
+pub struct LocalVoucher {
+    vouching: VouchingParameters,
+}
+
+impl LocalVoucher {
+    /// Returns a [`RemoteVoucher`] that vouches with `vouching`.
+    #[must_use]
+    pub fn new(vouching: VouchingParameters) -> LocalVoucher {
+        LocalVoucher { vouching }
+    }
+}
+
+impl RemoteVoucher for LocalVoucher {
+    type Error = std::convert::Infallible;
+
+    fn vouch(&self, value: u64) -> impl Future<Output = Result<Voucher, Self::Error>> + Send {
+        std::future::ready(Ok(self.vouching.vouch(value)))
+    }
+}
+
+/// Error type for [`CheckedRemote`].
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Hash)]
+pub enum RemoteError<E> {
+    /// The underlying service failed with this error.
+    Remote(E),
+    /// The service returned a voucher that doesn't match the value.
+    InvalidVoucher {
+        /// The value we asked a voucher for.
+        value: u64,
+        /// The invalid voucher.
+        voucher: Voucher,
+    },
+}
+
+impl<E: std::fmt::Display> std::fmt::Display for RemoteError<E> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            RemoteError::Remote(e) => write!(f, "remote vouching failed: {}", e),
+            RemoteError::InvalidVoucher { value, voucher } => write!(
+                f,
+                "remote returned invalid voucher {:016x} for value {:016x}",
+                voucher.0, value
+            ),
+        }
+    }
+}
+
+impl<E: std::fmt::Debug + std::fmt::Display> std::error::Error for RemoteError<E> {}
+
+/// Wraps a [`RemoteVoucher`] and checks every voucher it returns
+/// against local [`CheckingParameters`].
+///
+/// This catches misconfigurations (e.g., a service that vouches with
+/// rotated parameters) at the source, rather than when the voucher is
+/// eventually checked elsewhere.
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Hash)]
+pub struct CheckedRemote<R> {
+    remote: R,
+    checking: CheckingParameters,
+}
+
+impl<R: RemoteVoucher> CheckedRemote<R> {
+    /// Returns a [`RemoteVoucher`] that checks `remote`'s vouchers with `checking`.
+    pub fn new(remote: R, checking: CheckingParameters) -> CheckedRemote<R> {
+        CheckedRemote { remote, checking }
+    }
+
+    /// Returns the [`CheckingParameters`] used to check vouchers.
+    pub fn checking_parameters(&self) -> CheckingParameters {
+        self.checking
+    }
+}
+
+impl<R> RemoteVoucher for CheckedRemote<R>
+where
+    R: RemoteVoucher + Sync,
+{
+    type Error = RemoteError<R::Error>;
+
+    async fn vouch(&self, value: u64) -> Result<Voucher, Self::Error> {
+        let voucher = self
+            .remote
+            .vouch(value)
+            .await
+            .map_err(RemoteError::Remote)?;
+        if self.checking.check(value, voucher) {
+            Ok(voucher)
+        } else {
+            Err(RemoteError::InvalidVoucher { value, voucher })
+        }
+    }
+}
+
+#[test]
+fn test_local() {
+    let params = crate::testing::VOUCHING;
+    let local = LocalVoucher::new(params);
+
+    assert_eq!(
+        crate::testing::block_on(local.vouch(42)),
+        Ok(params.vouch(42))
+    );
+}
+
+#[cfg(not(feature = "insecure-accept-all"))]
+#[test]
+fn test_checked() {
+    let params = crate::testing::VOUCHING;
+    let other = crate::testing::OTHER;
+
+    let checked = CheckedRemote::new(LocalVoucher::new(params), params.checking_parameters());
+    assert_eq!(
+        crate::testing::block_on(checked.vouch(42)),
+        Ok(params.vouch(42))
+    );
+
+    // A remote that vouches with the wrong parameters is caught.
+    let wrong = CheckedRemote::new(LocalVoucher::new(other), params.checking_parameters());
+    assert_eq!(
+        crate::testing::block_on(wrong.vouch(42)),
+        Err(RemoteError::InvalidVoucher {
+            value: 42,
+            voucher: other.vouch(42)
+        })
+    );
+}
+
+#[test]
+fn test_remote_error() {
+    struct Down;
+
+    impl RemoteVoucher for Down {
+        type Error = &'static str;
+
+        fn vouch(&self, _value: u64) -> impl Future<Output = Result<Voucher, Self::Error>> + Send {
+            std::future::ready(Err("connection refused"))
+        }
+    }
+
+    let params = crate::testing::VOUCHING;
+    let checked = CheckedRemote::new(Down, params.checking_parameters());
+    let err = crate::testing::block_on(checked.vouch(42)).unwrap_err();
+    assert_eq!(err, RemoteError::Remote("connection refused"));
+    assert_eq!(
+        err.to_string(),
+        "remote vouching failed: connection refused"
+    );
+}
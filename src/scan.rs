@@ -0,0 +1,354 @@
+//! Scans source trees for hardcoded parameters and vouched values.
+//!
+//! Hardcoded `"VOUCH-..."` and `"CHECK-..."` parameter strings, and
+//! `"RVAL:..."` vouched values (see [`crate::qr`]), go stale when the
+//! parameters rotate, and nothing notices until the code that parses
+//! them runs.  [`scan_tree`] finds these string literals, and checks
+//! them against the current [`CheckingParameters`], for pre-merge
+//! checks:
+//!
+//! ```no_run
+//! # use raffle::CheckingParameters;
+//! let checking = CheckingParameters::parse_or_die("CHECK-0000000000000083-9b791a2755d2d996");
+//! for finding in raffle::scan::scan_tree(checking, "src").unwrap() {
+//!     if !finding.is_valid() {
+//!         eprintln!("{}", finding);
+//!     }
+//! }
+//! ```
+//!
+//! The scanner only looks at the text, so literals in macro call sites
+//! (e.g., [`crate::static_assert_vouched!`], even when split over
+//! several lines), comments and doc examples are reported as well.  It
+//! skips literals that are only a prefix, format strings (with `{`),
+//! and placeholders (with `...`).  Findings never include the literal
+//! itself, since `"VOUCH-..."` strings are secrets.
+//!
+//! A `"VOUCH-..."` literal for the current parameters is never valid:
+//! it's the vouching secret itself, in the source tree, so it's
+//! reported as [`Status::Leaked`].
+use std::path::Path;
+use std::path::PathBuf;
+
+use crate::qr::VOUCHED_PREFIX;
+use crate::CheckingParameters;
+use crate::VouchingParameters;
+
+/// The kind of string literal the scanner found.
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Hash)]
+pub enum LiteralKind {
+    /// A `"VOUCH-..."` string for [`VouchingParameters`].
+    Vouching,
+    /// A `"CHECK-..."` string for [`CheckingParameters`].
+    Checking,
+    /// A `"RVAL:..."` value and voucher, as encoded by [`crate::qr::encode_vouched`].
+    Vouched,
+}
+
+impl LiteralKind {
+    const ALL: [LiteralKind; 3] = [
+        LiteralKind::Vouching,
+        LiteralKind::Checking,
+        LiteralKind::Vouched,
+    ];
+
+    /// Returns the prefix of the kind's literals.
+    #[must_use]
+    pub const fn prefix(self) -> &'static str {
+        match self {
+            LiteralKind::Vouching => "VOUCH-",
+            LiteralKind::Checking => "CHECK-",
+            LiteralKind::Vouched => VOUCHED_PREFIX,
+        }
+    }
+}
+
+impl std::fmt::Display for LiteralKind {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            LiteralKind::Vouching => "vouching parameters",
+            LiteralKind::Checking => "checking parameters",
+            LiteralKind::Vouched => "vouched value",
+        })
+    }
+}
+
+/// Whether a literal matches the current [`CheckingParameters`].
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Hash)]
+pub enum Status {
+    /// The checking parameters match, or the voucher is valid for its
+    /// value.
+    Valid,
+    /// The literal is well-formed, but for other parameters.
+    Stale,
+    /// The literal is the vouching secret for the current parameters.
+    Leaked,
+    /// The literal doesn't parse.
+    Malformed(&'static str),
+}
+
+/// A string literal found by the scanner.
+#[derive(Clone, Debug, Eq, PartialEq, Hash)]
+pub struct Finding {
+    /// The file that contains the literal.
+    pub path: PathBuf,
+    /// The 1-based line number of the literal's opening quote.
+    pub line: usize,
+    /// What the literal looks like.
+    pub kind: LiteralKind,
+    /// Whether the literal matches the current parameters.
+    pub status: Status,
+}
+
+impl Finding {
+    /// Returns whether the literal matches the current parameters.
+    #[must_use]
+    pub fn is_valid(&self) -> bool {
+        self.status == Status::Valid
+    }
+}
+
+/// Formats the finding as `path:line: status kind`, without the literal.
+impl std::fmt::Display for Finding {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}:{}: ", self.path.display(), self.line)?;
+        match self.status {
+            Status::Valid => write!(f, "valid {}", self.kind),
+            Status::Stale => write!(f, "stale {}", self.kind),
+            Status::Leaked => write!(f, "leaked {}", self.kind),
+            Status::Malformed(reason) => write!(f, "malformed {}: {}", self.kind, reason),
+        }
+    }
+}
+
+/// Determines whether `literal`, a string of kind `kind`, matches `checking`.
+fn literal_status(checking: CheckingParameters, kind: LiteralKind, literal: &str) -> Status {
+    let matches = match kind {
+        LiteralKind::Vouching => VouchingParameters::parse(literal)
+            .map(|vouching| vouching.checking_parameters() == checking),
+        LiteralKind::Checking => {
+            CheckingParameters::parse(literal).map(|parsed| parsed == checking)
+        }
+        LiteralKind::Vouched => {
+            crate::qr::parse_vouched(literal).map(|(value, voucher)| checking.check(value, voucher))
+        }
+    };
+
+    match matches {
+        Ok(true) if kind == LiteralKind::Vouching => Status::Leaked,
+        Ok(true) => Status::Valid,
+        Ok(false) => Status::Stale,
+        Err(reason) => Status::Malformed(reason),
+    }
+}
+
+/// Scans `source`, the contents of the file at `path`, for parameter
+/// and vouched value literals, and checks them against `checking`.
+///
+/// Returns the findings in source order.
+#[must_use]
+pub fn scan_source(checking: CheckingParameters, path: &Path, source: &str) -> Vec<Finding> {
+    let mut findings = Vec::new();
+
+    for (index, line) in source.lines().enumerate() {
+        // Every literal kind starts with a double quote followed by its prefix.
+        for (start, _) in line.match_indices('"') {
+            let rest = &line[start + 1..];
+            let Some(kind) = LiteralKind::ALL
+                .into_iter()
+                .find(|kind| rest.starts_with(kind.prefix()))
+            else {
+                continue;
+            };
+
+            // None of the formats contain quotes or escapes, so the
+            // literal ends at the next quote on the same line.
+            let Some((literal, _)) = rest.split_once('"') else {
+                continue;
+            };
+
+            // Skip bare prefixes, format strings and placeholders in docs.
+            let body = &literal[kind.prefix().len()..];
+            if body.is_empty() || body.contains('{') || body.contains("...") {
+                continue;
+            }
+
+            findings.push(Finding {
+                path: path.to_owned(),
+                line: index + 1,
+                kind,
+                status: literal_status(checking, kind, literal),
+            });
+        }
+    }
+
+    findings
+}
+
+/// Scans the file at `path` with [`scan_source`].
+pub fn scan_file(
+    checking: CheckingParameters,
+    path: impl AsRef<Path>,
+) -> std::io::Result<Vec<Finding>> {
+    let path = path.as_ref();
+    let source = std::fs::read_to_string(path)?;
+    Ok(scan_source(checking, path, &source))
+}
+
+/// Scans all `.rs` files under `root` (or `root` itself, if it's a file)
+/// with [`scan_source`].
+///
+/// Skips hidden directories, `target` directories, and subdirectories
+/// with their own `Cargo.toml`: nested packages may use different
+/// parameters, so they should be scanned on their own.  Returns the
+/// findings sorted by path, then in source order.
+pub fn scan_tree(
+    checking: CheckingParameters,
+    root: impl AsRef<Path>,
+) -> std::io::Result<Vec<Finding>> {
+    fn walk(dir: &Path, files: &mut Vec<PathBuf>) -> std::io::Result<()> {
+        for entry in std::fs::read_dir(dir)? {
+            let entry = entry?;
+            let path = entry.path();
+            let name = entry.file_name();
+            let name = name.to_string_lossy();
+
+            if entry.file_type()?.is_dir() {
+                if !name.starts_with('.') && name != "target" && !path.join("Cargo.toml").exists() {
+                    walk(&path, files)?;
+                }
+            } else if path.extension().is_some_and(|extension| extension == "rs") {
+                files.push(path);
+            }
+        }
+
+        Ok(())
+    }
+
+    let root = root.as_ref();
+    let mut files = Vec::new();
+    if root.is_dir() {
+        walk(root, &mut files)?;
+        files.sort();
+    } else {
+        files.push(root.to_owned());
+    }
+
+    let mut findings = Vec::new();
+    for file in files {
+        findings.extend(scan_file(checking, file)?);
+    }
+
+    Ok(findings)
+}
+
+#[cfg(not(feature = "insecure-accept-all"))]
+#[test]
+fn test_scan_source() {
+    let params = crate::testing::VOUCHING;
+    let other = crate::testing::OTHER;
+    let checking = params.checking_parameters();
+
+    let source = format!(
+        "const A: &str = \"{}\";\nconst B: &str = \"{}\"; const C: &str = \"{}\";\n\n\
+         let d = \"{}\";\nlet e = \"{}\"; // \"CHECK-nope\"\nlet f = \"unrelated\";\n\
+         let g = format!(\"CHECK-{{:016x}}\"); // \"VOUCH-...\", \"RVAL:\"\n",
+        checking,
+        other.checking_parameters(),
+        params,
+        crate::qr::encode_vouched(42, params.vouch(42)),
+        crate::qr::encode_vouched(43, params.vouch(42)),
+    );
+
+    let findings = scan_source(checking, Path::new("src/lib.rs"), &source);
+    let summary: Vec<(usize, LiteralKind, Status)> = findings
+        .iter()
+        .map(|finding| (finding.line, finding.kind, finding.status))
+        .collect();
+    assert_eq!(
+        summary,
+        [
+            (1, LiteralKind::Checking, Status::Valid),
+            (2, LiteralKind::Checking, Status::Stale),
+            (2, LiteralKind::Vouching, Status::Leaked),
+            (4, LiteralKind::Vouched, Status::Valid),
+            (5, LiteralKind::Vouched, Status::Stale),
+            (
+                5,
+                LiteralKind::Checking,
+                Status::Malformed("Too few bytes in serialized raffle::CheckingParameters")
+            ),
+        ]
+    );
+
+    assert_eq!(
+        findings[1].to_string(),
+        "src/lib.rs:2: stale checking parameters"
+    );
+    // The vouching parameters are an error, and never appear in the report.
+    assert!(!findings[2].is_valid());
+    assert_eq!(
+        findings[2].to_string(),
+        "src/lib.rs:2: leaked vouching parameters"
+    );
+}
+
+#[test]
+fn test_scan_macro_call_sites() {
+    let params = crate::testing::VOUCHING;
+    let other = crate::testing::OTHER;
+    let checking = params.checking_parameters();
+
+    // Like rustfmt's output for long calls.
+    let source = format!(
+        "raffle::static_assert_vouched!(\"{}\", 42, V);\n\
+         raffle::static_assert_vouched!(\n    \"{}\",\n    LIMIT,\n    VOUCHER,\n);\n\
+         let limit = parse_vouched!(\"{}\");\n",
+        checking,
+        other.checking_parameters(),
+        crate::qr::encode_vouched(42, params.vouch(42)),
+    );
+
+    let summary: Vec<(usize, LiteralKind, Status)> =
+        scan_source(checking, Path::new("src/lib.rs"), &source)
+            .iter()
+            .map(|finding| (finding.line, finding.kind, finding.status))
+            .collect();
+    assert_eq!(
+        summary,
+        [
+            (1, LiteralKind::Checking, Status::Valid),
+            (3, LiteralKind::Checking, Status::Stale),
+            (7, LiteralKind::Vouched, Status::Valid),
+        ]
+    );
+}
+
+#[test]
+fn test_scan_tree() {
+    let params = crate::testing::VOUCHING;
+    let checking = params.checking_parameters();
+
+    let root = std::env::temp_dir().join(format!("raffle-scan-{}", std::process::id()));
+    let _ = std::fs::remove_dir_all(&root);
+    std::fs::create_dir_all(root.join("src/nested")).unwrap();
+    std::fs::create_dir_all(root.join("target")).unwrap();
+    std::fs::create_dir_all(root.join("nested-package/src")).unwrap();
+    std::fs::write(root.join("Cargo.toml"), "").unwrap();
+    std::fs::write(root.join("src/a.rs"), format!("\"{}\"", checking)).unwrap();
+    std::fs::write(root.join("src/nested/b.rs"), "\n\"CHECK-stale\"").unwrap();
+    std::fs::write(root.join("src/notes.txt"), "\"CHECK-ignored\"").unwrap();
+    std::fs::write(root.join("target/c.rs"), "\"CHECK-ignored\"").unwrap();
+    std::fs::write(root.join("nested-package/Cargo.toml"), "").unwrap();
+    std::fs::write(root.join("nested-package/src/d.rs"), "\"CHECK-ignored\"").unwrap();
+
+    let findings = scan_tree(checking, &root).unwrap();
+    std::fs::remove_dir_all(&root).unwrap();
+
+    assert_eq!(findings.len(), 2);
+    assert_eq!(findings[0].path, root.join("src/a.rs"));
+    assert!(findings[0].is_valid());
+    assert_eq!(findings[1].path, root.join("src/nested/b.rs"));
+    assert_eq!(findings[1].line, 2);
+    assert!(matches!(findings[1].status, Status::Malformed(_)));
+}
@@ -0,0 +1,251 @@
+//! Scheme-tagged parameters.
+//!
+//! Each vouching scheme has its own string representation, and the
+//! prefix of that representation (`CHECK-`/`VOUCH-` for the default
+//! affine scheme, `WIDE-CHECK-`/`WIDE-VOUCH-` for the wide scheme,
+//! `HMAC-SHA256-` for the `hmac` feature's scheme)
+//! doubles as a version tag.  [`AnyCheckingParameters`] and
+//! [`AnyVouchingParameters`] parse any of these formats and dispatch
+//! to the corresponding scheme, so the choice of scheme can be made
+//! per parameter set, in configuration, rather than in code.
+use crate::CheckingParameters;
+use crate::Voucher;
+use crate::VouchingParameters;
+use crate::WideCheckingParameters;
+use crate::WideVouchingParameters;
+
+#[cfg(feature = "hmac")]
+use crate::HmacParameters;
+
+/// Checking parameters for any of the schemes compiled in this build.
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Hash)]
+#[non_exhaustive]
+pub enum AnyCheckingParameters {
+    /// The default affine scheme.
+    Affine(CheckingParameters),
+    /// The wide scheme, with a 128-bit mixing step.
+    Wide(WideCheckingParameters),
+    /// HMAC-SHA256, truncated to 64 bits.  These parameters are secret.
+    #[cfg(feature = "hmac")]
+    HmacSha256(HmacParameters),
+}
+
+/// Vouching parameters for any of the schemes compiled in this build.
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Hash)]
+#[non_exhaustive]
+pub enum AnyVouchingParameters {
+    /// The default affine scheme.
+    Affine(VouchingParameters),
+    /// The wide scheme, with a 128-bit mixing step.
+    Wide(WideVouchingParameters),
+    /// HMAC-SHA256, truncated to 64 bits.
+    #[cfg(feature = "hmac")]
+    HmacSha256(HmacParameters),
+}
+
+impl AnyCheckingParameters {
+    /// Attempts to parse the string representation of checking
+    /// parameters for any supported scheme.
+    pub fn parse(string: &str) -> Result<AnyCheckingParameters, &'static str> {
+        #[cfg(feature = "hmac")]
+        if string.starts_with(crate::hmac_sha256::PREFIX) {
+            return Ok(AnyCheckingParameters::HmacSha256(HmacParameters::parse(
+                string,
+            )?));
+        }
+
+        if string.starts_with(crate::wide::CHECK_PREFIX) {
+            return Ok(AnyCheckingParameters::Wide(WideCheckingParameters::parse(
+                string,
+            )?));
+        }
+
+        Ok(AnyCheckingParameters::Affine(CheckingParameters::parse(
+            string,
+        )?))
+    }
+
+    /// Returns whether the `expected` value matches the `voucher`
+    /// under the scheme for these parameters.
+    #[must_use]
+    pub fn check(&self, expected: u64, voucher: Voucher) -> bool {
+        match self {
+            AnyCheckingParameters::Affine(params) => params.check(expected, voucher),
+            AnyCheckingParameters::Wide(params) => params.check(expected, voucher),
+            #[cfg(feature = "hmac")]
+            AnyCheckingParameters::HmacSha256(params) => params.check(expected, voucher),
+        }
+    }
+}
+
+impl AnyVouchingParameters {
+    /// Attempts to parse the string representation of vouching
+    /// parameters for any supported scheme.
+    pub fn parse(string: &str) -> Result<AnyVouchingParameters, &'static str> {
+        #[cfg(feature = "hmac")]
+        if string.starts_with(crate::hmac_sha256::PREFIX) {
+            return Ok(AnyVouchingParameters::HmacSha256(HmacParameters::parse(
+                string,
+            )?));
+        }
+
+        if string.starts_with(crate::wide::VOUCH_PREFIX) {
+            return Ok(AnyVouchingParameters::Wide(WideVouchingParameters::parse(
+                string,
+            )?));
+        }
+
+        Ok(AnyVouchingParameters::Affine(VouchingParameters::parse(
+            string,
+        )?))
+    }
+
+    /// Computes a [`Voucher`] for `value` under the scheme for these parameters.
+    #[must_use]
+    pub fn vouch(&self, value: u64) -> Voucher {
+        match self {
+            AnyVouchingParameters::Affine(params) => params.vouch(value),
+            AnyVouchingParameters::Wide(params) => params.vouch(value),
+            #[cfg(feature = "hmac")]
+            AnyVouchingParameters::HmacSha256(params) => params.vouch(value),
+        }
+    }
+
+    /// Returns the [`AnyCheckingParameters`] that will accept the
+    /// [`Voucher`]s generated with these parameters.
+    #[must_use]
+    pub fn checking_parameters(&self) -> AnyCheckingParameters {
+        match self {
+            AnyVouchingParameters::Affine(params) => {
+                AnyCheckingParameters::Affine(params.checking_parameters())
+            }
+            AnyVouchingParameters::Wide(params) => {
+                AnyCheckingParameters::Wide(params.checking_parameters())
+            }
+            #[cfg(feature = "hmac")]
+            AnyVouchingParameters::HmacSha256(params) => AnyCheckingParameters::HmacSha256(*params),
+        }
+    }
+}
+
+impl From<CheckingParameters> for AnyCheckingParameters {
+    fn from(params: CheckingParameters) -> AnyCheckingParameters {
+        AnyCheckingParameters::Affine(params)
+    }
+}
+
+impl From<VouchingParameters> for AnyVouchingParameters {
+    fn from(params: VouchingParameters) -> AnyVouchingParameters {
+        AnyVouchingParameters::Affine(params)
+    }
+}
+
+impl From<WideCheckingParameters> for AnyCheckingParameters {
+    fn from(params: WideCheckingParameters) -> AnyCheckingParameters {
+        AnyCheckingParameters::Wide(params)
+    }
+}
+
+impl From<WideVouchingParameters> for AnyVouchingParameters {
+    fn from(params: WideVouchingParameters) -> AnyVouchingParameters {
+        AnyVouchingParameters::Wide(params)
+    }
+}
+
+#[cfg(feature = "hmac")]
+impl From<HmacParameters> for AnyCheckingParameters {
+    fn from(params: HmacParameters) -> AnyCheckingParameters {
+        AnyCheckingParameters::HmacSha256(params)
+    }
+}
+
+#[cfg(feature = "hmac")]
+impl From<HmacParameters> for AnyVouchingParameters {
+    fn from(params: HmacParameters) -> AnyVouchingParameters {
+        AnyVouchingParameters::HmacSha256(params)
+    }
+}
+
+impl std::fmt::Display for AnyCheckingParameters {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            AnyCheckingParameters::Affine(params) => params.fmt(f),
+            AnyCheckingParameters::Wide(params) => params.fmt(f),
+            #[cfg(feature = "hmac")]
+            AnyCheckingParameters::HmacSha256(params) => params.fmt(f),
+        }
+    }
+}
+
+impl std::fmt::Display for AnyVouchingParameters {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            AnyVouchingParameters::Affine(params) => params.fmt(f),
+            AnyVouchingParameters::Wide(params) => params.fmt(f),
+            #[cfg(feature = "hmac")]
+            AnyVouchingParameters::HmacSha256(params) => params.fmt(f),
+        }
+    }
+}
+
+#[cfg(not(feature = "insecure-accept-all"))]
+#[test]
+fn test_affine_round_trip() {
+    let params = crate::testing::VOUCHING;
+    let any = AnyVouchingParameters::parse(&format!("{}", params)).expect("must parse");
+    assert_eq!(any, AnyVouchingParameters::Affine(params));
+
+    let checking = AnyCheckingParameters::parse(&format!("{}", params.checking_parameters()))
+        .expect("must parse");
+    assert_eq!(checking, any.checking_parameters());
+    assert_eq!(
+        format!("{}", checking),
+        format!("{}", params.checking_parameters())
+    );
+
+    assert!(checking.check(42, any.vouch(42)));
+    assert!(!checking.check(43, any.vouch(42)));
+
+    assert!(AnyCheckingParameters::parse(&format!("{}", params)).is_err());
+    assert!(AnyVouchingParameters::parse(&format!("{}", checking)).is_err());
+}
+
+#[cfg(not(feature = "insecure-accept-all"))]
+#[test]
+fn test_wide_round_trip() {
+    let params =
+        WideVouchingParameters::generate(crate::make_generator(&[131, 5, 131, 1, 2, 3, 4]))
+            .unwrap();
+    let vouching = AnyVouchingParameters::parse(&format!("{}", params)).expect("must parse");
+    let checking = AnyCheckingParameters::parse(&format!("{}", params.checking_parameters()))
+        .expect("must parse");
+    assert_eq!(vouching, AnyVouchingParameters::Wide(params));
+    assert_eq!(checking, vouching.checking_parameters());
+
+    assert!(checking.check(42, vouching.vouch(42)));
+    assert!(!checking.check(43, vouching.vouch(42)));
+
+    // The affine half of the wide parameters doesn't accept wide vouchers.
+    let affine = crate::testing::VOUCHING;
+    assert!(!AnyCheckingParameters::from(affine.checking_parameters()).check(42, params.vouch(42)));
+}
+
+#[cfg(all(feature = "hmac", not(feature = "insecure-accept-all")))]
+#[test]
+fn test_hmac_round_trip() {
+    let params = HmacParameters::generate(crate::make_generator(&[1, 2, 3, 4])).unwrap();
+    let serial = format!("{}", params);
+
+    let vouching = AnyVouchingParameters::parse(&serial).expect("must parse");
+    let checking = AnyCheckingParameters::parse(&serial).expect("must parse");
+    assert_eq!(vouching, AnyVouchingParameters::HmacSha256(params));
+    assert_eq!(checking, vouching.checking_parameters());
+    assert_eq!(format!("{}", checking), serial);
+
+    assert!(checking.check(42, vouching.vouch(42)));
+    assert!(!checking.check(43, vouching.vouch(42)));
+
+    // An affine checker doesn't accept HMAC vouchers.
+    let affine = crate::testing::VOUCHING;
+    assert!(!AnyCheckingParameters::from(affine.checking_parameters()).check(42, params.vouch(42)));
+}
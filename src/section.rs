@@ -0,0 +1,198 @@
+//! The format-independent `raffle` configuration section.
+//!
+//! [`RaffleSection`] holds the contents of the `raffle` section of
+//! configuration files; `raffle::toml_section` and
+//! `raffle::yaml_section` (behind the `toml` and `yaml` features)
+//! parse and emit it with the same layout.
+use std::collections::BTreeMap;
+
+use crate::config::Overrides;
+use crate::CheckingParameters;
+use crate::Keyring;
+use crate::Voucher;
+
+/// Name of the section.
+#[cfg(any(feature = "toml", feature = "yaml"))]
+pub(crate) const SECTION: &str = "raffle";
+
+/// The contents of a `raffle` configuration section: main checking
+/// parameters, named checking parameters, and vouched overrides.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct RaffleSection {
+    /// The `checking` parameters, if any.
+    pub checking: Option<CheckingParameters>,
+    /// The `keyring` table.
+    pub keyring: Keyring,
+    /// The `overrides` table: each setting's value and voucher.
+    /// These aren't checked until they're loaded from
+    /// [`RaffleSection::overrides`].
+    pub overrides: BTreeMap<String, (OverrideValue, Voucher)>,
+}
+
+/// The value of an override, with the type it had in the
+/// configuration file, so that it survives a round trip.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum OverrideValue {
+    /// A string value.
+    String(String),
+    /// An integer value.
+    Integer(i64),
+    /// A boolean value.
+    Boolean(bool),
+}
+
+impl std::fmt::Display for OverrideValue {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            OverrideValue::String(value) => value.fmt(f),
+            OverrideValue::Integer(value) => value.fmt(f),
+            OverrideValue::Boolean(value) => value.fmt(f),
+        }
+    }
+}
+
+impl RaffleSection {
+    /// Returns the section's overrides, accepted only when vouched for
+    /// by the `checking` parameters.
+    ///
+    /// Keyring entries are usually per-tenant keys, and must not
+    /// authorize application-wide overrides; see
+    /// [`RaffleSection::overrides_with_keyring`] to opt into that.
+    #[must_use]
+    pub fn overrides(&self) -> Overrides {
+        self.make_overrides(self.checking)
+    }
+
+    /// Returns the section's overrides, accepted when vouched for by
+    /// the `checking` parameters *or any keyring entry*.
+    ///
+    /// Only use this when every key in the keyring is trusted to
+    /// override any setting for the whole application: a voucher from
+    /// any one of them is enough.
+    #[must_use]
+    pub fn overrides_with_keyring(&self) -> Overrides {
+        let keys = self
+            .checking
+            .into_iter()
+            .chain(self.keyring.iter().map(|(_, checking)| checking));
+        self.make_overrides(keys)
+    }
+
+    fn make_overrides(&self, keys: impl IntoIterator<Item = CheckingParameters>) -> Overrides {
+        let mut overrides = Overrides::new(keys);
+        for (name, (value, voucher)) in &self.overrides {
+            overrides.insert(name.as_str(), value.to_string(), *voucher);
+        }
+
+        overrides
+    }
+}
+
+/// Error type for parsing a [`RaffleSection`], where `E` is the syntax
+/// error of the configuration format (see
+/// `raffle::toml_section::SectionError` and
+/// `raffle::yaml_section::SectionError`).
+#[cfg(any(feature = "toml", feature = "yaml"))]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum SectionError<E> {
+    /// The document isn't syntactically valid.
+    Syntax(E),
+    /// The entry at `key` (e.g., `raffle.keyring.tenant_a`) is invalid.
+    Invalid {
+        /// The dotted key of the invalid entry.
+        key: String,
+        /// What's wrong with the entry.
+        reason: &'static str,
+    },
+}
+
+#[cfg(any(feature = "toml", feature = "yaml"))]
+impl<E: std::error::Error + 'static> std::error::Error for SectionError<E>
+where
+    SectionError<E>: std::fmt::Display,
+{
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            SectionError::Syntax(e) => Some(e),
+            SectionError::Invalid { .. } => None,
+        }
+    }
+}
+
+/// Returns an error for the invalid entry at `key`.
+#[cfg(any(feature = "toml", feature = "yaml"))]
+pub(crate) fn invalid<E>(key: impl Into<String>, reason: &'static str) -> SectionError<E> {
+    SectionError::Invalid {
+        key: key.into(),
+        reason,
+    }
+}
+
+/// Parses the checking parameters at `key`, from the entry's `string`
+/// value (`None` if it isn't a string).
+#[cfg(any(feature = "toml", feature = "yaml"))]
+pub(crate) fn parse_checking<E>(
+    key: String,
+    string: Option<&str>,
+) -> Result<CheckingParameters, SectionError<E>> {
+    let Some(string) = string else {
+        return Err(invalid(key, "Expected a CHECK-... string"));
+    };
+
+    CheckingParameters::parse(string.trim()).map_err(|reason| invalid(key, reason))
+}
+
+/// Parses an override's voucher, 16 hex digits.
+#[cfg(any(feature = "toml", feature = "yaml", feature = "axum"))]
+pub(crate) fn parse_voucher(string: &str) -> Result<Voucher, &'static str> {
+    match crate::constparse::parse_hex(string.as_bytes(), 0) {
+        Some(voucher) if string.len() == 16 => Ok(Voucher(voucher)),
+        _ => Err("Voucher must be 16 hex digits"),
+    }
+}
+
+/// Formats an override's voucher for [`parse_voucher`].
+#[cfg(any(feature = "toml", feature = "yaml"))]
+pub(crate) fn format_voucher(voucher: Voucher) -> String {
+    format!("{:016x}", voucher.0)
+}
+
+#[test]
+fn test_overrides_keys() {
+    use crate::config::Setting;
+
+    static LIMIT: Setting<u32> = Setting::new("limit", 10);
+    let params = crate::testing::VOUCHING;
+    let other = crate::testing::OTHER;
+
+    let mut section = RaffleSection {
+        checking: Some(params.checking_parameters()),
+        ..Default::default()
+    };
+    section
+        .keyring
+        .insert("tenant", other.checking_parameters());
+    section.overrides.insert(
+        "limit".to_owned(),
+        (OverrideValue::Integer(1000), LIMIT.vouch(&params, &1000)),
+    );
+
+    // The checking parameters are always accepted.
+    assert_eq!(section.overrides().load(&LIMIT).unwrap().value, 1000);
+    assert_eq!(
+        section.overrides_with_keyring().load(&LIMIT).unwrap().value,
+        1000
+    );
+
+    // Keyring keys only with the explicit opt-in.
+    section.overrides.insert(
+        "limit".to_owned(),
+        (OverrideValue::Integer(1000), LIMIT.vouch(&other, &1000)),
+    );
+    #[cfg(not(feature = "insecure-accept-all"))]
+    assert!(section.overrides().load(&LIMIT).is_err());
+    assert_eq!(
+        section.overrides_with_keyring().load(&LIMIT).unwrap().value,
+        1000
+    );
+}
@@ -0,0 +1,222 @@
+//! Serde implementations for [`CheckingParameters`] and [`VouchingParameters`].
+//!
+//! This module is only compiled with the `serde` feature.  Human-readable
+//! formats (e.g., JSON) get the usual string representation, e.g.,
+//! `"CHECK-0000000000000083-9b791a2755d2d996"`.  Binary formats
+//! instead get a fixed-size encoding without strings, tuned for
+//! `postcard`: each component, in the order of the string
+//! representation, is serialised as an `[u8; 8]` of little-endian
+//! bytes.  That's 16 bytes for [`CheckingParameters`] and 32 bytes for
+//! [`VouchingParameters`], in formats that don't prefix fixed-size
+//! arrays with their length (e.g., `postcard`, or `bincode`).
+//!
+//! With `bincode`'s fixint encoding, every integer is fixed-size and
+//! little-endian, so the binary encoding of parameters is exactly their
+//! components as little-endian `u64`s, and [`crate::Voucher`]s (and
+//! [`crate::Vouched`] fields, with [`crate::serde_vouched`]) are also
+//! fixed-size.
+//!
+//! Deserialising [`VouchingParameters`] fails unless the components
+//! are consistent, as for [`VouchingParameters::try_new`].
+use serde::de::Error as _;
+use serde::de::SeqAccess;
+use serde::de::Visitor;
+use serde::ser::SerializeTuple;
+use serde::Deserialize;
+use serde::Deserializer;
+use serde::Serialize;
+use serde::Serializer;
+
+use crate::CheckingParameters;
+use crate::VouchingParameters;
+
+fn serialize_components<S: Serializer>(
+    components: &[u64],
+    serializer: S,
+) -> Result<S::Ok, S::Error> {
+    let mut tuple = serializer.serialize_tuple(components.len())?;
+    for component in components {
+        tuple.serialize_element(&component.to_le_bytes())?;
+    }
+
+    tuple.end()
+}
+
+/// Deserialises `N` components as a tuple of `[u8; 8]`.
+fn deserialize_components<'de, D: Deserializer<'de>, const N: usize>(
+    deserializer: D,
+) -> Result<[u64; N], D::Error> {
+    struct ComponentsVisitor<const N: usize>;
+
+    impl<'de, const N: usize> Visitor<'de> for ComponentsVisitor<N> {
+        type Value = [u64; N];
+
+        fn expecting(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+            write!(f, "a tuple of {} little-endian [u8; 8]", N)
+        }
+
+        fn visit_seq<A: SeqAccess<'de>>(self, mut seq: A) -> Result<[u64; N], A::Error> {
+            let mut ret = [0u64; N];
+            for (idx, dst) in ret.iter_mut().enumerate() {
+                let bytes: [u8; 8] = seq
+                    .next_element()?
+                    .ok_or_else(|| A::Error::invalid_length(idx, &self))?;
+                *dst = u64::from_le_bytes(bytes);
+            }
+
+            Ok(ret)
+        }
+    }
+
+    deserializer.deserialize_tuple(N, ComponentsVisitor::<N>)
+}
+
+impl Serialize for CheckingParameters {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        if serializer.is_human_readable() {
+            serializer.collect_str(self)
+        } else {
+            serialize_components(&[self.unoffset, self.unscale], serializer)
+        }
+    }
+}
+
+impl<'de> Deserialize<'de> for CheckingParameters {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        if deserializer.is_human_readable() {
+            let string = <std::borrow::Cow<'de, str>>::deserialize(deserializer)?;
+            CheckingParameters::parse(&string).map_err(D::Error::custom)
+        } else {
+            let [unoffset, unscale] = deserialize_components::<D, 2>(deserializer)?;
+            Ok(CheckingParameters { unoffset, unscale })
+        }
+    }
+}
+
+impl Serialize for VouchingParameters {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        if serializer.is_human_readable() {
+            serializer.collect_str(self)
+        } else {
+            let (offset, scale, unoffset, unscale) = self.components();
+            serialize_components(&[offset, scale, unoffset, unscale], serializer)
+        }
+    }
+}
+
+impl<'de> Deserialize<'de> for VouchingParameters {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        if deserializer.is_human_readable() {
+            let string = <std::borrow::Cow<'de, str>>::deserialize(deserializer)?;
+            VouchingParameters::parse(&string).map_err(D::Error::custom)
+        } else {
+            let [offset, scale, unoffset, unscale] = deserialize_components::<D, 4>(deserializer)?;
+            VouchingParameters::try_new(offset, scale, unoffset, unscale).map_err(D::Error::custom)
+        }
+    }
+}
+
+#[test]
+fn test_json() {
+    let params = crate::testing::VOUCHING;
+    let checking = params.checking_parameters();
+
+    let json = serde_json::to_string(&checking).unwrap();
+    assert_eq!(json, "\"CHECK-0000000000000083-9b791a2755d2d996\"");
+    assert_eq!(
+        serde_json::from_str::<CheckingParameters>(&json).unwrap(),
+        checking
+    );
+
+    let json = serde_json::to_string(&params).unwrap();
+    assert_eq!(json, format!("\"{}\"", params));
+    assert_eq!(
+        serde_json::from_str::<VouchingParameters>(&json).unwrap(),
+        params
+    );
+
+    assert!(serde_json::from_str::<CheckingParameters>("\"CHECK-garbage\"").is_err());
+}
+
+#[test]
+fn test_postcard() {
+    let params = crate::testing::VOUCHING;
+    let checking = params.checking_parameters();
+
+    let bytes = postcard::to_allocvec(&checking).unwrap();
+    assert_eq!(bytes.len(), 16);
+    assert_eq!(bytes[..8], 0x83u64.to_le_bytes());
+    assert_eq!(bytes[8..], 0x9b791a2755d2d996u64.to_le_bytes());
+    assert_eq!(
+        postcard::from_bytes::<CheckingParameters>(&bytes).unwrap(),
+        checking
+    );
+
+    let bytes = postcard::to_allocvec(&params).unwrap();
+    assert_eq!(bytes.len(), 32);
+    assert_eq!(
+        postcard::from_bytes::<VouchingParameters>(&bytes).unwrap(),
+        params
+    );
+
+    // Inconsistent parameters are rejected.
+    let mut corrupt = bytes.clone();
+    corrupt[0] ^= 1;
+    assert!(postcard::from_bytes::<VouchingParameters>(&corrupt).is_err());
+    // And so are truncated ones.
+    assert!(postcard::from_bytes::<CheckingParameters>(&bytes[..15]).is_err());
+}
+
+#[cfg(test)]
+fn bincode_fixint() -> impl bincode::Options {
+    use bincode::Options;
+
+    bincode::DefaultOptions::new()
+        .with_fixint_encoding()
+        .with_little_endian()
+}
+
+#[test]
+fn test_bincode_fixint() {
+    use bincode::Options;
+
+    let params = crate::testing::VOUCHING;
+    let checking = params.checking_parameters();
+
+    let bytes = bincode_fixint().serialize(&checking).unwrap();
+    let expected: Vec<u8> = [0x83u64, 0x9b791a2755d2d996]
+        .iter()
+        .flat_map(|x| x.to_le_bytes())
+        .collect();
+    assert_eq!(bytes, expected);
+    assert_eq!(
+        bincode_fixint()
+            .deserialize::<CheckingParameters>(&bytes)
+            .unwrap(),
+        checking
+    );
+
+    let (offset, scale, unoffset, unscale) = params.components();
+    let bytes = bincode_fixint().serialize(&params).unwrap();
+    let expected: Vec<u8> = [offset, scale, unoffset, unscale]
+        .iter()
+        .flat_map(|x| x.to_le_bytes())
+        .collect();
+    assert_eq!(bytes, expected);
+    assert_eq!(
+        bincode_fixint()
+            .deserialize::<VouchingParameters>(&bytes)
+            .unwrap(),
+        params
+    );
+
+    let voucher = params.vouch(42);
+    let bytes = bincode_fixint().serialize(&voucher).unwrap();
+    assert_eq!(bytes, voucher.0.to_le_bytes());
+    assert_eq!(
+        bincode_fixint()
+            .deserialize::<crate::Voucher>(&bytes)
+            .unwrap(),
+        voucher
+    );
+}
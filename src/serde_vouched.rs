@@ -0,0 +1,212 @@
+//! Serde adapters for [`Vouched`] fields.
+//!
+//! This module is only compiled with the `serde` feature.  A
+//! [`Vouched<T>`] field is represented as a `{value, voucher}` map, and
+//! deserialisation fails unless the voucher is valid for the value
+//! under the statically-configured [`CheckingParameters`] of a
+//! [`StaticParameters`] marker type:
+//!
+//! ```
+//! # use raffle::{serde_vouched::StaticParameters, CheckingParameters, Vouched};
+//! struct ConfigKey;
+//!
+//! impl StaticParameters for ConfigKey {
+//!     const CHECKING: CheckingParameters =
+//!         CheckingParameters::parse_or_die("CHECK-0000000000000083-9b791a2755d2d996");
+//! }
+//!
+//! // Defines `config_key::{serialize, deserialize}`.
+//! raffle::serde_vouched_module!(config_key, ConfigKey);
+//!
+//! #[derive(serde::Serialize, serde::Deserialize)]
+//! struct Config {
+//!     #[serde(with = "config_key")]
+//!     max_connections: Vouched<u32>,
+//! }
+//! # fn main() {}
+//! ```
+//!
+//! [`deserialize`] can't infer the marker type, so it can't be named
+//! directly in `#[serde(with = "...")]`; it can still be used with
+//! `deserialize_with = "raffle::serde_vouched::deserialize::<ConfigKey, _, _>"`
+//! (and `serialize_with = "raffle::serde_vouched::serialize"`).
+use serde::Deserialize;
+use serde::Deserializer;
+use serde::Serialize;
+use serde::Serializer;
+
+use crate::CheckingParameters;
+use crate::Vouchable;
+use crate::Vouched;
+use crate::Voucher;
+
+/// Marker types that implement [`StaticParameters`] name the
+/// [`CheckingParameters`] that [`deserialize`] uses.
+pub trait StaticParameters {
+    /// The checking parameters for vouched fields.
+    const CHECKING: CheckingParameters;
+}
+
+#[derive(serde::Serialize)]
+struct PairRef<'a, T> {
+    value: &'a T,
+    voucher: Voucher,
+}
+
+#[derive(serde::Deserialize)]
+struct Pair<T> {
+    value: T,
+    voucher: Voucher,
+}
+
+/// Serialises a [`Vouched`] value as a `{value, voucher}` map.
+pub fn serialize<S, T>(vouched: &Vouched<T>, serializer: S) -> Result<S::Ok, S::Error>
+where
+    S: Serializer,
+    T: Serialize,
+{
+    PairRef {
+        value: vouched.value(),
+        voucher: vouched.voucher(),
+    }
+    .serialize(serializer)
+}
+
+/// Deserialises a `{value, voucher}` map, and fails unless the voucher
+/// is valid for the value under `K::CHECKING`.
+pub fn deserialize<'de, K, D, T>(deserializer: D) -> Result<Vouched<T>, D::Error>
+where
+    K: StaticParameters,
+    D: Deserializer<'de>,
+    T: Deserialize<'de> + Vouchable,
+{
+    let pair = Pair::<T>::deserialize(deserializer)?;
+    Vouched::new(K::CHECKING, pair.value, pair.voucher)
+        .map_err(|_| serde::de::Error::custom("invalid voucher for vouched field"))
+}
+
+/// Defines a module `$name` with `serialize` and `deserialize`
+/// functions for [`Vouched`] fields checked with the
+/// [`StaticParameters`] marker type `$key`, for use with
+/// `#[serde(with = "$name")]`.
+///
+/// See [the module documentation](crate::serde_vouched) for an example.
+#[macro_export]
+macro_rules! serde_vouched_module {
+    ($vis:vis $name:ident, $key:ty $(,)?) => {
+        $vis mod $name {
+            // For `$key`, which is relative to the caller's module.
+            #[allow(unused_imports)]
+            use super::*;
+
+            pub use $crate::serde_vouched::serialize;
+
+            /// Deserialises a `{value, voucher}` map with
+            /// `raffle::serde_vouched::deserialize`.
+            pub fn deserialize<'de, D, T>(deserializer: D) -> ::core::result::Result<$crate::Vouched<T>, D::Error>
+            where
+                D: ::serde::Deserializer<'de>,
+                T: ::serde::Deserialize<'de> + $crate::Vouchable,
+            {
+                $crate::serde_vouched::deserialize::<$key, D, T>(deserializer)
+            }
+        }
+    };
+}
+
+#[cfg(test)]
+struct TestKey;
+
+#[cfg(test)]
+impl StaticParameters for TestKey {
+    const CHECKING: CheckingParameters =
+        CheckingParameters::parse_or_die("CHECK-0000000000000083-9b791a2755d2d996");
+}
+
+#[cfg(test)]
+crate::serde_vouched_module!(test_key, TestKey);
+
+#[cfg(test)]
+#[derive(Debug, PartialEq, serde::Serialize, serde::Deserialize)]
+struct TestConfig {
+    #[serde(with = "test_key")]
+    limit: Vouched<u32>,
+}
+
+#[cfg(test)]
+#[derive(Debug, PartialEq, serde::Serialize, serde::Deserialize)]
+struct ExplicitTestConfig {
+    #[serde(
+        serialize_with = "serialize",
+        deserialize_with = "deserialize::<TestKey, _, _>"
+    )]
+    limit: Vouched<u32>,
+}
+
+#[test]
+fn test_round_trip() {
+    let params = crate::testing::VOUCHING;
+    let config = TestConfig {
+        limit: Vouched::new(TestKey::CHECKING, 1000, params.vouch(1000)).unwrap(),
+    };
+
+    let json = serde_json::to_string(&config).unwrap();
+    assert_eq!(
+        json,
+        format!(
+            r#"{{"limit":{{"value":1000,"voucher":{}}}}}"#,
+            params.vouch(1000).0
+        )
+    );
+    assert_eq!(serde_json::from_str::<TestConfig>(&json).unwrap(), config);
+
+    let explicit = ExplicitTestConfig {
+        limit: config.limit,
+    };
+    assert_eq!(serde_json::to_string(&explicit).unwrap(), json);
+    assert_eq!(
+        serde_json::from_str::<ExplicitTestConfig>(&json).unwrap(),
+        explicit
+    );
+}
+
+#[cfg(not(feature = "insecure-accept-all"))]
+#[test]
+fn test_invalid() {
+    let params = crate::testing::VOUCHING;
+    let json = format!(
+        r#"{{"limit":{{"value":1001,"voucher":{}}}}}"#,
+        params.vouch(1000).0
+    );
+
+    let err = serde_json::from_str::<TestConfig>(&json).unwrap_err();
+    assert!(err
+        .to_string()
+        .contains("invalid voucher for vouched field"));
+}
+
+#[cfg(not(feature = "insecure-accept-all"))]
+#[test]
+fn test_bincode_fixint() {
+    use bincode::Options;
+
+    let params = crate::testing::VOUCHING;
+    let config = TestConfig {
+        limit: Vouched::new(TestKey::CHECKING, 1000, params.vouch(1000)).unwrap(),
+    };
+
+    let options = bincode::DefaultOptions::new()
+        .with_fixint_encoding()
+        .with_little_endian();
+    let bytes = options.serialize(&config).unwrap();
+    // A fixed-size u32 value, followed by the fixed-size voucher.
+    let mut expected = 1000u32.to_le_bytes().to_vec();
+    expected.extend_from_slice(&params.vouch(1000).0.to_le_bytes());
+    assert_eq!(bytes, expected);
+    assert_eq!(options.deserialize::<TestConfig>(&bytes).unwrap(), config);
+
+    // Corrupting the value invalidates the voucher.
+    let mut corrupt = bytes.clone();
+    corrupt[0] ^= 1;
+    assert!(options.deserialize::<TestConfig>(&corrupt).is_err());
+}
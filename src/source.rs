@@ -0,0 +1,162 @@
+//! Pluggable sources of [`CheckingParameters`].
+//!
+//! Code that consumes checking parameters usually shouldn't care
+//! whether they come from an environment variable, a file, or an
+//! external secret store.  The [`ParameterSource`] trait abstracts
+//! over that; this module implements it for the sources the crate
+//! knows how to read on its own, and anything else (Vault, AWS
+//! Secrets Manager, KMS, ...) can implement it outside the crate.
+use std::path::PathBuf;
+
+use crate::CheckingParameters;
+use crate::Error;
+
+/// A source of [`CheckingParameters`].
+///
+/// Each call to [`ParameterSource::load`] should fetch the current
+/// parameters from the source, so callers can call it again to pick up
+/// rotated parameters.
+pub trait ParameterSource {
+    /// Fetches the current [`CheckingParameters`] from the source.
+    fn load(&self) -> Result<CheckingParameters, Error>;
+}
+
+/// Hardcoded parameters are a trivial source.
+impl ParameterSource for CheckingParameters {
+    fn load(&self) -> Result<CheckingParameters, Error> {
+        Ok(*self)
+    }
+}
+
+impl<T: ParameterSource + ?Sized> ParameterSource for &T {
+    fn load(&self) -> Result<CheckingParameters, Error> {
+        (**self).load()
+    }
+}
+
+impl<T: ParameterSource + ?Sized> ParameterSource for Box<T> {
+    fn load(&self) -> Result<CheckingParameters, Error> {
+        (**self).load()
+    }
+}
+
+impl<T: ParameterSource + ?Sized> ParameterSource for std::sync::Arc<T> {
+    fn load(&self) -> Result<CheckingParameters, Error> {
+        (**self).load()
+    }
+}
+
+/// Loads parameters from an environment variable, with
+/// [`CheckingParameters::from_env`].
+#[derive(Clone, Debug, Eq, PartialEq, Hash)]
+pub struct EnvSource {
+    /// Name of the environment variable.
+    pub var: String,
+}
+
+impl EnvSource {
+    /// Returns a source for the environment variable `var`.
+    pub fn new(var: impl Into<String>) -> EnvSource {
+        EnvSource { var: var.into() }
+    }
+}
+
+impl ParameterSource for EnvSource {
+    fn load(&self) -> Result<CheckingParameters, Error> {
+        CheckingParameters::from_env(&self.var)
+    }
+}
+
+/// Loads parameters from a file, with [`CheckingParameters::from_file`].
+#[derive(Clone, Debug, Eq, PartialEq, Hash)]
+pub struct FileSource {
+    /// Path to the file.
+    pub path: PathBuf,
+}
+
+impl FileSource {
+    /// Returns a source for the file at `path`.
+    pub fn new(path: impl Into<PathBuf>) -> FileSource {
+        FileSource { path: path.into() }
+    }
+}
+
+impl ParameterSource for FileSource {
+    fn load(&self) -> Result<CheckingParameters, Error> {
+        CheckingParameters::from_file(&self.path)
+    }
+}
+
+/// Loads parameters from a systemd credential, with
+/// [`CheckingParameters::from_credential`].
+#[derive(Clone, Debug, Eq, PartialEq, Hash)]
+pub struct CredentialSource {
+    /// Name of the credential.
+    pub name: String,
+}
+
+impl CredentialSource {
+    /// Returns a source for the systemd credential `name`.
+    pub fn new(name: impl Into<String>) -> CredentialSource {
+        CredentialSource { name: name.into() }
+    }
+}
+
+impl ParameterSource for CredentialSource {
+    fn load(&self) -> Result<CheckingParameters, Error> {
+        CheckingParameters::from_credential(&self.name)
+    }
+}
+
+#[cfg(test)]
+const SERIAL: &str = "CHECK-0000000000000083-9b791a2755d2d996";
+
+#[test]
+fn test_sources() {
+    let params = CheckingParameters::parse_or_die(SERIAL);
+
+    let path = std::env::temp_dir().join(format!("raffle-source-{}", std::process::id()));
+    std::fs::write(&path, SERIAL).unwrap();
+
+    // Setting an environment variable would race with other tests, so
+    // `EnvSource` only gets the missing variable case below.
+    let sources: Vec<Box<dyn ParameterSource>> =
+        vec![Box::new(params), Box::new(FileSource::new(&path))];
+    for source in &sources {
+        assert_eq!(source.load().unwrap(), params);
+    }
+
+    std::fs::remove_file(&path).unwrap();
+    assert!(matches!(
+        FileSource::new(&path).load(),
+        Err(Error::Io { path: err_path, .. }) if err_path == path
+    ));
+    assert!(matches!(
+        EnvSource::new("RAFFLE_TEST_ENV_SOURCE_MISSING").load(),
+        Err(Error::MissingVariable(_))
+    ));
+}
+
+#[test]
+fn test_custom_source() {
+    struct Counter(std::cell::Cell<u64>);
+
+    impl ParameterSource for Counter {
+        fn load(&self) -> Result<CheckingParameters, Error> {
+            self.0.set(self.0.get() + 1);
+            CheckingParameters::parse(SERIAL).map_err(|reason| Error::Parse {
+                origin: "counter".to_string(),
+                reason,
+            })
+        }
+    }
+
+    let counter = Counter(Default::default());
+    fn load_twice(source: impl ParameterSource) -> CheckingParameters {
+        assert_eq!(source.load().unwrap(), source.load().unwrap());
+        source.load().unwrap()
+    }
+
+    load_twice(&counter);
+    assert_eq!(counter.0.get(), 3);
+}
@@ -0,0 +1,333 @@
+//! SQL support for checking parameters and vouched values.
+//!
+//! This module is only compiled with the `sqlx` or `diesel` features.
+//! Both types are stored as TEXT, in their ASCII formats, so rows stay
+//! readable from `psql` and other tools, and `sqlx` and `diesel` read
+//! each other's rows:
+//!
+//! - [`CheckingParameters`] as their `CHECK-...` string;
+//! - [`VouchedValue`]s as their [`crate::base58`] token, which has a
+//!   checksum, so corrupted rows fail to decode rather than silently
+//!   decoding to another value.
+//!
+//! The `sqlx` implementations are generic over the database, and work
+//! with any driver whose TEXT type maps to [`str`] (e.g., Postgres,
+//! MySQL or SQLite).  The `diesel` implementations cover Postgres, and
+//! SQLite with the `diesel-sqlite` feature.
+#[cfg(feature = "sqlx")]
+use sqlx::encode::IsNull;
+#[cfg(feature = "sqlx")]
+use sqlx::error::BoxDynError;
+#[cfg(feature = "sqlx")]
+use sqlx::Database;
+#[cfg(feature = "sqlx")]
+use sqlx::Decode;
+#[cfg(feature = "sqlx")]
+use sqlx::Encode;
+#[cfg(feature = "sqlx")]
+use sqlx::Type;
+
+use crate::CheckingParameters;
+use crate::Vouchable;
+use crate::Vouched;
+use crate::Voucher;
+
+/// A value (as its [`Vouchable::vouch_value`]) and its voucher, as
+/// stored in a database.
+///
+/// The voucher isn't checked when the row is decoded: convert the
+/// value to a [`Vouched`] value with [`VouchedValue::check`].
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Hash)]
+#[cfg_attr(
+    feature = "diesel",
+    derive(diesel::expression::AsExpression, diesel::deserialize::FromSqlRow)
+)]
+#[cfg_attr(feature = "diesel", diesel(sql_type = diesel::sql_types::Text))]
+pub struct VouchedValue {
+    /// The value's [`Vouchable::vouch_value`].
+    pub value: u64,
+    /// The value's voucher.
+    pub voucher: Voucher,
+}
+
+impl VouchedValue {
+    /// Returns a [`Vouched`] value if the voucher is valid for the value
+    /// under `checking`, and the value otherwise.
+    #[track_caller]
+    pub fn check(&self, checking: CheckingParameters) -> Result<Vouched<u64>, u64> {
+        Vouched::new(checking, self.value, self.voucher)
+    }
+}
+
+impl<T: Vouchable> From<&Vouched<T>> for VouchedValue {
+    fn from(vouched: &Vouched<T>) -> VouchedValue {
+        VouchedValue {
+            value: vouched.value().vouch_value(),
+            voucher: vouched.voucher(),
+        }
+    }
+}
+
+/// Formats the value as its [`crate::base58`] token.
+impl std::fmt::Display for VouchedValue {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(&crate::base58::encode_vouched(self.value, self.voucher))
+    }
+}
+
+impl std::str::FromStr for VouchedValue {
+    type Err = &'static str;
+
+    fn from_str(string: &str) -> Result<VouchedValue, &'static str> {
+        let (value, voucher) = crate::base58::parse_vouched(string)?;
+        Ok(VouchedValue { value, voucher })
+    }
+}
+
+#[cfg(feature = "sqlx")]
+impl<DB: Database> Type<DB> for CheckingParameters
+where
+    str: Type<DB>,
+{
+    fn type_info() -> DB::TypeInfo {
+        <str as Type<DB>>::type_info()
+    }
+
+    fn compatible(ty: &DB::TypeInfo) -> bool {
+        <str as Type<DB>>::compatible(ty)
+    }
+}
+
+#[cfg(feature = "sqlx")]
+impl<'q, DB: Database> Encode<'q, DB> for CheckingParameters
+where
+    String: Encode<'q, DB>,
+{
+    fn encode_by_ref(
+        &self,
+        buf: &mut <DB as Database>::ArgumentBuffer<'q>,
+    ) -> Result<IsNull, BoxDynError> {
+        <String as Encode<'q, DB>>::encode(self.to_string(), buf)
+    }
+}
+
+#[cfg(feature = "sqlx")]
+impl<'r, DB: Database> Decode<'r, DB> for CheckingParameters
+where
+    &'r str: Decode<'r, DB>,
+{
+    fn decode(value: <DB as Database>::ValueRef<'r>) -> Result<Self, BoxDynError> {
+        let string = <&'r str as Decode<'r, DB>>::decode(value)?;
+        Ok(CheckingParameters::parse(string.trim())?)
+    }
+}
+
+#[cfg(feature = "sqlx")]
+impl<DB: Database> Type<DB> for VouchedValue
+where
+    str: Type<DB>,
+{
+    fn type_info() -> DB::TypeInfo {
+        <str as Type<DB>>::type_info()
+    }
+
+    fn compatible(ty: &DB::TypeInfo) -> bool {
+        <str as Type<DB>>::compatible(ty)
+    }
+}
+
+#[cfg(feature = "sqlx")]
+impl<'q, DB: Database> Encode<'q, DB> for VouchedValue
+where
+    String: Encode<'q, DB>,
+{
+    fn encode_by_ref(
+        &self,
+        buf: &mut <DB as Database>::ArgumentBuffer<'q>,
+    ) -> Result<IsNull, BoxDynError> {
+        <String as Encode<'q, DB>>::encode(self.to_string(), buf)
+    }
+}
+
+#[cfg(feature = "sqlx")]
+impl<'r, DB: Database> Decode<'r, DB> for VouchedValue
+where
+    &'r str: Decode<'r, DB>,
+{
+    fn decode(value: <DB as Database>::ValueRef<'r>) -> Result<Self, BoxDynError> {
+        let string = <&'r str as Decode<'r, DB>>::decode(value)?;
+        Ok(string.parse::<VouchedValue>()?)
+    }
+}
+
+/// Implements `diesel`'s `FromSql` and `ToSql` for `$type`, as TEXT
+/// parsed with `$parse`.
+#[cfg(feature = "diesel")]
+macro_rules! impl_diesel_text {
+    ($type:ty, $parse:expr) => {
+        impl<DB> diesel::deserialize::FromSql<diesel::sql_types::Text, DB> for $type
+        where
+            DB: diesel::backend::Backend,
+            String: diesel::deserialize::FromSql<diesel::sql_types::Text, DB>,
+        {
+            fn from_sql(bytes: DB::RawValue<'_>) -> diesel::deserialize::Result<Self> {
+                let string = <String as diesel::deserialize::FromSql<
+                    diesel::sql_types::Text,
+                    DB,
+                >>::from_sql(bytes)?;
+                Ok($parse(string.trim())?)
+            }
+        }
+
+        impl diesel::serialize::ToSql<diesel::sql_types::Text, diesel::pg::Pg> for $type {
+            fn to_sql<'b>(
+                &'b self,
+                out: &mut diesel::serialize::Output<'b, '_, diesel::pg::Pg>,
+            ) -> diesel::serialize::Result {
+                use std::io::Write;
+
+                out.write_all(self.to_string().as_bytes())?;
+                Ok(diesel::serialize::IsNull::No)
+            }
+        }
+
+        #[cfg(feature = "diesel-sqlite")]
+        impl diesel::serialize::ToSql<diesel::sql_types::Text, diesel::sqlite::Sqlite> for $type {
+            fn to_sql<'b>(
+                &'b self,
+                out: &mut diesel::serialize::Output<'b, '_, diesel::sqlite::Sqlite>,
+            ) -> diesel::serialize::Result {
+                out.set_value(self.to_string());
+                Ok(diesel::serialize::IsNull::No)
+            }
+        }
+    };
+}
+
+#[cfg(feature = "diesel")]
+impl_diesel_text!(CheckingParameters, CheckingParameters::parse);
+#[cfg(feature = "diesel")]
+impl_diesel_text!(VouchedValue, str::parse::<VouchedValue>);
+
+#[test]
+fn test_text() {
+    let params = crate::testing::VOUCHING;
+    let checking = params.checking_parameters();
+
+    let vouched = VouchedValue::from(&Vouched::new(checking, 42u64, params.vouch(42)).unwrap());
+    assert_eq!(
+        vouched.to_string(),
+        crate::base58::encode_vouched(42, params.vouch(42))
+    );
+    assert_eq!(vouched.to_string().parse(), Ok(vouched));
+    assert_eq!(vouched.check(checking).map(|v| *v), Ok(42));
+    assert!("garbage".parse::<VouchedValue>().is_err());
+}
+
+#[cfg(feature = "sqlx")]
+#[test]
+fn test_sqlite_round_trip() {
+    use sqlx::Connection;
+
+    let params = crate::testing::VOUCHING;
+    let checking = params.checking_parameters();
+    let vouched = VouchedValue {
+        value: 42,
+        voucher: params.vouch(42),
+    };
+
+    let runtime = tokio::runtime::Builder::new_current_thread()
+        .enable_all()
+        .build()
+        .unwrap();
+    runtime.block_on(async {
+        let mut conn = sqlx::SqliteConnection::connect("sqlite::memory:")
+            .await
+            .unwrap();
+        sqlx::query("CREATE TABLE overrides (checking TEXT, vouched TEXT)")
+            .execute(&mut conn)
+            .await
+            .unwrap();
+        sqlx::query("INSERT INTO overrides VALUES (?, ?)")
+            .bind(checking)
+            .bind(vouched)
+            .execute(&mut conn)
+            .await
+            .unwrap();
+
+        let (text,): (String,) = sqlx::query_as("SELECT vouched FROM overrides")
+            .fetch_one(&mut conn)
+            .await
+            .unwrap();
+        assert_eq!(text, vouched.to_string());
+
+        let (decoded_checking, decoded_vouched): (CheckingParameters, VouchedValue) =
+            sqlx::query_as("SELECT checking, vouched FROM overrides")
+                .fetch_one(&mut conn)
+                .await
+                .unwrap();
+        assert_eq!(decoded_checking, checking);
+        assert_eq!(decoded_vouched, vouched);
+
+        // Corrupted rows fail to decode.
+        let corrupted: Result<(VouchedValue,), _> = sqlx::query_as("SELECT 'CHECK-nope'")
+            .fetch_one(&mut conn)
+            .await;
+        assert!(corrupted.is_err());
+    });
+}
+
+#[cfg(feature = "diesel-sqlite")]
+#[test]
+fn test_diesel_sqlite_round_trip() {
+    use diesel::Connection;
+    use diesel::RunQueryDsl;
+
+    #[derive(diesel::deserialize::QueryableByName)]
+    struct Row {
+        #[diesel(sql_type = diesel::sql_types::Text)]
+        checking: CheckingParameters,
+        #[diesel(sql_type = diesel::sql_types::Text)]
+        vouched: VouchedValue,
+    }
+
+    #[derive(diesel::deserialize::QueryableByName)]
+    struct TextRow {
+        #[diesel(sql_type = diesel::sql_types::Text)]
+        vouched: String,
+    }
+
+    let params = crate::testing::VOUCHING;
+    let checking = params.checking_parameters();
+    let vouched = VouchedValue {
+        value: 42,
+        voucher: params.vouch(42),
+    };
+
+    let mut conn = diesel::SqliteConnection::establish(":memory:").unwrap();
+    diesel::sql_query("CREATE TABLE overrides (checking TEXT, vouched TEXT)")
+        .execute(&mut conn)
+        .unwrap();
+    diesel::sql_query("INSERT INTO overrides VALUES (?, ?)")
+        .bind::<diesel::sql_types::Text, _>(checking)
+        .bind::<diesel::sql_types::Text, _>(vouched)
+        .execute(&mut conn)
+        .unwrap();
+
+    // Same representation as with `sqlx`.
+    let rows: Vec<TextRow> = diesel::sql_query("SELECT vouched FROM overrides")
+        .load(&mut conn)
+        .unwrap();
+    assert_eq!(rows[0].vouched, vouched.to_string());
+
+    let rows: Vec<Row> = diesel::sql_query("SELECT checking, vouched FROM overrides")
+        .load(&mut conn)
+        .unwrap();
+    assert_eq!(rows[0].checking, checking);
+    assert_eq!(rows[0].vouched, vouched);
+
+    let corrupted: Result<Vec<Row>, _> =
+        diesel::sql_query("SELECT checking, 'CHECK-nope' AS vouched FROM overrides")
+            .load(&mut conn);
+    assert!(corrupted.is_err());
+}
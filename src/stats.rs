@@ -0,0 +1,176 @@
+//! `metrics` counters.
+//!
+//! With the `metrics` feature, every audited check (see
+//! [`crate::CheckingParameters::check_audited`]) increments two
+//! counters, both labelled with the `fingerprint` of the checking
+//! parameters:
+//!
+//! - `raffle_checks_total`, for all checks;
+//! - `raffle_check_failures_total`, for failed checks only.
+//!
+//! The fingerprint label tracks usage per key, so a dashboard can show
+//! when traffic moves to a new key, or when a service suddenly rejects
+//! vouchers after a botched rotation.  Without the feature, this
+//! module compiles to nothing.
+use crate::Fingerprint;
+
+// Counter for all audited checks.
+#[cfg(feature = "metrics")]
+const CHECKS_TOTAL: &str = "raffle_checks_total";
+
+// Counter for failed audited checks.
+#[cfg(feature = "metrics")]
+const CHECK_FAILURES_TOTAL: &str = "raffle_check_failures_total";
+
+/// Counts one check with the parameters for `fingerprint`.
+#[inline(always)]
+pub(crate) fn checked(fingerprint: Fingerprint, valid: bool) {
+    #[cfg(feature = "metrics")]
+    {
+        let label = fingerprint_label(fingerprint);
+        if !valid {
+            metrics::counter!(CHECK_FAILURES_TOTAL, "fingerprint" => label.clone()).increment(1);
+        }
+
+        metrics::counter!(CHECKS_TOTAL, "fingerprint" => label).increment(1);
+    }
+
+    let _ = (fingerprint, valid);
+}
+
+/// Returns the `fingerprint` label for `fingerprint`.
+///
+/// Checks almost always use the same parameters as the previous check
+/// on the same thread, so each thread caches its last label rather
+/// than formatting one for every check.
+#[cfg(feature = "metrics")]
+fn fingerprint_label(fingerprint: Fingerprint) -> metrics::SharedString {
+    use std::cell::RefCell;
+
+    thread_local! {
+        static LAST_LABEL: RefCell<Option<(Fingerprint, metrics::SharedString)>> =
+            const { RefCell::new(None) };
+    }
+
+    LAST_LABEL.with_borrow_mut(|last| match last {
+        Some((cached, label)) if *cached == fingerprint => label.clone(),
+        _ => {
+            let label: std::sync::Arc<str> = fingerprint.to_string().into();
+            let label = metrics::SharedString::from(label);
+            *last = Some((fingerprint, label.clone()));
+            label
+        }
+    })
+}
+
+#[cfg(all(test, feature = "metrics", not(feature = "insecure-accept-all")))]
+mod test {
+    use std::collections::BTreeMap;
+    use std::sync::atomic::AtomicU64;
+    use std::sync::Arc;
+    use std::sync::Mutex;
+
+    /// Records counters as `name{label=value,...}`.
+    #[derive(Default)]
+    pub struct Counters(pub Mutex<BTreeMap<String, Arc<AtomicU64>>>);
+
+    impl metrics::Recorder for Counters {
+        fn describe_counter(
+            &self,
+            _key: metrics::KeyName,
+            _unit: Option<metrics::Unit>,
+            _description: metrics::SharedString,
+        ) {
+        }
+
+        fn describe_gauge(
+            &self,
+            _key: metrics::KeyName,
+            _unit: Option<metrics::Unit>,
+            _description: metrics::SharedString,
+        ) {
+        }
+
+        fn describe_histogram(
+            &self,
+            _key: metrics::KeyName,
+            _unit: Option<metrics::Unit>,
+            _description: metrics::SharedString,
+        ) {
+        }
+
+        fn register_counter(
+            &self,
+            key: &metrics::Key,
+            _metadata: &metrics::Metadata<'_>,
+        ) -> metrics::Counter {
+            let labels: Vec<String> = key
+                .labels()
+                .map(|label| format!("{}={}", label.key(), label.value()))
+                .collect();
+            let name = format!("{}{{{}}}", key.name(), labels.join(","));
+            let counter = self.0.lock().unwrap().entry(name).or_default().clone();
+            metrics::Counter::from_arc(counter)
+        }
+
+        fn register_gauge(
+            &self,
+            _key: &metrics::Key,
+            _metadata: &metrics::Metadata<'_>,
+        ) -> metrics::Gauge {
+            metrics::Gauge::noop()
+        }
+
+        fn register_histogram(
+            &self,
+            _key: &metrics::Key,
+            _metadata: &metrics::Metadata<'_>,
+        ) -> metrics::Histogram {
+            metrics::Histogram::noop()
+        }
+    }
+}
+
+#[cfg(all(feature = "metrics", not(feature = "insecure-accept-all")))]
+#[test]
+fn test_counters() {
+    use std::sync::atomic::Ordering;
+
+    let params = crate::testing::VOUCHING;
+    let other = crate::testing::OTHER;
+    let counters = test::Counters::default();
+
+    metrics::with_local_recorder(&counters, || {
+        assert!(params
+            .checking_parameters()
+            .check_audited(42, params.vouch(42)));
+        assert!(!params
+            .checking_parameters()
+            .check_audited(42, params.vouch(43)));
+        assert!(other
+            .checking_parameters()
+            .check_audited(42, other.vouch(42)));
+        // Plain checks aren't counted.
+        assert!(params.checking_parameters().check(42, params.vouch(42)));
+    });
+
+    let counts: Vec<(String, u64)> = counters
+        .0
+        .lock()
+        .unwrap()
+        .iter()
+        .map(|(name, count)| (name.clone(), count.load(Ordering::Relaxed)))
+        .collect();
+    let expected = [
+        (
+            "raffle_check_failures_total{fingerprint=5d5e18aad84a57df}",
+            1,
+        ),
+        ("raffle_checks_total{fingerprint=5d5e18aad84a57df}", 2),
+        ("raffle_checks_total{fingerprint=7d15181d5e236243}", 1),
+    ];
+    assert_eq!(
+        counts,
+        expected.map(|(name, count)| (name.to_string(), count))
+    );
+}
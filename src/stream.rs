@@ -0,0 +1,255 @@
+//! Push-based parsing for parameters that arrive in chunks.
+//!
+//! Network protocols may split a parameter string across reads.  A
+//! [`ParamsParser`] accepts bytes in arbitrary chunks, rejects bad
+//! input as soon as it sees it, and returns the parsed parameters once
+//! it has all the bytes, along with the number of bytes it consumed
+//! from the last chunk (the rest belongs to whatever follows):
+//!
+//! ```
+//! # use raffle::stream::{Parsed, ParamsParser, Progress};
+//! # use raffle::CheckingParameters;
+//! let mut parser = ParamsParser::new();
+//! assert_eq!(parser.push(b"CHECK-0000000000"), Ok(Progress::NeedMore));
+//! assert_eq!(
+//!     parser.push(b"000083-9b791a2755d2d996\nnext"),
+//!     Ok(Progress::Done {
+//!         parsed: Parsed::Checking(CheckingParameters::parse_or_die(
+//!             "CHECK-0000000000000083-9b791a2755d2d996"
+//!         )),
+//!         consumed: 23,
+//!     })
+//! );
+//! ```
+use crate::CheckingParameters;
+use crate::VouchingParameters;
+
+const PREFIX_LEN: usize = 6;
+
+/// Checking or vouching parameters, parsed from input that may hold
+/// either, e.g., by a [`ParamsParser`].
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Hash)]
+pub enum Parsed {
+    /// A `CHECK-...` string.
+    Checking(CheckingParameters),
+    /// A `VOUCH-...` string.
+    Vouching(VouchingParameters),
+}
+
+impl Parsed {
+    /// Parses a `CHECK-...` or `VOUCH-...` string.
+    pub fn parse(string: &str) -> Result<Parsed, &'static str> {
+        if string.starts_with("VOUCH-") {
+            VouchingParameters::parse(string).map(Parsed::Vouching)
+        } else {
+            CheckingParameters::parse(string).map(Parsed::Checking)
+        }
+    }
+
+    /// Returns the checking parameters, or the checking half of the
+    /// vouching parameters.
+    #[must_use]
+    pub fn checking_parameters(&self) -> CheckingParameters {
+        match self {
+            Parsed::Checking(checking) => *checking,
+            Parsed::Vouching(vouching) => vouching.checking_parameters(),
+        }
+    }
+}
+
+/// The result of a successful [`ParamsParser::push`].
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Hash)]
+pub enum Progress {
+    /// All bytes were consumed, and the parameters aren't complete yet.
+    NeedMore,
+    /// The parameters are complete.
+    Done {
+        /// The parameters.
+        parsed: Parsed,
+        /// The number of bytes consumed from the last chunk.
+        consumed: usize,
+    },
+}
+
+/// Incrementally parses a `CHECK-...` or `VOUCH-...` string.
+///
+/// After it returns a result (parameters or an error), the parser is
+/// reset, and ready to parse another string.
+#[derive(Clone, Debug)]
+pub struct ParamsParser {
+    buf: [u8; VouchingParameters::REPRESENTATION_BYTE_COUNT],
+    len: usize,
+}
+
+impl Default for ParamsParser {
+    fn default() -> ParamsParser {
+        ParamsParser::new()
+    }
+}
+
+impl ParamsParser {
+    /// Returns a parser that hasn't seen any byte yet.
+    #[must_use]
+    pub const fn new() -> ParamsParser {
+        ParamsParser {
+            buf: [0u8; VouchingParameters::REPRESENTATION_BYTE_COUNT],
+            len: 0,
+        }
+    }
+
+    /// Returns the number of bytes buffered for the current string.
+    #[must_use]
+    pub fn buffered(&self) -> usize {
+        self.len
+    }
+
+    /// Discards any buffered byte.
+    pub fn reset(&mut self) {
+        self.len = 0;
+    }
+
+    /// Returns the total length of the current string, once the prefix is known.
+    fn expected_len(&self) -> Option<usize> {
+        if self.len < PREFIX_LEN {
+            None
+        } else if self.buf[0] == b'C' {
+            Some(CheckingParameters::REPRESENTATION_BYTE_COUNT)
+        } else {
+            Some(VouchingParameters::REPRESENTATION_BYTE_COUNT)
+        }
+    }
+
+    /// Checks the byte at `self.buf[self.len]`, given the bytes before it.
+    fn check_byte(&self) -> Result<(), &'static str> {
+        let index = self.len;
+        let byte = self.buf[index];
+
+        if index < PREFIX_LEN {
+            let matches =
+                |prefix: &[u8]| prefix[..index] == self.buf[..index] && prefix[index] == byte;
+            return if matches(b"CHECK-") || matches(b"VOUCH-") {
+                Ok(())
+            } else {
+                Err("Incorrect prefix for raffle parameters. Expected CHECK- or VOUCH-")
+            };
+        }
+
+        // Fields are 16 hex digits, separated by dashes.
+        if (index - PREFIX_LEN) % 17 == 16 {
+            if byte == b'-' {
+                Ok(())
+            } else {
+                Err("Missing dash separator in raffle parameters")
+            }
+        } else if byte.is_ascii_hexdigit() {
+            Ok(())
+        } else {
+            Err("Invalid hex digit in raffle parameters")
+        }
+    }
+
+    /// Feeds `chunk` to the parser.
+    ///
+    /// Returns [`Progress::NeedMore`] when `chunk` was consumed and
+    /// the string is still incomplete, [`Progress::Done`] once the
+    /// string is complete, and an error as soon as the input can't be
+    /// valid parameters.  The parser is reset after `Done` or an error.
+    pub fn push(&mut self, chunk: &[u8]) -> Result<Progress, &'static str> {
+        for (consumed, &byte) in chunk.iter().enumerate() {
+            self.buf[self.len] = byte;
+            if let Err(e) = self.check_byte() {
+                self.reset();
+                return Err(e);
+            }
+
+            self.len += 1;
+            if self.expected_len() == Some(self.len) {
+                let bytes = &self.buf[..self.len];
+                let parsed = if self.len == CheckingParameters::REPRESENTATION_BYTE_COUNT {
+                    CheckingParameters::parse_bytes(bytes).map(Parsed::Checking)
+                } else {
+                    VouchingParameters::parse_bytes(bytes).map(Parsed::Vouching)
+                };
+
+                self.reset();
+                return parsed.map(|parsed| Progress::Done {
+                    parsed,
+                    consumed: consumed + 1,
+                });
+            }
+        }
+
+        Ok(Progress::NeedMore)
+    }
+}
+
+#[test]
+fn test_chunks() {
+    let params = crate::testing::VOUCHING;
+    let checking = params.checking_parameters();
+
+    for (string, expected) in [
+        (checking.to_string(), Parsed::Checking(checking)),
+        (params.to_string(), Parsed::Vouching(params)),
+        (format!("{:X}", params), Parsed::Vouching(params)),
+    ] {
+        for chunk_size in [1, 2, 5, 16, 38, 100] {
+            let mut parser = ParamsParser::new();
+            let mut result = None;
+            for chunk in string.as_bytes().chunks(chunk_size) {
+                assert_eq!(result, None);
+                match parser.push(chunk).unwrap() {
+                    Progress::NeedMore => {}
+                    Progress::Done { parsed, consumed } => {
+                        assert_eq!(consumed, chunk.len());
+                        result = Some(parsed);
+                    }
+                }
+            }
+
+            assert_eq!(result, Some(expected));
+            assert_eq!(parser.buffered(), 0);
+        }
+    }
+}
+
+#[test]
+fn test_trailing_bytes() {
+    let params = crate::testing::VOUCHING;
+    let input = format!("{}{}", params, params.checking_parameters());
+
+    let mut parser = ParamsParser::new();
+    assert_eq!(
+        parser.push(input.as_bytes()),
+        Ok(Progress::Done {
+            parsed: Parsed::Vouching(params),
+            consumed: VouchingParameters::REPRESENTATION_BYTE_COUNT,
+        })
+    );
+    assert_eq!(
+        parser.push(&input.as_bytes()[VouchingParameters::REPRESENTATION_BYTE_COUNT..]),
+        Ok(Progress::Done {
+            parsed: Parsed::Checking(params.checking_parameters()),
+            consumed: CheckingParameters::REPRESENTATION_BYTE_COUNT,
+        })
+    );
+}
+
+#[test]
+fn test_early_errors() {
+    let mut parser = ParamsParser::new();
+    assert_eq!(parser.push(b"CH"), Ok(Progress::NeedMore));
+    assert!(parser.push(b"x").is_err());
+    assert_eq!(parser.buffered(), 0);
+
+    assert!(parser.push(b"CHECK-00000000000000830").is_err());
+    assert!(parser.push(b"VOUCH-000000000000000g").is_err());
+
+    // Syntactically valid but inconsistent parameters fail at the end.
+    let params = crate::testing::VOUCHING;
+    let mut inconsistent = params.to_string().into_bytes();
+    inconsistent[6] = if inconsistent[6] == b'0' { b'1' } else { b'0' };
+    assert_eq!(parser.push(&inconsistent[..70]), Ok(Progress::NeedMore));
+    assert!(parser.push(&inconsistent[70..]).is_err());
+    assert_eq!(parser.buffered(), 0);
+}
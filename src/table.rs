@@ -0,0 +1,816 @@
+//! Voucher tables: sequences of `(value, voucher)` entries, each vouched
+//! for independently with [`crate::VouchingParameters::vouch`].
+//!
+//! Tables can be stored in two formats:
+//!
+//! - [`TableFormat::Csv`], one `value,voucher` entry per line, each as
+//!   16 hex digits (e.g., `000000000000002a,9f2b3c4d5e6f7081`).  Blank
+//!   lines and lines that start with `#` are ignored.
+//! - [`TableFormat::Rafl`], a binary format: the 8-byte header
+//!   `RAFL\x01\0\0\0` (magic and little-endian version 1), followed by
+//!   16 bytes per entry, the value and then the voucher, both as
+//!   little-endian [`u64`]s.
+//!
+//! Large RAFL tables can be checked in place with [`verify_rafl`], or,
+//! with the `mmap` feature, memory-mapped with `MappedTable`.  Tables
+//! embedded in the binary can be wrapped in a [`StaticTable`], which
+//! only checks the entries that are actually looked up.  Tables for
+//! every value in a contiguous range are best represented as a
+//! [`RangeTable`], in constant space.
+use std::io::BufRead;
+use std::io::Write;
+
+use crate::constparse::parse_hex;
+use crate::Voucher;
+
+/// Header for [`TableFormat::Rafl`] tables.
+pub const RAFL_HEADER: [u8; 8] = *b"RAFL\x01\0\0\0";
+
+/// Number of bytes for each entry in a [`TableFormat::Rafl`] table.
+pub const RAFL_ENTRY_BYTE_COUNT: usize = 16;
+
+/// The serialisation format for a voucher table.
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Hash)]
+pub enum TableFormat {
+    /// Text, one hex `value,voucher` pair per line.
+    Csv,
+    /// Binary, with a fixed header and 16 bytes per entry.
+    Rafl,
+}
+
+impl TableFormat {
+    /// Guesses the format from a file name: `.rafl` files are
+    /// [`TableFormat::Rafl`], everything else is [`TableFormat::Csv`].
+    #[must_use]
+    pub fn from_path(path: &std::path::Path) -> TableFormat {
+        match path.extension() {
+            Some(ext) if ext.eq_ignore_ascii_case("rafl") => TableFormat::Rafl,
+            _ => TableFormat::Csv,
+        }
+    }
+}
+
+fn invalid_data(message: String) -> std::io::Error {
+    std::io::Error::new(std::io::ErrorKind::InvalidData, message)
+}
+
+/// Parses one `value,voucher` line of a [`TableFormat::Csv`] table.
+///
+/// Returns `Ok(None)` for blank and comment lines.
+pub fn parse_csv_line(line: &str) -> Result<Option<(u64, Voucher)>, &'static str> {
+    let line = line.trim();
+    if line.is_empty() || line.starts_with('#') {
+        return Ok(None);
+    }
+
+    let bytes = line.as_bytes();
+    if bytes.len() != 33 {
+        return Err("Expected two 16-digit hex numbers separated by a comma");
+    }
+
+    let Some(value) = parse_hex(bytes, 0) else {
+        return Err("Failed to parse hex value");
+    };
+
+    if bytes[16] != b',' {
+        return Err("Missing comma separator after value");
+    }
+
+    let Some(voucher) = parse_hex(bytes, 17) else {
+        return Err("Failed to parse hex voucher");
+    };
+
+    Ok(Some((value, Voucher(voucher))))
+}
+
+/// Parses a [`TableFormat::Rafl`] entry.
+#[must_use]
+pub fn parse_rafl_entry(bytes: &[u8; RAFL_ENTRY_BYTE_COUNT]) -> (u64, Voucher) {
+    let (value, voucher) = bytes.split_at(8);
+    (
+        u64::from_le_bytes(value.try_into().expect("8 bytes")),
+        Voucher(u64::from_le_bytes(voucher.try_into().expect("8 bytes"))),
+    )
+}
+
+/// Streams the entries in a voucher table.
+pub struct TableReader<R> {
+    reader: R,
+    format: TableFormat,
+    // Line number for CSV, entry index for RAFL.
+    position: u64,
+    line: String,
+}
+
+impl<R: BufRead> TableReader<R> {
+    /// Returns a reader for the table in `reader`.  For
+    /// [`TableFormat::Rafl`], this function reads and checks the header.
+    pub fn new(mut reader: R, format: TableFormat) -> std::io::Result<TableReader<R>> {
+        if format == TableFormat::Rafl {
+            let mut header = [0u8; RAFL_HEADER.len()];
+            reader.read_exact(&mut header)?;
+            if header != RAFL_HEADER {
+                return Err(invalid_data("Incorrect header for RAFL table".to_string()));
+            }
+        }
+
+        Ok(TableReader {
+            reader,
+            format,
+            position: 0,
+            line: String::new(),
+        })
+    }
+
+    /// Returns the next entry, `None` at the end of the table, or an error.
+    pub fn next_entry(&mut self) -> std::io::Result<Option<(u64, Voucher)>> {
+        match self.format {
+            TableFormat::Csv => loop {
+                self.line.clear();
+                if self.reader.read_line(&mut self.line)? == 0 {
+                    return Ok(None);
+                }
+
+                self.position += 1;
+                match parse_csv_line(&self.line) {
+                    Ok(None) => continue,
+                    Ok(Some(entry)) => return Ok(Some(entry)),
+                    Err(e) => return Err(invalid_data(format!("line {}: {}", self.position, e))),
+                }
+            },
+            TableFormat::Rafl => {
+                let mut buf = [0u8; RAFL_ENTRY_BYTE_COUNT];
+                let mut filled = 0;
+                while filled < buf.len() {
+                    match self.reader.read(&mut buf[filled..]) {
+                        Ok(0) => break,
+                        Ok(n) => filled += n,
+                        Err(e) if e.kind() == std::io::ErrorKind::Interrupted => continue,
+                        Err(e) => return Err(e),
+                    }
+                }
+
+                match filled {
+                    0 => Ok(None),
+                    RAFL_ENTRY_BYTE_COUNT => {
+                        self.position += 1;
+                        Ok(Some(parse_rafl_entry(&buf)))
+                    }
+                    _ => Err(invalid_data(format!(
+                        "truncated RAFL entry at index {}",
+                        self.position
+                    ))),
+                }
+            }
+        }
+    }
+}
+
+impl<R: BufRead> Iterator for TableReader<R> {
+    type Item = std::io::Result<(u64, Voucher)>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.next_entry().transpose()
+    }
+}
+
+/// Writes entries to a voucher table.
+pub struct TableWriter<W> {
+    writer: W,
+    format: TableFormat,
+}
+
+impl<W: Write> TableWriter<W> {
+    /// Returns a writer for a table in `writer`.  For
+    /// [`TableFormat::Rafl`], this function immediately writes the header.
+    pub fn new(mut writer: W, format: TableFormat) -> std::io::Result<TableWriter<W>> {
+        if format == TableFormat::Rafl {
+            writer.write_all(&RAFL_HEADER)?;
+        }
+
+        Ok(TableWriter { writer, format })
+    }
+
+    /// Appends one entry to the table.
+    pub fn write_entry(&mut self, value: u64, voucher: Voucher) -> std::io::Result<()> {
+        match self.format {
+            TableFormat::Csv => writeln!(self.writer, "{:016x},{:016x}", value, voucher.0),
+            TableFormat::Rafl => {
+                let mut buf = [0u8; RAFL_ENTRY_BYTE_COUNT];
+                buf[..8].copy_from_slice(&value.to_le_bytes());
+                buf[8..].copy_from_slice(&voucher.0.to_le_bytes());
+                self.writer.write_all(&buf)
+            }
+        }
+    }
+
+    /// Flushes and returns the underlying writer.
+    pub fn finish(mut self) -> std::io::Result<W> {
+        self.writer.flush()?;
+        Ok(self.writer)
+    }
+}
+
+/// Reads all the entries in the table in `reader`, with a [`TableReader`].
+///
+/// `reader` doesn't have to be buffered, and may return partial reads.
+pub fn read_from(
+    reader: impl std::io::Read,
+    format: TableFormat,
+) -> std::io::Result<Vec<(u64, Voucher)>> {
+    TableReader::new(std::io::BufReader::new(reader), format)?.collect()
+}
+
+/// Writes `entries` as a complete table to `writer`, with a
+/// [`TableWriter`], and flushes `writer`.
+pub fn write_to(
+    writer: impl Write,
+    format: TableFormat,
+    entries: impl IntoIterator<Item = (u64, Voucher)>,
+) -> std::io::Result<()> {
+    let mut writer = TableWriter::new(std::io::BufWriter::new(writer), format)?;
+    for (value, voucher) in entries {
+        writer.write_entry(value, voucher)?;
+    }
+
+    writer.finish().map(drop)
+}
+
+/// Returns the entries in `bytes`, a complete [`TableFormat::Rafl`]
+/// table (header included), without the header.
+fn rafl_payload(bytes: &[u8]) -> std::io::Result<&[u8]> {
+    let Some(entries) = bytes.strip_prefix(&RAFL_HEADER[..]) else {
+        return Err(invalid_data("Incorrect header for RAFL table".to_string()));
+    };
+
+    if entries.len() % RAFL_ENTRY_BYTE_COUNT != 0 {
+        return Err(invalid_data(format!(
+            "truncated RAFL entry at index {}",
+            entries.len() / RAFL_ENTRY_BYTE_COUNT
+        )));
+    }
+
+    Ok(entries)
+}
+
+/// Returns the entries in `bytes`, a complete [`TableFormat::Rafl`]
+/// table (header included), as an iterator over 16-byte entries.
+///
+/// The iterator knows its length, and skips to any index in constant
+/// time (with [`Iterator::nth`]).
+pub fn rafl_entries(bytes: &[u8]) -> std::io::Result<std::slice::ChunksExact<'_, u8>> {
+    Ok(rafl_payload(bytes)?.chunks_exact(RAFL_ENTRY_BYTE_COUNT))
+}
+
+/// Parses an entry from [`rafl_entries`].
+fn parse_rafl_chunk(chunk: &[u8]) -> (u64, Voucher) {
+    parse_rafl_entry(chunk.try_into().expect("16-byte chunks"))
+}
+
+/// Checks every entry in `bytes`, a complete [`TableFormat::Rafl`]
+/// table, against `checking`, with `threads` worker threads.
+///
+/// Returns the (sorted) indices of the invalid entries on success, and
+/// an error if `bytes` isn't a well-formed RAFL table.
+#[cfg_attr(
+    feature = "tracing",
+    tracing::instrument(
+        level = "debug",
+        target = "raffle",
+        skip_all,
+        fields(fingerprint = %checking.fingerprint(), bytes = bytes.len(), threads)
+    )
+)]
+pub fn verify_rafl(
+    bytes: &[u8],
+    checking: crate::CheckingParameters,
+    threads: usize,
+) -> std::io::Result<Vec<usize>> {
+    let entries = rafl_payload(bytes)?;
+    let count = entries.len() / RAFL_ENTRY_BYTE_COUNT;
+    let chunk_size = count.div_ceil(threads.max(1)).max(1);
+
+    Ok(std::thread::scope(|scope| {
+        let workers: Vec<_> = entries
+            .chunks(chunk_size * RAFL_ENTRY_BYTE_COUNT)
+            .enumerate()
+            .map(|(chunk_index, chunk)| {
+                scope.spawn(move || {
+                    chunk
+                        .chunks_exact(RAFL_ENTRY_BYTE_COUNT)
+                        .enumerate()
+                        .filter(|(_, entry)| {
+                            let (value, voucher) = parse_rafl_chunk(entry);
+                            !checking.check(value, voucher)
+                        })
+                        .map(|(index, _)| chunk_index * chunk_size + index)
+                        .collect::<Vec<usize>>()
+                })
+            })
+            .collect();
+
+        workers
+            .into_iter()
+            .flat_map(|worker| worker.join().expect("verifier should not panic"))
+            .collect()
+    }))
+}
+
+/// A read-only memory-mapped [`TableFormat::Rafl`] table.
+///
+/// This type is only available with the `mmap` feature.  It lets us
+/// check individual entries lazily, or sweep the whole table with
+/// [`MappedTable::verify`], without reading the table into memory.
+#[cfg(feature = "mmap")]
+pub struct MappedTable {
+    map: memmap2::Mmap,
+}
+
+#[cfg(feature = "mmap")]
+impl MappedTable {
+    /// Maps the RAFL table at `path`, and checks its header and size.
+    pub fn open(path: impl AsRef<std::path::Path>) -> std::io::Result<MappedTable> {
+        let file = std::fs::File::open(path)?;
+        // SAFETY: the mapping is read-only, and we only ever look at
+        // it as plain bytes.  Concurrent modifications of the file may
+        // yield garbage entries, but can't cause undefined behaviour
+        // beyond what the OS already exposes for `MAP_SHARED` mappings.
+        let map = unsafe { memmap2::Mmap::map(&file)? };
+        rafl_payload(&map)?;
+        Ok(MappedTable { map })
+    }
+
+    fn entries(&self) -> std::slice::ChunksExact<'_, u8> {
+        rafl_entries(&self.map).expect("validated in `open`")
+    }
+
+    /// Returns the number of entries in the table.
+    #[must_use]
+    pub fn len(&self) -> usize {
+        self.entries().len()
+    }
+
+    /// Returns whether the table has no entry.
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Returns the entry at `index`, if any.
+    #[must_use]
+    pub fn get(&self, index: usize) -> Option<(u64, Voucher)> {
+        self.entries().nth(index).map(parse_rafl_chunk)
+    }
+
+    /// Returns an iterator over all the entries in the table.
+    pub fn iter(&self) -> impl Iterator<Item = (u64, Voucher)> + '_ {
+        self.entries().map(parse_rafl_chunk)
+    }
+
+    /// Returns whether the entry at `index` is valid for `checking`,
+    /// or `None` if `index` is out of bounds.
+    #[must_use]
+    pub fn check_entry(&self, checking: crate::CheckingParameters, index: usize) -> Option<bool> {
+        self.get(index)
+            .map(|(value, voucher)| checking.check(value, voucher))
+    }
+
+    /// Checks every entry against `checking` with `threads` worker
+    /// threads, and returns the (sorted) indices of invalid entries.
+    #[must_use]
+    pub fn verify(&self, checking: crate::CheckingParameters, threads: usize) -> Vec<usize> {
+        verify_rafl(&self.map, checking, threads).expect("validated in `open`")
+    }
+}
+
+/// A `static` table of `(value, voucher)` entries, sorted by value,
+/// that checks each entry the first time it's looked up.
+///
+/// Binaries may embed large vouched lookup tables of which they only
+/// ever use a few entries; a [`StaticTable`] doesn't pay to check the
+/// rest.  The result of each check is cached, so repeated lookups are
+/// a binary search and an atomic load.  Tables must be declared as
+/// `static`s (not `const`s) for the cache to be shared:
+///
+/// ```
+/// # use raffle::{table::StaticTable, Voucher, VouchingParameters};
+/// # const VOUCHING: VouchingParameters = VouchingParameters::parse_or_die(
+/// #     "VOUCH-b4b0de979c8a90a9-676e696863756fd5-0000000000000083-9b791a2755d2d996");
+/// static ENTRIES: [(u64, Voucher); 2] = [(1, VOUCHING.vouch(1)), (2, VOUCHING.vouch(3))];
+/// static LIMITS: StaticTable<2> = StaticTable::new(VOUCHING.checking_parameters(), &ENTRIES);
+///
+/// assert_eq!(LIMITS.get(1), Some(VOUCHING.vouch(1)));
+/// # #[cfg(not(feature = "insecure-accept-all"))]
+/// assert!(!LIMITS.contains(2)); // Invalid voucher.
+/// ```
+pub struct StaticTable<const N: usize> {
+    checking: crate::CheckingParameters,
+    entries: &'static [(u64, Voucher); N],
+    /// One of the `STATE_*` constants for each entry.
+    states: [std::sync::atomic::AtomicU8; N],
+}
+
+const STATE_UNKNOWN: u8 = 0;
+const STATE_VALID: u8 = 1;
+const STATE_INVALID: u8 = 2;
+
+impl<const N: usize> StaticTable<N> {
+    /// Wraps `entries`, to be checked against `checking`.
+    ///
+    /// Panics (at compile time, in `static` initialisers) unless the
+    /// entries are sorted by strictly increasing value.
+    pub const fn new(
+        checking: crate::CheckingParameters,
+        entries: &'static [(u64, Voucher); N],
+    ) -> StaticTable<N> {
+        let mut i = 1;
+        while i < N {
+            assert!(
+                entries[i - 1].0 < entries[i].0,
+                "StaticTable entries must be sorted by strictly increasing value"
+            );
+            i += 1;
+        }
+
+        StaticTable {
+            checking,
+            entries,
+            states: [const { std::sync::atomic::AtomicU8::new(STATE_UNKNOWN) }; N],
+        }
+    }
+
+    /// Returns the number of entries in the table, valid or not.
+    #[must_use]
+    pub const fn len(&self) -> usize {
+        N
+    }
+
+    /// Returns whether the table has no entry.
+    #[must_use]
+    pub const fn is_empty(&self) -> bool {
+        N == 0
+    }
+
+    /// Returns the voucher for `value`, if the table has an entry for
+    /// `value` and its voucher is valid.
+    ///
+    /// The first lookup for each entry checks its voucher with
+    /// [`crate::CheckingParameters::check_audited`].
+    #[must_use]
+    #[track_caller]
+    pub fn get(&self, value: u64) -> Option<Voucher> {
+        use std::sync::atomic::Ordering;
+
+        let index = self
+            .entries
+            .binary_search_by_key(&value, |(value, _)| *value)
+            .ok()?;
+        let voucher = self.entries[index].1;
+        let state = match self.states[index].load(Ordering::Relaxed) {
+            STATE_UNKNOWN => {
+                // Racing threads compute the same result.
+                let state = if self.checking.check_audited(value, voucher) {
+                    STATE_VALID
+                } else {
+                    STATE_INVALID
+                };
+                self.states[index].store(state, Ordering::Relaxed);
+                state
+            }
+            state => state,
+        };
+
+        (state == STATE_VALID).then_some(voucher)
+    }
+
+    /// Returns whether the table has a valid entry for `value`.
+    #[must_use]
+    #[track_caller]
+    pub fn contains(&self, value: u64) -> bool {
+        self.get(value).is_some()
+    }
+
+    /// Returns the number of entries that have been checked so far.
+    #[must_use]
+    pub fn checked_count(&self) -> usize {
+        self.states
+            .iter()
+            .filter(|state| state.load(std::sync::atomic::Ordering::Relaxed) != STATE_UNKNOWN)
+            .count()
+    }
+}
+
+/// Vouchers for every value in a contiguous range, with O(1) lookups.
+///
+/// Vouchers are an affine function of the value, so the difference
+/// between the vouchers of consecutive values is the same everywhere.
+/// Delta-compressing the table thus leaves only the first voucher and
+/// that difference: the table takes constant space, whatever the size
+/// of the range, and [`RangeTable::get`] is a multiply-add.
+///
+/// A [`RangeTable`] is secret material.  Like any table with vouchers
+/// for consecutive values (compressed or not), it can be extrapolated
+/// to vouch for values outside the range: it's exactly as sensitive as
+/// the [`crate::VouchingParameters`] themselves, and must be protected
+/// the same way.  It's meant for processes that must vouch for every
+/// value in `0..N` at runtime, without holding the parameters in their
+/// usual form.  Its [`Debug`](std::fmt::Debug) output only shows the
+/// range, and it doesn't implement equality or hashing, which wouldn't
+/// run in constant time.
+#[derive(Clone)]
+pub struct RangeTable {
+    range: std::ops::Range<u64>,
+    first: Voucher,
+    delta: u64,
+}
+
+impl RangeTable {
+    /// Precomputes the vouchers for every value in `range` with `vouching`.
+    #[must_use]
+    pub fn new(vouching: &crate::VouchingParameters, range: std::ops::Range<u64>) -> RangeTable {
+        let first = vouching.vouch(range.start);
+        let delta = vouching
+            .vouch(range.start.wrapping_add(1))
+            .0
+            .wrapping_sub(first.0);
+        let table = RangeTable {
+            range,
+            first,
+            delta,
+        };
+
+        if !table.range.is_empty() {
+            let last = table.range.end - 1;
+            assert_eq!(
+                table.get(last),
+                Some(vouching.vouch(last)),
+                "vouchers must be affine in the value"
+            );
+        }
+
+        table
+    }
+
+    /// Returns the range of values in the table.
+    #[must_use]
+    pub fn range(&self) -> std::ops::Range<u64> {
+        self.range.clone()
+    }
+
+    /// Returns the voucher for `value`, if it's in the table's range.
+    #[must_use]
+    #[inline]
+    pub fn get(&self, value: u64) -> Option<Voucher> {
+        self.range.contains(&value).then(|| {
+            let index = value - self.range.start;
+            Voucher(self.first.0.wrapping_add(index.wrapping_mul(self.delta)))
+        })
+    }
+
+    /// Returns whether the table's vouchers are valid for `checking`.
+    ///
+    /// Since all vouchers follow from the first one and the delta,
+    /// checking the first two values checks the whole table.
+    #[must_use]
+    pub fn verify(&self, checking: crate::CheckingParameters) -> bool {
+        let second = self.range.start.wrapping_add(1);
+        checking.check(self.range.start, self.first)
+            && checking.check(second, Voucher(self.first.0.wrapping_add(self.delta)))
+    }
+}
+
+/// The vouchers are secret, so don't print them in debug output.
+impl std::fmt::Debug for RangeTable {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("RangeTable")
+            .field("range", &self.range)
+            .finish_non_exhaustive()
+    }
+}
+
+#[cfg(test)]
+fn round_trip(format: TableFormat, entries: &[(u64, Voucher)]) -> Vec<(u64, Voucher)> {
+    let mut writer = TableWriter::new(Vec::new(), format).unwrap();
+    for &(value, voucher) in entries {
+        writer.write_entry(value, voucher).unwrap();
+    }
+
+    let bytes = writer.finish().unwrap();
+    TableReader::new(&bytes[..], format)
+        .unwrap()
+        .collect::<std::io::Result<_>>()
+        .unwrap()
+}
+
+#[test]
+fn test_round_trip() {
+    let entries = [
+        (42u64, Voucher(1234)),
+        (u64::MAX, Voucher(0)),
+        (0, Voucher(u64::MAX)),
+    ];
+
+    assert_eq!(round_trip(TableFormat::Csv, &entries), entries);
+    assert_eq!(round_trip(TableFormat::Rafl, &entries), entries);
+    assert_eq!(round_trip(TableFormat::Csv, &[]), []);
+    assert_eq!(round_trip(TableFormat::Rafl, &[]), []);
+}
+
+#[test]
+fn test_read_from_write_to() {
+    let entries = [(42u64, Voucher(1234)), (u64::MAX, Voucher(0))];
+
+    for format in [TableFormat::Csv, TableFormat::Rafl] {
+        let mut bytes = Vec::new();
+        write_to(&mut bytes, format, entries).unwrap();
+        assert_eq!(read_from(&bytes[..], format).unwrap(), entries);
+
+        // Partial reads are fine.
+        let trickle = std::io::Read::chain(&bytes[..3], &bytes[3..]);
+        assert_eq!(read_from(trickle, format).unwrap(), entries);
+    }
+
+    // A missing trailing newline is fine, too.
+    let csv = "000000000000002a,00000000000004d2";
+    assert_eq!(
+        read_from(csv.as_bytes(), TableFormat::Csv).unwrap(),
+        [(42, Voucher(1234))]
+    );
+}
+
+#[test]
+fn test_csv() {
+    let table =
+        "# comment\n\n000000000000002a,00000000000004d2\r\n  000000000000002b,00000000000004d3  ";
+    let entries: Vec<_> = TableReader::new(table.as_bytes(), TableFormat::Csv)
+        .unwrap()
+        .collect::<std::io::Result<_>>()
+        .unwrap();
+    assert_eq!(entries, [(42, Voucher(1234)), (43, Voucher(1235))]);
+
+    assert!(parse_csv_line("000000000000002a;00000000000004d2").is_err());
+    assert!(parse_csv_line("000000000000002a,00000000000004d").is_err());
+    assert!(parse_csv_line("00000000000000xa,00000000000004d2").is_err());
+
+    let err = TableReader::new("\n2a,4d2\n".as_bytes(), TableFormat::Csv)
+        .unwrap()
+        .next_entry()
+        .unwrap_err();
+    assert!(err.to_string().starts_with("line 2:"));
+}
+
+#[test]
+fn test_rafl_errors() {
+    assert!(TableReader::new(&b"RAFL\x02\0\0\0"[..], TableFormat::Rafl).is_err());
+    assert!(TableReader::new(&b"RAF"[..], TableFormat::Rafl).is_err());
+
+    let mut truncated = RAFL_HEADER.to_vec();
+    truncated.extend_from_slice(&[1u8; 20]);
+    let mut reader = TableReader::new(&truncated[..], TableFormat::Rafl).unwrap();
+    assert!(reader.next_entry().unwrap().is_some());
+    assert!(reader.next_entry().is_err());
+}
+
+#[test]
+fn test_format_from_path() {
+    use std::path::Path;
+
+    assert_eq!(
+        TableFormat::from_path(Path::new("x.rafl")),
+        TableFormat::Rafl
+    );
+    assert_eq!(
+        TableFormat::from_path(Path::new("x.RAFL")),
+        TableFormat::Rafl
+    );
+    assert_eq!(TableFormat::from_path(Path::new("x.csv")), TableFormat::Csv);
+    assert_eq!(TableFormat::from_path(Path::new("x")), TableFormat::Csv);
+}
+
+#[cfg(all(test, not(feature = "insecure-accept-all")))]
+fn make_rafl(count: u64, corrupt: &[u64]) -> (crate::VouchingParameters, Vec<u8>) {
+    let params = crate::testing::VOUCHING;
+    let mut writer = TableWriter::new(Vec::new(), TableFormat::Rafl).unwrap();
+    for value in 0..count {
+        let stored = if corrupt.contains(&value) {
+            value + 1
+        } else {
+            value
+        };
+        writer.write_entry(stored, params.vouch(value)).unwrap();
+    }
+
+    (params, writer.finish().unwrap())
+}
+
+#[cfg(not(feature = "insecure-accept-all"))]
+#[test]
+fn test_verify_rafl() {
+    let (params, bytes) = make_rafl(1000, &[3, 500, 999]);
+    let checking = params.checking_parameters();
+
+    assert_eq!(rafl_entries(&bytes).unwrap().len(), 1000);
+    for threads in [1, 3, 8, 2000] {
+        assert_eq!(
+            verify_rafl(&bytes, checking, threads).unwrap(),
+            [3, 500, 999]
+        );
+    }
+
+    let (_, empty) = make_rafl(0, &[]);
+    assert_eq!(
+        verify_rafl(&empty, checking, 4).unwrap(),
+        Vec::<usize>::new()
+    );
+
+    assert!(verify_rafl(&bytes[..bytes.len() - 1], checking, 4).is_err());
+    assert!(verify_rafl(&bytes[1..], checking, 4).is_err());
+}
+
+#[cfg(all(feature = "mmap", not(feature = "insecure-accept-all")))]
+#[test]
+fn test_mapped_table() {
+    let (params, bytes) = make_rafl(100, &[42]);
+    let checking = params.checking_parameters();
+
+    let path = std::env::temp_dir().join(format!("raffle-test-{}.rafl", std::process::id()));
+    std::fs::write(&path, &bytes).unwrap();
+    let table = MappedTable::open(&path).unwrap();
+    std::fs::remove_file(&path).unwrap();
+
+    assert_eq!(table.len(), 100);
+    assert!(!table.is_empty());
+    assert_eq!(table.get(10), Some((10, params.vouch(10))));
+    assert_eq!(table.get(100), None);
+    assert_eq!(table.check_entry(checking, 41), Some(true));
+    assert_eq!(table.check_entry(checking, 42), Some(false));
+    assert_eq!(table.check_entry(checking, 100), None);
+    assert_eq!(table.iter().count(), 100);
+    assert_eq!(table.verify(checking, 4), [42]);
+}
+
+#[cfg(not(feature = "insecure-accept-all"))]
+#[test]
+fn test_static_table() {
+    use crate::testing::vouch;
+    use crate::testing::CHECKING;
+
+    static ENTRIES: [(u64, Voucher); 4] = [
+        (1, vouch(1)),
+        (10, vouch(10)),
+        (100, vouch(101)),
+        (1000, vouch(1000)),
+    ];
+    static TABLE: StaticTable<4> = StaticTable::new(CHECKING, &ENTRIES);
+
+    assert_eq!(TABLE.len(), 4);
+    assert!(!TABLE.is_empty());
+    assert_eq!(TABLE.checked_count(), 0);
+
+    assert_eq!(TABLE.get(10), Some(vouch(10)));
+    assert_eq!(TABLE.checked_count(), 1);
+    assert_eq!(TABLE.get(10), Some(vouch(10)));
+    assert_eq!(TABLE.checked_count(), 1);
+
+    // Invalid entries stay invalid, and missing entries don't count.
+    assert!(!TABLE.contains(100));
+    assert!(!TABLE.contains(100));
+    assert!(!TABLE.contains(5));
+    assert_eq!(TABLE.checked_count(), 2);
+}
+
+#[test]
+#[should_panic(expected = "sorted by strictly increasing value")]
+fn test_static_table_unsorted() {
+    static ENTRIES: [(u64, Voucher); 2] = [(2, Voucher(0)), (1, Voucher(0))];
+    let _ = StaticTable::new(crate::testing::CHECKING, &ENTRIES);
+}
+
+#[cfg(not(feature = "insecure-accept-all"))]
+#[test]
+fn test_range_table() {
+    let params = crate::testing::VOUCHING;
+    let other = crate::testing::OTHER;
+
+    let table = RangeTable::new(&params, 100..1100);
+    assert_eq!(table.range(), 100..1100);
+    for value in table.range() {
+        assert_eq!(table.get(value), Some(params.vouch(value)));
+    }
+
+    assert_eq!(table.get(99), None);
+    assert_eq!(table.get(1100), None);
+    assert!(table.verify(params.checking_parameters()));
+    assert!(!table.verify(other.checking_parameters()));
+
+    // Ranges may end at u64::MAX, or be empty.
+    let table = RangeTable::new(&params, u64::MAX - 2..u64::MAX);
+    assert_eq!(table.get(u64::MAX - 1), Some(params.vouch(u64::MAX - 1)));
+    assert_eq!(RangeTable::new(&params, 5..5).get(5), None);
+
+    // Debug output doesn't leak the vouchers.
+    assert_eq!(
+        format!("{:?}", RangeTable::new(&params, 5..7)),
+        "RangeTable { range: 5..7, .. }"
+    );
+}
@@ -0,0 +1,182 @@
+//! Stable, insecure parameters for tests.
+//!
+//! This module is only compiled with the `testing` feature (and for
+//! raffle's own tests).  Enable it in `[dev-dependencies]`, e.g.,
+//! `raffle = { version = "...", features = ["testing"] }`, so that
+//! tests share one deterministic parameter set and fixtures, rather
+//! than each generating their own parameters.
+//!
+//! **These parameters are public: never use them outside tests.**  The
+//! vouching half is in this documentation, so anyone can mint valid
+//! vouchers for [`CHECKING`].
+//!
+//! [`OTHER`] is a second fixed key, for tests that check that vouchers
+//! from the wrong key are rejected.  [`MockVoucher`] instead derives
+//! parameters from a seed, for tests that need many independent keys.
+//!
+//! ```
+//! use raffle::testing;
+//!
+//! let voucher = testing::VOUCHING.vouch(42);
+//! assert!(testing::CHECKING.check(42, voucher));
+//! assert_eq!(testing::SAMPLES[2], (42, voucher));
+//! ```
+use crate::CheckingParameters;
+use crate::Voucher;
+use crate::VouchingParameters;
+
+/// String representation of [`VOUCHING`].
+pub const VOUCHING_STRING: &str =
+    "VOUCH-b4b0de979c8a90a9-676e696863756fd5-0000000000000083-9b791a2755d2d996";
+
+/// String representation of [`CHECKING`].
+pub const CHECKING_STRING: &str = "CHECK-0000000000000083-9b791a2755d2d996";
+
+/// Insecure vouching parameters for tests.
+pub const VOUCHING: VouchingParameters = VouchingParameters::parse_or_die(VOUCHING_STRING);
+
+/// Checking parameters for [`VOUCHING`].
+pub const CHECKING: CheckingParameters = VOUCHING.checking_parameters();
+
+/// Other insecure vouching parameters for tests, independent of
+/// [`VOUCHING`].
+pub const OTHER: VouchingParameters = VouchingParameters::parse_or_die(
+    "VOUCH-af1819412c310450-676e696863756b85-000000000000162e-527ea33b603464e6",
+);
+
+/// Sample values, paired with their voucher under [`VOUCHING`].
+pub const SAMPLES: [(u64, Voucher); 6] = [
+    (0, VOUCHING.vouch(0)),
+    (1, VOUCHING.vouch(1)),
+    (42, VOUCHING.vouch(42)),
+    (1000, VOUCHING.vouch(1000)),
+    (i64::MAX as u64, VOUCHING.vouch(i64::MAX as u64)),
+    (u64::MAX, VOUCHING.vouch(u64::MAX)),
+];
+
+/// Returns the voucher for `value` under [`VOUCHING`].
+#[must_use]
+pub const fn vouch(value: u64) -> Voucher {
+    VOUCHING.vouch(value)
+}
+
+/// Mints predictable vouchers from parameters derived from a fixed seed.
+///
+/// Different seeds yield independent parameters, so tests can simulate
+/// several keys (e.g., for rotations) without real secrets or entropy.
+/// With the `async` feature, [`MockVoucher`] also implements
+/// [`crate::RemoteVoucher`], so it can stand in for a vouching service.
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Hash)]
+pub struct MockVoucher {
+    vouching: VouchingParameters,
+}
+
+impl MockVoucher {
+    /// Returns a [`MockVoucher`] with parameters derived from `seed`.
+    #[must_use]
+    pub fn new(seed: u64) -> MockVoucher {
+        // SplitMix64, which never yields the same value twice in a row.
+        let mut state = seed;
+        let generator = || {
+            state = state.wrapping_add(0x9e3779b97f4a7c15);
+            let mut z = state;
+            z = (z ^ (z >> 30)).wrapping_mul(0xbf58476d1ce4e5b9);
+            z = (z ^ (z >> 27)).wrapping_mul(0x94d049bb133111eb);
+            Ok::<u64, std::convert::Infallible>(z ^ (z >> 31))
+        };
+
+        let vouching =
+            VouchingParameters::generate(generator).expect("SplitMix64 is a healthy generator");
+        MockVoucher { vouching }
+    }
+
+    /// Returns the voucher for `value`.
+    #[must_use]
+    pub fn vouch(&self, value: u64) -> Voucher {
+        self.vouching.vouch(value)
+    }
+
+    /// Returns the vouching parameters derived from the seed.
+    #[must_use]
+    pub fn vouching_parameters(&self) -> VouchingParameters {
+        self.vouching
+    }
+
+    /// Returns the checking parameters for this mock's vouchers.
+    #[must_use]
+    pub fn checking_parameters(&self) -> CheckingParameters {
+        self.vouching.checking_parameters()
+    }
+}
+
+#[cfg(feature = "async")]
+impl crate::RemoteVoucher for MockVoucher {
+    type Error = std::convert::Infallible;
+
+    fn vouch(
+        &self,
+        value: u64,
+    ) -> impl std::future::Future<Output = Result<Voucher, Self::Error>> + Send {
+        std::future::ready(Ok(MockVoucher::vouch(self, value)))
+    }
+}
+
+/// Polls `future` to completion on the current thread.  Only useful for
+/// tests, where every future is ready without any IO.
+#[cfg(all(test, any(feature = "async", feature = "axum", feature = "tokio")))]
+pub(crate) fn block_on<F: std::future::Future>(future: F) -> F::Output {
+    let mut future = std::pin::pin!(future);
+    let mut context = std::task::Context::from_waker(std::task::Waker::noop());
+    loop {
+        if let std::task::Poll::Ready(ret) = future.as_mut().poll(&mut context) {
+            return ret;
+        }
+    }
+}
+
+#[cfg(not(feature = "insecure-accept-all"))]
+#[test]
+fn test_mock_voucher() {
+    let mock = MockVoucher::new(1);
+    assert_eq!(mock, MockVoucher::new(1));
+    assert_ne!(
+        mock.checking_parameters(),
+        MockVoucher::new(2).checking_parameters()
+    );
+
+    assert!(mock.checking_parameters().check(42, mock.vouch(42)));
+    assert!(!MockVoucher::new(2)
+        .checking_parameters()
+        .check(42, mock.vouch(42)));
+}
+
+#[cfg(feature = "async")]
+#[test]
+fn test_mock_remote() {
+    let mock = MockVoucher::new(1);
+    let checked = crate::remote::CheckedRemote::new(mock, mock.checking_parameters());
+    assert_eq!(
+        block_on(crate::RemoteVoucher::vouch(&checked, 42)),
+        Ok(mock.vouch(42))
+    );
+}
+
+#[test]
+fn test_testing_parameters() {
+    let generated =
+        VouchingParameters::generate(crate::make_generator(&crate::TEST_DRAWS)).unwrap();
+    assert_eq!(VOUCHING, generated);
+    assert_eq!(VOUCHING.to_string(), VOUCHING_STRING);
+    assert_eq!(CHECKING.to_string(), CHECKING_STRING);
+
+    assert_eq!(
+        OTHER,
+        VouchingParameters::generate(crate::make_generator(&[1234, 5678])).unwrap()
+    );
+    assert_ne!(OTHER.checking_parameters(), CHECKING);
+
+    for (value, voucher) in SAMPLES {
+        assert!(CHECKING.check(value, voucher));
+        assert_eq!(vouch(value), voucher);
+    }
+}
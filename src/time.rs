@@ -0,0 +1,118 @@
+//! Canonical [`u64`] encodings for durations and timestamps.
+//!
+//! Timeouts and deadlines are common override targets ("raise this
+//! timeout to something absurd").  [`Duration`]s and [`SystemTime`]s
+//! are vouched as whole seconds ([`InSeconds`]) or milliseconds
+//! ([`InMillis`]): timestamps are relative to the Unix epoch, fractions
+//! are truncated, and out-of-range values saturate to `0` (timestamps
+//! before the epoch) or [`u64::MAX`].
+//!
+//! Truncation means a voucher for 1.5 seconds is also valid for 1.9
+//! seconds; callers that care should pick the unit that exactly
+//! represents the values they vouch for.
+use std::time::Duration;
+use std::time::SystemTime;
+
+use crate::Vouchable;
+
+/// Vouches for the inner [`Duration`] or [`SystemTime`] in whole seconds.
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Hash, PartialOrd, Ord)]
+pub struct InSeconds<T>(pub T);
+
+/// Vouches for the inner [`Duration`] or [`SystemTime`] in whole milliseconds.
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Hash, PartialOrd, Ord)]
+pub struct InMillis<T>(pub T);
+
+/// Returns `duration` in whole seconds.
+#[must_use]
+pub const fn duration_secs(duration: Duration) -> u64 {
+    duration.as_secs()
+}
+
+/// Returns `duration` in whole milliseconds, saturating at [`u64::MAX`].
+#[must_use]
+pub const fn duration_millis(duration: Duration) -> u64 {
+    let millis = duration.as_millis();
+    if millis > u64::MAX as u128 {
+        u64::MAX
+    } else {
+        millis as u64
+    }
+}
+
+/// Returns the time since the Unix epoch, or zero for earlier times.
+fn since_epoch(time: SystemTime) -> Duration {
+    time.duration_since(SystemTime::UNIX_EPOCH)
+        .unwrap_or(Duration::ZERO)
+}
+
+/// Returns `time` in whole seconds since the Unix epoch, saturating at 0.
+#[must_use]
+pub fn system_time_secs(time: SystemTime) -> u64 {
+    duration_secs(since_epoch(time))
+}
+
+/// Returns `time` in whole milliseconds since the Unix epoch, saturating at 0.
+#[must_use]
+pub fn system_time_millis(time: SystemTime) -> u64 {
+    duration_millis(since_epoch(time))
+}
+
+impl Vouchable for InSeconds<Duration> {
+    fn vouch_value(&self) -> u64 {
+        duration_secs(self.0)
+    }
+}
+
+impl Vouchable for InMillis<Duration> {
+    fn vouch_value(&self) -> u64 {
+        duration_millis(self.0)
+    }
+}
+
+impl Vouchable for InSeconds<SystemTime> {
+    fn vouch_value(&self) -> u64 {
+        system_time_secs(self.0)
+    }
+}
+
+impl Vouchable for InMillis<SystemTime> {
+    fn vouch_value(&self) -> u64 {
+        system_time_millis(self.0)
+    }
+}
+
+#[test]
+fn test_durations() {
+    assert_eq!(InSeconds(Duration::from_millis(1999)).vouch_value(), 1);
+    assert_eq!(InMillis(Duration::from_millis(1999)).vouch_value(), 1999);
+    assert_eq!(InMillis(Duration::from_nanos(1_999_999)).vouch_value(), 1);
+    assert_eq!(InSeconds(Duration::MAX).vouch_value(), u64::MAX);
+    assert_eq!(InMillis(Duration::MAX).vouch_value(), u64::MAX);
+
+    const TIMEOUT: u64 = duration_millis(Duration::from_secs(30));
+    assert_eq!(TIMEOUT, 30_000);
+}
+
+#[test]
+fn test_system_times() {
+    let at = SystemTime::UNIX_EPOCH + Duration::from_millis(1_700_000_000_123);
+    assert_eq!(InSeconds(at).vouch_value(), 1_700_000_000);
+    assert_eq!(InMillis(at).vouch_value(), 1_700_000_000_123);
+
+    let before = SystemTime::UNIX_EPOCH - Duration::from_secs(1);
+    assert_eq!(InSeconds(before).vouch_value(), 0);
+    assert_eq!(InMillis(before).vouch_value(), 0);
+}
+
+#[cfg(not(feature = "insecure-accept-all"))]
+#[test]
+fn test_vouch_timeout() {
+    let params = crate::testing::VOUCHING;
+    let checking = params.checking_parameters();
+    let timeout = InSeconds(Duration::from_secs(3600));
+    let voucher = params.vouch(timeout.vouch_value());
+
+    assert!(crate::Vouched::new(checking, timeout, voucher).is_ok());
+    assert!(crate::Vouched::new(checking, InMillis(timeout.0), voucher).is_err());
+}
@@ -0,0 +1,275 @@
+//! Async IO helpers, for `tokio`.
+//!
+//! This module is only compiled with the `tokio` feature.  It mirrors
+//! the blocking helpers ([`CheckingParameters::read_from`],
+//! [`crate::table::read_from`], [`crate::table::TableReader`], ...) over
+//! [`tokio::io::AsyncRead`] and [`tokio::io::AsyncWrite`], so async
+//! services can load parameters and verify voucher tables without
+//! spawning blocking tasks.
+use tokio::io::AsyncBufRead;
+use tokio::io::AsyncBufReadExt;
+use tokio::io::AsyncRead;
+use tokio::io::AsyncReadExt;
+use tokio::io::AsyncWrite;
+use tokio::io::AsyncWriteExt;
+
+use crate::table::parse_csv_line;
+use crate::table::parse_rafl_entry;
+use crate::table::TableFormat;
+use crate::table::RAFL_ENTRY_BYTE_COUNT;
+use crate::table::RAFL_HEADER;
+use crate::CheckingParameters;
+use crate::Voucher;
+use crate::VouchingParameters;
+
+/// Stop reading parameters after this many bytes: valid input is much shorter.
+const MAX_READ_BYTE_COUNT: u64 = 1024;
+
+fn invalid_data(message: impl Into<Box<dyn std::error::Error + Send + Sync>>) -> std::io::Error {
+    std::io::Error::new(std::io::ErrorKind::InvalidData, message)
+}
+
+/// Reads `reader` until EOF, and parses the contents with `parse`.
+async fn read_parameters<T>(
+    reader: impl AsyncRead + Unpin,
+    parse: impl FnOnce(&[u8]) -> Result<T, &'static str>,
+) -> std::io::Result<T> {
+    let mut buf = Vec::new();
+    reader
+        .take(MAX_READ_BYTE_COUNT)
+        .read_to_end(&mut buf)
+        .await?;
+    if buf.len() as u64 >= MAX_READ_BYTE_COUNT {
+        return Err(invalid_data("Too many bytes for raffle parameters"));
+    }
+
+    parse(buf.trim_ascii()).map_err(invalid_data)
+}
+
+impl CheckingParameters {
+    /// Like [`CheckingParameters::write_to`], for async writers.
+    pub async fn write_to_async(&self, mut writer: impl AsyncWrite + Unpin) -> std::io::Result<()> {
+        writer.write_all(format!("{}\n", self).as_bytes()).await?;
+        writer.flush().await
+    }
+
+    /// Like [`CheckingParameters::read_from`], for async readers.
+    pub async fn read_from_async(
+        reader: impl AsyncRead + Unpin,
+    ) -> std::io::Result<CheckingParameters> {
+        read_parameters(reader, CheckingParameters::parse_bytes).await
+    }
+}
+
+impl VouchingParameters {
+    /// Like [`VouchingParameters::write_to`], for async writers.
+    pub async fn write_to_async(&self, mut writer: impl AsyncWrite + Unpin) -> std::io::Result<()> {
+        writer.write_all(format!("{}\n", self).as_bytes()).await?;
+        writer.flush().await
+    }
+
+    /// Like [`VouchingParameters::read_from`], for async readers.
+    pub async fn read_from_async(
+        reader: impl AsyncRead + Unpin,
+    ) -> std::io::Result<VouchingParameters> {
+        read_parameters(reader, VouchingParameters::parse_bytes).await
+    }
+}
+
+/// Like [`crate::table::TableReader`], for async readers.
+pub struct AsyncTableReader<R> {
+    reader: R,
+    format: TableFormat,
+    // Line number for CSV, entry index for RAFL.
+    position: u64,
+    line: String,
+}
+
+impl<R: AsyncBufRead + Unpin> AsyncTableReader<R> {
+    /// Returns a reader for the table in `reader`.  For
+    /// [`TableFormat::Rafl`], this function reads and checks the header.
+    pub async fn new(mut reader: R, format: TableFormat) -> std::io::Result<AsyncTableReader<R>> {
+        if format == TableFormat::Rafl {
+            let mut header = [0u8; RAFL_HEADER.len()];
+            reader.read_exact(&mut header).await?;
+            if header != RAFL_HEADER {
+                return Err(invalid_data("Incorrect header for RAFL table"));
+            }
+        }
+
+        Ok(AsyncTableReader {
+            reader,
+            format,
+            position: 0,
+            line: String::new(),
+        })
+    }
+
+    /// Returns the next entry, `None` at the end of the table, or an error.
+    pub async fn next_entry(&mut self) -> std::io::Result<Option<(u64, Voucher)>> {
+        match self.format {
+            TableFormat::Csv => loop {
+                self.line.clear();
+                if self.reader.read_line(&mut self.line).await? == 0 {
+                    return Ok(None);
+                }
+
+                self.position += 1;
+                match parse_csv_line(&self.line) {
+                    Ok(None) => continue,
+                    Ok(Some(entry)) => return Ok(Some(entry)),
+                    Err(e) => return Err(invalid_data(format!("line {}: {}", self.position, e))),
+                }
+            },
+            TableFormat::Rafl => {
+                let mut buf = [0u8; RAFL_ENTRY_BYTE_COUNT];
+                let mut filled = 0;
+                while filled < buf.len() {
+                    match self.reader.read(&mut buf[filled..]).await? {
+                        0 => break,
+                        n => filled += n,
+                    }
+                }
+
+                match filled {
+                    0 => Ok(None),
+                    RAFL_ENTRY_BYTE_COUNT => {
+                        self.position += 1;
+                        Ok(Some(parse_rafl_entry(&buf)))
+                    }
+                    _ => Err(invalid_data(format!(
+                        "truncated RAFL entry at index {}",
+                        self.position
+                    ))),
+                }
+            }
+        }
+    }
+}
+
+/// Like [`crate::table::read_from`], for async readers.
+pub async fn read_table(
+    reader: impl AsyncRead + Unpin,
+    format: TableFormat,
+) -> std::io::Result<Vec<(u64, Voucher)>> {
+    let mut reader = AsyncTableReader::new(tokio::io::BufReader::new(reader), format).await?;
+    let mut entries = Vec::new();
+    while let Some(entry) = reader.next_entry().await? {
+        entries.push(entry);
+    }
+
+    Ok(entries)
+}
+
+/// Like [`crate::table::write_to`], for async writers.
+pub async fn write_table(
+    writer: impl AsyncWrite + Unpin,
+    format: TableFormat,
+    entries: impl IntoIterator<Item = (u64, Voucher)>,
+) -> std::io::Result<()> {
+    let mut writer = tokio::io::BufWriter::new(writer);
+    if format == TableFormat::Rafl {
+        writer.write_all(&RAFL_HEADER).await?;
+    }
+
+    for (value, voucher) in entries {
+        match format {
+            TableFormat::Csv => {
+                writer
+                    .write_all(format!("{:016x},{:016x}\n", value, voucher.0).as_bytes())
+                    .await?
+            }
+            TableFormat::Rafl => {
+                writer.write_all(&value.to_le_bytes()).await?;
+                writer.write_all(&voucher.0.to_le_bytes()).await?;
+            }
+        }
+    }
+
+    writer.flush().await
+}
+
+/// Streams the table in `reader`, and checks each entry against
+/// `checking` as it arrives, without holding the table in memory.
+///
+/// Returns the (0-based, sorted) indices of the invalid entries, like
+/// [`crate::table::verify_rafl`].
+pub async fn verify_table(
+    reader: impl AsyncRead + Unpin,
+    format: TableFormat,
+    checking: CheckingParameters,
+) -> std::io::Result<Vec<usize>> {
+    let mut reader = AsyncTableReader::new(tokio::io::BufReader::new(reader), format).await?;
+    let mut invalid = Vec::new();
+    let mut index = 0;
+    while let Some((value, voucher)) = reader.next_entry().await? {
+        if !checking.check(value, voucher) {
+            invalid.push(index);
+        }
+
+        index += 1;
+    }
+
+    Ok(invalid)
+}
+
+#[test]
+fn test_parameters() {
+    let params = crate::testing::VOUCHING;
+    let checking = params.checking_parameters();
+
+    let mut buf = Vec::new();
+    crate::testing::block_on(checking.write_to_async(&mut buf)).unwrap();
+    assert_eq!(buf, format!("{}\n", checking).into_bytes());
+    assert_eq!(
+        crate::testing::block_on(CheckingParameters::read_from_async(&buf[..])).unwrap(),
+        checking
+    );
+
+    let mut buf = Vec::new();
+    crate::testing::block_on(params.write_to_async(&mut buf)).unwrap();
+    assert_eq!(
+        crate::testing::block_on(VouchingParameters::read_from_async(&buf[..])).unwrap(),
+        params
+    );
+    assert!(crate::testing::block_on(CheckingParameters::read_from_async(&buf[..])).is_err());
+}
+
+#[cfg(not(feature = "insecure-accept-all"))]
+#[test]
+fn test_tables() {
+    let params = crate::testing::VOUCHING;
+    let entries = [
+        (1u64, params.vouch(1)),
+        (2, params.vouch(3)),
+        (3, params.vouch(3)),
+    ];
+
+    for format in [TableFormat::Csv, TableFormat::Rafl] {
+        let mut bytes = Vec::new();
+        crate::testing::block_on(write_table(&mut bytes, format, entries)).unwrap();
+
+        // Same bytes as the blocking writer.
+        let mut expected = Vec::new();
+        crate::table::write_to(&mut expected, format, entries).unwrap();
+        assert_eq!(bytes, expected);
+
+        assert_eq!(
+            crate::testing::block_on(read_table(&bytes[..], format)).unwrap(),
+            entries
+        );
+        assert_eq!(
+            crate::testing::block_on(verify_table(
+                &bytes[..],
+                format,
+                params.checking_parameters()
+            ))
+            .unwrap(),
+            [1]
+        );
+    }
+
+    assert!(
+        crate::testing::block_on(read_table(&b"RAFL\x02\0\0\0"[..], TableFormat::Rafl)).is_err()
+    );
+}
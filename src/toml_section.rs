@@ -0,0 +1,242 @@
+//! The `[raffle]` section of TOML configuration files.
+//!
+//! This module is only compiled with the `toml` feature.  It defines a
+//! canonical layout for raffle settings in TOML configuration, so each
+//! application doesn't have to invent its own:
+//!
+//! ```toml
+//! [raffle]
+//! # Optional: the application's main checking parameters.
+//! checking = "CHECK-0000000000000083-9b791a2755d2d996"
+//!
+//! # Optional: named checking parameters, for a `raffle::Keyring`.
+//! [raffle.keyring]
+//! tenant_a = "CHECK-0000000000000084-9b791a2755d2d996"
+//!
+//! # Optional: vouched overrides for `raffle::config::Setting`s.  Values
+//! # may be strings, integers or booleans; vouchers are 16 hex digits.
+//! [raffle.overrides]
+//! max_connections = { value = 10000, voucher = "0123456789abcdef" }
+//! ```
+//!
+//! [`RaffleSection::parse_toml`] reads that section from a TOML
+//! document, and [`RaffleSection::to_toml_string`] writes it back.
+use crate::section::format_voucher;
+use crate::section::invalid;
+use crate::section::parse_checking;
+use crate::section::parse_voucher;
+use crate::section::SECTION;
+use crate::OverrideValue;
+use crate::RaffleSection;
+use crate::Voucher;
+
+/// Error type for [`RaffleSection::parse_toml`].
+pub type SectionError = crate::SectionError<toml::de::Error>;
+
+impl std::fmt::Display for SectionError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            SectionError::Syntax(e) => write!(f, "invalid TOML: {}", e),
+            SectionError::Invalid { key, reason } => write!(f, "invalid {}: {}", key, reason),
+        }
+    }
+}
+
+fn parse_voucher_value(key: String, value: Option<&toml::Value>) -> Result<Voucher, SectionError> {
+    let string = value.and_then(toml::Value::as_str).unwrap_or_default();
+    parse_voucher(string).map_err(|reason| invalid(key, reason))
+}
+
+fn parse_override(
+    key: String,
+    entry: &toml::Value,
+) -> Result<(OverrideValue, Voucher), SectionError> {
+    let Some(entry) = entry.as_table() else {
+        return Err(invalid(key, "Expected a table with a value and a voucher"));
+    };
+
+    let value = match entry.get("value") {
+        Some(toml::Value::String(value)) => OverrideValue::String(value.clone()),
+        Some(toml::Value::Integer(value)) => OverrideValue::Integer(*value),
+        Some(toml::Value::Boolean(value)) => OverrideValue::Boolean(*value),
+        _ => return Err(invalid(key, "Value must be a string, integer or boolean")),
+    };
+
+    Ok((
+        value,
+        parse_voucher_value(format!("{}.voucher", key), entry.get("voucher"))?,
+    ))
+}
+
+impl RaffleSection {
+    /// Parses the `[raffle]` section of the TOML `document`.
+    ///
+    /// Returns an empty section if the document doesn't have one.
+    /// Unknown keys in the section are errors, to catch typos.
+    pub fn parse_toml(document: &str) -> Result<RaffleSection, SectionError> {
+        let document: toml::Table = document.parse().map_err(SectionError::Syntax)?;
+        match document.get(SECTION) {
+            None => Ok(RaffleSection::default()),
+            Some(section) => RaffleSection::from_toml_value(section),
+        }
+    }
+
+    /// Parses the contents of a `[raffle]` section, e.g., a sub-table
+    /// of a document that was already parsed.
+    pub fn from_toml_value(section: &toml::Value) -> Result<RaffleSection, SectionError> {
+        let Some(section) = section.as_table() else {
+            return Err(invalid(SECTION, "Expected a table"));
+        };
+
+        let mut ret = RaffleSection::default();
+        for (key, value) in section {
+            let path = format!("{}.{}", SECTION, key);
+            match key.as_str() {
+                "checking" => ret.checking = Some(parse_checking(path, value.as_str())?),
+                "keyring" => {
+                    let Some(keyring) = value.as_table() else {
+                        return Err(invalid(path, "Expected a table"));
+                    };
+
+                    for (name, value) in keyring {
+                        let checking =
+                            parse_checking(format!("{}.{}", path, name), value.as_str())?;
+                        ret.keyring.insert(name.as_str(), checking);
+                    }
+                }
+                "overrides" => {
+                    let Some(overrides) = value.as_table() else {
+                        return Err(invalid(path, "Expected a table"));
+                    };
+
+                    for (name, entry) in overrides {
+                        let entry = parse_override(format!("{}.{}", path, name), entry)?;
+                        ret.overrides.insert(name.clone(), entry);
+                    }
+                }
+                _ => return Err(invalid(path, "Unknown key")),
+            }
+        }
+
+        Ok(ret)
+    }
+
+    /// Returns the contents of the section, as a TOML table.
+    #[must_use]
+    pub fn to_toml_value(&self) -> toml::Value {
+        let mut section = toml::Table::new();
+        if let Some(checking) = self.checking {
+            section.insert("checking".to_owned(), checking.to_string().into());
+        }
+
+        if !self.keyring.is_empty() {
+            let keyring: toml::Table = self
+                .keyring
+                .iter()
+                .map(|(name, checking)| (name.to_owned(), checking.to_string().into()))
+                .collect();
+            section.insert("keyring".to_owned(), keyring.into());
+        }
+
+        if !self.overrides.is_empty() {
+            let overrides: toml::Table = self
+                .overrides
+                .iter()
+                .map(|(name, (value, voucher))| {
+                    let mut entry = toml::Table::new();
+                    let value = match value {
+                        OverrideValue::String(value) => value.clone().into(),
+                        OverrideValue::Integer(value) => (*value).into(),
+                        OverrideValue::Boolean(value) => (*value).into(),
+                    };
+                    entry.insert("value".to_owned(), value);
+                    entry.insert("voucher".to_owned(), format_voucher(*voucher).into());
+                    (name.clone(), entry.into())
+                })
+                .collect();
+            section.insert("overrides".to_owned(), overrides.into());
+        }
+
+        section.into()
+    }
+
+    /// Returns a TOML document with only this `[raffle]` section.
+    #[must_use]
+    pub fn to_toml_string(&self) -> String {
+        let mut document = toml::Table::new();
+        document.insert(SECTION.to_owned(), self.to_toml_value());
+        document.to_string()
+    }
+}
+
+#[test]
+fn test_round_trip() {
+    let params = crate::testing::VOUCHING;
+    let other = crate::testing::OTHER;
+    static LIMIT: crate::config::Setting<u32> = crate::config::Setting::new("limit", 10);
+
+    let document = format!(
+        r#"
+[server]
+port = 80
+
+[raffle]
+checking = "{}"
+
+[raffle.keyring]
+tenant = "{}"
+
+[raffle.overrides]
+limit = {{ value = 1000, voucher = "{:016x}" }}
+"#,
+        params.checking_parameters(),
+        other.checking_parameters(),
+        LIMIT.vouch(&params, &1000).0,
+    );
+
+    let section = RaffleSection::parse_toml(&document).unwrap();
+    assert_eq!(section.checking, Some(params.checking_parameters()));
+    assert_eq!(
+        section.keyring.get("tenant"),
+        Some(other.checking_parameters())
+    );
+    assert_eq!(
+        section.overrides.get("limit"),
+        Some(&(OverrideValue::Integer(1000), LIMIT.vouch(&params, &1000)))
+    );
+    assert_eq!(section.overrides().load(&LIMIT).unwrap().value, 1000);
+
+    // Integers stay integers.
+    assert!(section.to_toml_string().contains("value = 1000\n"));
+    assert_eq!(
+        RaffleSection::parse_toml(&section.to_toml_string()).unwrap(),
+        section
+    );
+    assert_eq!(
+        RaffleSection::parse_toml("[server]\nport = 80\n").unwrap(),
+        RaffleSection::default()
+    );
+}
+
+#[test]
+fn test_errors() {
+    let error = |document: &str| RaffleSection::parse_toml(document).unwrap_err().to_string();
+
+    assert!(error("[raffle").starts_with("invalid TOML: "));
+    assert_eq!(
+        error("[raffle]\nchecks = 1"),
+        "invalid raffle.checks: Unknown key"
+    );
+    assert_eq!(
+        error("[raffle.keyring]\na = \"CHECK-\""),
+        "invalid raffle.keyring.a: Too few bytes in serialized raffle::CheckingParameters"
+    );
+    assert_eq!(
+        error("[raffle.overrides]\nx = { value = 1, voucher = \"12\" }"),
+        "invalid raffle.overrides.x.voucher: Voucher must be 16 hex digits"
+    );
+    assert_eq!(
+        error("[raffle.overrides]\nx = { value = 1.5, voucher = \"0123456789abcdef\" }"),
+        "invalid raffle.overrides.x: Value must be a string, integer or boolean"
+    );
+}
@@ -0,0 +1,135 @@
+//! `tracing` instrumentation.
+//!
+//! The functions in this module emit events with the `tracing`
+//! feature, and compile to nothing otherwise.  Events use the `raffle`
+//! target, and identify parameters by [`Fingerprint`], never by their
+//! (possibly secret) string representation.
+//!
+//! The batch verification APIs ([`crate::CheckingParameters::check_many`],
+//! [`crate::table::verify_rafl`], [`crate::migrate_table`]) are also
+//! wrapped in `debug`-level spans with `tracing::instrument`.
+use crate::Fingerprint;
+
+/// Reports freshly generated parameters.
+#[inline(always)]
+pub(crate) fn generated(fingerprint: Fingerprint) {
+    #[cfg(feature = "tracing")]
+    tracing::info!(target: "raffle", fingerprint = %fingerprint, "generated parameters");
+    let _ = fingerprint;
+}
+
+/// Reports a failure to parse parameters from `origin`.
+#[inline(always)]
+pub(crate) fn parse_failed(origin: &str, reason: &'static str) {
+    #[cfg(feature = "tracing")]
+    tracing::warn!(target: "raffle", origin, reason, "failed to parse parameters");
+    let _ = (origin, reason);
+}
+
+/// Reports a failed check for `value`.
+#[inline(always)]
+pub(crate) fn check_failed(fingerprint: Fingerprint, value: u64) {
+    #[cfg(feature = "tracing")]
+    tracing::warn!(target: "raffle", fingerprint = %fingerprint, value, "invalid voucher");
+    let _ = (fingerprint, value);
+}
+
+/// Reports the likely cause of a failed check for `value`, when the
+/// caller mustn't learn it.
+#[cfg(feature = "axum")]
+#[inline(always)]
+pub(crate) fn check_diagnosed(fingerprint: Fingerprint, value: u64, diagnosis: crate::Diagnosis) {
+    #[cfg(feature = "tracing")]
+    tracing::info!(target: "raffle", fingerprint = %fingerprint, value, diagnosis = ?diagnosis, "invalid voucher diagnosis");
+    let _ = (fingerprint, value, diagnosis);
+}
+
+#[cfg(all(test, feature = "tracing", not(feature = "insecure-accept-all")))]
+mod test {
+    use std::sync::Mutex;
+
+    /// Collects events, formatted as `message field=value...`.
+    #[derive(Default)]
+    pub struct Collector {
+        pub events: Mutex<Vec<String>>,
+        pub spans: Mutex<Vec<String>>,
+    }
+
+    struct Fields(String);
+
+    impl tracing::field::Visit for Fields {
+        fn record_debug(&mut self, field: &tracing::field::Field, value: &dyn std::fmt::Debug) {
+            if field.name() == "message" {
+                self.0.insert_str(0, &format!("{:?}", value));
+            } else {
+                self.0.push_str(&format!(" {}={:?}", field.name(), value));
+            }
+        }
+    }
+
+    impl tracing::Subscriber for Collector {
+        fn enabled(&self, _metadata: &tracing::Metadata<'_>) -> bool {
+            true
+        }
+
+        fn new_span(&self, span: &tracing::span::Attributes<'_>) -> tracing::span::Id {
+            let mut fields = Fields(span.metadata().name().to_string());
+            span.record(&mut fields);
+            let mut spans = self.spans.lock().unwrap();
+            spans.push(fields.0);
+            tracing::span::Id::from_u64(spans.len() as u64)
+        }
+
+        fn record(&self, _span: &tracing::span::Id, _values: &tracing::span::Record<'_>) {}
+
+        fn record_follows_from(&self, _span: &tracing::span::Id, _follows: &tracing::span::Id) {}
+
+        fn event(&self, event: &tracing::Event<'_>) {
+            let mut fields = Fields(String::new());
+            event.record(&mut fields);
+            self.events.lock().unwrap().push(fields.0);
+        }
+
+        fn enter(&self, _span: &tracing::span::Id) {}
+
+        fn exit(&self, _span: &tracing::span::Id) {}
+    }
+}
+
+#[cfg(all(feature = "tracing", not(feature = "insecure-accept-all")))]
+#[test]
+fn test_events() {
+    use crate::CheckingParameters;
+    use crate::VouchingParameters;
+
+    let collector = std::sync::Arc::new(test::Collector::default());
+    tracing::subscriber::with_default(collector.clone(), || {
+        let params =
+            VouchingParameters::generate(crate::make_generator(&crate::TEST_DRAWS)).unwrap();
+        let checking = params.checking_parameters();
+
+        assert!(checking.check_audited(42, params.vouch(42)));
+        assert!(!checking.check_audited(42, params.vouch(43)));
+        let vouchers: Vec<_> = params.vouch_many([1, 2]).collect();
+        assert!(checking.check_many(&[1, 2], &vouchers));
+
+        assert!(CheckingParameters::parse_var(
+            "RAFFLE_TEST_TRACE_PARSE",
+            Ok("CHECK-garbage".to_string())
+        )
+        .is_err());
+    });
+
+    assert_eq!(
+        *collector.events.lock().unwrap(),
+        [
+            "generated parameters fingerprint=5d5e18aad84a57df",
+            "invalid voucher fingerprint=5d5e18aad84a57df value=42",
+            "failed to parse parameters origin=\"environment variable RAFFLE_TEST_TRACE_PARSE\" reason=\"Too few bytes in serialized raffle::CheckingParameters\""
+        ]
+    );
+    assert_eq!(
+        *collector.spans.lock().unwrap(),
+        ["check_many fingerprint=5d5e18aad84a57df count=2"]
+    );
+}
@@ -0,0 +1,178 @@
+//! Conversion of typed values to the [`u64`]s we vouch for.
+//!
+//! [`crate::VouchingParameters::vouch`] and
+//! [`crate::CheckingParameters::check`] work on raw [`u64`]s; the
+//! typed wrappers in this crate (e.g., [`crate::VouchedCell`]) instead
+//! accept any [`Vouchable`] type, and use [`Vouchable::vouch_value`]
+//! to find the [`u64`] to check.
+//!
+//! The mapping for each type must be stable: changing it invalidates
+//! every voucher minted for that type.
+
+/// A type whose values can be vouched for.
+pub trait Vouchable {
+    /// Returns the [`u64`] that stands for `self` when vouching and checking.
+    fn vouch_value(&self) -> u64;
+}
+
+impl<T: Vouchable + ?Sized> Vouchable for &T {
+    fn vouch_value(&self) -> u64 {
+        (**self).vouch_value()
+    }
+}
+
+macro_rules! vouchable_unsigned {
+    ($($ty:ty),*) => {
+        $(
+            /// Unsigned integers are zero-extended.
+            impl Vouchable for $ty {
+                fn vouch_value(&self) -> u64 {
+                    *self as u64
+                }
+            }
+        )*
+    };
+}
+
+macro_rules! vouchable_signed {
+    ($($ty:ty),*) => {
+        $(
+            /// Signed integers are sign-extended, and then reinterpreted as [`u64`].
+            impl Vouchable for $ty {
+                fn vouch_value(&self) -> u64 {
+                    *self as i64 as u64
+                }
+            }
+        )*
+    };
+}
+
+vouchable_unsigned!(u8, u16, u32, u64);
+vouchable_signed!(i8, i16, i32, i64);
+
+/// `false` is 0 and `true` is 1.
+impl Vouchable for bool {
+    fn vouch_value(&self) -> u64 {
+        *self as u64
+    }
+}
+
+/// Returns the 64-bit FNV-1a hash of `name`.
+///
+/// This is the stable hash we use to separate vouched values by name
+/// (e.g., with [`Named`]).
+#[must_use]
+pub const fn name_hash(name: &str) -> u64 {
+    bytes_hash(name.as_bytes())
+}
+
+/// Returns the 64-bit FNV-1a hash of `bytes`, like [`name_hash`].
+#[must_use]
+pub(crate) const fn bytes_hash(bytes: &[u8]) -> u64 {
+    const OFFSET_BASIS: u64 = 0xcbf29ce484222325;
+    const PRIME: u64 = 0x100000001b3;
+
+    let mut ret = OFFSET_BASIS;
+    let mut i = 0;
+    while i < bytes.len() {
+        ret ^= bytes[i] as u64;
+        ret = ret.wrapping_mul(PRIME);
+        i += 1;
+    }
+
+    ret
+}
+
+/// Binds `value` to `key` (a name hash, host identifier, nonce, ...)
+/// in the domain `tag`, i.e., returns
+/// `key ^ tag ^ (value * 0x9e3779b97f4a7c15)` (mod 2**64), where
+/// `value` is the inner value's [`Vouchable::vouch_value`].
+///
+/// For a given key and tag, that's a permutation of the inner values.
+/// [`Named`] uses a tag of 0; other bindings (e.g.,
+/// [`crate::HostBound`]) each have their own non-zero tag, so their
+/// keys don't collide with name hashes.
+#[inline]
+pub(crate) fn bind<T: Vouchable + ?Sized>(key: u64, tag: u64, value: &T) -> u64 {
+    key ^ tag ^ value.vouch_value().wrapping_mul(0x9e3779b97f4a7c15)
+}
+
+/// A value tagged with a name, for domain separation: a voucher for
+/// one named value isn't valid for another name with the same value.
+///
+/// The vouched [`u64`] is `name_hash(name) ^ (value * 0x9e3779b97f4a7c15)`
+/// (mod 2**64), where `value` is the inner value's [`Vouchable::vouch_value`].
+/// For a given name, that's a permutation of the inner values.
+///
+/// The separation only protects against accidental mixups, not
+/// against adversaries: the mapping is public and invertible, so
+/// anyone with a voucher for one name and value can compute the value
+/// for which the same voucher is valid under any other name.
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Hash)]
+pub struct Named<'a, T: ?Sized> {
+    /// The name for the value.
+    pub name: &'a str,
+    /// The inner value.
+    pub value: &'a T,
+}
+
+impl<'a, T: Vouchable + ?Sized> Named<'a, T> {
+    /// Tags `value` with `name`.
+    pub fn new(name: &'a str, value: &'a T) -> Named<'a, T> {
+        Named { name, value }
+    }
+}
+
+impl<T: Vouchable + ?Sized> Vouchable for Named<'_, T> {
+    fn vouch_value(&self) -> u64 {
+        bind(name_hash(self.name), 0, self.value)
+    }
+}
+
+#[test]
+fn test_integers() {
+    assert_eq!(42u8.vouch_value(), 42);
+    assert_eq!(u64::MAX.vouch_value(), u64::MAX);
+    assert_eq!((-1i8).vouch_value(), u64::MAX);
+    assert_eq!((-2i32).vouch_value(), (-2i64) as u64);
+    assert_eq!(true.vouch_value(), 1);
+    assert_eq!(false.vouch_value(), 0);
+    assert_eq!(Vouchable::vouch_value(&&7u16), 7);
+}
+
+#[test]
+fn test_named() {
+    // Reference values from the FNV-1a spec's test vectors.
+    assert_eq!(name_hash(""), 0xcbf29ce484222325);
+    assert_eq!(name_hash("a"), 0xaf63dc4c8601ec8c);
+    assert_eq!(name_hash("foobar"), 0x85944171f73967e8);
+
+    assert_eq!(Named::new("a", &0u64).vouch_value(), name_hash("a"));
+    assert_ne!(
+        Named::new("a", &1u64).vouch_value(),
+        Named::new("b", &1u64).vouch_value()
+    );
+    assert_ne!(
+        Named::new("a", &1u64).vouch_value(),
+        Named::new("a", &2u64).vouch_value()
+    );
+}
+
+#[cfg(feature = "macros")]
+#[test]
+fn test_derive() {
+    #[derive(crate::Vouchable)]
+    enum Mode {
+        Normal,
+        #[vouch(tag = "Mode::Debug")]
+        Verbose,
+        Maintenance,
+    }
+
+    assert_eq!(Mode::Normal.vouch_value(), name_hash("Mode::Normal"));
+    assert_eq!(Mode::Verbose.vouch_value(), name_hash("Mode::Debug"));
+    assert_eq!(
+        Mode::Maintenance.vouch_value(),
+        name_hash("Mode::Maintenance")
+    );
+}
@@ -0,0 +1,105 @@
+//! Proof-carrying vouched values.
+
+use crate::CheckingParameters;
+use crate::Vouchable;
+use crate::Voucher;
+
+/// A value whose [`Voucher`] has been checked.
+///
+/// The only way to get a [`Vouched`] value is to check its voucher
+/// (e.g., with [`Vouched::new`]), so functions that take a
+/// `Vouched<T>` don't have to check anything themselves.  The type
+/// doesn't record *which* [`CheckingParameters`] accepted the voucher,
+/// so mixing several sets of parameters in the same program calls for
+/// some care.
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Hash)]
+pub struct Vouched<T> {
+    value: T,
+    voucher: Voucher,
+}
+
+impl<T: Vouchable> Vouched<T> {
+    /// Returns a [`Vouched`] value if `voucher` is valid for `value`
+    /// under `checking`, and gives `value` back otherwise.
+    #[track_caller]
+    pub fn new(checking: CheckingParameters, value: T, voucher: Voucher) -> Result<Vouched<T>, T> {
+        if checking.check_audited(value.vouch_value(), voucher) {
+            Ok(Vouched { value, voucher })
+        } else {
+            Err(value)
+        }
+    }
+
+    /// Returns a [`Vouched`] value if `voucher` is valid for `value`
+    /// tagged with `name` (see [`crate::Named`]) under `checking`, and
+    /// gives `value` back otherwise.
+    ///
+    /// The result doesn't record `name` either: callers are responsible
+    /// for only mixing values vouched under the same name.
+    #[track_caller]
+    pub fn new_named(
+        checking: CheckingParameters,
+        name: &str,
+        value: T,
+        voucher: Voucher,
+    ) -> Result<Vouched<T>, T> {
+        if checking.check_audited(crate::Named::new(name, &value).vouch_value(), voucher) {
+            Ok(Vouched { value, voucher })
+        } else {
+            Err(value)
+        }
+    }
+}
+
+impl<T> Vouched<T> {
+    /// Wraps `value` and its `voucher`, which the caller already
+    /// checked and reported to the audit sink.
+    #[cfg(feature = "tower")]
+    pub(crate) fn checked(value: T, voucher: Voucher) -> Vouched<T> {
+        Vouched { value, voucher }
+    }
+
+    /// Returns a reference to the vouched value.
+    pub fn value(&self) -> &T {
+        &self.value
+    }
+
+    /// Returns the value's [`Voucher`].
+    pub fn voucher(&self) -> Voucher {
+        self.voucher
+    }
+
+    /// Returns the vouched value.
+    pub fn into_inner(self) -> T {
+        self.value
+    }
+
+    /// Returns the vouched value and its [`Voucher`].
+    pub fn into_parts(self) -> (T, Voucher) {
+        (self.value, self.voucher)
+    }
+}
+
+impl<T> std::ops::Deref for Vouched<T> {
+    type Target = T;
+
+    fn deref(&self) -> &T {
+        &self.value
+    }
+}
+
+#[cfg(not(feature = "insecure-accept-all"))]
+#[test]
+fn test_vouched() {
+    let params = crate::testing::VOUCHING;
+    let checking = params.checking_parameters();
+
+    let vouched = Vouched::new(checking, 42u64, params.vouch(42)).expect("valid");
+    assert_eq!(*vouched, 42);
+    assert_eq!(vouched.value(), &42);
+    assert_eq!(vouched.voucher(), params.vouch(42));
+    assert_eq!(vouched.into_parts(), (42, params.vouch(42)));
+    assert_eq!(vouched.into_inner(), 42);
+
+    assert_eq!(Vouched::new(checking, 43u64, params.vouch(42)), Err(43));
+}
@@ -0,0 +1,453 @@
+//! The "wide" scheme: a 128-bit mixing step in front of the affine scheme.
+//!
+//! The default scheme is an affine function of the vouched value, so
+//! any two values that differ by `d` have vouchers that differ by a
+//! constant multiple of `d`.  The wide scheme first maps the value
+//! through a two-round Feistel network whose round function is a
+//! 128-bit affine transformation (keyed by 256 bits of parameters),
+//! and then vouches for the mixed value with the affine scheme.  Each
+//! Feistel round is a permutation of the [`u64`]s, so, just like for
+//! the default scheme, a voucher is never valid for more than one
+//! value under a given set of parameters.
+//!
+//! The mixing parameters are part of the checking parameters (checkers
+//! have to mix the expected value too), so this does nothing against
+//! deliberate forgery by code with access to the checking parameters.
+//! It does however break the linear relationship between values and
+//! vouchers, so vouchers for a handful of values don't let anyone
+//! extrapolate vouchers for nearby values, and accidental agreement
+//! between unrelated parameter sets isn't structured anymore.
+use crate::constparse::parse_hex;
+use crate::CheckingParameters;
+use crate::GenerateError;
+use crate::Voucher;
+use crate::VouchingParameters;
+
+/// [`WideCheckingParameters`] confirm whether a [`Voucher`] was generated
+/// from a given [`u64`] value with the associated [`WideVouchingParameters`].
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Hash)]
+pub struct WideCheckingParameters {
+    mix_offset: u128,
+    mix_scale: u128,
+    checking: CheckingParameters,
+}
+
+/// [`WideVouchingParameters`] convert arbitrary [`u64`] values to
+/// [`Voucher`]s that can be checked with the associated
+/// [`WideCheckingParameters`].
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Hash)]
+pub struct WideVouchingParameters {
+    vouching: VouchingParameters,
+    checking: WideCheckingParameters,
+}
+
+/// Prefix for the string representation of [`WideCheckingParameters`].
+pub(crate) const CHECK_PREFIX: &str = "WIDE-CHECK-";
+
+/// Prefix for the string representation of [`WideVouchingParameters`].
+pub(crate) const VOUCH_PREFIX: &str = "WIDE-VOUCH-";
+
+/// One Feistel round function: a 128-bit affine transformation of
+/// `half`, from which we extract 32 bits in the middle of the result.
+#[inline(always)]
+const fn round(offset: u128, scale: u128, half: u32) -> u32 {
+    let wide = (half as u128).wrapping_add(offset).wrapping_mul(scale | 1);
+    (wide >> 48) as u32
+}
+
+/// Mixes `value` with a two-round Feistel network.  This is a
+/// permutation of the [`u64`]s for any `offset` and `scale`.
+#[inline(always)]
+const fn mix(offset: u128, scale: u128, value: u64) -> u64 {
+    let mut hi = (value >> 32) as u32;
+    let mut lo = value as u32;
+
+    lo ^= round(offset, scale, hi);
+    hi ^= round(offset.rotate_left(64), scale.rotate_left(64), lo);
+
+    ((hi as u64) << 32) | (lo as u64)
+}
+
+/// Parses the 32 hex digits at `bytes[base..base + 32]` as a [`u128`].
+const fn parse_hex128(bytes: &[u8], base: usize) -> Option<u128> {
+    let Some(hi) = parse_hex(bytes, base) else {
+        return None;
+    };
+    let Some(lo) = parse_hex(bytes, base + 16) else {
+        return None;
+    };
+
+    Some(((hi as u128) << 64) | (lo as u128))
+}
+
+/// Returns whether `bytes[base..]` starts with `prefix`.
+const fn has_prefix(bytes: &[u8], base: usize, prefix: &[u8]) -> bool {
+    if bytes.len() < base + prefix.len() {
+        return false;
+    }
+
+    let mut i = 0;
+    while i < prefix.len() {
+        if bytes[base + i] != prefix[i] {
+            return false;
+        }
+
+        i += 1;
+    }
+
+    true
+}
+
+impl WideCheckingParameters {
+    /// Number of ASCII characters in the string representation for
+    /// one [`WideCheckingParameters`] instance.
+    pub const REPRESENTATION_BYTE_COUNT: usize = CHECK_PREFIX.len() + 32 + 1 + 32 + 1 + 33;
+
+    /// Returns whether the `expected` value matches the `voucher`,
+    /// assuming the voucher was generated with the
+    /// [`WideVouchingParameters`] from which these parameters came.
+    #[must_use]
+    #[inline(always)]
+    pub const fn check(self, expected: u64, voucher: Voucher) -> bool {
+        self.checking
+            .check(mix(self.mix_offset, self.mix_scale, expected), voucher)
+    }
+
+    /// Attempts to parse the string representation of a [`WideCheckingParameters`] instance.
+    #[inline(always)]
+    pub const fn parse(string: &str) -> Result<WideCheckingParameters, &'static str> {
+        Self::parse_bytes(string.as_bytes())
+    }
+
+    /// Parses the string representation of a [`WideCheckingParameters`] object
+    /// or panics.
+    ///
+    /// This function is mostly useful to initialise `const` literals.
+    #[inline(never)]
+    pub const fn parse_or_die(string: &str) -> WideCheckingParameters {
+        match Self::parse(string) {
+            Ok(ret) => ret,
+            Err(_) => panic!("failed to parse wide checking parameter string."),
+        }
+    }
+
+    /// Attempts to parse `bytes`, which must be the utf-8 (it's all
+    /// ASCII) representation of a serialised [`WideCheckingParameters`],
+    /// with a length of exactly `REPRESENTATION_BYTE_COUNT` bytes.
+    #[inline(never)]
+    pub const fn parse_bytes(bytes: &[u8]) -> Result<WideCheckingParameters, &'static str> {
+        // Expected length:
+        //  "WIDE-CHECK-"  [  0,  11)
+        //  hex mix offset [ 11,  43)
+        //  "-"            [ 43,  44)
+        //  hex mix scale  [ 44,  76)
+        //  "-"            [ 76,  77)
+        //  hex unoffset   [ 77,  93)
+        //  "-"            [ 93,  94)
+        //  hex unscale    [ 94, 110)
+        if bytes.len() < Self::REPRESENTATION_BYTE_COUNT {
+            return Err("Too few bytes in serialized raffle::WideCheckingParameters");
+        }
+
+        if bytes.len() > Self::REPRESENTATION_BYTE_COUNT {
+            return Err("Too many bytes in serialized raffle::WideCheckingParameters");
+        }
+
+        if !has_prefix(bytes, 0, CHECK_PREFIX.as_bytes()) {
+            return Err(
+                "Incorrect prefix for raffle::WideCheckingParameters. Expected WIDE-CHECK-",
+            );
+        }
+
+        Self::parse_suffix(bytes, CHECK_PREFIX.len())
+    }
+
+    /// Parses the mixing parameters and the affine checking parameters
+    /// in `bytes[base..]`.
+    const fn parse_suffix(
+        bytes: &[u8],
+        base: usize,
+    ) -> Result<WideCheckingParameters, &'static str> {
+        let Some(mix_offset) = parse_hex128(bytes, base) else {
+            return Err("Failed to parse hex mix offset in raffle::WideCheckingParameters.");
+        };
+
+        if bytes[base + 32] != b'-' {
+            return Err(
+                "Missing dash separator after mix offset in raffle::WideCheckingParameters",
+            );
+        }
+
+        let Some(mix_scale) = parse_hex128(bytes, base + 33) else {
+            return Err("Failed to parse hex mix scale in raffle::WideCheckingParameters.");
+        };
+
+        if bytes[base + 65] != b'-' {
+            return Err("Missing dash separator after mix scale in raffle::WideCheckingParameters");
+        }
+
+        let Some(unoffset) = parse_hex(bytes, base + 66) else {
+            return Err("Failed to parse hex unoffset in raffle::WideCheckingParameters.");
+        };
+
+        if bytes[base + 82] != b'-' {
+            return Err("Missing dash separator after unoffset in raffle::WideCheckingParameters");
+        }
+
+        let Some(unscale) = parse_hex(bytes, base + 83) else {
+            return Err("Failed to parse hex unscale in raffle::WideCheckingParameters.");
+        };
+
+        Ok(WideCheckingParameters {
+            mix_offset,
+            mix_scale,
+            checking: CheckingParameters { unoffset, unscale },
+        })
+    }
+}
+
+impl WideVouchingParameters {
+    /// Number of ASCII characters in the string representation for
+    /// one [`WideVouchingParameters`] instance.
+    pub const REPRESENTATION_BYTE_COUNT: usize = VOUCH_PREFIX.len() + 34 + 32 + 1 + 32 + 1 + 33;
+
+    /// Attempts to generate a fresh set of [`WideVouchingParameters`]
+    /// by repeatedly calling `generator` to get [`u64`] values.
+    ///
+    /// See [`VouchingParameters::generate`] for the requirements on `generator`.
+    pub fn generate<Err>(
+        mut generator: impl FnMut() -> Result<u64, Err>,
+    ) -> Result<WideVouchingParameters, GenerateError<Err>> {
+        let vouching = VouchingParameters::generate(&mut generator)?;
+        let mut gen64 = || generator().map_err(GenerateError::Generator);
+        let mut gen128 = || -> Result<u128, GenerateError<Err>> {
+            Ok(((gen64()? as u128) << 64) | (gen64()? as u128))
+        };
+
+        let mix_offset = gen128()?;
+        let mix_scale = gen128()? | 1;
+        Ok(WideVouchingParameters {
+            vouching,
+            checking: WideCheckingParameters {
+                mix_offset,
+                mix_scale,
+                checking: vouching.checking_parameters(),
+            },
+        })
+    }
+
+    /// Computes a [`Voucher`] for `value`.  The match can be confirmed
+    /// by [`WideCheckingParameters::check`]ing it against `value`, with
+    /// [`Self::checking_parameters`] as the checking parameters.
+    #[must_use]
+    #[inline(always)]
+    pub const fn vouch(&self, value: u64) -> Voucher {
+        self.vouching.vouch(mix(
+            self.checking.mix_offset,
+            self.checking.mix_scale,
+            value,
+        ))
+    }
+
+    /// Returns the [`WideCheckingParameters`] that will accept the
+    /// [`Voucher`]s generated with these [`WideVouchingParameters`].
+    #[must_use]
+    #[inline(always)]
+    pub const fn checking_parameters(&self) -> WideCheckingParameters {
+        self.checking
+    }
+
+    /// Attempts to parse the string representation of [`WideVouchingParameters`].
+    #[inline(always)]
+    pub const fn parse(string: &str) -> Result<WideVouchingParameters, &'static str> {
+        Self::parse_bytes(string.as_bytes())
+    }
+
+    /// Parses the string representation of a [`WideVouchingParameters`] object
+    /// or panics.
+    ///
+    /// This function is mostly useful to initialise `const` literals.
+    #[inline(never)]
+    pub const fn parse_or_die(string: &str) -> WideVouchingParameters {
+        match Self::parse(string) {
+            Ok(ret) => ret,
+            Err(_) => panic!("failed to parse wide vouching parameter string."),
+        }
+    }
+
+    /// Attempts to parse `bytes`, which must be the utf-8 (it's all
+    /// ASCII) representation of a serialised [`WideVouchingParameters`],
+    /// with a length of exactly `REPRESENTATION_BYTE_COUNT` bytes.
+    ///
+    /// Like [`VouchingParameters::parse_bytes`], this function checks
+    /// that the vouching and checking halves match.
+    #[inline(never)]
+    pub const fn parse_bytes(bytes: &[u8]) -> Result<WideVouchingParameters, &'static str> {
+        // Expected length:
+        //  "WIDE-VOUCH-"  [  0,  11)
+        //  hex offset     [ 11,  27)
+        //  "-"            [ 27,  28)
+        //  hex scale      [ 28,  44)
+        //  "-"            [ 44,  45)
+        //  checking suffix, as in `WideCheckingParameters` [45, 144)
+        if bytes.len() < Self::REPRESENTATION_BYTE_COUNT {
+            return Err("Too few bytes in serialized raffle::WideVouchingParameters");
+        }
+
+        if bytes.len() > Self::REPRESENTATION_BYTE_COUNT {
+            return Err("Too many bytes in serialized raffle::WideVouchingParameters");
+        }
+
+        if !has_prefix(bytes, 0, VOUCH_PREFIX.as_bytes()) {
+            return Err(
+                "Incorrect prefix for serialized raffle::WideVouchingParameters. Expected WIDE-VOUCH-",
+            );
+        }
+
+        let Some(offset) = parse_hex(bytes, 11) else {
+            return Err("Failed to parse hex offset in serialized raffle::WideVouchingParameters.");
+        };
+
+        if bytes[27] != b'-' {
+            return Err(
+                "Missing dash separator after offset in serialized raffle::WideVouchingParameters",
+            );
+        }
+
+        let Some(scale) = parse_hex(bytes, 28) else {
+            return Err("Failed to parse hex scale in serialized raffle::WideVouchingParameters.");
+        };
+
+        if bytes[44] != b'-' {
+            return Err(
+                "Missing dash separator after scale in serialized raffle::WideVouchingParameters",
+            );
+        }
+
+        let checking = match WideCheckingParameters::parse_suffix(bytes, 45) {
+            Ok(checking) => checking,
+            Err(e) => return Err(e),
+        };
+
+        match VouchingParameters::validate(
+            offset,
+            scale,
+            checking.checking.unoffset,
+            checking.checking.unscale,
+        ) {
+            Ok(vouching) => Ok(WideVouchingParameters { vouching, checking }),
+            Err(_) => Err("Invalid WideVouchingParameters values"),
+        }
+    }
+}
+
+impl std::fmt::Display for WideCheckingParameters {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{}{:032x}-{:032x}-{:016x}-{:016x}",
+            CHECK_PREFIX,
+            self.mix_offset,
+            self.mix_scale,
+            self.checking.unoffset,
+            self.checking.unscale
+        )
+    }
+}
+
+impl std::fmt::Display for WideVouchingParameters {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let checking = format!("{}", self.checking);
+        write!(
+            f,
+            "{}{:016x}-{:016x}-{}",
+            VOUCH_PREFIX,
+            self.vouching.offset,
+            self.vouching.scale,
+            &checking[CHECK_PREFIX.len()..]
+        )
+    }
+}
+
+#[test]
+fn test_mix_permutation() {
+    const OFFSET: u128 = 0x0123456789abcdef_fedcba9876543210;
+    const SCALE: u128 = 0x9e3779b97f4a7c15_f39cc0605cedc835;
+
+    // Spot check that the mix is a bijection on a small neighbourhood.
+    let mut mixed: Vec<u64> = (0..1000u64).map(|x| mix(OFFSET, SCALE, x)).collect();
+    mixed.sort();
+    mixed.dedup();
+    assert_eq!(mixed.len(), 1000);
+
+    // And that it isn't affine.
+    let d1 = mix(OFFSET, SCALE, 1).wrapping_sub(mix(OFFSET, SCALE, 0));
+    let d2 = mix(OFFSET, SCALE, 2).wrapping_sub(mix(OFFSET, SCALE, 1));
+    assert_ne!(d1, d2);
+}
+
+#[cfg(not(feature = "insecure-accept-all"))]
+#[test]
+fn test_round_trip() {
+    let params =
+        WideVouchingParameters::generate(crate::make_generator(&[131, 5, 131, 1, 2, 3, 4]))
+            .expect("must succeed");
+    let checking = params.checking_parameters();
+
+    let voucher = params.vouch(42);
+    assert!(checking.check(42, voucher));
+    assert!(!checking.check(43, voucher));
+    assert!(!checking.check(42, Voucher(voucher.0 + 1)));
+
+    // The wide voucher isn't the affine voucher.
+    assert!(!checking.checking.check(42, voucher));
+}
+
+#[test]
+fn test_parse() {
+    let params =
+        WideVouchingParameters::generate(crate::make_generator(&[131, 5, 131, 1, 2, 3, 4]))
+            .expect("must succeed");
+
+    const SERIAL: &str = "WIDE-VOUCH-b4b0de979c8a90a9-676e696863756fd5-00000000000000010000000000000002-00000000000000030000000000000005-0000000000000083-9b791a2755d2d996";
+    const CHECK_SERIAL: &str = "WIDE-CHECK-00000000000000010000000000000002-00000000000000030000000000000005-0000000000000083-9b791a2755d2d996";
+    assert_eq!(format!("{}", params), SERIAL);
+    assert_eq!(format!("{}", params.checking_parameters()), CHECK_SERIAL);
+    assert_eq!(
+        SERIAL.len(),
+        WideVouchingParameters::REPRESENTATION_BYTE_COUNT
+    );
+    assert_eq!(
+        CHECK_SERIAL.len(),
+        WideCheckingParameters::REPRESENTATION_BYTE_COUNT
+    );
+
+    const COPY: WideVouchingParameters = WideVouchingParameters::parse_or_die(SERIAL);
+    const CHECK_COPY: WideCheckingParameters = WideCheckingParameters::parse_or_die(CHECK_SERIAL);
+    assert_eq!(params, COPY);
+    assert_eq!(params.checking_parameters(), CHECK_COPY);
+}
+
+#[test]
+fn test_parse_fail() {
+    const SERIAL: &str = "WIDE-VOUCH-b4b0de979c8a90a9-676e696863756fd5-00000000000000010000000000000002-00000000000000030000000000000005-0000000000000083-9b791a2755d2d996";
+    const CHECK_SERIAL: &str = "WIDE-CHECK-00000000000000010000000000000002-00000000000000030000000000000005-0000000000000083-9b791a2755d2d996";
+
+    // Inconsistent affine parameters.
+    assert!(WideVouchingParameters::parse(&SERIAL.replace("d996", "d995")).is_err());
+    // Bad prefixes.
+    assert!(WideVouchingParameters::parse(CHECK_SERIAL).is_err());
+    assert!(WideCheckingParameters::parse(&SERIAL[..CHECK_SERIAL.len()]).is_err());
+    // Bad dashes.
+    assert!(WideCheckingParameters::parse(&CHECK_SERIAL.replace("0002-", "0002.")).is_err());
+    assert!(WideVouchingParameters::parse(&SERIAL.replace("fd5-", "fd5.")).is_err());
+    // Bad lengths.
+    assert!(WideCheckingParameters::parse(&CHECK_SERIAL[1..]).is_err());
+    assert!(WideVouchingParameters::parse(&format!("{}0", SERIAL)).is_err());
+}
+
+#[test]
+#[should_panic(expected = "failed to parse wide checking parameter string.")]
+fn test_parse_check_fail() {
+    WideCheckingParameters::parse_or_die("WIDE-CHECK-");
+}
@@ -0,0 +1,122 @@
+//! Pronounceable word encoding for vouchers.
+//!
+//! Hex strings are easy to mangle when read aloud.  This module
+//! encodes each 16-bit chunk of a voucher as a five-letter
+//! consonant-vowel-consonant-vowel-consonant word (a "proquint"), so a
+//! 64-bit voucher becomes four words, e.g., `lusab-babad-gutih-tugad`.
+//! The "wordlist" is fixed: 16 consonants (`bdfghjklmnprstvz`) for 4
+//! bits each, and 4 vowels (`aiou`) for 2 bits each.
+//!
+//! [`Voucher::to_words`] renders words most significant first, and
+//! [`Voucher::parse_words`] parses them back, case-insensitively, with
+//! any mix of dashes and whitespace between words.
+use crate::Voucher;
+
+const CONSONANTS: &[u8; 16] = b"bdfghjklmnprstvz";
+const VOWELS: &[u8; 4] = b"aiou";
+
+/// Number of words in the encoding of a voucher.
+const WORD_COUNT: usize = 4;
+
+fn encode_word(chunk: u16, out: &mut String) {
+    let chunk = chunk as usize;
+    out.push(CONSONANTS[(chunk >> 12) & 15] as char);
+    out.push(VOWELS[(chunk >> 10) & 3] as char);
+    out.push(CONSONANTS[(chunk >> 6) & 15] as char);
+    out.push(VOWELS[(chunk >> 4) & 3] as char);
+    out.push(CONSONANTS[chunk & 15] as char);
+}
+
+fn decode_word(word: &str) -> Result<u16, &'static str> {
+    fn index(table: &[u8], byte: u8) -> Option<u16> {
+        let byte = byte.to_ascii_lowercase();
+        table.iter().position(|&x| x == byte).map(|x| x as u16)
+    }
+
+    let bytes = word.as_bytes();
+    if bytes.len() != 5 {
+        return Err("Voucher words must have 5 letters");
+    }
+
+    let mut ret = 0u16;
+    for (idx, &byte) in bytes.iter().enumerate() {
+        ret = if idx % 2 == 0 {
+            (ret << 4) | index(CONSONANTS, byte).ok_or("Invalid consonant in voucher word")?
+        } else {
+            (ret << 2) | index(VOWELS, byte).ok_or("Invalid vowel in voucher word")?
+        };
+    }
+
+    Ok(ret)
+}
+
+impl Voucher {
+    /// Returns this voucher as four dash-separated pronounceable words.
+    #[must_use]
+    pub fn to_words(&self) -> String {
+        let mut ret = String::with_capacity(WORD_COUNT * 6);
+        for idx in 0..WORD_COUNT {
+            if idx > 0 {
+                ret.push('-');
+            }
+
+            encode_word((self.0 >> (16 * (WORD_COUNT - 1 - idx))) as u16, &mut ret);
+        }
+
+        ret
+    }
+
+    /// Parses the word representation generated by [`Voucher::to_words`].
+    ///
+    /// Words may be separated by dashes or whitespace, and are case-insensitive.
+    pub fn parse_words(string: &str) -> Result<Voucher, &'static str> {
+        let mut words = string
+            .split(|c: char| c == '-' || c.is_whitespace())
+            .filter(|word| !word.is_empty());
+
+        let mut ret = 0u64;
+        for _ in 0..WORD_COUNT {
+            let word = words.next().ok_or("Too few words in voucher")?;
+            ret = (ret << 16) | decode_word(word)? as u64;
+        }
+
+        if words.next().is_some() {
+            return Err("Too many words in voucher");
+        }
+
+        Ok(Voucher(ret))
+    }
+}
+
+#[test]
+fn test_known_words() {
+    // Reference values from the proquint specification (for 32-bit IPv4 addresses).
+    let voucher = Voucher(0x7f00_0001 | (0x3f54_dcc1 << 32));
+    assert_eq!(voucher.to_words(), "gutih-tugad-lusab-babad");
+    assert_eq!(Voucher(0).to_words(), "babab-babab-babab-babab");
+    assert_eq!(Voucher(u64::MAX).to_words(), "zuzuz-zuzuz-zuzuz-zuzuz");
+}
+
+#[test]
+fn test_round_trip() {
+    let params = crate::testing::VOUCHING;
+
+    for value in [0u64, 1, 42, u64::MAX] {
+        let voucher = params.vouch(value);
+        let words = voucher.to_words();
+        assert_eq!(Voucher::parse_words(&words), Ok(voucher));
+        assert_eq!(
+            Voucher::parse_words(&words.replace('-', "  ").to_uppercase()),
+            Ok(voucher)
+        );
+    }
+}
+
+#[test]
+fn test_parse_errors() {
+    assert!(Voucher::parse_words("gutih-tugad-lusab").is_err());
+    assert!(Voucher::parse_words("gutih-tugad-lusab-babad-babad").is_err());
+    assert!(Voucher::parse_words("gutih-tugad-lusab-babadd").is_err());
+    assert!(Voucher::parse_words("gutih-tugad-lusab-aabad").is_err());
+    assert!(Voucher::parse_words("gutih-tugad-lusab-bebad").is_err());
+}
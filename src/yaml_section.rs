@@ -0,0 +1,269 @@
+//! The `raffle` section of YAML configuration files.
+//!
+//! This module is only compiled with the `yaml` feature.  The layout
+//! mirrors the TOML layout (see `raffle::toml_section`), and
+//! [`RaffleSection`] holds the same contents:
+//!
+//! ```yaml
+//! raffle:
+//!   # Optional: the application's main checking parameters.
+//!   checking: CHECK-0000000000000083-9b791a2755d2d996
+//!   # Optional: named checking parameters, for a `raffle::Keyring`.
+//!   keyring:
+//!     tenant_a: CHECK-0000000000000084-9b791a2755d2d996
+//!   # Optional: vouched overrides for `raffle::config::Setting`s.
+//!   overrides:
+//!     max_connections:
+//!       value: 10000
+//!       voucher: "0123456789abcdef"
+//! ```
+//!
+//! Values may be strings, integers or booleans.  Vouchers are strings
+//! of 16 hex digits; quote them, since YAML would otherwise read some
+//! (e.g., all digits) as numbers.
+//!
+//! [`RaffleSection::parse_yaml`] reads that section from a YAML
+//! document, and [`RaffleSection::to_yaml_string`] writes it back.
+use serde_yaml::Mapping;
+use serde_yaml::Value;
+
+use crate::section::format_voucher;
+use crate::section::invalid;
+use crate::section::parse_checking;
+use crate::section::parse_voucher;
+use crate::section::SECTION;
+use crate::OverrideValue;
+use crate::RaffleSection;
+use crate::Voucher;
+
+/// Error type for [`RaffleSection::parse_yaml`].
+pub type SectionError = crate::SectionError<serde_yaml::Error>;
+
+impl std::fmt::Display for SectionError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            SectionError::Syntax(e) => write!(f, "invalid YAML: {}", e),
+            SectionError::Invalid { key, reason } => write!(f, "invalid {}: {}", key, reason),
+        }
+    }
+}
+
+/// Returns the entries of the mapping `value` at `key`, with string keys.
+fn entries<'a>(
+    key: &str,
+    value: &'a Value,
+) -> Result<impl Iterator<Item = Result<(&'a str, &'a Value), SectionError>>, SectionError> {
+    let Some(mapping) = value.as_mapping() else {
+        return Err(invalid(key, "Expected a mapping"));
+    };
+
+    let key = key.to_owned();
+    Ok(mapping
+        .iter()
+        .map(move |(name, value)| match name.as_str() {
+            Some(name) => Ok((name, value)),
+            None => Err(invalid(key.clone(), "Expected string keys")),
+        }))
+}
+
+fn parse_override(key: String, entry: &Value) -> Result<(OverrideValue, Voucher), SectionError> {
+    let Some(entry) = entry.as_mapping() else {
+        return Err(invalid(
+            key,
+            "Expected a mapping with a value and a voucher",
+        ));
+    };
+
+    let value = match entry.get("value") {
+        Some(Value::String(value)) => Some(OverrideValue::String(value.clone())),
+        Some(Value::Number(value)) => value.as_i64().map(OverrideValue::Integer),
+        Some(Value::Bool(value)) => Some(OverrideValue::Boolean(*value)),
+        _ => None,
+    };
+    let Some(value) = value else {
+        return Err(invalid(key, "Value must be a string, integer or boolean"));
+    };
+
+    let voucher = entry
+        .get("voucher")
+        .and_then(Value::as_str)
+        .unwrap_or_default();
+    let voucher =
+        parse_voucher(voucher).map_err(|reason| invalid(format!("{}.voucher", key), reason))?;
+    Ok((value, voucher))
+}
+
+impl RaffleSection {
+    /// Parses the `raffle` section of the YAML `document`.
+    ///
+    /// Returns an empty section if the document doesn't have one.
+    /// Unknown keys in the section are errors, to catch typos.
+    pub fn parse_yaml(document: &str) -> Result<RaffleSection, SectionError> {
+        let document: Value = serde_yaml::from_str(document).map_err(SectionError::Syntax)?;
+        match &document {
+            Value::Null => Ok(RaffleSection::default()),
+            Value::Mapping(mapping) => match mapping.get(SECTION) {
+                None => Ok(RaffleSection::default()),
+                Some(section) => RaffleSection::from_yaml_value(section),
+            },
+            _ => Err(invalid("", "Expected a mapping at the top level")),
+        }
+    }
+
+    /// Parses the contents of a `raffle` section, e.g., a sub-mapping
+    /// of a document that was already parsed.
+    pub fn from_yaml_value(section: &Value) -> Result<RaffleSection, SectionError> {
+        let mut ret = RaffleSection::default();
+        for entry in entries(SECTION, section)? {
+            let (key, value) = entry?;
+            let path = format!("{}.{}", SECTION, key);
+            match key {
+                "checking" => ret.checking = Some(parse_checking(path, value.as_str())?),
+                "keyring" => {
+                    for entry in entries(&path, value)? {
+                        let (name, value) = entry?;
+                        let checking =
+                            parse_checking(format!("{}.{}", path, name), value.as_str())?;
+                        ret.keyring.insert(name, checking);
+                    }
+                }
+                "overrides" => {
+                    for entry in entries(&path, value)? {
+                        let (name, entry) = entry?;
+                        let entry = parse_override(format!("{}.{}", path, name), entry)?;
+                        ret.overrides.insert(name.to_owned(), entry);
+                    }
+                }
+                _ => return Err(invalid(path, "Unknown key")),
+            }
+        }
+
+        Ok(ret)
+    }
+
+    /// Returns the contents of the section, as a YAML mapping.
+    #[must_use]
+    pub fn to_yaml_value(&self) -> Value {
+        let mut section = Mapping::new();
+        if let Some(checking) = self.checking {
+            section.insert("checking".into(), checking.to_string().into());
+        }
+
+        if !self.keyring.is_empty() {
+            let keyring: Mapping = self
+                .keyring
+                .iter()
+                .map(|(name, checking)| (name.into(), checking.to_string().into()))
+                .collect();
+            section.insert("keyring".into(), keyring.into());
+        }
+
+        if !self.overrides.is_empty() {
+            let overrides: Mapping = self
+                .overrides
+                .iter()
+                .map(|(name, (value, voucher))| {
+                    let mut entry = Mapping::new();
+                    let value = match value {
+                        OverrideValue::String(value) => value.as_str().into(),
+                        OverrideValue::Integer(value) => (*value).into(),
+                        OverrideValue::Boolean(value) => (*value).into(),
+                    };
+                    entry.insert("value".into(), value);
+                    entry.insert("voucher".into(), format_voucher(*voucher).into());
+                    (name.as_str().into(), entry.into())
+                })
+                .collect();
+            section.insert("overrides".into(), overrides.into());
+        }
+
+        section.into()
+    }
+
+    /// Returns a YAML document with only this `raffle` section.
+    #[must_use]
+    pub fn to_yaml_string(&self) -> String {
+        let mut document = Mapping::new();
+        document.insert(SECTION.into(), self.to_yaml_value());
+        serde_yaml::to_string(&document).expect("YAML mappings of strings always serialize")
+    }
+}
+
+#[test]
+fn test_round_trip() {
+    let params = crate::testing::VOUCHING;
+    let other = crate::testing::OTHER;
+    static LIMIT: crate::config::Setting<u32> = crate::config::Setting::new("limit", 10);
+
+    let document = format!(
+        r#"
+server:
+  port: 80
+raffle:
+  checking: {}
+  keyring:
+    tenant: "{}"
+  overrides:
+    limit:
+      value: 1000
+      voucher: "{:016x}"
+"#,
+        params.checking_parameters(),
+        other.checking_parameters(),
+        LIMIT.vouch(&params, &1000).0,
+    );
+
+    let section = RaffleSection::parse_yaml(&document).unwrap();
+    assert_eq!(section.checking, Some(params.checking_parameters()));
+    assert_eq!(
+        section.keyring.get("tenant"),
+        Some(other.checking_parameters())
+    );
+    assert_eq!(
+        section.overrides.get("limit"),
+        Some(&(OverrideValue::Integer(1000), LIMIT.vouch(&params, &1000)))
+    );
+    assert_eq!(section.overrides().load(&LIMIT).unwrap().value, 1000);
+
+    // Integers stay integers.
+    assert!(section.to_yaml_string().contains("value: 1000\n"));
+    assert_eq!(
+        RaffleSection::parse_yaml(&section.to_yaml_string()).unwrap(),
+        section
+    );
+    assert_eq!(
+        RaffleSection::parse_yaml("server:\n  port: 80\n").unwrap(),
+        RaffleSection::default()
+    );
+    assert_eq!(
+        RaffleSection::parse_yaml("").unwrap(),
+        RaffleSection::default()
+    );
+}
+
+#[test]
+fn test_errors() {
+    let error = |document: &str| RaffleSection::parse_yaml(document).unwrap_err().to_string();
+
+    assert!(error("raffle: [").starts_with("invalid YAML: "));
+    assert_eq!(
+        error("raffle:\n  checks: 1"),
+        "invalid raffle.checks: Unknown key"
+    );
+    assert_eq!(
+        error("raffle:\n  keyring:\n    a: CHECK-"),
+        "invalid raffle.keyring.a: Too few bytes in serialized raffle::CheckingParameters"
+    );
+    assert_eq!(
+        error("raffle:\n  overrides:\n    x: { value: 1, voucher: 1234567890123456 }"),
+        "invalid raffle.overrides.x.voucher: Voucher must be 16 hex digits"
+    );
+    assert_eq!(
+        error("raffle:\n  overrides:\n    x: { value: 1.5, voucher: \"0123456789abcdef\" }"),
+        "invalid raffle.overrides.x: Value must be a string, integer or boolean"
+    );
+    assert_eq!(
+        error("raffle:\n  keyring:\n    1: CHECK-"),
+        "invalid raffle.keyring: Expected string keys"
+    );
+}